@@ -0,0 +1,68 @@
+//! Compares `powmod` with square-and-multiply written in Fift
+//! for RSA-sized numbers.
+//!
+//! Run with `cargo bench --bench powmod`.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use fift::core::env::EmptyEnvironment;
+use fift::core::SourceBlock;
+use fift::Context;
+
+const PREAMBLE: &str = include_str!("../src/stdlib/Fift.fif");
+
+const DEFINITIONS: &str = r#"
+variable X variable R variable M
+// x y m -- x^y mod m
+{ M ! swap X ! 1 R !
+  { dup 0> } {
+    dup 1 and { R @ X @ M @ *mod R ! } if
+    X @ dup M @ *mod X ! 1 >>
+  } while drop R @
+} : naive-powmod
+// Odd modulus of about 2048 bits
+1 { 3 * } 1290 times 2 + constant N
+"#;
+
+const CASES: &[(&str, &str)] = &[
+    ("e=65537", "3 65537 N"),
+    ("e=128 bits", "3 1 127 << 12345 + N"),
+    ("e=2048 bits", "3 N 2/ 12345 + N"),
+];
+
+const ITERATIONS: u32 = 20;
+
+fn main() -> Result<()> {
+    let mut env = EmptyEnvironment;
+    let mut stdout = std::io::sink();
+    let mut ctx = Context::new(&mut env, &mut stdout).with_basic_modules()?;
+    ctx.add_source_block(SourceBlock::new(
+        "Fift.fif",
+        std::io::Cursor::new(format!("{PREAMBLE}\n{DEFINITIONS}")),
+    ));
+    ctx.run()?;
+
+    for (name, args) in CASES {
+        ctx.eval_str(&format!("{args} powmod {args} naive-powmod"))?;
+        let naive = ctx.stack.pop_int()?;
+        let fast = ctx.stack.pop_int()?;
+        anyhow::ensure!(naive == fast, "Results mismatch for {name}");
+
+        let fast = measure(&mut ctx, &format!("{args} powmod drop"))?;
+        let naive = measure(&mut ctx, &format!("{args} naive-powmod drop"))?;
+        println!(
+            "{name:<12} powmod: {fast:>10.2?}  naive: {naive:>10.2?}  speedup: {:.1}x",
+            naive.as_secs_f64() / fast.as_secs_f64()
+        );
+    }
+    Ok(())
+}
+
+fn measure(ctx: &mut Context, source: &str) -> Result<Duration> {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        ctx.eval_str(source)?;
+    }
+    Ok(start.elapsed() / ITERATIONS)
+}
@@ -0,0 +1,41 @@
+use std::io::{BufRead, Write};
+
+use anyhow::Result;
+use fift::core::{Cont, Context, DebugHook, PauseReason};
+
+/// Sub-REPL which is started on breakpoints and steps.
+pub struct ReplDebugHook;
+
+impl DebugHook for ReplDebugHook {
+    fn on_pause(&mut self, ctx: &mut Context, cont: &Cont, reason: &PauseReason) -> Result<()> {
+        ctx.stdout.flush()?;
+
+        eprintln!(
+            "Paused on {reason} before `{}`",
+            cont.display_name(&ctx.dictionary)
+        );
+        eprintln!("Stack: {}", ctx.stack.display_dump());
+        if let Some(next) = &ctx.next {
+            eprintln!("Backtrace:\n{}", next.display_backtrace(&ctx.dictionary));
+        }
+        eprintln!("Type `step` or `continue` to proceed");
+
+        let mut stdin = std::io::stdin().lock();
+        let mut line = String::new();
+        while ctx.debugger.is_paused() {
+            eprint!("(debug) ");
+            line.clear();
+            if stdin.read_line(&mut line)? == 0 {
+                ctx.debugger.resume();
+                break;
+            }
+
+            if let Err(e) = ctx.eval_str(&line) {
+                eprintln!("Error: {e:?}");
+            }
+            ctx.stdout.flush()?;
+        }
+
+        Ok(())
+    }
+}
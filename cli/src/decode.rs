@@ -0,0 +1,37 @@
+use std::process::ExitCode;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use everscale_types::prelude::*;
+
+use fift::core::OpCodes;
+
+/// Print a message body from a BOC file with its op code labeled
+#[derive(FromArgs)]
+#[argh(subcommand, name = "decode")]
+pub struct CmdDecode {
+    /// path to the BOC file with a message body (raw or base64 encoded)
+    #[argh(positional)]
+    path: String,
+
+    /// max number of printed cells
+    #[argh(option, default = "100")]
+    limit: usize,
+}
+
+impl CmdDecode {
+    pub fn run(self) -> Result<ExitCode> {
+        let data =
+            std::fs::read(&self.path).with_context(|| format!("Failed to read `{}`", self.path))?;
+
+        let body = match Boc::decode(&data) {
+            Ok(body) => body,
+            Err(_) => Boc::decode_base64(String::from_utf8_lossy(&data).trim())
+                .with_context(|| format!("Invalid BOC in `{}`", self.path))?,
+        };
+
+        let op_codes = OpCodes::default();
+        print!("{}", op_codes.display_body(body.as_slice()?, self.limit));
+        Ok(ExitCode::SUCCESS)
+    }
+}
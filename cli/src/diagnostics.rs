@@ -0,0 +1,141 @@
+use std::str::FromStr;
+
+use fift::error::FiftError;
+
+/// Output format of errors and warnings.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticsFormat {
+    /// Source snippets for the terminal.
+    #[default]
+    Human,
+    /// SARIF 2.1.0 log for code review tools and editors.
+    Sarif,
+}
+
+impl FromStr for DiagnosticsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Self::Human),
+            "sarif" => Ok(Self::Sarif),
+            _ => Err(format!(
+                "unknown diagnostics format `{s}`, expected `human` or `sarif`"
+            )),
+        }
+    }
+}
+
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl Diagnostic {
+    pub fn warning(message: String) -> Self {
+        Self {
+            level: Level::Warning,
+            message,
+            location: None,
+        }
+    }
+
+    /// Creates an error diagnostic with the position of the failed word.
+    pub fn error(e: &anyhow::Error) -> Self {
+        let location = e.downcast_ref::<FiftError>().map(|e| Location {
+            file: e.source_block.clone(),
+            line: e.line_number,
+            column: e.column,
+            end_column: e.column + e.line[e.span.clone()].chars().count(),
+            text: e.line.clone(),
+        });
+
+        Self {
+            level: Level::Error,
+            message: format!("{:#}", FiftError::unwrap_position(e)),
+            location,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl Level {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    /// Column of the token, starting from 1.
+    pub column: usize,
+    /// Column after the token.
+    pub end_column: usize,
+    /// Source line.
+    pub text: String,
+}
+
+/// Builds a SARIF log with a single run.
+pub fn make_sarif(diagnostics: &[Diagnostic]) -> serde_json::Value {
+    use serde_json::json;
+
+    let results = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let mut result = json!({
+                "ruleId": diagnostic.level.as_str(),
+                "level": diagnostic.level.as_str(),
+                "message": { "text": diagnostic.message },
+            });
+            if let Some(location) = &diagnostic.location {
+                result["locations"] = json!([{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": location.file },
+                        "region": {
+                            "startLine": location.line,
+                            "startColumn": location.column,
+                            "endColumn": location.end_column,
+                            "snippet": { "text": location.text },
+                        },
+                    },
+                }]);
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "fift",
+                    "version": crate::util::VERSION,
+                    "informationUri": "https://github.com/broxus/fift",
+                    "rules": [
+                        {
+                            "id": "error",
+                            "shortDescription": { "text": "Script execution failed" },
+                        },
+                        {
+                            "id": "warning",
+                            "shortDescription": { "text": "Non-fatal interpreter diagnostic" },
+                        },
+                    ],
+                },
+            },
+            "results": results,
+        }],
+    })
+}
@@ -0,0 +1,30 @@
+use std::process::ExitCode;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use everscale_types::prelude::*;
+
+/// Print TVM bytecode from a BOC file as a list of instructions
+#[derive(FromArgs)]
+#[argh(subcommand, name = "disasm")]
+pub struct CmdDisasm {
+    /// path to the BOC file with code (raw or base64 encoded)
+    #[argh(positional)]
+    path: String,
+}
+
+impl CmdDisasm {
+    pub fn run(self) -> Result<ExitCode> {
+        let data =
+            std::fs::read(&self.path).with_context(|| format!("Failed to read `{}`", self.path))?;
+
+        let code = match Boc::decode(&data) {
+            Ok(code) => code,
+            Err(_) => Boc::decode_base64(String::from_utf8_lossy(&data).trim())
+                .with_context(|| format!("Invalid BOC in `{}`", self.path))?,
+        };
+
+        print!("{}", fift::disasm::disassemble(code.as_slice()?)?);
+        Ok(ExitCode::SUCCESS)
+    }
+}
@@ -1,24 +1,36 @@
 use std::fs::File;
-use std::io::{BufReader, Read, Result, Seek, SeekFrom};
+use std::io::{BufRead, BufReader, BufWriter, IsTerminal, Read, Result, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use fift::core::{Environment, SourceBlock};
 
 pub struct SystemEnvironment {
     include_dirs: Vec<PathBuf>,
+    warnings: Option<Vec<String>>,
+    #[cfg(feature = "keychain")]
+    keychain: OsKeychain,
 }
 
 impl SystemEnvironment {
-    pub fn with_include_dirs(dirs: &str) -> Self {
-        let dirs = dirs.trim();
-        let include_dirs = if dirs.is_empty() {
-            Vec::new()
-        } else {
-            dirs.split(':')
-                .map(|item| PathBuf::from(item.trim()))
-                .collect()
-        };
-        Self { include_dirs }
+    pub fn with_include_dirs(include_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            include_dirs,
+            warnings: None,
+            #[cfg(feature = "keychain")]
+            keychain: OsKeychain,
+        }
+    }
+
+    /// Stores warnings instead of printing them to stderr.
+    pub fn collect_warnings(&mut self) {
+        self.warnings.get_or_insert_with(Vec::new);
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        self.warnings
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
     }
 
     fn resolve_file(&self, name: &str) -> Result<PathBuf> {
@@ -62,7 +74,7 @@ impl Environment for SystemEnvironment {
     }
 
     fn read_file(&mut self, name: &str) -> std::io::Result<Vec<u8>> {
-        std::fs::read(self.resolve_file(name)?).map_err(From::from)
+        std::fs::read(self.resolve_file(name)?)
     }
 
     fn read_file_part(&mut self, name: &str, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
@@ -80,4 +92,93 @@ impl Environment for SystemEnvironment {
         let buffer = BufReader::new(file);
         Ok(fift::core::SourceBlock::new(name, buffer))
     }
+
+    fn ask(&mut self, prompt: &str, secret: bool) -> Result<Option<String>> {
+        let stdin = std::io::stdin();
+        let res = if !stdin.is_terminal() {
+            // Piped answers are read without prompts,
+            // e.g. `echo $PASSWORD | fift sign.fif`
+            read_line(&mut stdin.lock())
+        } else if secret {
+            rpassword::prompt_password(prompt).map(Some)
+        } else {
+            let mut stderr = std::io::stderr();
+            stderr.write_all(prompt.as_bytes())?;
+            stderr.flush()?;
+            read_line(&mut stdin.lock())
+        };
+
+        match res {
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+            res => res,
+        }
+    }
+
+    #[cfg(feature = "keychain")]
+    fn keychain(&mut self) -> Option<&mut dyn fift::core::Keychain> {
+        Some(&mut self.keychain)
+    }
+
+    fn warn(&mut self, message: &str) {
+        match &mut self.warnings {
+            Some(warnings) => warnings.push(message.to_owned()),
+            None => eprintln!("Warning: {message}"),
+        }
+    }
+
+    fn trace(&mut self, message: &str) {
+        eprintln!("{message}");
+    }
+
+    fn create_file(&mut self, name: &str) -> Result<Box<dyn Write>> {
+        let file = File::create(name)?;
+        Ok(Box::new(BufWriter::new(file)))
+    }
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let len = line.trim_end_matches(['\r', '\n']).len();
+    line.truncate(len);
+    Ok(Some(line))
+}
+
+/// Secrets in the platform credential store, hex-encoded.
+#[cfg(feature = "keychain")]
+struct OsKeychain;
+
+#[cfg(feature = "keychain")]
+impl OsKeychain {
+    const SERVICE: &'static str = "fift";
+
+    fn entry(name: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(Self::SERVICE, name).map_err(keychain_error)
+    }
+}
+
+#[cfg(feature = "keychain")]
+impl fift::core::Keychain for OsKeychain {
+    fn store(&mut self, name: &str, secret: &[u8]) -> Result<()> {
+        Self::entry(name)?
+            .set_password(&hex::encode(secret))
+            .map_err(keychain_error)
+    }
+
+    fn load(&mut self, name: &str) -> Result<Option<Vec<u8>>> {
+        match Self::entry(name)?.get_password() {
+            Ok(secret) => hex::decode(secret)
+                .map(Some)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keychain_error(e)),
+        }
+    }
+}
+
+#[cfg(feature = "keychain")]
+fn keychain_error(e: keyring::Error) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, e)
 }
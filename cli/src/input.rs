@@ -1,30 +1,48 @@
 use std::cell::Cell;
-use std::io::{BufRead, Write};
+use std::io::Write;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use anyhow::Result;
-use rustyline::{DefaultEditor, ExternalPrinter};
+use fift::core::{LineEditor, ReadLine};
+use rustyline::{Config, DefaultEditor, ExternalPrinter};
 
 pub struct LineReader {
     editor: DefaultEditor,
-    line: String,
-    offset: usize,
+    history_path: Option<PathBuf>,
     add_newline: Rc<Cell<bool>>,
-    finished: bool,
 }
 
 impl LineReader {
-    pub fn new() -> Result<Self> {
-        let editor = DefaultEditor::new()?;
+    pub fn new(history_path: Option<PathBuf>) -> Result<Self> {
+        let config = Config::builder()
+            .max_history_size(1000)?
+            .history_ignore_dups(true)?
+            .history_ignore_space(true)
+            .build();
+
+        let mut editor = DefaultEditor::with_config(config)?;
+        if let Some(path) = &history_path {
+            // History file might not exist yet
+            editor.load_history(path).ok();
+        }
+
         Ok(Self {
             editor,
-            line: String::default(),
-            offset: 0,
+            history_path,
             add_newline: Default::default(),
-            finished: false,
         })
     }
 
+    /// Returns the path from `FIFT_HISTORY`, or `~/.fift_history`.
+    pub fn default_history_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("FIFT_HISTORY") {
+            return (!path.is_empty()).then(|| path.into());
+        }
+        let home = std::env::var_os("HOME")?;
+        Some(PathBuf::from(home).join(".fift_history"))
+    }
+
     pub fn create_external_printer(&mut self) -> Result<Box<dyn Write>> {
         let printer = self.editor.create_external_printer()?;
         Ok(Box::new(TerminalWriter {
@@ -34,6 +52,34 @@ impl LineReader {
     }
 }
 
+impl LineEditor for LineReader {
+    fn read_line(&mut self, continuation: bool) -> std::io::Result<ReadLine> {
+        use rustyline::error::ReadlineError;
+
+        if self.add_newline.get() {
+            self.add_newline.set(false);
+            println!();
+        }
+
+        let prompt = if continuation { ". " } else { "> " };
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                let entry = line.trim();
+                if !entry.is_empty() && self.editor.add_history_entry(entry).unwrap_or_default() {
+                    if let Some(path) = &self.history_path {
+                        self.editor.append_history(path).ok();
+                    }
+                }
+                Ok(ReadLine::Line(line))
+            }
+            Err(ReadlineError::Interrupted) => Ok(ReadLine::Interrupted),
+            Err(ReadlineError::Eof) => Ok(ReadLine::Eof),
+            Err(ReadlineError::Io(e)) => Err(e),
+            Err(e) => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)),
+        }
+    }
+}
+
 struct TerminalWriter<T> {
     printer: T,
     add_newline: Rc<Cell<bool>>,
@@ -52,63 +98,3 @@ impl<T: ExternalPrinter> Write for TerminalWriter<T> {
         Ok(())
     }
 }
-
-impl std::io::Read for LineReader {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        if self.finished {
-            return Ok(0);
-        }
-
-        let n = {
-            let mut rem = self.fill_buf()?;
-            rem.read(buf)?
-        };
-        self.consume(n);
-        Ok(n)
-    }
-}
-
-impl std::io::BufRead for LineReader {
-    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
-        use rustyline::error::ReadlineError;
-
-        if self.offset >= self.line.len() {
-            loop {
-                if self.add_newline.get() {
-                    self.add_newline.set(false);
-                    println!();
-                }
-
-                match self.editor.readline("> ") {
-                    Ok(line) if line.is_empty() => continue,
-                    Ok(mut line) => {
-                        {
-                            let line = line.trim();
-                            if !line.is_empty() {
-                                self.editor.add_history_entry(line.to_owned()).ok();
-                            }
-                        }
-
-                        line.push('\n');
-                        self.line = line;
-                        self.offset = 0;
-                        break;
-                    }
-                    Err(ReadlineError::Interrupted | ReadlineError::Eof) => {
-                        self.line = Default::default();
-                        self.offset = 0;
-                        self.finished = true;
-                        break;
-                    }
-                    Err(ReadlineError::Io(e)) => return Err(e),
-                    Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)),
-                }
-            }
-        }
-        Ok(self.line[self.offset..].as_bytes())
-    }
-
-    fn consume(&mut self, amt: usize) {
-        self.offset += amt;
-    }
-}
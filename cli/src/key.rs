@@ -0,0 +1,91 @@
+use std::process::ExitCode;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use everscale_crypto::ed25519;
+
+/// Generate a new ed25519 key and save it to a passphrase-protected key file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "keygen")]
+pub struct CmdKeygen {
+    /// path to the new key file
+    #[argh(positional)]
+    path: String,
+
+    /// overwrite the key file if it already exists
+    #[argh(switch, short = 'f')]
+    force: bool,
+}
+
+impl CmdKeygen {
+    pub fn run(self) -> Result<ExitCode> {
+        anyhow::ensure!(
+            self.force || !std::path::Path::new(&self.path).exists(),
+            "Key file `{}` already exists",
+            self.path
+        );
+
+        let passphrase = rpassword::prompt_password("Passphrase: ")?;
+        let confirmation = rpassword::prompt_password("Repeat passphrase: ")?;
+        anyhow::ensure!(passphrase == confirmation, "Passphrases do not match");
+
+        let secret = ed25519::SecretKey::generate(&mut rand::thread_rng());
+        let public = ed25519::PublicKey::from(&secret);
+
+        let container = fift::keyfile::encrypt(secret.as_bytes(), passphrase.as_bytes())?;
+        std::fs::write(&self.path, container)
+            .with_context(|| format!("Failed to write `{}`", self.path))?;
+
+        println!("{}", hex::encode(public.as_bytes()));
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Manage passphrase-protected key files
+#[derive(FromArgs)]
+#[argh(subcommand, name = "key")]
+pub struct CmdKey {
+    #[argh(subcommand)]
+    command: KeyCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum KeyCommand {
+    Export(CmdKeyExport),
+}
+
+impl CmdKey {
+    pub fn run(self) -> Result<ExitCode> {
+        match self.command {
+            KeyCommand::Export(cmd) => cmd.run(),
+        }
+    }
+}
+
+/// Decrypt a key file into a raw 32-byte private key file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "export")]
+struct CmdKeyExport {
+    /// path to the key file
+    #[argh(positional)]
+    path: String,
+
+    /// path to the raw private key file, e.g. `wallet.pk`
+    #[argh(positional)]
+    output: String,
+}
+
+impl CmdKeyExport {
+    fn run(self) -> Result<ExitCode> {
+        let container =
+            std::fs::read(&self.path).with_context(|| format!("Failed to read `{}`", self.path))?;
+
+        let passphrase = rpassword::prompt_password("Passphrase: ")?;
+        let secret = fift::keyfile::decrypt(&container, passphrase.as_bytes())?;
+        std::fs::write(&self.output, secret)
+            .with_context(|| format!("Failed to write `{}`", self.output))?;
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
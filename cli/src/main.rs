@@ -1,19 +1,53 @@
 use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use argh::FromArgs;
 
-use fift::core::{Environment, SourceBlock};
+use fift::alloc::CountingAlloc;
+use fift::config::split_include_paths;
+use fift::core::{DumpStyle, Environment, SourceBlock};
+use fift::crash::CrashReport;
+use fift::error::{FiftError, SourceSnippet};
+use fift::FiftConfig;
 
+use self::debugger::ReplDebugHook;
+use self::decode::CmdDecode;
+use self::diagnostics::{Diagnostic, DiagnosticsFormat};
+use self::disasm::CmdDisasm;
 use self::env::SystemEnvironment;
+#[cfg(feature = "repl")]
 use self::input::LineReader;
+use self::key::{CmdKey, CmdKeygen};
+use self::minimize::CmdMinimize;
 use self::util::ArgsOrVersion;
 
+mod debugger;
+mod decode;
+mod diagnostics;
+mod disasm;
 mod env;
+#[cfg(feature = "repl")]
 mod input;
+mod key;
+mod minimize;
 mod util;
 
+// mimalloc takes precedence if both allocators are enabled (e.g. with `--all-features`)
+#[cfg(feature = "mimalloc")]
+#[global_allocator]
+static GLOBAL: CountingAlloc<mimalloc::MiMalloc> = CountingAlloc(mimalloc::MiMalloc);
+
+#[cfg(all(feature = "jemalloc", not(feature = "mimalloc")))]
+#[global_allocator]
+static GLOBAL: CountingAlloc<tikv_jemallocator::Jemalloc> =
+    CountingAlloc(tikv_jemallocator::Jemalloc);
+
+#[cfg(not(any(feature = "mimalloc", feature = "jemalloc")))]
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc(std::alloc::System);
+
 /// A simple Fift interpreter. Type `bye` to quie,
 /// or `words` to get a list of all commands
 #[derive(FromArgs)]
@@ -22,89 +56,354 @@ struct App {
     #[argh(switch, short = 'n')]
     bare: bool,
 
-    /// sets color-separated library source include path.
+    /// adds colon-separated library source include paths, can be repeated.
     /// If not indicated, $FIFTPATH is used instead
     #[argh(option, short = 'I')]
-    include: Option<String>,
+    include: Vec<String>,
 
     /// sets an explicit path to the library source file.
     /// If not indicated, a default one will be used
     #[argh(option, short = 'L')]
     lib: Option<String>,
 
+    /// do not restore the stack after a failed line in interactive mode
+    #[argh(switch)]
+    raw_errors: bool,
+
+    /// write an lcov coverage report of executed word lists to the specified file
+    #[argh(option)]
+    coverage: Option<String>,
+
+    /// print time spent in each source block to stderr
+    #[argh(switch)]
+    timings: bool,
+
+    /// print the number of calls and time spent in each word to stderr
+    #[argh(switch)]
+    profile: bool,
+
+    /// treat lines starting with `#` as comments in the source file
+    #[argh(switch)]
+    script: bool,
+
+    /// set a named script input which is read by `param`, as `name=value`
+    #[argh(option)]
+    param: Vec<String>,
+
+    /// write run counters in the Prometheus text format to the specified file
+    #[argh(option)]
+    metrics: Option<String>,
+
+    /// print each executed word with the top of the stack to stderr
+    #[argh(switch)]
+    trace: bool,
+
+    /// write a crash bundle into `.fift-crash/` if the script fails
+    #[argh(switch)]
+    crash_report: bool,
+
+    /// start a debugger prompt on `bp` breakpoints and `step`s
+    #[argh(switch)]
+    debug: bool,
+
+    /// reject deprecated and non-standard words
+    #[argh(switch)]
+    strict: bool,
+
+    /// format of items printed by `.s`: `plain` (default), `pretty` or `colored`.
+    /// Colors are disabled by `NO_COLOR`
+    #[argh(option)]
+    dump_style: Option<DumpStyle>,
+
+    /// diagnostics format: `human` (default) or `sarif`.
+    /// SARIF logs are written to stderr
+    #[argh(option, default = "DiagnosticsFormat::Human")]
+    diagnostics: DiagnosticsFormat,
+
+    /// path to the config file. If not indicated, `fift.toml`
+    /// from the current directory is used if it exists
+    #[argh(option)]
+    config: Option<String>,
+
     /// an optional path to the source file (stdin will be used otherwise)
     #[argh(positional)]
     source_file: Option<String>,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Disasm(CmdDisasm),
+    Decode(CmdDecode),
+    Minimize(CmdMinimize),
+    Keygen(CmdKeygen),
+    Key(CmdKey),
 }
 
 fn main() -> Result<ExitCode> {
     let ArgsOrVersion::<App>(app) = argh::from_env();
 
+    if let Some(command) = app.command {
+        return match command {
+            Command::Disasm(cmd) => cmd.run(),
+            Command::Decode(cmd) => cmd.run(),
+            Command::Minimize(cmd) => cmd.run(),
+            Command::Keygen(cmd) => cmd.run(),
+            Command::Key(cmd) => cmd.run(),
+        };
+    }
+
+    // Prepare config (the config file, then env variables, then CLI flags)
+    let mut config = load_config(app.config.as_deref())?;
+    config.apply_env(|name| std::env::var(name).ok())?;
+    if !app.include.is_empty() {
+        config.include_paths = app
+            .include
+            .iter()
+            .flat_map(|paths| split_include_paths(paths))
+            .collect();
+    }
+    if let Some(lib) = &app.lib {
+        config.lib = Some(lib.into());
+    }
+    config.bare |= app.bare;
+    if let Some(style) = app.dump_style {
+        config.dump_style = style;
+    }
+    config.features.coverage |= app.coverage.is_some();
+    config.features.strict |= app.strict;
+    config.features.timings |= app.timings;
+    config.features.profile |= app.profile;
+    config.features.trace |= app.trace;
+
     // Prepare system environment
-    let mut env = SystemEnvironment::with_include_dirs(
-        &app.include
-            .unwrap_or_else(|| std::env::var("FIFTPATH").unwrap_or_default()),
-    );
+    let mut env = SystemEnvironment::with_include_dirs(config.include_paths.clone());
+    let sarif = app.diagnostics == DiagnosticsFormat::Sarif;
+    if sarif {
+        env.collect_warnings();
+    }
 
     // Prepare the source block which will be executed
     let mut stdout: Box<dyn std::io::Write> = Box::new(std::io::stdout());
-    let base_source_block = if let Some(path) = app.source_file {
-        env.include(&path)?
+    let mut interactive = false;
+    let base_source_block = if let Some(path) = &app.source_file {
+        env.include(path)?
     } else if std::io::stdin().is_terminal() {
-        let mut line_reader = LineReader::new()?;
-        stdout = line_reader.create_external_printer()?;
-        SourceBlock::new("<stdin>", line_reader)
+        interactive = true;
+        make_interactive_block(&mut stdout)?
     } else {
         SourceBlock::new("<stdin>", std::io::stdin().lock())
     };
+    anyhow::ensure!(
+        !(sarif && interactive),
+        "SARIF diagnostics are not supported in interactive mode"
+    );
 
     // Prepare preamble block
-    let library_source_block = if app.bare {
+    let library_source_block = if config.bare {
         None
-    } else if let Some(lib) = &app.lib {
-        Some(env.include(lib)?)
+    } else if let Some(lib) = &config.lib {
+        Some(env.include(&lib.to_string_lossy())?)
     } else {
         Some(SourceBlock::new(
             "<default Fift.fif>",
-            std::io::Cursor::new(include_str!("Fift.fif")),
+            std::io::Cursor::new(fift::stdlib::FIFT),
         ))
     };
 
     // Prepare Fift context
+    config.features.history |= interactive;
+    if interactive && config.stack_page_size.is_none() {
+        config.stack_page_size = Some(REPL_STACK_PAGE_SIZE);
+    }
+    let crash_config = app.crash_report.then(|| config.clone());
     let mut ctx = fift::Context::new(&mut env, &mut stdout)
         .with_basic_modules()?
-        .with_source_block(base_source_block);
+        .with_config(&config)?
+        .with_stdlib();
+
+    ctx.input.set_script_mode(app.script);
+    ctx.add_source_block(base_source_block);
+    ctx.input.set_script_mode(false);
 
     if let Some(lib) = library_source_block {
         ctx.add_source_block(lib);
     }
 
+    for param in &app.param {
+        let (name, value) = param
+            .split_once('=')
+            .with_context(|| format!("Invalid parameter `{param}`, expected `name=value`"))?;
+        ctx.params.insert(name.to_owned(), value.to_owned());
+    }
+
+    if app.debug {
+        ctx.set_debug_hook(ReplDebugHook);
+    }
+    if app.metrics.is_some() && ctx.profiler.is_none() {
+        ctx = ctx.with_profiler();
+    }
+
     // Execute
-    match ctx.run() {
-        Ok(exit_code) => Ok(ExitCode::from(!exit_code)),
-        Err(e) => {
-            use ariadne::{Color, Label, Report, ReportKind, Source};
+    let mut diagnostics = Vec::new();
+    let mut failed = false;
+    let result = loop {
+        match ctx.run() {
+            Ok(exit_code) => break Ok(ExitCode::from(!exit_code)),
+            Err(e) => {
+                failed = true;
+                if let (Some(config), false) = (&crash_config, interactive) {
+                    let mut report = CrashReport::capture(&ctx, &e).with_config(config.clone());
+                    if let Some(path) = &app.source_file {
+                        report.script = std::fs::read_to_string(path).ok();
+                    }
+                    match write_crash_bundle(&report, ctx.env.now_ms()) {
+                        Ok(path) => eprintln!("Crash report saved to `{}`", path.display()),
+                        Err(e) => eprintln!("Failed to save crash report: {e:?}"),
+                    }
+                }
 
-            if let Some(next) = ctx.next {
-                eprintln!("Backtrace:\n{}\n", next.display_backtrace(&ctx.dictionary));
-            }
+                if sarif {
+                    diagnostics.push(Diagnostic::error(&e));
+                    break Ok(ExitCode::FAILURE);
+                }
+
+                if !report_error(&ctx, &e, config.color) {
+                    break Err(e);
+                }
 
-            let Some(pos) = ctx.input.get_position() else {
-                return Err(e);
-            };
-
-            let id = pos.source_block_name;
-            Report::build(ReportKind::Error, id, 0)
-                .with_message(format!("{e:?}"))
-                .with_label(
-                    Label::new((id, pos.line_offset_start..pos.line_offset_end))
-                        .with_color(Color::Red),
-                )
-                .finish()
-                .eprint((id, Source::from(pos.line)))
-                .unwrap();
-
-            Ok(ExitCode::FAILURE)
+                if !interactive {
+                    break Ok(ExitCode::FAILURE);
+                }
+                ctx.recover(!app.raw_errors);
+            }
         }
+    };
+
+    if let (Some(path), Some(coverage)) = (&app.coverage, &ctx.coverage) {
+        std::fs::write(path, coverage.display_lcov().to_string())
+            .with_context(|| format!("Failed to write coverage report to `{path}`"))?;
+    }
+
+    if let Some(timings) = ctx.input.timings() {
+        eprint!("{}", timings.display_table());
+    }
+
+    if let Some(path) = &app.metrics {
+        let mut metrics = fift::metrics::Metrics::default();
+        metrics.record(&ctx, failed);
+        std::fs::write(path, metrics.display_prometheus().to_string())
+            .with_context(|| format!("Failed to write metrics to `{path}`"))?;
+    }
+
+    if let (true, Some(profiler)) = (config.features.profile, &ctx.profiler) {
+        eprint!("{}", profiler.display_report(&ctx.dictionary));
+    }
+
+    if sarif {
+        drop(ctx);
+        let warnings = env.take_warnings().into_iter().map(Diagnostic::warning);
+        let diagnostics = warnings.chain(diagnostics).collect::<Vec<_>>();
+        let log = diagnostics::make_sarif(&diagnostics);
+        eprintln!("{}", serde_json::to_string_pretty(&log)?);
+    }
+
+    result
+}
+
+#[cfg(feature = "repl")]
+fn make_interactive_block(stdout: &mut Box<dyn std::io::Write>) -> Result<SourceBlock> {
+    let mut line_reader = LineReader::new(LineReader::default_history_path())?;
+    *stdout = line_reader.create_external_printer()?;
+    Ok(SourceBlock::interactive("<stdin>", line_reader))
+}
+
+#[cfg(not(feature = "repl"))]
+fn make_interactive_block(_: &mut Box<dyn std::io::Write>) -> Result<SourceBlock> {
+    Ok(SourceBlock::interactive("<stdin>", StdinLines))
+}
+
+/// Plain stdin input without line editing.
+#[cfg(not(feature = "repl"))]
+struct StdinLines;
+
+#[cfg(not(feature = "repl"))]
+impl fift::core::LineEditor for StdinLines {
+    fn read_line(&mut self, continuation: bool) -> std::io::Result<fift::core::ReadLine> {
+        use std::io::Write;
+
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(if continuation { b". " } else { b"> " })?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        Ok(match std::io::stdin().read_line(&mut line)? {
+            0 => fift::core::ReadLine::Eof,
+            _ => {
+                let len = line.trim_end_matches(['\r', '\n']).len();
+                line.truncate(len);
+                fift::core::ReadLine::Line(line)
+            }
+        })
+    }
+}
+
+/// Number of items printed by `.s` in the interactive mode, unless configured.
+const REPL_STACK_PAGE_SIZE: usize = 100;
+
+fn load_config(path: Option<&str>) -> Result<FiftConfig> {
+    const DEFAULT_CONFIG: &str = "fift.toml";
+
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(DEFAULT_CONFIG).is_file() => DEFAULT_CONFIG,
+        None => return Ok(FiftConfig::default()),
+    };
+
+    let config = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config from `{path}`"))?;
+    toml::from_str(&config).with_context(|| format!("Invalid config `{path}`"))
+}
+
+fn write_crash_bundle(report: &CrashReport, now_ms: u64) -> Result<PathBuf> {
+    let dir = Path::new(".fift-crash").join(now_ms.to_string());
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create `{}`", dir.display()))?;
+
+    if let Some(script) = &report.script {
+        std::fs::write(dir.join("script.fif"), script)?;
+    }
+    let report = serde_json::to_string_pretty(report)?;
+    std::fs::write(dir.join("report.json"), report)?;
+
+    Ok(dir)
+}
+
+fn report_error(ctx: &fift::Context, e: &anyhow::Error, color: bool) -> bool {
+    use ariadne::{Color, Config, Label, Report, ReportKind, Source};
+
+    if let Some(next) = &ctx.next {
+        eprintln!("Backtrace:\n{}\n", next.display_backtrace(&ctx.dictionary));
     }
+
+    let (id, snippet) = match e.downcast_ref::<FiftError>() {
+        Some(e) => (e.source_block.as_str(), e.snippet()),
+        None => match ctx.input.get_position() {
+            Some(pos) => (pos.source_block_name, SourceSnippet::from_position(&pos)),
+            None => return false,
+        },
+    };
+
+    Report::build(ReportKind::Error, id, 0)
+        .with_config(Config::default().with_color(color))
+        .with_message(format!("{e:?}"))
+        .with_label(Label::new((id, snippet.span)).with_color(Color::Red))
+        .finish()
+        .eprint((id, Source::from(snippet.text)))
+        .unwrap();
+
+    true
 }
@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+
+use fift::config::split_include_paths;
+use fift::core::{Environment, SourceBlock};
+
+use crate::env::SystemEnvironment;
+
+/// Reduce a failing script to a minimal one which fails with the same error
+#[derive(FromArgs)]
+#[argh(subcommand, name = "minimize")]
+pub struct CmdMinimize {
+    /// path to the failing script
+    #[argh(positional)]
+    path: String,
+
+    /// do not preload standard preamble file `Fift.fif`
+    #[argh(switch, short = 'n')]
+    bare: bool,
+
+    /// sets color-separated library source include path.
+    /// If not indicated, $FIFTPATH is used instead
+    #[argh(option, short = 'I')]
+    include: Option<String>,
+
+    /// max number of executed words for each run
+    #[argh(option, default = "1000000")]
+    max_steps: u64,
+
+    /// write the minimized script to the specified file instead of stdout
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+impl CmdMinimize {
+    pub fn run(self) -> Result<ExitCode> {
+        let source = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read `{}`", self.path))?;
+
+        let include_dirs = split_include_paths(
+            &self
+                .include
+                .clone()
+                .unwrap_or_else(|| std::env::var("FIFTPATH").unwrap_or_default()),
+        );
+        let runner = Runner {
+            name: self.path.clone(),
+            include_dirs,
+            bare: self.bare,
+            max_steps: self.max_steps,
+        };
+
+        let Some(expected) = runner.run(&source) else {
+            anyhow::bail!("`{}` does not fail", self.path);
+        };
+        eprintln!("Error to reproduce: {expected}");
+
+        let tokens = tokenize(&source);
+        let total = tokens.len();
+        let tokens = ddmin(tokens, |tokens| {
+            runner.run(&join_tokens(tokens)).as_deref() == Some(expected.as_str())
+        });
+        eprintln!("Reduced from {total} to {} tokens", tokens.len());
+
+        let result = join_tokens(&tokens);
+        match &self.output {
+            Some(path) => {
+                std::fs::write(path, result).with_context(|| format!("Failed to write `{path}`"))?
+            }
+            None => print!("{result}"),
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+struct Runner {
+    name: String,
+    include_dirs: Vec<PathBuf>,
+    bare: bool,
+    max_steps: u64,
+}
+
+impl Runner {
+    /// Runs the script, returns the error message if it fails.
+    fn run(&self, source: &str) -> Option<String> {
+        let mut env = SandboxEnvironment(SystemEnvironment::with_include_dirs(
+            self.include_dirs.clone(),
+        ));
+        let mut stdout = std::io::sink();
+
+        let result = (|| {
+            let mut ctx = fift::Context::new(&mut env, &mut stdout)
+                .with_basic_modules()?
+                .with_source_block(SourceBlock::new(
+                    self.name.as_str(),
+                    std::io::Cursor::new(source.to_owned()),
+                ));
+            if !self.bare {
+                ctx.add_source_block(SourceBlock::new(
+                    "<default Fift.fif>",
+                    std::io::Cursor::new(fift::stdlib::FIFT),
+                ));
+            }
+
+            let max_steps = self.max_steps;
+            let mut steps = 0u64;
+            ctx.set_before_word_hook(move |_, _| {
+                steps += 1;
+                anyhow::ensure!(steps <= max_steps, "Step limit exceeded");
+                Ok(())
+            });
+
+            ctx.run()
+        })();
+
+        result.err().map(|e| e.to_string())
+    }
+}
+
+/// Delta debugging which only tries to remove chunks of tokens.
+fn ddmin<F>(mut tokens: Vec<Token>, mut is_failing: F) -> Vec<Token>
+where
+    F: FnMut(&[Token]) -> bool,
+{
+    let mut n = 2;
+    while tokens.len() >= 2 {
+        let chunk_len = (tokens.len() + n - 1) / n;
+
+        let mut reduced = false;
+        for start in (0..tokens.len()).step_by(chunk_len) {
+            let end = std::cmp::min(start + chunk_len, tokens.len());
+            let mut candidate = Vec::with_capacity(tokens.len() - (end - start));
+            candidate.extend_from_slice(&tokens[..start]);
+            candidate.extend_from_slice(&tokens[end..]);
+
+            if is_failing(&candidate) {
+                tokens = candidate;
+                n = std::cmp::max(n - 1, 2);
+                reduced = true;
+                break;
+            }
+        }
+
+        if !reduced {
+            if n >= tokens.len() {
+                break;
+            }
+            n = std::cmp::min(n * 2, tokens.len());
+        }
+    }
+
+    // Try to remove the last token as well
+    if tokens.len() == 1 && is_failing(&[]) {
+        tokens.clear();
+    }
+    tokens
+}
+
+#[derive(Clone)]
+struct Token {
+    text: String,
+    /// Whether the token is the last one in its line.
+    line_end: bool,
+}
+
+/// Splits the source into whitespace separated tokens keeping line breaks,
+/// since line comments and some words depend on them.
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for line in source.lines() {
+        let start = tokens.len();
+        tokens.extend(line.split_whitespace().map(|text| Token {
+            text: text.to_owned(),
+            line_end: false,
+        }));
+        if tokens.len() > start {
+            if let Some(last) = tokens.last_mut() {
+                last.line_end = true;
+            }
+        }
+    }
+    tokens
+}
+
+fn join_tokens(tokens: &[Token]) -> String {
+    let mut result = String::new();
+    for token in tokens {
+        result.push_str(&token.text);
+        result.push(if token.line_end { '\n' } else { ' ' });
+    }
+    result
+}
+
+/// System environment which does not write files.
+struct SandboxEnvironment(SystemEnvironment);
+
+impl Environment for SandboxEnvironment {
+    fn now_ms(&self) -> u64 {
+        self.0.now_ms()
+    }
+
+    fn get_env(&self, name: &str) -> Option<String> {
+        self.0.get_env(name)
+    }
+
+    fn file_exists(&self, name: &str) -> bool {
+        self.0.file_exists(name)
+    }
+
+    fn write_file(&mut self, _: &str, _: &[u8]) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn read_file(&mut self, name: &str) -> std::io::Result<Vec<u8>> {
+        self.0.read_file(name)
+    }
+
+    fn read_file_part(&mut self, name: &str, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+        self.0.read_file_part(name, offset, len)
+    }
+
+    fn include(&self, name: &str) -> std::io::Result<SourceBlock> {
+        self.0.include(name)
+    }
+}
@@ -0,0 +1,79 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator wrapper which counts allocated bytes.
+///
+/// ```ignore
+/// #[global_allocator]
+/// static GLOBAL: fift::alloc::CountingAlloc = fift::alloc::CountingAlloc(std::alloc::System);
+/// ```
+pub struct CountingAlloc<A = System>(pub A);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc(layout);
+        if !ptr.is_null() {
+            add_allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            add_allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.0.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            add_allocated(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn add_allocated(size: usize) {
+    if !INSTALLED.load(Ordering::Relaxed) {
+        INSTALLED.store(true, Ordering::Relaxed);
+    }
+    let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(allocated, Ordering::Relaxed);
+}
+
+/// Heap usage counted by [`CountingAlloc`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Bytes which are currently allocated.
+    pub allocated: usize,
+    /// Max number of bytes allocated at once.
+    pub peak: usize,
+}
+
+impl AllocStats {
+    /// Returns the current stats, or `None` if [`CountingAlloc`]
+    /// is not used as a global allocator.
+    pub fn get() -> Option<Self> {
+        INSTALLED.load(Ordering::Relaxed).then(|| Self {
+            allocated: ALLOCATED.load(Ordering::Relaxed),
+            peak: PEAK.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Starts measuring the peak from the current usage.
+    pub fn reset_peak() {
+        PEAK.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+}
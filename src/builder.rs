@@ -0,0 +1,294 @@
+use std::io::{BufRead, Write};
+
+use ahash::HashMap;
+use anyhow::Result;
+
+use crate::config::FiftConfig;
+use crate::core::cont::{ContextWordFunc, StackWordFunc};
+use crate::core::env::EmptyEnvironment;
+use crate::core::{
+    AsyncEnvironment, Dictionary, Environment, IncludeResolver, Module, SourceBlock,
+    SourceBlockValue, Stack,
+};
+use crate::crash::CrashReport;
+use crate::metrics::Metrics;
+use crate::Context;
+
+type Initializer = Box<dyn Fn(&mut Dictionary) -> Result<()>>;
+
+/// Interpreter for host applications which owns its environment and output.
+///
+/// Each run starts with a fresh context with all registered words and
+/// preloaded libraries.
+pub struct Fift {
+    env: Box<dyn Environment>,
+    stdout: Box<dyn Write>,
+    tees: Vec<Box<dyn Write>>,
+    stdin: Option<Box<dyn BufRead>>,
+    include_resolver: Option<Box<dyn IncludeResolver>>,
+    initializers: Vec<Initializer>,
+    libraries: Vec<SourceBlockValue>,
+    params: HashMap<String, String>,
+    config: FiftConfig,
+    metrics: Option<Metrics>,
+    #[cfg(feature = "stdlib")]
+    stdlib: bool,
+}
+
+impl Fift {
+    pub fn builder() -> FiftBuilder {
+        FiftBuilder::default()
+    }
+
+    /// Returns the counters of all runs, if enabled with [`FiftBuilder::metrics`].
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Resets the counters of all runs.
+    pub fn reset_metrics(&mut self) {
+        if let Some(metrics) = &mut self.metrics {
+            metrics.clear();
+        }
+    }
+
+    /// Interprets the script, returns the resulting stack.
+    pub fn run_script(&mut self, source: &str) -> Result<Stack> {
+        let (mut ctx, metrics) = self.make_context(script_block(source))?;
+        let res = ctx.run();
+        record_run(metrics, &ctx, &res);
+        res?;
+        finish(ctx)
+    }
+
+    /// Interprets the script, fetching included files from the async environment.
+    pub async fn run_script_async(
+        &mut self,
+        source: &str,
+        fetcher: &mut dyn AsyncEnvironment,
+    ) -> Result<Stack> {
+        let (mut ctx, metrics) = self.make_context(script_block(source))?;
+        let res = ctx.run_async(fetcher).await;
+        record_run(metrics, &ctx, &res);
+        res?;
+        finish(ctx)
+    }
+
+    /// Interprets the script, returns a crash report with the interpreter
+    /// state at the moment of failure if it fails.
+    pub fn run_script_with_report(&mut self, source: &str) -> Result<Stack, Box<CrashReport>> {
+        let config = self.config.clone();
+        let make_report =
+            |report: CrashReport| Box::new(report.with_script(source).with_config(config.clone()));
+
+        let (mut ctx, metrics) = self
+            .make_context(script_block(source))
+            .map_err(|e| make_report(CrashReport::new(&e)))?;
+        let res = ctx.run();
+        record_run(metrics, &ctx, &res);
+        if let Err(e) = res {
+            return Err(make_report(CrashReport::capture(&ctx, &e)));
+        }
+        finish(ctx).map_err(|e| make_report(CrashReport::new(&e)))
+    }
+
+    /// Interprets the input which was set with [`FiftBuilder::stdin`].
+    pub fn run_stdin(&mut self) -> Result<Stack> {
+        let stdin = self
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Stdin is not set or was already consumed"))?;
+        let (mut ctx, metrics) = self.make_context(SourceBlock::new("<stdin>", stdin))?;
+        let res = ctx.run();
+        record_run(metrics, &ctx, &res);
+        res?;
+        finish(ctx)
+    }
+
+    fn make_context(&mut self, block: SourceBlock) -> Result<(Context<'_>, Option<&mut Metrics>)> {
+        let mut ctx = Context::new(self.env.as_mut(), &mut self.stdout)
+            .with_basic_modules()?
+            .with_config(&self.config)?;
+        for tee in &mut self.tees {
+            ctx.stdout.add_tee(tee);
+        }
+        if let Some(resolver) = &mut self.include_resolver {
+            ctx.set_include_resolver(|name: &str| resolver.resolve(name));
+        }
+        for init in &self.initializers {
+            init(&mut ctx.dictionary)?;
+        }
+        ctx.params = self.params.clone();
+        #[cfg(feature = "stdlib")]
+        if self.stdlib {
+            ctx = ctx.with_stdlib();
+        }
+        if self.metrics.is_some() && ctx.profiler.is_none() {
+            ctx = ctx.with_profiler();
+        }
+
+        // Source blocks are executed in reverse order
+        ctx.add_source_block(block);
+        for library in self.libraries.iter().rev() {
+            ctx.add_source_block(library.to_source_block());
+        }
+        Ok((ctx, self.metrics.as_mut()))
+    }
+}
+
+fn script_block(source: &str) -> SourceBlock {
+    SourceBlockValue::new("<script>", source).to_source_block()
+}
+
+fn record_run<T>(metrics: Option<&mut Metrics>, ctx: &Context<'_>, res: &Result<T>) {
+    if let Some(metrics) = metrics {
+        metrics.record(ctx, res.is_err());
+    }
+}
+
+fn finish(mut ctx: Context<'_>) -> Result<Stack> {
+    ctx.stdout.flush()?;
+    Ok(std::mem::replace(&mut ctx.stack, Stack::new(None)))
+}
+
+pub struct FiftBuilder {
+    env: Box<dyn Environment>,
+    stdout: Box<dyn Write>,
+    tees: Vec<Box<dyn Write>>,
+    stdin: Option<Box<dyn BufRead>>,
+    include_resolver: Option<Box<dyn IncludeResolver>>,
+    initializers: Vec<Initializer>,
+    libraries: Vec<SourceBlockValue>,
+    params: HashMap<String, String>,
+    config: FiftConfig,
+    metrics: Option<Metrics>,
+    #[cfg(feature = "stdlib")]
+    stdlib: bool,
+}
+
+impl Default for FiftBuilder {
+    fn default() -> Self {
+        Self {
+            env: Box::new(EmptyEnvironment),
+            stdout: Box::new(std::io::stdout()),
+            tees: Vec::new(),
+            stdin: None,
+            include_resolver: None,
+            initializers: Vec::new(),
+            libraries: Vec::new(),
+            params: HashMap::default(),
+            config: FiftConfig::default(),
+            metrics: None,
+            #[cfg(feature = "stdlib")]
+            stdlib: false,
+        }
+    }
+}
+
+impl FiftBuilder {
+    /// Sets the environment, [`EmptyEnvironment`] is used by default.
+    pub fn env<E: Environment + 'static>(mut self, env: E) -> Self {
+        self.env = Box::new(env);
+        self
+    }
+
+    /// Sets the output of printing words, the process stdout is used by default.
+    pub fn stdout<W: Write + 'static>(mut self, stdout: W) -> Self {
+        self.stdout = Box::new(stdout);
+        self
+    }
+
+    /// Duplicates the output of printing words to the specified writer
+    /// (e.g. a log file).
+    pub fn tee<W: Write + 'static>(mut self, writer: W) -> Self {
+        self.tees.push(Box::new(writer));
+        self
+    }
+
+    /// Sets the input for [`Fift::run_stdin`].
+    pub fn stdin<R: BufRead + 'static>(mut self, stdin: R) -> Self {
+        self.stdin = Some(Box::new(stdin));
+        self
+    }
+
+    /// Sets a provider which supplies sources of included files
+    /// (e.g. from a database), the environment is used as a fallback.
+    pub fn include_resolver<R: IncludeResolver + 'static>(mut self, resolver: R) -> Self {
+        self.include_resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Includes embedded library files if they are not found in the environment,
+    /// see [`Context::with_stdlib`].
+    #[cfg(feature = "stdlib")]
+    pub fn stdlib(mut self) -> Self {
+        self.stdlib = true;
+        self
+    }
+
+    /// Enables counters of all runs, see [`Fift::metrics`].
+    ///
+    /// Word timings are collected with a profiler, which slows down the execution.
+    pub fn metrics(mut self) -> Self {
+        self.metrics = Some(Metrics::default());
+        self
+    }
+
+    /// Sets a named input of each script, see `param`.
+    pub fn param<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.params.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn config(mut self, config: FiftConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn module<T: Module + 'static>(mut self, module: T) -> Self {
+        self.initializers
+            .push(Box::new(move |d: &mut Dictionary| module.init(d)));
+        self
+    }
+
+    pub fn word<T: Into<String>>(mut self, name: T, f: ContextWordFunc) -> Self {
+        let name = name.into();
+        self.initializers.push(Box::new(move |d: &mut Dictionary| {
+            d.define_context_word(name.clone(), f)
+        }));
+        self
+    }
+
+    pub fn stack_word<T: Into<String>>(mut self, name: T, f: StackWordFunc) -> Self {
+        let name = name.into();
+        self.initializers.push(Box::new(move |d: &mut Dictionary| {
+            d.define_stack_word(name.clone(), f)
+        }));
+        self
+    }
+
+    /// Adds a library source which is interpreted before each script.
+    ///
+    /// Libraries are interpreted in the order they were added.
+    pub fn library<N: AsRef<str>, S: AsRef<str>>(mut self, name: N, source: S) -> Self {
+        self.libraries.push(SourceBlockValue::new(name, source));
+        self
+    }
+
+    pub fn build(self) -> Fift {
+        Fift {
+            env: self.env,
+            stdout: self.stdout,
+            tees: self.tees,
+            stdin: self.stdin,
+            include_resolver: self.include_resolver,
+            initializers: self.initializers,
+            libraries: self.libraries,
+            params: self.params,
+            config: self.config,
+            metrics: self.metrics,
+            #[cfg(feature = "stdlib")]
+            stdlib: self.stdlib,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::core::{DumpStyle, History};
+
+/// Interpreter settings shared by the CLI and embedders.
+///
+/// Can be loaded from `fift.toml` with the `serde` feature enabled.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields, rename_all = "kebab-case")
+)]
+pub struct FiftConfig {
+    /// Library source include paths.
+    pub include_paths: Vec<PathBuf>,
+    /// An explicit path to the preamble file instead of the default one.
+    pub lib: Option<PathBuf>,
+    /// Do not preload the preamble file.
+    pub bare: bool,
+    pub limits: FiftLimits,
+    pub features: FiftFeatures,
+    /// Radix used by `.` to print integers.
+    pub radix: u32,
+    /// Max number of items printed by `.s` and `.sl` at once, all items if not set.
+    pub stack_page_size: Option<usize>,
+    /// Format of the items printed by `.s`.
+    pub dump_style: DumpStyle,
+    /// Whether to use colors in diagnostics and stack dumps.
+    pub color: bool,
+}
+
+impl Default for FiftConfig {
+    fn default() -> Self {
+        Self {
+            include_paths: Vec::new(),
+            lib: None,
+            bare: false,
+            limits: Default::default(),
+            features: Default::default(),
+            radix: 10,
+            stack_page_size: None,
+            dump_style: DumpStyle::Plain,
+            color: true,
+        }
+    }
+}
+
+impl FiftConfig {
+    /// Overrides settings with environment variables:
+    /// `FIFTPATH` (colon-separated include paths), `FIFT_RADIX` and `NO_COLOR`.
+    ///
+    /// Variables are read with `get_env`, e.g. `|name| std::env::var(name).ok()`.
+    pub fn apply_env<F>(&mut self, get_env: F) -> Result<()>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(paths) = get_env("FIFTPATH") {
+            self.include_paths = split_include_paths(&paths);
+        }
+        if let Some(radix) = get_env("FIFT_RADIX") {
+            self.radix = radix
+                .trim()
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid FIFT_RADIX value `{radix}`"))?;
+        }
+        if get_env("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            self.color = false;
+        }
+        self.validate()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        anyhow::ensure!(
+            (2..=36).contains(&self.radix),
+            "Output radix must be in range 2..=36, got {}",
+            self.radix
+        );
+        anyhow::ensure!(
+            self.stack_page_size != Some(0),
+            "Stack page size must be positive"
+        );
+        Ok(())
+    }
+}
+
+/// Parses a colon-separated list of include paths.
+pub fn split_include_paths(paths: &str) -> Vec<PathBuf> {
+    paths
+        .split(':')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields, rename_all = "kebab-case")
+)]
+pub struct FiftLimits {
+    /// Max number of items on the stack, unlimited if not set.
+    pub stack_depth: Option<usize>,
+    /// Max number of executed continuations, unlimited if not set.
+    pub steps: Option<u64>,
+    /// Max number of interactive history entries.
+    pub history: usize,
+}
+
+impl Default for FiftLimits {
+    fn default() -> Self {
+        Self {
+            stack_depth: None,
+            steps: None,
+            history: History::DEFAULT_CAPACITY,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields, rename_all = "kebab-case")
+)]
+pub struct FiftFeatures {
+    /// Track the stack effect of each input line.
+    pub history: bool,
+    /// Collect coverage of executed word lists.
+    pub coverage: bool,
+    /// Reject deprecated and non-standard words.
+    pub strict: bool,
+    /// Collect time spent in each source block.
+    pub timings: bool,
+    /// Collect the number of calls and time spent in each word.
+    pub profile: bool,
+    /// Report each executed word with the top of the stack.
+    pub trace: bool,
+}
@@ -0,0 +1,107 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+
+use ahash::HashMap;
+use anyhow::Result;
+
+use super::Context;
+
+/// Boxed future returned by [`AsyncEnvironment`] methods.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// Asynchronous source provider for [`Context::run_async`],
+/// e.g. to load library sources over HTTP.
+pub trait AsyncEnvironment {
+    /// Returns the source of the included file, or `None` to include it
+    /// using the include resolver or the environment.
+    fn fetch_include<'a>(
+        &'a mut self,
+        name: &'a str,
+    ) -> BoxFuture<'a, std::io::Result<Option<String>>>;
+}
+
+/// Number of executed continuations between yields to the async runtime.
+const YIELD_INTERVAL: usize = 1024;
+
+#[derive(Default)]
+pub(super) struct AsyncIncludes {
+    pub fetched: HashMap<String, Option<String>>,
+    /// File which must be fetched before the next step.
+    pub pending: Option<String>,
+    /// Fetch error which is returned by the next include.
+    pub error: Option<std::io::Error>,
+}
+
+impl Context<'_> {
+    /// Same as [`Context::run`], but included files are fetched from
+    /// the async environment without blocking the current thread.
+    ///
+    /// The interpreter loop periodically yields to the runtime at word boundaries.
+    ///
+    /// The returned future is not `Send`, since the context holds `Rc` values,
+    /// so it must be polled on the thread which created the context, e.g. with
+    /// a single-threaded runtime or a `LocalSet`.
+    pub async fn run_async(&mut self, env: &mut dyn AsyncEnvironment) -> Result<u8> {
+        let prev_includes = self.async_includes.replace(Default::default());
+
+        let mut run = self.start_run();
+        let mut steps = 0usize;
+        let res = loop {
+            if let Some(res) = self.run_step(&mut run) {
+                break res;
+            }
+
+            let pending = self.async_includes.as_mut().and_then(|i| i.pending.take());
+            if let Some(name) = pending {
+                let fetched = env.fetch_include(&name).await;
+                let includes = self.async_includes.get_or_insert_with(Default::default);
+                match fetched {
+                    Ok(source) => {
+                        includes.fetched.insert(name, source);
+                    }
+                    Err(e) => {
+                        let message = format!("Failed to fetch `{name}`: {e}");
+                        includes.error = Some(std::io::Error::new(e.kind(), message));
+                    }
+                }
+            }
+
+            steps += 1;
+            if steps % YIELD_INTERVAL == 0 {
+                YieldNow(false).await;
+            }
+        };
+
+        self.async_includes = prev_includes;
+        res
+    }
+
+    /// Returns `true` if the file must be fetched by [`Context::run_async`]
+    /// before it is included.
+    pub(crate) fn request_include(&mut self, name: &str) -> bool {
+        let Some(includes) = &mut self.async_includes else {
+            return false;
+        };
+        if includes.fetched.contains_key(name) {
+            return false;
+        }
+        includes.pending = Some(name.to_owned());
+        true
+    }
+}
+
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
@@ -1,10 +1,12 @@
 use std::cell::RefCell;
+use std::io::Write;
 use std::rc::Rc;
 
 use anyhow::Result;
 use num_bigint::BigInt;
 
 use super::{Context, Dictionary, Stack, StackValue, StackValueType, WordList};
+use crate::error::UnclosedBlocks;
 use crate::util::*;
 
 pub type Cont = Rc<dyn ContImpl>;
@@ -21,6 +23,16 @@ pub trait ContImpl {
     fn fmt_dump(&self, d: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.fmt_name(d, f)
     }
+
+    /// Returns the compiled words of a block which was not started yet.
+    fn word_list(&self) -> Option<&WordList> {
+        None
+    }
+
+    /// Returns the values pushed by a literal continuation.
+    fn literals(&self) -> Option<&[Box<dyn StackValue>]> {
+        None
+    }
 }
 
 impl dyn ContImpl + '_ {
@@ -78,6 +90,108 @@ impl dyn ContImpl + '_ {
 
         ContinuationDump { d, cont: self }
     }
+
+    /// Displays a block as an approximation of its Fift source.
+    pub fn display_source<'a>(&'a self, d: &'a Dictionary) -> impl std::fmt::Display + 'a {
+        struct ContinuationSource<'a> {
+            d: &'a Dictionary,
+            cont: &'a dyn ContImpl,
+        }
+
+        impl std::fmt::Display for ContinuationSource<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                SourceNode::from_cont(self.cont, self.d).write(0, f)
+            }
+        }
+
+        ContinuationSource { d, cont: self }
+    }
+}
+
+enum SourceNode {
+    Word(String),
+    Block(Vec<SourceNode>),
+}
+
+impl SourceNode {
+    const MAX_WIDTH: usize = 80;
+    const INDENT: usize = 2;
+
+    fn from_cont(cont: &dyn ContImpl, d: &Dictionary) -> Self {
+        match cont.word_list() {
+            Some(list) => {
+                let mut items = Vec::with_capacity(list.items.len());
+                for item in &list.items {
+                    Self::push_item(item.as_ref(), d, &mut items);
+                }
+                Self::Block(items)
+            }
+            None => Self::Block(vec![Self::Word(cont.display_name(d).to_string())]),
+        }
+    }
+
+    fn push_item(item: &dyn ContImpl, d: &Dictionary, items: &mut Vec<Self>) {
+        let Some(literals) = item.literals() else {
+            items.push(Self::Word(item.display_name(d).to_string()));
+            return;
+        };
+
+        for value in literals {
+            items.push(match value.as_cont() {
+                // Named words are not expanded to keep the output close to the source
+                Ok(cont) if d.resolve_name(cont.as_ref()).is_none() => {
+                    Self::from_cont(cont.as_ref(), d)
+                }
+                Ok(cont) => Self::Block(vec![Self::Word(cont.display_name(d).to_string())]),
+                Err(_) => Self::Word(value.display_dump().to_string()),
+            });
+        }
+    }
+
+    fn flat_len(&self) -> usize {
+        match self {
+            Self::Word(word) => word.len(),
+            Self::Block(items) => items.iter().map(|item| item.flat_len() + 1).sum::<usize>() + 3,
+        }
+    }
+
+    fn write_flat(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word(word) => f.write_str(word),
+            Self::Block(items) => {
+                f.write_str("{")?;
+                for item in items {
+                    f.write_str(" ")?;
+                    item.write_flat(f)?;
+                }
+                f.write_str(" }")
+            }
+        }
+    }
+
+    fn write(&self, indent: usize, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let items = match self {
+            Self::Block(items) if indent + self.flat_len() > Self::MAX_WIDTH => items,
+            _ => return self.write_flat(f),
+        };
+
+        let inner = indent + Self::INDENT;
+        let mut line_len = 0;
+        f.write_str("{")?;
+        for item in items {
+            let len = item.flat_len();
+            if line_len > 0 && inner + line_len + 1 + len <= Self::MAX_WIDTH {
+                f.write_str(" ")?;
+                line_len += 1;
+            } else {
+                write!(f, "\n{:inner$}", "")?;
+                line_len = 0;
+            }
+            item.write(inner, f)?;
+            line_len += len;
+        }
+        write!(f, "\n{:indent$}}}", "")
+    }
 }
 
 pub struct InterpreterCont;
@@ -97,16 +211,29 @@ impl ContImpl for InterpreterCont {
             'token: {
                 let mut rewind = 0;
                 let entry = 'entry: {
+                    if let Some(history) = &mut ctx.history {
+                        ctx.input.scan_skip_whitespace()?;
+                        history.observe(ctx.input.get_position(), &ctx.stack);
+                    }
+
                     let Some(token) = ctx.input.scan_word()? else {
                         if ctx.input.pop_source_block() {
                             continue 'source_block;
                         }
+                        if ctx.state.is_compile() {
+                            anyhow::bail!(UnclosedBlocks {
+                                blocks: ctx.open_blocks.clone(),
+                            });
+                        }
                         return Ok(None);
                     };
 
                     // Find the largest subtoken first
                     for subtoken in token.subtokens() {
                         if let Some(entry) = ctx.dictionary.lookup(subtoken) {
+                            if let Some(strict) = &ctx.strict {
+                                strict.check(subtoken)?;
+                            }
                             rewind = token.delta(subtoken);
                             break 'entry entry;
                         }
@@ -120,6 +247,9 @@ impl ContImpl for InterpreterCont {
                         word.push(' ');
                         ctx.dictionary.lookup(&word)
                     }) {
+                        if let Some(strict) = &ctx.strict {
+                            strict.check(token.data)?;
+                        }
                         break 'entry entry;
                     }
 
@@ -129,6 +259,9 @@ impl ContImpl for InterpreterCont {
                         if let Some(denom) = value.denom {
                             ctx.stack.push(denom)?;
                             ctx.stack.push_argcount(2, ctx.dictionary.make_nop())?;
+                        } else if let Some(width) = value.width {
+                            ctx.stack.push_int(width)?;
+                            ctx.stack.push_argcount(2, ctx.dictionary.make_nop())?;
                         } else {
                             ctx.stack.push_argcount(1, ctx.dictionary.make_nop())?;
                         }
@@ -193,9 +326,15 @@ impl ContImpl for ListCont {
     fn run(mut self: Rc<Self>, ctx: &mut Context) -> Result<Option<Cont>> {
         let is_last = self.pos + 1 >= self.list.items.len();
         let Some(current) = self.list.items.get(self.pos).cloned() else {
-            return Ok(ctx.next.take())
+            return Ok(ctx.next.take());
         };
 
+        if let Some(coverage) = &mut ctx.coverage {
+            if let Some(Some(pos)) = self.list.positions.get(self.pos) {
+                coverage.hit(pos);
+            }
+        }
+
         match Rc::get_mut(&mut self) {
             Some(this) => {
                 ctx.insert_before_next(&mut this.after);
@@ -236,6 +375,10 @@ impl ContImpl for ListCont {
         write_cont_name(self, d, f)
     }
 
+    fn word_list(&self) -> Option<&WordList> {
+        (self.pos == 0).then_some(self.list.as_ref())
+    }
+
     fn fmt_dump(&self, d: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.pos == 0 {
             f.write_str("{")?;
@@ -251,7 +394,7 @@ impl ContImpl for ListCont {
             }
 
             let len = self.list.items.len();
-            let start = if self.pos >= N { self.pos - N } else { 0 };
+            let start = self.pos.saturating_sub(N);
             let items = self.list.items.iter();
 
             if start > 0 {
@@ -546,6 +689,10 @@ impl ContImpl for LitCont {
     fn fmt_name(&self, d: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write_lit_cont_name(self.0.as_ref(), d, f)
     }
+
+    fn literals(&self) -> Option<&[Box<dyn StackValue>]> {
+        Some(std::slice::from_ref(&self.0))
+    }
 }
 
 pub struct MultiLitCont(pub Vec<Box<dyn StackValue>>);
@@ -579,6 +726,86 @@ impl ContImpl for MultiLitCont {
         }
         Ok(())
     }
+
+    fn literals(&self) -> Option<&[Box<dyn StackValue>]> {
+        Some(&self.0)
+    }
+}
+
+/// Runs a parser word and uses its result as a literal.
+pub struct LiteralPrefixCont(pub Cont);
+
+impl ContImpl for LiteralPrefixCont {
+    fn run(self: Rc<Self>, ctx: &mut Context) -> Result<Option<Cont>> {
+        thread_local! {
+            static FINISH: Cont = Rc::new((|ctx| {
+                ctx.stack.check_underflow(1)?;
+                ctx.stack.push_argcount(1, ctx.dictionary.make_nop())
+            }) as ContextWordFunc);
+        };
+
+        ctx.next = SeqCont::make(Some(FINISH.with(|c| c.clone())), ctx.next.take());
+        Ok(Some(self.0.clone()))
+    }
+
+    fn fmt_name(&self, d: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<literal prefix> ")?;
+        self.0.fmt_name(d, f)
+    }
+}
+
+/// Removes the innermost `try` handler after its body has finished.
+pub struct ExcQuitCont;
+
+impl ContImpl for ExcQuitCont {
+    fn run(self: Rc<Self>, ctx: &mut Context) -> Result<Option<Cont>> {
+        ctx.pop_exception_handler();
+        ctx.stack.push_bool(false)?;
+        Ok(None)
+    }
+
+    fn fmt_name(&self, _: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<exception quit continuation>")
+    }
+}
+
+/// Makes the vocabulary the first searched one.
+pub struct VocabularyCont(pub String);
+
+impl ContImpl for VocabularyCont {
+    fn run(self: Rc<Self>, ctx: &mut Context) -> Result<Option<Cont>> {
+        ctx.dictionary.set_top_vocabulary(&self.0)?;
+        Ok(None)
+    }
+
+    fn fmt_name(&self, d: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_cont_name(self, d, f)
+    }
+}
+
+/// Deprecated name of a word which warns once before running the new one.
+pub struct DeprecatedAliasCont {
+    pub old_name: String,
+    pub new_name: String,
+    pub target: Cont,
+    pub warned: std::cell::Cell<bool>,
+}
+
+impl ContImpl for DeprecatedAliasCont {
+    fn run(self: Rc<Self>, ctx: &mut Context) -> Result<Option<Cont>> {
+        if !self.warned.replace(true) {
+            ctx.env.warn(&format!(
+                "word `{}` is deprecated, use `{}` instead",
+                self.old_name.trim_end(),
+                self.new_name.trim_end()
+            ));
+        }
+        Ok(Some(self.target.clone()))
+    }
+
+    fn fmt_name(&self, d: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.target.fmt_name(d, f)
+    }
 }
 
 pub type ContextWordFunc = fn(&mut Context) -> Result<()>;
@@ -619,10 +846,10 @@ impl ContImpl for StackWordFunc {
     }
 }
 
-/// === impl Context ===
+// === impl Context ===
 
 impl Context<'_> {
-    fn insert_before_next(&mut self, cont: &mut Option<Cont>) {
+    pub(crate) fn insert_before_next(&mut self, cont: &mut Option<Cont>) {
         if let Some(next) = self.next.take() {
             *cont = match cont.take() {
                 Some(prev) => Some(Rc::new(SeqCont {
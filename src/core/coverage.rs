@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use super::lexer::LexerPosition;
+
+/// Line coverage of compiled word lists.
+#[derive(Default)]
+pub struct Coverage {
+    sources: BTreeMap<Rc<str>, BTreeMap<usize, u64>>,
+}
+
+impl Coverage {
+    /// Registers a compiled word list item at the specified position.
+    pub fn register(&mut self, pos: Option<LexerPosition<'_>>) -> Option<WordPosition> {
+        let pos = pos?;
+        let source = match self.sources.get_key_value(pos.source_block_name) {
+            Some((source, _)) => source.clone(),
+            None => Rc::from(pos.source_block_name),
+        };
+
+        self.sources
+            .entry(source.clone())
+            .or_default()
+            .entry(pos.line_number)
+            .or_default();
+
+        Some(WordPosition {
+            source,
+            line_number: pos.line_number,
+        })
+    }
+
+    /// Marks the word list item at the specified position as executed.
+    pub fn hit(&mut self, pos: &WordPosition) {
+        if let Some(lines) = self.sources.get_mut(&pos.source) {
+            *lines.entry(pos.line_number).or_default() += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.sources.clear();
+    }
+
+    /// Displays the collected coverage as an lcov tracefile.
+    pub fn display_lcov(&self) -> impl std::fmt::Display + '_ {
+        struct DisplayLcov<'a>(&'a Coverage);
+
+        impl std::fmt::Display for DisplayLcov<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                writeln!(f, "TN:")?;
+                for (source, lines) in &self.0.sources {
+                    writeln!(f, "SF:{source}")?;
+                    for (line, hits) in lines {
+                        writeln!(f, "DA:{line},{hits}")?;
+                    }
+                    let hit = lines.values().filter(|hits| **hits > 0).count();
+                    writeln!(f, "LF:{}", lines.len())?;
+                    writeln!(f, "LH:{hit}")?;
+                    writeln!(f, "end_of_record")?;
+                }
+                Ok(())
+            }
+        }
+
+        DisplayLcov(self)
+    }
+}
+
+/// Source position of the compiled word list item.
+#[derive(Debug, Clone)]
+pub struct WordPosition {
+    pub source: Rc<str>,
+    pub line_number: usize,
+}
@@ -0,0 +1,108 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use super::cont::{Cont, ContImpl};
+use super::Context;
+
+/// Breakpoints and stepping state of the interpreter.
+///
+/// The execution is only paused when a [`DebugHook`] is set.
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: Vec<(String, Cont)>,
+    stepping: bool,
+    paused: bool,
+}
+
+impl Debugger {
+    /// Pauses the execution before the word definition is executed.
+    pub fn set_breakpoint<T: Into<String>>(&mut self, name: T, definition: Cont) {
+        let name = name.into();
+        self.breakpoints.retain(|(item, _)| *item != name);
+        self.breakpoints.push((name, definition));
+    }
+
+    pub fn remove_breakpoint(&mut self, name: &str) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|(item, _)| item != name);
+        self.breakpoints.len() != len
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = &str> + '_ {
+        self.breakpoints.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Pauses the execution before the next continuation.
+    pub fn step(&mut self) {
+        self.stepping = true;
+        self.paused = false;
+    }
+
+    /// Runs until the next breakpoint.
+    pub fn resume(&mut self) {
+        self.stepping = false;
+        self.paused = false;
+    }
+
+    /// Returns whether the execution is paused and waits for `step` or `continue`.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn pause_reason(&self, cont: &dyn ContImpl) -> Option<PauseReason> {
+        if self.stepping {
+            return Some(PauseReason::Step);
+        }
+
+        // Erase trait data from fat pointers
+        let cont = cont as *const dyn ContImpl as *const ();
+        self.breakpoints.iter().find_map(|(name, definition)| {
+            std::ptr::eq(Rc::as_ptr(definition) as *const (), cont)
+                .then(|| PauseReason::Breakpoint(name.clone()))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PauseReason {
+    Breakpoint(String),
+    Step,
+}
+
+impl std::fmt::Display for PauseReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Breakpoint(name) => write!(f, "breakpoint `{name}`"),
+            Self::Step => f.write_str("step"),
+        }
+    }
+}
+
+/// Handler of paused executions, e.g. an interactive sub-REPL.
+pub trait DebugHook {
+    /// Called before the continuation is executed.
+    ///
+    /// The hook can inspect and modify the context and evaluate code
+    /// with [`Context::eval_str`]. The execution is continued after
+    /// the hook returns, an error aborts it.
+    fn on_pause(&mut self, ctx: &mut Context, cont: &Cont, reason: &PauseReason) -> Result<()>;
+}
+
+impl Context<'_> {
+    pub(crate) fn pause(&mut self, cont: &Cont) -> Result<()> {
+        let Some(reason) = self.debugger.pause_reason(cont.as_ref()) else {
+            return Ok(());
+        };
+
+        // The hook is taken to prevent pauses while it evaluates code
+        let Some(mut hook) = self.debug_hook.take() else {
+            return Ok(());
+        };
+        self.debugger.paused = true;
+        let res = hook.on_pause(self, cont, &reason);
+        self.debugger.paused = false;
+        self.debug_hook = Some(hook);
+        res
+    }
+}
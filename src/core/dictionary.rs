@@ -3,11 +3,18 @@ use std::rc::Rc;
 
 use anyhow::Result;
 
-use super::cont::{Cont, ContImpl, ContextTailWordFunc, ContextWordFunc, StackWordFunc};
+use super::cont::{
+    Cont, ContImpl, ContextTailWordFunc, ContextWordFunc, DeprecatedAliasCont, LiteralPrefixCont,
+    StackWordFunc,
+};
+use super::lexer::LexerPosition;
 
+#[derive(Clone)]
 pub struct DictionaryEntry {
     pub definition: Cont,
     pub active: bool,
+    /// Where the word was defined by a script, if known.
+    pub location: Option<WordLocation>,
 }
 
 impl DictionaryEntry {
@@ -15,6 +22,7 @@ impl DictionaryEntry {
         Self {
             definition,
             active: false,
+            location: None,
         }
     }
 
@@ -22,12 +30,52 @@ impl DictionaryEntry {
         Self {
             definition,
             active: true,
+            location: None,
         }
     }
 }
 
+/// Source position of a word definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordLocation {
+    pub source_block: String,
+    pub line_number: usize,
+    /// Column of the word name, starting from 1.
+    pub column: usize,
+}
+
+impl WordLocation {
+    pub fn new(pos: &LexerPosition<'_>) -> Self {
+        let start = std::cmp::min(pos.line_offset_start, pos.line.len());
+        Self {
+            source_block: pos.source_block_name.to_owned(),
+            line_number: pos.line_number,
+            column: pos.line[..start].chars().count() + 1,
+        }
+    }
+}
+
+impl std::fmt::Display for WordLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.source_block, self.line_number, self.column
+        )
+    }
+}
+
+#[derive(Clone)]
 pub struct Dictionary {
+    /// Words of the root vocabulary.
     words: WordsMap,
+    vocabularies: HashMap<String, WordsMap>,
+    /// Vocabularies which are searched before the root one, from the last.
+    search_order: Vec<String>,
+    /// Vocabulary for new definitions, the root one if not set.
+    current: Option<String>,
+    /// Names of the defined words by their definitions, in order of definition.
+    names: HashMap<*const (), Vec<String>>,
     nop: Cont,
 }
 
@@ -51,6 +99,10 @@ impl Default for Dictionary {
 
         Self {
             words: Default::default(),
+            vocabularies: Default::default(),
+            search_order: Vec::new(),
+            current: None,
+            names: Default::default(),
             nop: Rc::new(NopCont),
         }
     }
@@ -67,21 +119,25 @@ impl Dictionary {
         std::ptr::eq(left, right)
     }
 
+    /// Name of the vocabulary which is always searched last.
+    pub const ROOT_VOCABULARY: &'static str = "forth";
+
     pub fn lookup(&self, name: &str) -> Option<&DictionaryEntry> {
+        for vocabulary in self.search_order.iter().rev() {
+            if let Some(entry) = self
+                .vocabulary(vocabulary)
+                .and_then(|words| words.get(name))
+            {
+                return Some(entry);
+            }
+        }
         self.words.get(name)
     }
 
+    /// Returns the first defined name of the word.
     pub fn resolve_name(&self, definition: &dyn ContImpl) -> Option<&str> {
-        for (name, entry) in &self.words {
-            // NOTE: erase trait data from fat pointers
-            let left = Rc::as_ptr(&entry.definition) as *const ();
-            let right = definition as *const _ as *const ();
-            // Compare only the address part
-            if std::ptr::eq(left, right) {
-                return Some(name);
-            }
-        }
-        None
+        let names = self.names.get(&cont_addr(definition))?;
+        names.first().map(String::as_str)
     }
 
     pub fn define_context_word<T: Into<String>>(
@@ -94,6 +150,7 @@ impl Dictionary {
             DictionaryEntry {
                 definition: Rc::new(f),
                 active: false,
+                location: None,
             },
             false,
         )
@@ -109,6 +166,7 @@ impl Dictionary {
             DictionaryEntry {
                 definition: Rc::new(f),
                 active: false,
+                location: None,
             },
             false,
         )
@@ -124,6 +182,7 @@ impl Dictionary {
             DictionaryEntry {
                 definition: Rc::new(f),
                 active: true,
+                location: None,
             },
             false,
         )
@@ -135,11 +194,144 @@ impl Dictionary {
             DictionaryEntry {
                 definition: Rc::new(f),
                 active: false,
+                location: None,
             },
             false,
         )
     }
 
+    /// Defines an active prefix which runs the `parser` word when a token
+    /// starts with it. The parser must leave exactly one value on the stack.
+    pub fn define_literal_prefix<T: Into<String>>(
+        &mut self,
+        prefix: T,
+        parser: Cont,
+    ) -> Result<()> {
+        self.define_word(
+            prefix,
+            DictionaryEntry::new_active(Rc::new(LiteralPrefixCont(parser))),
+            true,
+        )
+    }
+
+    /// Defines `old` as another name of the existing word `new`.
+    ///
+    /// Names are used as is, so ordinary words must include a trailing space.
+    pub fn define_alias(&mut self, old: &str, new: &str) -> Result<()> {
+        let entry = self.alias_target(old, new)?;
+        self.define_word(old, entry, false)
+    }
+
+    /// Same as [`define_alias`], but the first usage of `old`
+    /// reports a deprecation warning through [`Environment::warn`].
+    ///
+    /// [`define_alias`]: Self::define_alias
+    /// [`Environment::warn`]: super::Environment::warn
+    pub fn define_deprecated_alias(&mut self, old: &str, new: &str) -> Result<()> {
+        let target = self.alias_target(old, new)?;
+        let entry = DictionaryEntry {
+            definition: Rc::new(DeprecatedAliasCont {
+                old_name: old.to_owned(),
+                new_name: new.to_owned(),
+                target: target.definition,
+                warned: Default::default(),
+            }),
+            active: target.active,
+            location: target.location,
+        };
+        self.define_word(old, entry, false)
+    }
+
+    fn alias_target(&self, old: &str, new: &str) -> Result<DictionaryEntry> {
+        match self.lookup(new) {
+            Some(entry) => Ok(entry.clone()),
+            None => anyhow::bail!("Undefined word `{new}` for alias `{old}`"),
+        }
+    }
+
+    // === Vocabularies ===
+
+    pub fn define_vocabulary<T: Into<String>>(&mut self, name: T) -> Result<()> {
+        let name = name.into();
+        anyhow::ensure!(
+            name != Self::ROOT_VOCABULARY && !self.vocabularies.contains_key(&name),
+            "Vocabulary `{name}` already exists"
+        );
+        self.vocabularies.insert(name, Default::default());
+        Ok(())
+    }
+
+    pub fn has_vocabulary(&self, name: &str) -> bool {
+        self.vocabulary(name).is_some()
+    }
+
+    /// Returns vocabularies which are searched before the root one,
+    /// from the first searched.
+    pub fn search_order(&self) -> impl Iterator<Item = &str> + '_ {
+        self.search_order.iter().rev().map(String::as_str)
+    }
+
+    /// Replaces the first searched vocabulary.
+    pub fn set_top_vocabulary(&mut self, name: &str) -> Result<()> {
+        anyhow::ensure!(self.has_vocabulary(name), "Unknown vocabulary `{name}`");
+        match self.search_order.last_mut() {
+            Some(top) => *top = name.to_owned(),
+            // The root vocabulary is always searched
+            None if name == Self::ROOT_VOCABULARY => {}
+            None => self.search_order.push(name.to_owned()),
+        }
+        Ok(())
+    }
+
+    /// Duplicates the first searched vocabulary, so that it is
+    /// kept in the search order after [`set_top_vocabulary`].
+    ///
+    /// [`set_top_vocabulary`]: Self::set_top_vocabulary
+    pub fn also(&mut self) {
+        let top = self
+            .search_order
+            .last()
+            .cloned()
+            .unwrap_or_else(|| Self::ROOT_VOCABULARY.to_owned());
+        self.search_order.push(top);
+    }
+
+    /// Removes the first searched vocabulary.
+    pub fn previous(&mut self) -> Result<()> {
+        anyhow::ensure!(
+            self.search_order.pop().is_some(),
+            "Vocabulary search order is empty"
+        );
+        Ok(())
+    }
+
+    /// Leaves only the root vocabulary in the search order.
+    pub fn only(&mut self) {
+        self.search_order.clear();
+    }
+
+    /// Makes the first searched vocabulary the one for new definitions.
+    pub fn definitions(&mut self) {
+        self.current = self
+            .search_order
+            .last()
+            .filter(|name| *name != Self::ROOT_VOCABULARY)
+            .cloned();
+    }
+
+    /// Returns the vocabulary for new definitions.
+    pub fn current_vocabulary(&self) -> &str {
+        self.current.as_deref().unwrap_or(Self::ROOT_VOCABULARY)
+    }
+
+    fn vocabulary(&self, name: &str) -> Option<&WordsMap> {
+        if name == Self::ROOT_VOCABULARY {
+            Some(&self.words)
+        } else {
+            self.vocabularies.get(name)
+        }
+    }
+
     pub fn define_word<T: Into<String>>(
         &mut self,
         name: T,
@@ -151,25 +343,110 @@ impl Dictionary {
             name: String,
             word: DictionaryEntry,
             allow_redefine: bool,
-        ) -> Result<()> {
+        ) -> Result<Option<DictionaryEntry>> {
             match words.entry(name.clone()) {
                 hash_map::Entry::Vacant(entry) => {
                     entry.insert(word);
-                    Ok(())
+                    Ok(None)
                 }
                 hash_map::Entry::Occupied(mut entry) if allow_redefine => {
-                    entry.insert(word);
-                    Ok(())
+                    Ok(Some(entry.insert(word)))
                 }
                 _ => anyhow::bail!("Word `{name}` unexpectedly redefined"),
             }
         }
-        define_word_impl(&mut self.words, name.into(), word, allow_redefine)
+        let words = match &self.current {
+            Some(current) => self
+                .vocabularies
+                .get_mut(current)
+                .expect("current vocabulary must exist"),
+            None => &mut self.words,
+        };
+
+        let name = name.into();
+        let definition = cont_addr(word.definition.as_ref());
+        if let Some(prev) = define_word_impl(words, name.clone(), word, allow_redefine)? {
+            self.remove_name(&prev, &name);
+        }
+        self.names.entry(definition).or_default().push(name);
+        Ok(())
+    }
+
+    /// Defines a word in the specified vocabulary instead of the current one.
+    pub fn define_word_in<T: Into<String>>(
+        &mut self,
+        vocabulary: &str,
+        name: T,
+        word: DictionaryEntry,
+        allow_redefine: bool,
+    ) -> Result<()> {
+        anyhow::ensure!(
+            self.has_vocabulary(vocabulary),
+            "Unknown vocabulary `{vocabulary}`"
+        );
+        let current = (vocabulary != Self::ROOT_VOCABULARY).then(|| vocabulary.to_owned());
+        let current = std::mem::replace(&mut self.current, current);
+        let res = self.define_word(name, word, allow_redefine);
+        self.current = current;
+        res
+    }
+
+    /// Returns all words with their vocabulary names, in no particular order.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, &DictionaryEntry)> + '_ {
+        let root = self
+            .words
+            .iter()
+            .map(|(name, entry)| (Self::ROOT_VOCABULARY, name.as_str(), entry));
+        let vocabularies = self.vocabularies.iter().flat_map(|(vocabulary, words)| {
+            words
+                .iter()
+                .map(move |(name, entry)| (vocabulary.as_str(), name.as_str(), entry))
+        });
+        root.chain(vocabularies)
     }
 
+    /// Removes the first found word with the specified name.
     pub fn undefine_word(&mut self, name: &str) -> bool {
-        self.words.remove(name).is_some()
+        let mut removed = None;
+        for vocabulary in self.search_order.iter().rev() {
+            let words = match self.vocabularies.get_mut(vocabulary) {
+                Some(words) => words,
+                None => &mut self.words,
+            };
+            removed = words.remove(name);
+            if removed.is_some() {
+                break;
+            }
+        }
+        if removed.is_none() {
+            removed = self.words.remove(name);
+        }
+
+        match removed {
+            Some(entry) => {
+                self.remove_name(&entry, name);
+                true
+            }
+            None => false,
+        }
     }
+
+    fn remove_name(&mut self, entry: &DictionaryEntry, name: &str) {
+        let addr = cont_addr(entry.definition.as_ref());
+        if let hash_map::Entry::Occupied(mut names) = self.names.entry(addr) {
+            if let Some(i) = names.get().iter().position(|item| item == name) {
+                names.get_mut().remove(i);
+            }
+            if names.get().is_empty() {
+                names.remove();
+            }
+        }
+    }
+}
+
+// NOTE: erase trait data from fat pointers to compare only the address part
+fn cont_addr(cont: &dyn ContImpl) -> *const () {
+    cont as *const dyn ContImpl as *const ()
 }
 
 type WordsMap = HashMap<String, DictionaryEntry>;
@@ -0,0 +1,156 @@
+use everscale_types::cell::CellType;
+
+use super::dictionary::Dictionary;
+use super::stack::{StackValue, StackValueType};
+use crate::format::DisplayInt;
+
+/// Format of the stack items printed by `.s`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum DumpStyle {
+    /// Same output as the reference implementation.
+    #[default]
+    Plain,
+    /// Cells as truncated hashes, slices and builders with their sizes,
+    /// continuations with the names of dictionary words.
+    Pretty,
+    /// Same as [`DumpStyle::Pretty`] with ANSI colors for each type.
+    Colored,
+}
+
+impl std::str::FromStr for DumpStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Self::Plain),
+            "pretty" => Ok(Self::Pretty),
+            "colored" => Ok(Self::Colored),
+            _ => Err(format!(
+                "unknown dump style `{s}`, expected `plain`, `pretty` or `colored`"
+            )),
+        }
+    }
+}
+
+impl dyn StackValue + '_ {
+    /// Displays the value in the specified style, like `.s` does.
+    pub fn display_styled<'a>(
+        &'a self,
+        d: &'a Dictionary,
+        style: DumpStyle,
+    ) -> impl std::fmt::Display + 'a {
+        struct DisplayStyled<'a> {
+            value: &'a dyn StackValue,
+            d: &'a Dictionary,
+            style: DumpStyle,
+        }
+
+        impl std::fmt::Display for DisplayStyled<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self.style {
+                    DumpStyle::Plain => self.value.fmt_dump(f),
+                    DumpStyle::Pretty => fmt_pretty(self.value, self.d, false, f),
+                    DumpStyle::Colored => fmt_pretty(self.value, self.d, true, f),
+                }
+            }
+        }
+
+        DisplayStyled {
+            value: self,
+            d,
+            style,
+        }
+    }
+}
+
+fn fmt_pretty(
+    value: &dyn StackValue,
+    d: &Dictionary,
+    colored: bool,
+    f: &mut std::fmt::Formatter<'_>,
+) -> std::fmt::Result {
+    const HASH_PREFIX_LEN: usize = 4;
+
+    let ty = value.ty();
+    if let Ok(tuple) = value.as_tuple() {
+        if tuple.is_empty() {
+            return f.write_str("[]");
+        }
+        f.write_str("[")?;
+        for item in tuple {
+            f.write_str(" ")?;
+            fmt_pretty(item.as_ref(), d, colored, f)?;
+        }
+        return f.write_str(" ]");
+    }
+
+    let color = match ty {
+        StackValueType::Null => "\x1b[2m",
+        StackValueType::Int => "\x1b[36m",
+        StackValueType::String => "\x1b[32m",
+        StackValueType::Bytes => "\x1b[35m",
+        StackValueType::Cell | StackValueType::Builder | StackValueType::Slice => "\x1b[33m",
+        StackValueType::Cont | StackValueType::WordList => "\x1b[34m",
+        _ => "",
+    };
+    if colored && !color.is_empty() {
+        f.write_str(color)?;
+    }
+
+    match ty {
+        StackValueType::Null => f.write_str("(null)")?,
+        StackValueType::Int => match value.as_int() {
+            Ok(int) => std::fmt::Display::fmt(&DisplayInt::new(int, 10), f)?,
+            Err(_) => value.fmt_dump(f)?,
+        },
+        StackValueType::Cell => match value.as_cell() {
+            Ok(cell) => {
+                f.write_str("C{")?;
+                if cell.cell_type() != CellType::Ordinary {
+                    write!(f, "{:?}:", cell.cell_type())?;
+                }
+                for byte in &cell.repr_hash().as_array()[..HASH_PREFIX_LEN] {
+                    write!(f, "{byte:02x}")?;
+                }
+                f.write_str("..}")?;
+            }
+            Err(_) => value.fmt_dump(f)?,
+        },
+        StackValueType::Builder => match value.as_builder() {
+            Ok(builder) => write!(
+                f,
+                "BC{{bits={}, refs={}}}",
+                builder.bit_len(),
+                builder.references().len()
+            )?,
+            Err(_) => value.fmt_dump(f)?,
+        },
+        StackValueType::Slice => match value.as_slice() {
+            Ok(slice) => write!(
+                f,
+                "CS{{bits={}, refs={}}}",
+                slice.remaining_bits(),
+                slice.remaining_refs()
+            )?,
+            Err(_) => value.fmt_dump(f)?,
+        },
+        StackValueType::Cont => match value.as_cont() {
+            Ok(cont) => match d.resolve_name(cont.as_ref()) {
+                Some(name) => write!(f, "Cont{{{}}}", name.trim_end())?,
+                None => write!(f, "Cont{{{}}}", cont.display_name(d))?,
+            },
+            Err(_) => value.fmt_dump(f)?,
+        },
+        _ => value.fmt_dump(f)?,
+    }
+
+    if colored && !color.is_empty() {
+        f.write_str("\x1b[0m")?;
+    }
+    Ok(())
+}
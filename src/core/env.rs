@@ -1,4 +1,9 @@
-use std::io::BufRead;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use super::lexer::OpenBlocks;
 
 pub trait Environment {
     fn now_ms(&self) -> u64;
@@ -14,18 +19,128 @@ pub trait Environment {
     fn read_file_part(&mut self, name: &str, offset: u64, len: u64) -> std::io::Result<Vec<u8>>;
 
     fn include(&self, name: &str) -> std::io::Result<SourceBlock>;
+
+    /// Creates (or truncates) a file which is written incrementally,
+    /// e.g. an output log.
+    fn create_file(&mut self, name: &str) -> std::io::Result<Box<dyn Write>> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("cannot create `{name}` in this environment"),
+        ))
+    }
+
+    /// Reports a non-fatal diagnostic, e.g. a deprecated word usage.
+    ///
+    /// Ignored by default.
+    fn warn(&mut self, message: &str) {
+        let _ = message;
+    }
+
+    /// Reads a line from the user, e.g. a passphrase without echoing it
+    /// if `secret` is set. Returns `None` if the input is closed.
+    fn ask(&mut self, prompt: &str, secret: bool) -> std::io::Result<Option<String>> {
+        let _ = (prompt, secret);
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "cannot read user input in this environment",
+        ))
+    }
+
+    /// Reports an executed word in the trace mode.
+    ///
+    /// Ignored by default.
+    fn trace(&mut self, message: &str) {
+        let _ = message;
+    }
+
+    /// Returns a signer which holds the private key outside of the interpreter.
+    fn signer(&mut self) -> Option<&mut dyn Signer> {
+        None
+    }
+
+    /// Returns a storage of private keys, e.g. the OS credential store.
+    fn keychain(&mut self) -> Option<&mut dyn Keychain> {
+        None
+    }
+}
+
+/// Source provider which is asked before the environment when a file
+/// is included, e.g. to load sources from a database in a compilation service.
+pub trait IncludeResolver {
+    /// Returns the source of the file, or `None` to include it from the environment.
+    fn resolve(&mut self, name: &str) -> std::io::Result<Option<String>>;
+}
+
+impl<F> IncludeResolver for F
+where
+    F: FnMut(&str) -> std::io::Result<Option<String>>,
+{
+    fn resolve(&mut self, name: &str) -> std::io::Result<Option<String>> {
+        self(name)
+    }
+}
+
+/// Ed25519 signer supplied by the embedder (e.g. an HSM or a ledger bridge).
+pub trait Signer {
+    fn public_key(&self) -> [u8; 32];
+
+    fn sign(&mut self, data: &[u8]) -> std::io::Result<[u8; 64]>;
+}
+
+/// Named secrets storage supplied by the embedder (e.g. the OS keychain),
+/// so that private keys are not kept in plaintext files.
+pub trait Keychain {
+    fn store(&mut self, name: &str, secret: &[u8]) -> std::io::Result<()>;
+
+    /// Returns `None` if there is no secret with this name.
+    fn load(&mut self, name: &str) -> std::io::Result<Option<Vec<u8>>>;
+}
+
+/// Keychain which keeps secrets in memory, e.g. for tests
+/// or headless environments without a credential store.
+#[derive(Default, Clone)]
+pub struct MemoryKeychain {
+    secrets: HashMap<String, Vec<u8>>,
+}
+
+impl Keychain for MemoryKeychain {
+    fn store(&mut self, name: &str, secret: &[u8]) -> std::io::Result<()> {
+        self.secrets.insert(name.to_owned(), secret.to_vec());
+        Ok(())
+    }
+
+    fn load(&mut self, name: &str) -> std::io::Result<Option<Vec<u8>>> {
+        Ok(self.secrets.get(name).cloned())
+    }
 }
 
 pub struct SourceBlock {
     name: String,
-    buffer: Box<dyn BufRead>,
+    buffer: SourceBuffer,
 }
 
 impl SourceBlock {
     pub fn new<N: Into<String>, B: BufRead + 'static>(name: N, buffer: B) -> Self {
         Self {
             name: name.into(),
-            buffer: Box::new(buffer),
+            buffer: SourceBuffer::Reader(Box::new(buffer)),
+        }
+    }
+
+    /// Creates a source block which reads lines from the interactive editor.
+    ///
+    /// Lines are passed to the lexer only when all `{ ... }` blocks,
+    /// string literals and comments are closed.
+    pub fn interactive<N: Into<String>, E: LineEditor + 'static>(name: N, editor: E) -> Self {
+        Self {
+            name: name.into(),
+            buffer: SourceBuffer::Interactive(InteractiveBuffer {
+                editor: Box::new(editor),
+                line: String::new(),
+                offset: 0,
+                blocks: OpenBlocks::default(),
+                finished: false,
+            }),
         }
     }
 
@@ -33,8 +148,126 @@ impl SourceBlock {
         &self.name
     }
 
+    pub fn is_interactive(&self) -> bool {
+        matches!(&self.buffer, SourceBuffer::Interactive(_))
+    }
+
     pub fn buffer_mut(&mut self) -> &mut dyn BufRead {
-        &mut self.buffer
+        match &mut self.buffer {
+            SourceBuffer::Reader(reader) => reader,
+            SourceBuffer::Interactive(buffer) => buffer,
+        }
+    }
+}
+
+enum SourceBuffer {
+    Reader(Box<dyn BufRead>),
+    Interactive(InteractiveBuffer),
+}
+
+/// Line editor for the interactive input (e.g. a terminal with history).
+pub trait LineEditor {
+    /// Reads the next line without the trailing newline.
+    ///
+    /// `continuation` is set when the previous lines have an unclosed block.
+    fn read_line(&mut self, continuation: bool) -> std::io::Result<ReadLine>;
+}
+
+pub enum ReadLine {
+    Line(String),
+    /// Discards the unfinished block, or stops reading if there is none.
+    Interrupted,
+    Eof,
+}
+
+struct InteractiveBuffer {
+    editor: Box<dyn LineEditor>,
+    line: String,
+    offset: usize,
+    blocks: OpenBlocks,
+    finished: bool,
+}
+
+impl std::io::Read for InteractiveBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = {
+            let mut rem = self.fill_buf()?;
+            rem.read(buf)?
+        };
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for InteractiveBuffer {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.offset >= self.line.len() && !self.finished {
+            self.line.clear();
+            self.offset = 0;
+
+            loop {
+                match self.editor.read_line(!self.blocks.is_balanced())? {
+                    ReadLine::Line(line) if line.is_empty() && self.blocks.is_balanced() => {
+                        continue
+                    }
+                    ReadLine::Line(line) => {
+                        // Wait for the rest of the unfinished block
+                        self.blocks.feed_line(&line);
+                        self.line.push_str(&line);
+                        self.line.push('\n');
+                        if self.blocks.is_balanced() {
+                            break;
+                        }
+                    }
+                    ReadLine::Interrupted if !self.blocks.is_balanced() => {
+                        // Discard the unfinished block
+                        self.blocks.reset();
+                        self.line.clear();
+                    }
+                    ReadLine::Interrupted | ReadLine::Eof => {
+                        self.line.clear();
+                        self.finished = true;
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(&self.line.as_bytes()[self.offset..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.offset += amt;
+    }
+}
+
+/// Captured source code which can be interpreted later.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SourceBlockValue {
+    name: Rc<str>,
+    source: Rc<str>,
+}
+
+impl SourceBlockValue {
+    pub fn new<N: AsRef<str>, S: AsRef<str>>(name: N, source: S) -> Self {
+        Self {
+            name: Rc::from(name.as_ref()),
+            source: Rc::from(source.as_ref()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn to_source_block(&self) -> SourceBlock {
+        SourceBlock::new(
+            self.name.as_ref(),
+            std::io::Cursor::new(self.source.to_string()),
+        )
     }
 }
 
@@ -68,6 +301,164 @@ impl Environment for EmptyEnvironment {
     fn include(&self, name: &str) -> std::io::Result<SourceBlock> {
         Err(not_found(name))
     }
+
+    fn create_file(&mut self, _: &str) -> std::io::Result<Box<dyn Write>> {
+        Ok(Box::new(std::io::sink()))
+    }
+}
+
+/// Environment without system access, e.g. for WebAssembly builds.
+///
+/// Files are kept in memory and shared between clones, so the host can
+/// add sources before the run and read written files after it. The time
+/// is provided by the host as well.
+#[derive(Clone, Default)]
+pub struct MemoryEnvironment {
+    files: Rc<RefCell<HashMap<String, SharedFile>>>,
+    vars: HashMap<String, String>,
+    include_paths: Vec<String>,
+    clock: Option<Rc<dyn Fn() -> u64>>,
+    warnings: Rc<RefCell<Vec<String>>>,
+    answers: Rc<RefCell<VecDeque<String>>>,
+    keychain: MemoryKeychain,
+}
+
+impl MemoryEnvironment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the source of the current time in milliseconds,
+    /// the time is zero by default.
+    pub fn with_clock<F: Fn() -> u64 + 'static>(mut self, clock: F) -> Self {
+        self.clock = Some(Rc::new(clock));
+        self
+    }
+
+    /// Sets the variable returned by `getenv`.
+    pub fn with_var<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Adds a line which is returned by the next `ask` or `ask-secret`.
+    pub fn with_answer<T: Into<String>>(self, answer: T) -> Self {
+        self.answers.borrow_mut().push_back(answer.into());
+        self
+    }
+
+    /// Adds a directory prefix which is searched for included files
+    /// which are not found by their names.
+    pub fn with_include_path<P: Into<String>>(mut self, path: P) -> Self {
+        self.include_paths.push(path.into());
+        self
+    }
+
+    /// Adds (or replaces) a file.
+    pub fn insert_file<N: Into<String>, C: Into<Vec<u8>>>(&self, name: N, contents: C) {
+        let contents = Rc::new(RefCell::new(contents.into()));
+        self.files.borrow_mut().insert(name.into(), contents);
+    }
+
+    /// Returns a copy of the file contents.
+    pub fn file(&self, name: &str) -> Option<Vec<u8>> {
+        let files = self.files.borrow();
+        let contents = files.get(name)?.borrow().clone();
+        Some(contents)
+    }
+
+    pub fn remove_file(&self, name: &str) -> Option<Vec<u8>> {
+        let contents = self.files.borrow_mut().remove(name)?;
+        let contents = contents.borrow().clone();
+        Some(contents)
+    }
+
+    /// Returns names of all files in an unspecified order.
+    pub fn file_names(&self) -> Vec<String> {
+        self.files.borrow().keys().cloned().collect()
+    }
+
+    /// Returns reported warnings.
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut self.warnings.borrow_mut())
+    }
+}
+
+impl Environment for MemoryEnvironment {
+    fn now_ms(&self) -> u64 {
+        self.clock.as_ref().map(|clock| clock()).unwrap_or_default()
+    }
+
+    fn get_env(&self, name: &str) -> Option<String> {
+        self.vars.get(name).cloned()
+    }
+
+    fn file_exists(&self, name: &str) -> bool {
+        self.files.borrow().contains_key(name)
+    }
+
+    fn write_file(&mut self, name: &str, contents: &[u8]) -> std::io::Result<()> {
+        self.insert_file(name, contents);
+        Ok(())
+    }
+
+    fn read_file(&mut self, name: &str) -> std::io::Result<Vec<u8>> {
+        self.file(name).ok_or_else(|| not_found(name))
+    }
+
+    fn read_file_part(&mut self, name: &str, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+        let files = self.files.borrow();
+        let contents = files.get(name).ok_or_else(|| not_found(name))?.borrow();
+        let start = std::cmp::min(offset, contents.len() as u64) as usize;
+        let end = std::cmp::min(offset.saturating_add(len), contents.len() as u64) as usize;
+        Ok(contents[start..end].to_vec())
+    }
+
+    fn include(&self, name: &str) -> std::io::Result<SourceBlock> {
+        let contents = std::iter::once(name.to_owned())
+            .chain(self.include_paths.iter().map(|dir| {
+                let dir = dir.trim_end_matches('/');
+                format!("{dir}/{name}")
+            }))
+            .find_map(|path| self.file(&path))
+            .ok_or_else(|| not_found(name))?;
+        Ok(SourceBlock::new(name, std::io::Cursor::new(contents)))
+    }
+
+    fn create_file(&mut self, name: &str) -> std::io::Result<Box<dyn Write>> {
+        let contents = Rc::new(RefCell::new(Vec::new()));
+        self.files
+            .borrow_mut()
+            .insert(name.to_owned(), contents.clone());
+        Ok(Box::new(MemoryFile(contents)))
+    }
+
+    fn warn(&mut self, message: &str) {
+        self.warnings.borrow_mut().push(message.to_owned());
+    }
+
+    fn ask(&mut self, _: &str, _: bool) -> std::io::Result<Option<String>> {
+        Ok(self.answers.borrow_mut().pop_front())
+    }
+
+    fn keychain(&mut self) -> Option<&mut dyn Keychain> {
+        Some(&mut self.keychain)
+    }
+}
+
+type SharedFile = Rc<RefCell<Vec<u8>>>;
+
+struct MemoryFile(SharedFile);
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 fn not_found(name: &str) -> std::io::Error {
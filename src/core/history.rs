@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+
+use super::lexer::LexerPosition;
+use super::stack::{Stack, StackValue};
+
+/// Interactive input history with stack effects of each line.
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+    last_line: usize,
+    snapshot: Vec<Box<dyn StackValue>>,
+}
+
+impl History {
+    pub const DEFAULT_CAPACITY: usize = 100;
+
+    /// Max number of stack items which are kept in the digest.
+    const DIGEST_ITEMS: usize = 3;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Default::default(),
+            capacity: std::cmp::max(capacity, 1),
+            last_line: 0,
+            snapshot: Vec::new(),
+        }
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &HistoryEntry> + '_ {
+        self.entries.iter()
+    }
+
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.back()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Records a new entry when the bottom source block advances to the next line.
+    ///
+    /// Returns `true` if a new entry was started.
+    pub fn observe(&mut self, pos: Option<LexerPosition<'_>>, stack: &Stack) -> bool {
+        let Some(pos) = pos else {
+            return false;
+        };
+        if pos.offset != 1 || pos.line_number == self.last_line {
+            return false;
+        }
+        self.last_line = pos.line_number;
+
+        self.finish_last(stack);
+
+        let line = pos.line.trim();
+        if line.is_empty() {
+            return false;
+        }
+
+        self.snapshot = stack.items().to_vec();
+
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(HistoryEntry {
+            line_number: pos.line_number,
+            line: line.to_owned(),
+            depth_before: stack.depth(),
+            depth_after: None,
+            digest: None,
+            failed: false,
+        });
+        true
+    }
+
+    /// Returns the stack items as they were before the last entry.
+    pub fn snapshot(&self) -> &[Box<dyn StackValue>] {
+        &self.snapshot
+    }
+
+    /// Marks the last entry as failed.
+    pub fn fail_last(&mut self, stack: &Stack) {
+        self.finish_last(stack);
+        if let Some(last) = self.entries.back_mut() {
+            last.failed = true;
+        }
+    }
+
+    /// Stores the resulting stack effect of the last entry.
+    pub fn finish_last(&mut self, stack: &Stack) {
+        if let Some(last) = self.entries.back_mut() {
+            if last.depth_after.is_none() {
+                last.depth_after = Some(stack.depth());
+                last.digest = Some(make_digest(stack, Self::DIGEST_ITEMS));
+            }
+        }
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+pub struct HistoryEntry {
+    pub line_number: usize,
+    pub line: String,
+    pub depth_before: usize,
+    pub depth_after: Option<usize>,
+    pub digest: Option<String>,
+    pub failed: bool,
+}
+
+impl std::fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:>4}: {}", self.line_number, self.line)?;
+        if self.failed {
+            return f.write_str("  ( failed )");
+        }
+        match (self.depth_after, &self.digest) {
+            (Some(depth_after), Some(digest)) => {
+                write!(f, "  ( {} -> {} ) {digest}", self.depth_before, depth_after)
+            }
+            _ => f.write_str("  ( running )"),
+        }
+    }
+}
+
+fn make_digest(stack: &Stack, n: usize) -> String {
+    use std::fmt::Write;
+
+    let items = stack.items();
+    let start = items.len().saturating_sub(n);
+
+    let mut result = String::new();
+    if start > 0 {
+        result.push_str("... ");
+    }
+    for (i, item) in items[start..].iter().enumerate() {
+        if i > 0 {
+            result.push(' ');
+        }
+        write!(&mut result, "{}", item.display_dump()).ok();
+    }
+    result
+}
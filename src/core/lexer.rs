@@ -1,20 +1,95 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use anyhow::Result;
 
 use super::env::SourceBlock;
-use crate::error::UnexpectedEof;
+use super::timings::Timings;
+use crate::error::{OpenLiteral, UnexpectedEof};
 
-#[derive(Default)]
 pub struct Lexer {
     blocks: Vec<SourceBlockState>,
+    max_chunk_len: usize,
+    script_mode: bool,
+    timings: Option<Timings>,
+}
+
+impl Default for Lexer {
+    fn default() -> Self {
+        Self {
+            blocks: Vec::new(),
+            max_chunk_len: Self::DEFAULT_MAX_CHUNK_LEN,
+            script_mode: false,
+            timings: None,
+        }
+    }
 }
 
 impl Lexer {
-    pub fn push_source_block(&mut self, block: SourceBlock) {
-        self.blocks.push(SourceBlockState::from(block));
+    pub const DEFAULT_MAX_CHUNK_LEN: usize = 64 * 1024;
+
+    /// Sets the maximum number of bytes read at once for the next source blocks.
+    ///
+    /// Longer lines are read in chunks, so only the current token
+    /// is kept in memory instead of the whole line.
+    pub fn set_max_chunk_len(&mut self, len: usize) {
+        self.max_chunk_len = std::cmp::max(len, 1);
+    }
+
+    /// Treats lines starting with a `#` word as comments in the next source blocks,
+    /// e.g. in scripts which are executed directly on Unix.
+    ///
+    /// A leading `#!` line is skipped regardless of this flag.
+    pub fn set_script_mode(&mut self, enabled: bool) {
+        self.script_mode = enabled;
+    }
+
+    /// Starts collecting statistics of the next source blocks.
+    pub fn enable_timings(&mut self) {
+        self.timings.get_or_insert_with(Default::default);
+    }
+
+    /// Starts collecting statistics of the next source blocks into `timings`.
+    pub fn set_timings(&mut self, timings: Timings) {
+        self.timings = Some(timings);
+    }
+
+    pub fn timings(&self) -> Option<&Timings> {
+        self.timings.as_ref()
+    }
+
+    pub fn timings_mut(&mut self) -> Option<&mut Timings> {
+        self.timings.as_mut()
+    }
+
+    pub fn push_source_block(&mut self, block: SourceBlock) -> SourceBlockId {
+        let mut state = SourceBlockState::from(block);
+        state.max_chunk_len = self.max_chunk_len;
+        state.script_mode = self.script_mode;
+        let id = state.id;
+        self.blocks.push(state);
+        id
     }
 
     pub fn pop_source_block(&mut self) -> bool {
-        self.blocks.pop().is_some()
+        let Some(input) = self.blocks.pop() else {
+            return false;
+        };
+        if let (true, Some(timings)) = (input.timed, &mut self.timings) {
+            timings.exit();
+        }
+        true
+    }
+
+    pub fn depth(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns the name of the source block if it is still being read.
+    pub fn source_block_name(&self, id: SourceBlockId) -> Option<&str> {
+        self.blocks
+            .iter()
+            .find(|input| input.id == id)
+            .map(|input| input.block.name())
     }
 
     pub fn get_position(&self) -> Option<LexerPosition<'_>> {
@@ -22,11 +97,12 @@ impl Lexer {
         let input = self.blocks.last()?;
         Some(LexerPosition {
             offset,
+            source_block_id: input.id,
             source_block_name: input.block.name(),
             line: &input.line,
-            line_offset_start: std::cmp::min(input.prev_line_offset + 1, input.line_offset),
+            line_offset_start: std::cmp::min(input.token_start, input.line_offset),
             line_offset_end: input.line_offset,
-            line_number: input.line_number.unwrap_or_default(),
+            line_number: input.line_number,
         })
     }
 
@@ -34,36 +110,94 @@ impl Lexer {
         let Some(input) = self.blocks.last_mut() else {
             return Ok(None);
         };
-        input.scan_word()
+        if let Some(timings) = &mut self.timings {
+            // Blocks are measured from the first scanned word, so that
+            // the preamble pushed after the script is not nested into it
+            if !input.timed {
+                input.timed = true;
+                timings.enter(input.block.name());
+            }
+        }
+        let token = input.scan_word()?;
+        if let (Some(_), Some(timings)) = (&token, &mut self.timings) {
+            timings.add_token();
+        }
+        Ok(token)
     }
 
     pub fn scan_until_space_or_eof(&mut self) -> Result<Token<'_>> {
-        if let Some(input) = self.blocks.last_mut() {
-            if let Some(word) = input.scan_word()? {
-                return Ok(word);
-            }
-        }
-        Ok(Token { data: "" })
+        let Some(input) = self.blocks.last_mut() else {
+            return Ok(Token::empty(SourceBlockId::NONE, 0));
+        };
+        let (id, offset) = (input.id, input.absolute_offset());
+        Ok(input.scan_word()?.unwrap_or(Token::empty(id, offset)))
     }
 
     pub fn scan_until_delimiter(&mut self, delimiter: char) -> Result<Token<'_>> {
-        if let Some(token) = self.use_last_block()?.scan_until(delimiter)? {
-            Ok(token)
-        } else if delimiter as u32 == 0 {
-            Ok(Token { data: "" })
-        } else {
-            anyhow::bail!(UnexpectedEof)
+        if delimiter as u32 == 0 {
+            let input = self.use_last_block()?;
+            let (id, offset) = (input.id, input.absolute_offset());
+            return Ok(input
+                .scan_until(delimiter)?
+                .unwrap_or(Token::empty(id, offset)));
         }
+        self.scan_literal(LiteralKind::Delimited(delimiter))
     }
 
     pub fn scan_until<P: Delimiter>(&mut self, p: P) -> Result<Token<'_>> {
         if let Some(token) = self.use_last_block()?.scan_until(p)? {
             Ok(token)
         } else {
-            anyhow::bail!(UnexpectedEof)
+            anyhow::bail!(UnexpectedEof::default())
+        }
+    }
+
+    /// Scans the rest of the literal up to its closing delimiter.
+    ///
+    /// Literals in interactive source blocks can span multiple lines.
+    pub fn scan_literal(&mut self, kind: LiteralKind) -> Result<Token<'_>> {
+        let input = self.use_last_block()?;
+        let open = OpenLiteral {
+            kind,
+            source_block: input.block.name().to_owned(),
+            line_number: input.line_number,
+            column: input.line[..input.line_offset].chars().count() + 1,
+        };
+
+        match input.scan_until(kind.closing())? {
+            Some(token) => Ok(token),
+            None => anyhow::bail!(UnexpectedEof {
+                literal: Some(open),
+            }),
         }
     }
 
+    pub fn scan_char(&mut self) -> Result<Option<char>> {
+        match self.blocks.last_mut() {
+            Some(input) => input.scan_char(),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the rest of the current line without reading the next one.
+    pub fn scan_line_rest(&mut self) -> Result<Token<'_>> {
+        match self.blocks.last_mut() {
+            Some(input) => input.scan_line_rest(),
+            None => Ok(Token::empty(SourceBlockId::NONE, 0)),
+        }
+    }
+
+    /// Reads the next line of the current source block.
+    pub fn scan_next_line(&mut self) -> Result<Option<Token<'_>>> {
+        let Some(input) = self.blocks.last_mut() else {
+            return Ok(None);
+        };
+        if !input.read_line()? {
+            return Ok(None);
+        }
+        input.scan_line_rest().map(Some)
+    }
+
     pub fn rewind(&mut self, offset: usize) {
         if let Some(input) = self.blocks.last_mut() {
             input.rewind(offset)
@@ -82,6 +216,12 @@ impl Lexer {
         self.skip_while(char::is_whitespace)
     }
 
+    pub fn skip_line(&mut self) {
+        if let Some(input) = self.blocks.last_mut() {
+            input.skip_line();
+        }
+    }
+
     pub fn skip_until<P: Delimiter>(&mut self, mut p: P) {
         if let Some(input) = self.blocks.last_mut() {
             input.skip_until(|c| !p.delim(c))
@@ -101,13 +241,61 @@ impl Lexer {
     }
 
     fn use_last_block(&mut self) -> Result<&mut SourceBlockState> {
-        self.blocks.last_mut().ok_or_else(|| UnexpectedEof.into())
+        self.blocks
+            .last_mut()
+            .ok_or_else(|| UnexpectedEof::default().into())
+    }
+}
+
+/// Literal which is scanned up to the closing delimiter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralKind {
+    /// `"..."`
+    String,
+    /// `x{...}` or `b{...}`
+    Bitstring,
+    /// Input scanned by `word` or `parse`.
+    Delimited(char),
+}
+
+impl LiteralKind {
+    pub fn closing(&self) -> char {
+        match self {
+            Self::String => '"',
+            Self::Bitstring => '}',
+            Self::Delimited(c) => *c,
+        }
+    }
+}
+
+impl std::fmt::Display for LiteralKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::String => f.write_str("string literal"),
+            Self::Bitstring => f.write_str("bitstring literal"),
+            Self::Delimited(c) => write!(f, "input delimited by `{c}`"),
+        }
+    }
+}
+
+/// Unique id of the source block pushed to the lexer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceBlockId(u64);
+
+impl SourceBlockId {
+    /// Id of tokens which were scanned without any source block.
+    pub const NONE: Self = Self(0);
+
+    fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct LexerPosition<'a> {
     pub offset: usize,
+    pub source_block_id: SourceBlockId,
     pub source_block_name: &'a str,
     pub line: &'a str,
     pub line_offset_start: usize,
@@ -115,12 +303,103 @@ pub struct LexerPosition<'a> {
     pub line_number: usize,
 }
 
+/// Tracks constructs which can span multiple input lines:
+/// word lists (`{ ... }`), strings (`"..."`), bitstring and
+/// bytes literals (`x{...}`, `b{...}`, `B{...}`) and block comments.
+#[derive(Debug, Default, Clone)]
+pub struct OpenBlocks {
+    depth: usize,
+    literal: Option<char>,
+    comment: bool,
+}
+
+impl OpenBlocks {
+    pub fn is_balanced(&self) -> bool {
+        self.depth == 0 && self.literal.is_none() && !self.comment
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn feed_line(&mut self, mut line: &str) {
+        loop {
+            if let Some(delim) = self.literal {
+                match line.find(delim) {
+                    Some(i) => {
+                        line = &line[i + delim.len_utf8()..];
+                        self.literal = None;
+                    }
+                    None => return,
+                }
+            }
+
+            line = line.trim_start();
+            if line.is_empty() {
+                return;
+            }
+
+            let end = line.find(char::is_whitespace).unwrap_or(line.len());
+            let token = &line[..end];
+
+            if self.comment {
+                self.comment = token != "*/";
+                line = &line[end..];
+                continue;
+            }
+
+            let literal = if let Some(i) = token.find('"') {
+                Some((i + 1, '"'))
+            } else if token.starts_with("x{") || token.starts_with("b{") || token.starts_with("B{")
+            {
+                Some((2, '}'))
+            } else {
+                None
+            };
+
+            match token {
+                "{" => self.depth += 1,
+                "}" => self.depth = self.depth.saturating_sub(1),
+                "//" => return,
+                "/*" => self.comment = true,
+                _ => {}
+            }
+
+            line = match literal {
+                // Literal contents may contain spaces,
+                // so continue right after the opening delimiter
+                Some((offset, delim)) => {
+                    self.literal = Some(delim);
+                    &line[offset..]
+                }
+                None => &line[end..],
+            };
+        }
+    }
+}
+
 pub struct Token<'a> {
     pub data: &'a str,
+    /// Source block which contains the token.
+    pub source_block: SourceBlockId,
+    /// Byte range of the token from the beginning of the source block.
+    pub span: std::ops::Range<usize>,
 }
 
-impl Token<'_> {
-    pub fn subtokens(&self) -> Subtokens {
+impl<'a> Token<'a> {
+    fn empty(source_block: SourceBlockId, offset: usize) -> Self {
+        Self {
+            data: "",
+            source_block,
+            span: offset..offset,
+        }
+    }
+
+    pub fn subtokens(&self) -> Subtokens<'_> {
         Subtokens(self.data)
     }
 
@@ -160,83 +439,151 @@ impl Delimiter for char {
 }
 
 struct SourceBlockState {
+    id: SourceBlockId,
     block: SourceBlock,
+    /// Current line or its chunk if the line is longer than `max_chunk_len`.
     line: String,
+    /// Offset of the current line from the beginning of the block.
+    line_start: usize,
     line_offset: usize,
-    prev_line_offset: usize,
-    line_number: Option<usize>,
+    /// Offset of the last scanned token in the current line.
+    token_start: usize,
+    line_number: usize,
+    max_chunk_len: usize,
+    /// Whether the current chunk ends with the end of the line.
+    line_complete: bool,
+    /// Whether the remaining chunks of the current line must be discarded.
+    skip_line_rest: bool,
+    /// Whether lines starting with a `#` word are skipped.
+    script_mode: bool,
+    /// Whether the block is measured by the lexer timings.
+    timed: bool,
 }
 
 impl From<SourceBlock> for SourceBlockState {
     fn from(block: SourceBlock) -> Self {
         Self {
+            id: SourceBlockId::next(),
             block,
             line: Default::default(),
+            line_start: 0,
             line_offset: 0,
-            prev_line_offset: 0,
-            line_number: None,
+            token_start: 0,
+            line_number: 0,
+            max_chunk_len: Lexer::DEFAULT_MAX_CHUNK_LEN,
+            line_complete: true,
+            skip_line_rest: false,
+            script_mode: false,
+            timed: false,
         }
     }
 }
 
 impl SourceBlockState {
-    fn scan_word(&mut self) -> Result<Option<Token<'_>>> {
-        self.prev_line_offset = self.line_offset;
+    fn absolute_offset(&self) -> usize {
+        self.line_start + self.line_offset
+    }
+
+    fn make_token(&mut self, start: usize, end: usize) -> Token<'_> {
+        self.token_start = start;
+        Token {
+            data: &self.line[start..end],
+            source_block: self.id,
+            span: self.line_start + start..self.line_start + end,
+        }
+    }
 
+    fn scan_word(&mut self) -> Result<Option<Token<'_>>> {
         loop {
             if (self.line.is_empty() || self.line_offset >= self.line.len()) && !self.read_line()? {
                 return Ok(None);
             }
 
             self.skip_line_whitespace();
-            let start = self.line_offset;
-            self.skip_until(char::is_whitespace);
+            let mut start = self.line_offset;
+            loop {
+                self.skip_until(char::is_whitespace);
+                // The word can continue in the next chunk of a long line
+                if self.line_offset < self.line.len()
+                    || self.line_complete
+                    || !self.read_more(&mut start)?
+                {
+                    break;
+                }
+            }
             let end = self.line_offset;
 
             if start == end {
                 continue;
             }
 
-            return Ok(Some(Token {
-                data: &self.line[start..end],
-            }));
+            return Ok(Some(self.make_token(start, end)));
         }
     }
 
     fn scan_until<P: Delimiter>(&mut self, mut p: P) -> Result<Option<Token<'_>>> {
-        self.prev_line_offset = self.line_offset;
-
         if (self.line.is_empty() || self.line_offset >= self.line.len()) && !self.read_line()? {
             return Ok(None);
         }
 
-        let start = self.line_offset;
+        let mut start = self.line_offset;
 
         let mut found = false;
-        self.skip_until(|c| {
-            found |= p.delim(c);
-            found
-        });
+        loop {
+            self.skip_until(|c| {
+                found |= p.delim(c);
+                found
+            });
+
+            if found || !self.read_more(&mut start)? {
+                break;
+            }
+        }
 
         let end = self.line_offset;
 
         Ok(if found && end >= start {
             self.skip_symbol();
-            Some(Token {
-                data: &self.line[start..end],
-            })
+            Some(self.make_token(start, end))
         } else {
             None
         })
     }
 
+    fn scan_char(&mut self) -> Result<Option<char>> {
+        if self.line_offset >= self.line.len() && !self.read_line()? {
+            return Ok(None);
+        }
+
+        let c = self.line[self.line_offset..].chars().next();
+        self.token_start = self.line_offset;
+        self.skip_symbol();
+        Ok(c)
+    }
+
+    fn scan_line_rest(&mut self) -> Result<Token<'_>> {
+        let mut start = std::cmp::min(self.line_offset, self.line.len());
+        loop {
+            self.line_offset = self.line.len();
+            if self.line_complete || !self.read_more(&mut start)? {
+                break;
+            }
+        }
+        let end = start + self.line[start..].trim_end_matches(['\n', '\r']).len();
+        Ok(self.make_token(start, end))
+    }
+
+    fn skip_line(&mut self) {
+        self.line_offset = self.line.len();
+        // The rest of a long line is discarded on the next read
+        self.skip_line_rest = !self.line_complete;
+    }
+
     fn rewind(&mut self, offset: usize) {
         self.line_offset -= offset;
     }
 
     fn skip_whitespace(&mut self) -> Result<()> {
-        self.prev_line_offset = self.line_offset;
-
         loop {
             if (self.line.is_empty() || self.line_offset >= self.line.len()) && !self.read_line()? {
                 return Ok(());
@@ -273,18 +620,124 @@ impl SourceBlockState {
         self.line_offset = self.line.len();
     }
 
+    /// Reads the next chunk without discarding the data from `start`.
+    ///
+    /// The next line is only appended in interactive source blocks.
+    fn read_more(&mut self, start: &mut usize) -> Result<bool> {
+        if self.line_complete {
+            // Interactive input is only passed when all literals are closed
+            if !self.block.is_interactive() {
+                return Ok(false);
+            }
+        } else {
+            // Drop the scanned part of a long line to keep the buffer bounded
+            self.line.drain(..*start);
+            self.line_start += *start;
+            self.line_offset -= *start;
+            self.token_start = self.token_start.saturating_sub(*start);
+            *start = 0;
+        }
+        Ok(self.read_chunk()? > 0)
+    }
+
     fn read_line(&mut self) -> Result<bool> {
-        self.prev_line_offset = 0;
-        self.line_offset = 0;
-        self.line.clear();
-        let n = self.block.buffer_mut().read_line(&mut self.line)?;
+        loop {
+            self.line_start += self.line.len();
+            self.token_start = 0;
+            self.line_offset = 0;
+            self.line.clear();
+
+            let skip = std::mem::take(&mut self.skip_line_rest);
+            if self.read_chunk()? == 0 {
+                return Ok(false);
+            } else if !skip && !self.is_comment_line()? {
+                return Ok(true);
+            }
 
-        if let Some(line_number) = &mut self.line_number {
-            *line_number += 1;
-        } else {
-            self.line_number = Some(0);
+            self.line_offset = self.line.len();
+            self.skip_line_rest = !self.line_complete;
+        }
+    }
+
+    /// Returns `true` for a `#!` line at the beginning of the block,
+    /// or for a `#` comment in the script mode.
+    fn is_comment_line(&mut self) -> Result<bool> {
+        if !self.script_mode && self.line_start > 0 {
+            return Ok(false);
+        }
+
+        // The prefix can be split between chunks of a long line
+        while !self.line_complete && matches!(self.line.trim_start(), "" | "#") {
+            if self.read_chunk()? == 0 {
+                break;
+            }
+        }
+
+        if self.line_start == 0 && self.line.starts_with("#!") {
+            return Ok(true);
+        }
+        if !self.script_mode {
+            return Ok(false);
         }
+        Ok(match self.line.trim_start().strip_prefix('#') {
+            Some(rest) => rest.is_empty() || rest.starts_with(char::is_whitespace),
+            None => false,
+        })
+    }
 
-        Ok(n > 0)
+    /// Appends the next chunk of at most `max_chunk_len` bytes (extended
+    /// to the end of the last char) up to the end of the line.
+    fn read_chunk(&mut self) -> Result<usize> {
+        let limit = self.max_chunk_len;
+        let buffer = self.block.buffer_mut();
+
+        let mut chunk = Vec::new();
+        let mut complete = false;
+        while chunk.len() < limit {
+            let available = buffer.fill_buf()?;
+            if available.is_empty() {
+                complete = true;
+                break;
+            }
+
+            let len = std::cmp::min(available.len(), limit - chunk.len());
+            let (len, eol) = match available[..len].iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (len, false),
+            };
+            chunk.extend_from_slice(&available[..len]);
+            buffer.consume(len);
+
+            if eol {
+                complete = true;
+                break;
+            }
+        }
+
+        // Chunks must not split chars
+        while !complete {
+            match buffer.fill_buf()?.first() {
+                Some(&b) if b & 0xc0 == 0x80 => {
+                    chunk.push(b);
+                    buffer.consume(1);
+                }
+                Some(_) => break,
+                None => complete = true,
+            }
+        }
+
+        let chunk = String::from_utf8(chunk).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })?;
+
+        if !chunk.is_empty() && self.line_complete {
+            self.line_number += 1;
+        }
+        self.line_complete = complete;
+        self.line.push_str(&chunk);
+        Ok(chunk.len())
     }
 }
@@ -1,27 +1,59 @@
+use std::any::{Any, TypeId};
 use std::io::Write;
 use std::num::NonZeroU32;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
+use ahash::HashMap;
 use anyhow::{Context as _, Result};
 
 pub use fift_proc::fift_module;
 
+use crate::config::FiftConfig;
+
+pub use self::async_env::AsyncEnvironment;
 pub use self::cont::{Cont, ContImpl};
-pub use self::dictionary::{Dictionary, DictionaryEntry};
-pub use self::env::{Environment, SourceBlock};
-pub use self::lexer::{Lexer, Token};
+pub use self::coverage::{Coverage, WordPosition};
+pub use self::debugger::{DebugHook, Debugger, PauseReason};
+pub use self::dictionary::{Dictionary, DictionaryEntry, WordLocation};
+pub use self::dump::DumpStyle;
+pub use self::env::{
+    Environment, IncludeResolver, Keychain, LineEditor, MemoryEnvironment, MemoryKeychain,
+    ReadLine, Signer, SourceBlock, SourceBlockValue,
+};
+pub use self::history::{History, HistoryEntry};
+pub use self::lexer::{Lexer, LiteralKind, OpenBlocks, SourceBlockId, Token};
+pub use self::op_codes::OpCodes;
+pub use self::output::Output;
+pub use self::profiler::{Profiler, WordReport};
 pub use self::stack::{
-    OwnedCellSlice, SharedBox, Stack, StackTuple, StackValue, StackValueType, WordList,
+    Atom, Atoms, OwnedCellSlice, SharedBox, Stack, StackTuple, StackValue, StackValueType, WordList,
 };
+pub use self::strict::{StrictMode, StrictReason, StrictWord};
+pub use self::timings::{SourceBlockTiming, Timings};
 
+pub mod async_env;
 pub mod cont;
+pub mod coverage;
+pub mod debugger;
 pub mod dictionary;
+pub mod dump;
 pub mod env;
+pub mod history;
 pub mod lexer;
+pub mod op_codes;
+pub mod output;
+pub mod profiler;
+pub mod snapshot;
 pub mod stack;
+pub mod strict;
+pub mod timings;
 
 pub struct Context<'a> {
     pub state: State,
+    /// Positions of the open `{ ... }` blocks, from the outermost one.
+    pub open_blocks: Vec<OpenBlock>,
     pub stack: Stack,
     pub exit_code: u8,
     pub next: Option<Cont>,
@@ -29,23 +61,93 @@ pub struct Context<'a> {
 
     pub input: Lexer,
     pub exit_interpret: SharedBox,
+    pub history: Option<History>,
+    pub coverage: Option<Coverage>,
+    /// Statistics of executed words, if enabled.
+    pub profiler: Option<Profiler>,
+    /// Number of executed continuations.
+    pub steps: u64,
+    /// Number of top stack items printed for each executed word,
+    /// the trace mode is disabled if not set.
+    pub trace: Option<usize>,
+    pub cancellation: Option<CancellationToken>,
+    /// Remaining execution budget, unlimited if not set.
+    pub fuel: Option<Fuel>,
+    pub debugger: Debugger,
+    /// Registry of words which are rejected, if set.
+    pub strict: Option<StrictMode>,
+    pub op_codes: OpCodes,
+    /// Max number of items printed by `.s` and `.sl` at once, all items if not set.
+    pub stack_page_size: Option<usize>,
+    /// Format of the items printed by `.s`.
+    pub dump_style: DumpStyle,
+    /// Names of files included by the script, in order.
+    pub includes: Vec<String>,
+    /// Named inputs of the script, which are read by `param`.
+    pub params: HashMap<String, String>,
 
     pub env: &'a mut dyn Environment,
-    pub stdout: &'a mut dyn Write,
+    pub stdout: Output<'a>,
+
+    before_word_hook: Option<BeforeWordHook<'a>>,
+    after_word_hook: Option<AfterWordHook<'a>>,
+    debug_hook: Option<Box<dyn DebugHook + 'a>>,
+    include_resolver: Option<Box<dyn IncludeResolver + 'a>>,
+    async_includes: Option<async_env::AsyncIncludes>,
+    /// Radix used by `.` to print integers, always in range 2..=36.
+    output_radix: u32,
+    /// Whether missing files are included from the embedded library.
+    #[cfg(feature = "stdlib")]
+    stdlib: bool,
+    /// Limits of the source blocks which are still being read.
+    block_limits: Vec<(SourceBlockId, Fuel)>,
+    exception_handlers: Vec<ExceptionHandler>,
+    thrown: Option<Box<dyn StackValue>>,
+    user_data: HashMap<TypeId, Box<dyn Any>>,
 }
 
 impl<'a> Context<'a> {
+    /// Number of top stack items printed in the trace mode by default.
+    pub const DEFAULT_TRACE_ITEMS: usize = 3;
+
     pub fn new(env: &'a mut dyn Environment, stdout: &'a mut dyn Write) -> Self {
         Self {
             state: Default::default(),
+            open_blocks: Vec::new(),
             stack: Stack::new(None),
             exit_code: 0,
             next: None,
             dictionary: Default::default(),
             input: Default::default(),
             exit_interpret: Default::default(),
+            history: None,
+            coverage: None,
+            profiler: None,
+            steps: 0,
+            trace: None,
+            cancellation: None,
+            fuel: None,
+            debugger: Default::default(),
+            strict: None,
+            op_codes: Default::default(),
+            output_radix: 10,
+            stack_page_size: None,
+            dump_style: DumpStyle::Plain,
+            includes: Vec::new(),
+            params: Default::default(),
             env,
-            stdout,
+            stdout: Output::new(stdout),
+            before_word_hook: None,
+            after_word_hook: None,
+            debug_hook: None,
+            include_resolver: None,
+            #[cfg(feature = "stdlib")]
+            stdlib: false,
+            async_includes: None,
+            block_limits: Vec::new(),
+            exception_handlers: Vec::new(),
+            thrown: None,
+            user_data: Default::default(),
         }
     }
 
@@ -67,17 +169,551 @@ impl<'a> Context<'a> {
         self.input.push_source_block(block);
     }
 
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history = Some(History::new(capacity));
+        self
+    }
+
+    /// Applies limits, features and output settings from the config.
+    pub fn with_config(mut self, config: &FiftConfig) -> Result<Self> {
+        config.validate()?;
+        self.stack.set_capacity(config.limits.stack_depth);
+        self.output_radix = config.radix;
+        self.stack_page_size = config.stack_page_size;
+        self.dump_style = match config.dump_style {
+            DumpStyle::Colored if !config.color => DumpStyle::Pretty,
+            style => style,
+        };
+        if let Some(steps) = config.limits.steps {
+            self.fuel.get_or_insert_with(Default::default).steps = Some(steps);
+        }
+        if config.features.history {
+            self.history = Some(History::new(config.limits.history));
+        }
+        if config.features.coverage {
+            self.coverage = Some(Coverage::default());
+        }
+        if config.features.strict {
+            self.strict = Some(StrictMode::default());
+        }
+        if config.features.timings {
+            self.input.enable_timings();
+        }
+        if config.features.profile {
+            self.profiler = Some(Profiler::default());
+        }
+        if config.features.trace {
+            self.trace = Some(Self::DEFAULT_TRACE_ITEMS);
+        }
+        Ok(self)
+    }
+
+    /// Duplicates the output of printing words to the specified writer.
+    pub fn with_output_tee<W: Write + 'a>(mut self, writer: W) -> Self {
+        self.stdout.add_tee(writer);
+        self
+    }
+
+    pub fn with_include_resolver<R: IncludeResolver + 'a>(mut self, resolver: R) -> Self {
+        self.set_include_resolver(resolver);
+        self
+    }
+
+    /// Includes embedded library files (e.g. `Fift.fif`) if they are
+    /// not found in the environment.
+    #[cfg(feature = "stdlib")]
+    pub fn with_stdlib(mut self) -> Self {
+        self.stdlib = true;
+        self
+    }
+
+    /// Sets a named input of the script, see `param`.
+    pub fn with_param<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.params.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_coverage(mut self) -> Self {
+        self.coverage = Some(Coverage::default());
+        self
+    }
+
+    /// Collects statistics of the source blocks which are added afterwards.
+    pub fn with_timings(mut self) -> Self {
+        self.input.enable_timings();
+        self
+    }
+
+    /// Collects the number of calls and time spent in each word.
+    pub fn with_profiler(mut self) -> Self {
+        self.profiler = Some(Profiler::default());
+        self
+    }
+
+    /// Rejects deprecated and non-standard words.
+    pub fn with_strict_mode(mut self) -> Self {
+        self.strict = Some(StrictMode::default());
+        self
+    }
+
+    /// Limits the execution of untrusted scripts.
+    pub fn with_fuel(mut self, fuel: Fuel) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Limits continuations executed while the source block is being read,
+    /// e.g. a third-party library, in addition to the context fuel.
+    pub fn limit_source_block(&mut self, id: SourceBlockId, fuel: Fuel) {
+        self.block_limits.push((id, fuel));
+    }
+
+    fn trace_word(&mut self, cont: &dyn ContImpl, items: usize) {
+        const MAX_INDENT: usize = 32;
+
+        if self.dictionary.is_nop(cont) {
+            return;
+        }
+        let Some(name) = self.dictionary.resolve_name(cont) else {
+            return;
+        };
+
+        let mut nesting = 0;
+        let mut next = self.next.as_ref();
+        while let (Some(cont), true) = (next, nesting < MAX_INDENT) {
+            nesting += 1;
+            next = cont.up();
+        }
+
+        let depth = self.stack.depth();
+        let mut line = format!(
+            "{:indent$}{} <{depth}>",
+            "",
+            name.trim_end(),
+            indent = nesting * 2
+        );
+        for item in &self.stack.items()[depth.saturating_sub(items)..] {
+            line.push(' ');
+            line.push_str(&item.display_dump().to_string());
+        }
+        self.env.trace(&line);
+    }
+
+    fn consume_block_limits(&mut self, cont: &Cont) -> Result<()> {
+        let input = &self.input;
+        self.block_limits
+            .retain(|(id, _)| input.source_block_name(*id).is_some());
+
+        let depth = self.stack.depth();
+        for (id, fuel) in &mut self.block_limits {
+            if let Err(reason) = fuel.consume(depth) {
+                let name = self.input.source_block_name(*id).unwrap_or_default();
+                let reason = format!("{reason} in `{name}`");
+                let backtrace = cont.display_backtrace(&self.dictionary).to_string();
+                self.next = Some(cont.clone());
+                anyhow::bail!(crate::error::FuelExhausted { reason, backtrace });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a handle which can be used to abort the execution from another thread.
+    pub fn cancellation_token(&mut self) -> CancellationToken {
+        self.cancellation
+            .get_or_insert_with(Default::default)
+            .clone()
+    }
+
+    /// Sets a function which is called before each executed continuation.
+    ///
+    /// An error returned from the hook aborts the execution.
+    pub fn set_before_word_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&WordInfo<'_>, &Stack) -> Result<()> + 'a,
+    {
+        self.before_word_hook = Some(Box::new(hook));
+    }
+
+    /// Sets a function which is called after each executed continuation
+    /// with an error if it has failed.
+    pub fn set_after_word_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&WordInfo<'_>, &Stack, Option<&anyhow::Error>) + 'a,
+    {
+        self.after_word_hook = Some(Box::new(hook));
+    }
+
+    /// Sets a handler which is called when the execution is paused
+    /// on a breakpoint or a step.
+    pub fn set_debug_hook<H: DebugHook + 'a>(&mut self, hook: H) {
+        self.debug_hook = Some(Box::new(hook));
+    }
+
+    pub fn clear_debug_hook(&mut self) {
+        self.debug_hook = None;
+    }
+
+    pub fn clear_word_hooks(&mut self) {
+        self.before_word_hook = None;
+        self.after_word_hook = None;
+    }
+
+    /// Radix used by `.` to print integers.
+    pub fn output_radix(&self) -> u32 {
+        self.output_radix
+    }
+
+    /// Sets the radix used by `.`, which must be in range `2..=36`.
+    pub fn set_output_radix(&mut self, radix: u32) -> Result<()> {
+        anyhow::ensure!(
+            (2..=36).contains(&radix),
+            "Output radix must be in range 2..=36, got {radix}"
+        );
+        self.output_radix = radix;
+        Ok(())
+    }
+
+    /// Sets a provider which supplies sources of included files
+    /// before falling back to the environment.
+    pub fn set_include_resolver<R: IncludeResolver + 'a>(&mut self, resolver: R) {
+        self.include_resolver = Some(Box::new(resolver));
+    }
+
+    pub fn clear_include_resolver(&mut self) {
+        self.include_resolver = None;
+    }
+
+    /// Opens the included file using the include resolver or the environment.
+    ///
+    /// Relative paths are resolved against the directory of the including
+    /// file first, then the environment searches its include paths.
+    pub fn open_include(&mut self, name: &str) -> std::io::Result<SourceBlock> {
+        if let Some(includes) = &mut self.async_includes {
+            if let Some(e) = includes.error.take() {
+                return Err(e);
+            }
+            if let Some(Some(source)) = includes.fetched.get(name) {
+                return Ok(SourceBlockValue::new(name, source).to_source_block());
+            }
+        }
+        if let Some(resolver) = &mut self.include_resolver {
+            if let Some(source) = resolver.resolve(name)? {
+                return Ok(SourceBlockValue::new(name, source).to_source_block());
+            }
+        }
+        let res = match self.find_sibling_file(name) {
+            Some(path) => self.env.include(&path),
+            None => self.env.include(name),
+        };
+
+        #[cfg(feature = "stdlib")]
+        if let Err(e) = &res {
+            if let (true, std::io::ErrorKind::NotFound) = (self.stdlib, e.kind()) {
+                if let Some(source) = crate::stdlib::find(name) {
+                    return Ok(SourceBlockValue::new(name, source).to_source_block());
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Returns the path of the file in the directory of the current source block.
+    fn find_sibling_file(&self, name: &str) -> Option<String> {
+        if std::path::Path::new(name).is_absolute() {
+            return None;
+        }
+        let current = self.input.get_position()?.source_block_name;
+        if current.starts_with('<') {
+            return None;
+        }
+        let dir = std::path::Path::new(current).parent()?;
+        if dir.as_os_str().is_empty() {
+            return None;
+        }
+        let path = dir.join(name).to_str()?.to_owned();
+        self.env.file_exists(&path).then_some(path)
+    }
+
+    /// Stores a custom state of type `T`, returning the previous value.
+    pub fn set_user_data<T: 'static>(&mut self, data: T) -> Option<T> {
+        let prev = self.user_data.insert(TypeId::of::<T>(), Box::new(data))?;
+        prev.downcast().ok().map(|prev| *prev)
+    }
+
+    pub fn get_user_data<T: 'static>(&self) -> Option<&T> {
+        self.user_data.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    pub fn get_user_data_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.user_data.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    /// Returns a custom state of type `T`, storing its default value first
+    /// if there is none.
+    pub fn user_data_or_default<T: Default + 'static>(&mut self) -> &mut T {
+        self.user_data
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut()
+            .expect("user data is stored by its type id")
+    }
+
+    pub fn remove_user_data<T: 'static>(&mut self) -> Option<T> {
+        let data = self.user_data.remove(&TypeId::of::<T>())?;
+        data.downcast().ok().map(|data| *data)
+    }
+
     pub fn run(&mut self) -> Result<u8> {
-        let mut current = Some(Rc::new(cont::InterpreterCont) as Cont);
-        while let Some(cont) = current.take() {
-            //eprintln!("   >>> {}", cont.display_name(&self.dictionary));
-            current = cont.run(self)?;
-            if current.is_none() {
-                current = self.next.take();
+        let mut run = self.start_run();
+        loop {
+            if let Some(res) = self.run_step(&mut run) {
+                return res;
+            }
+        }
+    }
+
+    /// Prepares the interpreter loop of [`Context::run`].
+    fn start_run(&self) -> RunLoop {
+        RunLoop {
+            current: Some(Rc::new(cont::InterpreterCont) as Cont),
+            handlers_base: self.exception_handlers.len(),
+        }
+    }
+
+    /// Runs the next continuation of the interpreter loop.
+    ///
+    /// Returns the exit code or the error once the loop is finished.
+    fn run_step(&mut self, run: &mut RunLoop) -> Option<Result<u8>> {
+        let res = match run.current.take() {
+            Some(cont) => match self.step(cont, run.handlers_base) {
+                Ok(Some(next)) => {
+                    run.current = Some(next);
+                    return None;
+                }
+                Ok(None) => Ok(self.exit_code),
+                Err(e) => Err(e),
+            },
+            None => Ok(self.exit_code),
+        };
+
+        self.exception_handlers.truncate(run.handlers_base);
+        Some(res)
+    }
+
+    /// Runs the continuation and returns the next one.
+    fn step(&mut self, cont: Cont, handlers_base: usize) -> Result<Option<Cont>> {
+        let next = match self.run_cont(cont) {
+            Ok(next) => next,
+            Err(e) if self.exception_handlers.len() > handlers_base && is_catchable(&e) => {
+                self.catch(e)?
             }
+            Err(e) => return Err(self.attach_position(e)),
+        };
+        Ok(match next {
+            Some(next) => Some(next),
+            None => self.next.take(),
+        })
+    }
+
+    /// Executes `body` and catches errors until it finishes.
+    ///
+    /// Pushes `0` on success, or the error value and `-1` on failure.
+    pub fn begin_try(&mut self, body: Cont) -> Cont {
+        let next = self.next.take();
+        self.exception_handlers.push(ExceptionHandler {
+            stack_depth: self.stack.depth(),
+            next: next.clone(),
+        });
+        self.next = cont::SeqCont::make(Some(Rc::new(cont::ExcQuitCont)), next);
+        body
+    }
+
+    /// Creates an error which is caught by `try` as the specified value.
+    pub fn throw(&mut self, value: Box<dyn StackValue>) -> anyhow::Error {
+        let dump = value.display_dump().to_string();
+        self.thrown = Some(value);
+        crate::error::Thrown { value: dump }.into()
+    }
+
+    pub(crate) fn pop_exception_handler(&mut self) {
+        self.exception_handlers.pop();
+    }
+
+    fn catch(&mut self, error: anyhow::Error) -> Result<Option<Cont>> {
+        let Some(handler) = self.exception_handlers.pop() else {
+            return Err(error);
+        };
+        let error = crate::error::FiftError::unwrap_position(&error);
+
+        let value = match self.thrown.take() {
+            Some(value) if error.is::<crate::error::Thrown>() => value,
+            _ => Box::new(error.to_string()) as Box<dyn StackValue>,
+        };
+
+        self.stack.truncate(handler.stack_depth);
+        self.stack.push_raw(value)?;
+        self.stack.push_bool(true)?;
+        self.next = None;
+        Ok(handler.next)
+    }
+
+    fn run_cont(&mut self, cont: Cont) -> Result<Option<Cont>> {
+        if let Some(token) = &self.cancellation {
+            if token.take() {
+                let backtrace = cont.display_backtrace(&self.dictionary).to_string();
+                self.next = Some(cont);
+                anyhow::bail!(crate::error::Cancelled { backtrace });
+            }
+        }
+        if let Some(fuel) = &mut self.fuel {
+            if let Err(reason) = fuel.consume(self.stack.depth()) {
+                let backtrace = cont.display_backtrace(&self.dictionary).to_string();
+                self.next = Some(cont);
+                anyhow::bail!(crate::error::FuelExhausted { reason, backtrace });
+            }
+        }
+        if !self.block_limits.is_empty() {
+            self.consume_block_limits(&cont)?;
+        }
+        if self.debug_hook.is_some() {
+            self.pause(&cont)?;
+        }
+        if let Some(items) = self.trace {
+            self.trace_word(cont.as_ref(), items);
+        }
+        if let Some(hook) = &mut self.before_word_hook {
+            let word = WordInfo::new(cont.as_ref(), &self.dictionary);
+            hook(&word, &self.stack)?;
+        }
+
+        //eprintln!("   >>> {}", cont.display_name(&self.dictionary));
+        let word = self.after_word_hook.is_some().then(|| cont.clone());
+        let profiled = self.profiler.as_ref().map(|p| (cont.clone(), p.now()));
+        self.steps += 1;
+        let res = cont.run(self);
+        if let (Some((cont, started_at)), Some(profiler)) = (profiled, &mut self.profiler) {
+            profiler.record(cont, started_at);
+        }
+
+        if let (Some(word), Some(hook)) = (word, &mut self.after_word_hook) {
+            let word = WordInfo::new(word.as_ref(), &self.dictionary);
+            hook(&word, &self.stack, res.as_ref().err());
+        }
+        res
+    }
+
+    fn attach_position(&self, error: anyhow::Error) -> anyhow::Error {
+        // Errors from nested runs already have a more precise position
+        if error.is::<crate::error::FiftError>() {
+            return error;
+        }
+        match self.input.get_position() {
+            Some(pos) => crate::error::FiftError::new(&pos, error).into(),
+            None => error,
+        }
+    }
+
+    /// Interprets the specified source in the current context.
+    ///
+    /// Can also be used from native words, the current input
+    /// and continuation are restored afterwards.
+    pub fn eval_str(&mut self, source: &str) -> Result<()> {
+        let input = std::mem::take(&mut self.input);
+        let next = self.next.take();
+
+        self.input.push_source_block(SourceBlock::new(
+            "<eval>",
+            std::io::Cursor::new(source.to_owned()),
+        ));
+        let res = self.run();
+
+        self.input = input;
+        self.next = next;
+        res.map(|_| ())
+    }
+
+    /// Runs each source block as a separate script, starting from
+    /// the current dictionary (e.g. with an already executed preamble).
+    ///
+    /// Scripts don't share stacks or definitions, and the dictionary
+    /// is restored after all of them are executed. Words defined by
+    /// scripts (and boxes reachable from them) are copied for each run
+    /// like in [`Context::try_clone`], so variables changed by one script
+    /// are not visible to the next one.
+    ///
+    /// Fails if the state can't be saved with [`Context::snapshot`].
+    pub fn run_many<I>(&mut self, scripts: I) -> Result<Vec<Result<u8>>>
+    where
+        I: IntoIterator<Item = SourceBlock>,
+    {
+        self.reset();
+        let base = self.dictionary.clone();
+        let base_includes = self.includes.clone();
+        let snapshot = self.snapshot()?;
+
+        let mut results = Vec::new();
+        for script in scripts {
+            self.reset();
+            self.dictionary.clone_from(&base);
+            self.includes.clone_from(&base_includes);
+            self.restore(&snapshot)?;
+            self.input.push_source_block(script);
+            results.push(self.run());
+        }
+
+        self.reset();
+        self.dictionary = base;
+        self.includes = base_includes;
+        Ok(results)
+    }
+
+    fn reset(&mut self) {
+        self.state = State::Interpret;
+        self.open_blocks.clear();
+        self.stack.clear();
+        self.exit_code = 0;
+        self.next = None;
+        self.exit_interpret = Default::default();
+        self.block_limits.clear();
+        self.exception_handlers.clear();
+        self.thrown = None;
+        while self.input.pop_source_block() {}
+    }
+
+    /// Resets the interpreter after a failed interactive line,
+    /// so that the execution can be continued from the next line.
+    pub fn recover(&mut self, restore_stack: bool) {
+        self.next = None;
+        self.state = State::Interpret;
+        self.open_blocks.clear();
+
+        while self.input.depth() > 1 {
+            self.input.pop_source_block();
+        }
+        self.input.skip_line();
+
+        if let Some(history) = &mut self.history {
+            if restore_stack {
+                self.stack.replace_items(history.snapshot().to_vec());
+            }
+            history.fail_last(&self.stack);
+        }
+    }
+
+    /// Begins a new `{ ... }` block at the current input position.
+    pub fn begin_block(&mut self) -> Result<()> {
+        self.state.begin_compile()?;
+        if let Some(pos) = self.input.get_position() {
+            self.open_blocks.push(OpenBlock::new(&pos));
         }
+        Ok(())
+    }
 
-        Ok(self.exit_code)
+    pub fn end_block(&mut self) -> Result<()> {
+        self.state.end_compile()?;
+        self.open_blocks.pop();
+        Ok(())
     }
 
     pub(crate) fn execute_stack_top(&mut self) -> Result<Cont> {
@@ -105,16 +741,106 @@ impl<'a> Context<'a> {
         };
 
         let mut word_list = self.stack.pop_word_list()?;
+        let prev_len = word_list.items.len();
         word_list.items.extend(cont);
 
         if !self.dictionary.is_nop(&**word_def) {
             word_list.items.push(*word_def);
         }
 
+        if let Some(coverage) = &mut self.coverage {
+            let pos = coverage.register(self.input.get_position());
+            word_list.positions.resize(prev_len, None);
+            word_list.positions.resize(word_list.items.len(), pos);
+        }
+
         self.stack.push_raw(word_list)
     }
 }
 
+type BeforeWordHook<'a> = Box<dyn FnMut(&WordInfo<'_>, &Stack) -> Result<()> + 'a>;
+type AfterWordHook<'a> = Box<dyn FnMut(&WordInfo<'_>, &Stack, Option<&anyhow::Error>) + 'a>;
+
+/// A continuation which is passed to the execution hooks.
+#[derive(Clone, Copy)]
+pub struct WordInfo<'a> {
+    pub cont: &'a dyn ContImpl,
+    pub dictionary: &'a Dictionary,
+}
+
+impl<'a> WordInfo<'a> {
+    fn new(cont: &'a dyn ContImpl, dictionary: &'a Dictionary) -> Self {
+        Self { cont, dictionary }
+    }
+
+    pub fn name(&self) -> impl std::fmt::Display + 'a {
+        self.cont.display_name(self.dictionary)
+    }
+
+    /// Returns the dictionary word name if the continuation is a defined word.
+    pub fn word_name(&self) -> Option<&'a str> {
+        let name = self.dictionary.resolve_name(self.cont)?;
+        Some(name.trim_end())
+    }
+}
+
+/// A shared flag which aborts the execution at the next dispatch.
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Resets the flag, returning whether the execution was cancelled.
+    fn take(&self) -> bool {
+        // Plain load first to keep the hot path cheap
+        self.is_cancelled() && self.0.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// Execution budget which aborts the script when exceeded.
+///
+/// Unlike errors of native words, the failure can't be caught by `try`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Fuel {
+    /// Remaining number of continuations which can be executed.
+    pub steps: Option<u64>,
+    /// Max number of items on the stack before each continuation.
+    pub stack_depth: Option<usize>,
+}
+
+impl Fuel {
+    pub fn with_steps(steps: u64) -> Self {
+        Self {
+            steps: Some(steps),
+            stack_depth: None,
+        }
+    }
+
+    fn consume(&mut self, stack_depth: usize) -> Result<(), String> {
+        if let Some(max) = self.stack_depth {
+            if stack_depth > max {
+                return Err(format!(
+                    "stack depth {stack_depth} exceeds the limit of {max}"
+                ));
+            }
+        }
+        if let Some(steps) = &mut self.steps {
+            match steps.checked_sub(1) {
+                Some(rest) => *steps = rest,
+                None => return Err("no steps left".to_owned()),
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Default)]
 pub enum State {
     #[default]
@@ -128,6 +854,18 @@ impl State {
         matches!(self, Self::Compile(_))
     }
 
+    pub fn is_interpret_internal(&self) -> bool {
+        matches!(self, Self::InterpretInternal(_))
+    }
+
+    /// Returns the number of open `{ ... }` blocks.
+    pub fn depth(&self) -> u32 {
+        match self {
+            Self::Interpret => 0,
+            Self::Compile(depth) | Self::InterpretInternal(depth) => depth.get(),
+        }
+    }
+
     pub fn begin_compile(&mut self) -> Result<()> {
         match self {
             Self::Interpret => {
@@ -173,6 +911,54 @@ impl State {
     }
 }
 
+/// Source position of an open `{ ... }` block.
+#[derive(Debug, Clone)]
+pub struct OpenBlock {
+    pub source_block: String,
+    pub line_number: usize,
+    /// Column of the `{`, starting from 1.
+    pub column: usize,
+}
+
+impl OpenBlock {
+    fn new(pos: &lexer::LexerPosition<'_>) -> Self {
+        let end = std::cmp::min(pos.line_offset_end, pos.line.len());
+        Self {
+            source_block: pos.source_block_name.to_owned(),
+            line_number: pos.line_number,
+            column: std::cmp::max(pos.line[..end].chars().count(), 1),
+        }
+    }
+}
+
+impl std::fmt::Display for OpenBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.source_block, self.line_number, self.column
+        )
+    }
+}
+
+struct ExceptionHandler {
+    stack_depth: usize,
+    next: Option<Cont>,
+}
+
+/// State of the interpreter loop, shared by [`Context::run`]
+/// and [`Context::run_async`].
+struct RunLoop {
+    current: Option<Cont>,
+    /// Handlers from outer runs must not catch errors of this one.
+    handlers_base: usize,
+}
+
+fn is_catchable(error: &anyhow::Error) -> bool {
+    let error = crate::error::FiftError::unwrap_position(error);
+    !error.is::<crate::error::Cancelled>() && !error.is::<crate::error::FuelExhausted>()
+}
+
 pub trait Module {
     fn init(&self, d: &mut Dictionary) -> Result<()>;
 }
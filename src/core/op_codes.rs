@@ -0,0 +1,104 @@
+use ahash::HashMap;
+use everscale_types::prelude::*;
+
+use crate::util::{load_snake_data, DisplaySliceExt};
+
+/// Names of known message body op codes.
+#[derive(Clone)]
+pub struct OpCodes {
+    names: HashMap<u32, String>,
+}
+
+impl OpCodes {
+    pub const TEXT_COMMENT: u32 = 0x00000000;
+    pub const BOUNCE: u32 = 0xffffffff;
+
+    /// Creates a registry without any known op codes.
+    pub fn empty() -> Self {
+        Self {
+            names: Default::default(),
+        }
+    }
+
+    pub fn get(&self, op: u32) -> Option<&str> {
+        self.names.get(&op).map(String::as_str)
+    }
+
+    /// Registers a name for the op code, returns the previous name if any.
+    pub fn insert<T: Into<String>>(&mut self, op: u32, name: T) -> Option<String> {
+        self.names.insert(op, name.into())
+    }
+
+    pub fn remove(&mut self, op: u32) -> Option<String> {
+        self.names.remove(&op)
+    }
+
+    /// Displays the message body with its op code labeled.
+    pub fn display_body<'a>(&'a self, body: CellSlice<'a>, limit: usize) -> DisplayBody<'a> {
+        DisplayBody {
+            op_codes: self,
+            body,
+            limit,
+        }
+    }
+}
+
+impl Default for OpCodes {
+    fn default() -> Self {
+        let mut op_codes = Self::empty();
+        for (op, name) in COMMON_OP_CODES {
+            op_codes.insert(*op, *name);
+        }
+        op_codes
+    }
+}
+
+pub struct DisplayBody<'a> {
+    op_codes: &'a OpCodes,
+    body: CellSlice<'a>,
+    limit: usize,
+}
+
+impl std::fmt::Display for DisplayBody<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut cs = self.body;
+        let Ok(op) = cs.load_u32() else {
+            return write!(f, "{}", self.body.display_slice_tree(self.limit));
+        };
+
+        if op == OpCodes::TEXT_COMMENT {
+            if let Some(comment) = load_snake_data(cs)
+                .ok()
+                .and_then(|data| String::from_utf8(data).ok())
+            {
+                return writeln!(f, "comment {comment:?}");
+            }
+        }
+
+        match self.op_codes.get(op) {
+            Some(name) => writeln!(f, "op 0x{op:08x} ({name})")?,
+            None => writeln!(f, "op 0x{op:08x}")?,
+        }
+        write!(f, "{}", cs.display_slice_tree(self.limit))
+    }
+}
+
+const COMMON_OP_CODES: &[(u32, &str)] = &[
+    (OpCodes::TEXT_COMMENT, "comment"),
+    (OpCodes::BOUNCE, "bounce"),
+    (0xd53276db, "excesses"),
+    // TEP-62
+    (0x5fcc3d14, "nft_transfer"),
+    (0x05138d91, "nft_ownership_assigned"),
+    (0x2fcb26a2, "get_static_data"),
+    (0x8b771735, "report_static_data"),
+    // TEP-74
+    (0x0f8a7ea5, "jetton_transfer"),
+    (0x178d4519, "jetton_internal_transfer"),
+    (0x7362d09c, "jetton_transfer_notification"),
+    (0x595f07bc, "jetton_burn"),
+    (0x7bdd97de, "jetton_burn_notification"),
+    // TEP-89
+    (0x2c76b973, "provide_wallet_address"),
+    (0xd1735400, "take_wallet_address"),
+];
@@ -0,0 +1,77 @@
+use std::io::Write;
+
+/// Output of printing words.
+///
+/// Everything written to the primary writer is also duplicated to the
+/// additional writers (e.g. a terminal and a build log file).
+pub struct Output<'a> {
+    primary: &'a mut dyn Write,
+    tees: Vec<Box<dyn Write + 'a>>,
+    log: Option<Box<dyn Write + 'a>>,
+}
+
+impl<'a> Output<'a> {
+    pub fn new(primary: &'a mut dyn Write) -> Self {
+        Self {
+            primary,
+            tees: Vec::new(),
+            log: None,
+        }
+    }
+
+    /// Duplicates the output to the specified writer.
+    pub fn add_tee<W: Write + 'a>(&mut self, writer: W) {
+        self.tees.push(Box::new(writer));
+    }
+
+    /// Flushes and removes all additional writers, except the log.
+    pub fn clear_tees(&mut self) -> std::io::Result<()> {
+        for mut tee in self.tees.drain(..) {
+            tee.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Duplicates the output to the log writer, replacing the previous one.
+    ///
+    /// Used by the `log-output-to` word.
+    pub fn set_log<W: Write + 'a>(&mut self, writer: W) -> std::io::Result<()> {
+        self.clear_log()?;
+        self.log = Some(Box::new(writer));
+        Ok(())
+    }
+
+    /// Flushes and removes the log writer.
+    pub fn clear_log(&mut self) -> std::io::Result<()> {
+        match self.log.take() {
+            Some(mut log) => log.flush(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn has_log(&self) -> bool {
+        self.log.is_some()
+    }
+
+    fn secondary(&mut self) -> impl Iterator<Item = &mut Box<dyn Write + 'a>> {
+        self.tees.iter_mut().chain(&mut self.log)
+    }
+}
+
+impl Write for Output<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.primary.write(buf)?;
+        for writer in self.secondary() {
+            writer.write_all(&buf[..n])?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.primary.flush()?;
+        for writer in self.secondary() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use ahash::HashMap;
+
+use super::cont::Cont;
+use super::dictionary::Dictionary;
+use super::timings::{default_clock, Clock};
+
+/// Number of calls and time spent in each executed dictionary word.
+///
+/// Only the dispatch of the word itself is measured, words which are
+/// called from a word defined in Fift are counted separately.
+pub struct Profiler {
+    words: HashMap<*const (), WordProfile>,
+    clock: Clock,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::with_clock(default_clock)
+    }
+}
+
+impl Profiler {
+    pub fn with_clock(clock: Clock) -> Self {
+        Self {
+            words: Default::default(),
+            clock,
+        }
+    }
+
+    pub(crate) fn now(&self) -> Duration {
+        (self.clock)()
+    }
+
+    /// Records an execution of the continuation which started at `started_at`.
+    pub(crate) fn record(&mut self, cont: Cont, started_at: Duration) {
+        let elapsed = self.now().saturating_sub(started_at);
+        let entry = self
+            .words
+            .entry(Rc::as_ptr(&cont) as *const ())
+            .or_insert_with(|| WordProfile {
+                cont,
+                calls: 0,
+                elapsed: Duration::ZERO,
+            });
+        entry.calls += 1;
+        entry.elapsed += elapsed;
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear();
+    }
+
+    /// Returns statistics of the dictionary words, sorted by the total time.
+    pub fn report(&self, d: &Dictionary) -> Vec<WordReport> {
+        let mut report = self
+            .words
+            .values()
+            .filter_map(|word| {
+                let name = d.resolve_name(word.cont.as_ref())?;
+                Some(WordReport {
+                    name: name.trim_end().to_owned(),
+                    calls: word.calls,
+                    elapsed: word.elapsed,
+                })
+            })
+            .collect::<Vec<_>>();
+        report.sort_by(|a, b| b.elapsed.cmp(&a.elapsed).then_with(|| a.name.cmp(&b.name)));
+        report
+    }
+
+    /// Displays the statistics of the dictionary words as a table.
+    pub fn display_report<'a>(&'a self, d: &'a Dictionary) -> impl std::fmt::Display + 'a {
+        struct DisplayReport<'a>(&'a Profiler, &'a Dictionary);
+
+        impl std::fmt::Display for DisplayReport<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                writeln!(
+                    f,
+                    "{:>10} {:>10} {:>10}  word",
+                    "calls", "total ms", "avg us"
+                )?;
+                for word in self.0.report(self.1) {
+                    let total = word.elapsed.as_secs_f64() * 1000.0;
+                    let avg = word.elapsed.as_secs_f64() * 1e6 / word.calls as f64;
+                    writeln!(
+                        f,
+                        "{:>10} {total:>10.3} {avg:>10.3}  {}",
+                        word.calls, word.name
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        DisplayReport(self, d)
+    }
+}
+
+/// Statistics of a single dictionary word.
+#[derive(Debug, Clone)]
+pub struct WordReport {
+    pub name: String,
+    pub calls: u64,
+    /// Total time spent in the word dispatch.
+    pub elapsed: Duration,
+}
+
+struct WordProfile {
+    /// Keeps the continuation alive, so that its address is not reused.
+    cont: Cont,
+    calls: u64,
+    elapsed: Duration,
+}
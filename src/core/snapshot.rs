@@ -0,0 +1,569 @@
+use std::collections::hash_map::{self, HashMap};
+use std::io::Write;
+use std::rc::Rc;
+
+use anyhow::{Context as _, Result};
+use everscale_types::boc::Boc;
+use everscale_types::prelude::*;
+use num_bigint::BigInt;
+
+use super::cont::{ListCont, LitCont, MultiLitCont};
+use super::{
+    Atom, Cont, Context, Dictionary, DictionaryEntry, Environment, OwnedCellSlice, SharedBox,
+    StackValue, StackValueType, WordList, WordLocation,
+};
+
+impl Context<'_> {
+    /// Serializes the stack, words defined by scripts and boxes
+    /// reachable from them into a binary blob.
+    ///
+    /// Native words are saved by their names, so the snapshot can only
+    /// be restored into a context with the same modules.
+    pub fn snapshot(&self) -> Result<Vec<u8>> {
+        let mut encoder = Encoder::new(&self.dictionary);
+
+        let mut stack = Vec::new();
+        write_len(&mut stack, self.stack.depth());
+        for item in self.stack.items() {
+            encoder.write_value(&mut stack, item.as_ref())?;
+        }
+
+        let mut words = Vec::new();
+        let mut word_count = 0;
+        for (vocabulary, name, entry) in self.dictionary.entries() {
+            let Some(location) = &entry.location else {
+                continue;
+            };
+            let id = encoder.write_cont(&entry.definition)?;
+            write_str(&mut words, vocabulary);
+            write_str(&mut words, name);
+            words.push(entry.active as u8);
+            write_str(&mut words, &location.source_block);
+            write_len(&mut words, location.line_number);
+            write_len(&mut words, location.column);
+            write_len(&mut words, id);
+            word_count += 1;
+        }
+
+        // Box contents can reference new boxes and continuations
+        let mut boxes = Vec::new();
+        let mut i = 0;
+        while let Some(shared_box) = encoder.boxes.get(i) {
+            let value = shared_box.fetch();
+            boxes.push(shared_box.is_frozen() as u8);
+            encoder.write_value(&mut boxes, value.as_ref())?;
+            i += 1;
+        }
+
+        let mut result = Vec::with_capacity(
+            MAGIC.len() + encoder.nodes.len() + boxes.len() + stack.len() + words.len() + 16,
+        );
+        result.extend_from_slice(MAGIC);
+        write_len(&mut result, encoder.boxes.len());
+        write_len(&mut result, encoder.node_count);
+        result.extend_from_slice(&encoder.nodes);
+        result.extend_from_slice(&boxes);
+        result.extend_from_slice(&stack);
+        write_len(&mut result, word_count);
+        result.extend_from_slice(&words);
+        Ok(result)
+    }
+
+    /// Creates a context with a copy of the stack and the dictionary,
+    /// e.g. to evaluate a snippet speculatively and compare the outcome.
+    ///
+    /// Native words are shared, while words defined by scripts and boxes
+    /// reachable from them or from the stack are copied, so the clone
+    /// can't affect this context. Input, hooks and user data are not copied.
+    ///
+    /// Fails if the state can't be saved with [`Context::snapshot`].
+    pub fn try_clone<'b>(
+        &self,
+        env: &'b mut dyn Environment,
+        stdout: &'b mut dyn Write,
+    ) -> Result<Context<'b>> {
+        let snapshot = self.snapshot()?;
+
+        let mut ctx = Context::new(env, stdout);
+        ctx.dictionary = self.dictionary.clone();
+        ctx.stack.set_capacity(self.stack.capacity());
+        *ctx.stack.atoms_mut() = self.stack.atoms().clone();
+        ctx.fuel = self.fuel;
+        ctx.strict = self.strict.clone();
+        ctx.op_codes = self.op_codes.clone();
+        ctx.output_radix = self.output_radix;
+        ctx.stack_page_size = self.stack_page_size;
+        ctx.dump_style = self.dump_style;
+        ctx.includes = self.includes.clone();
+        ctx.params = self.params.clone();
+        #[cfg(feature = "stdlib")]
+        {
+            ctx.stdlib = self.stdlib;
+        }
+        ctx.restore(&snapshot)?;
+        Ok(ctx)
+    }
+
+    /// Replaces the stack and redefines words from the snapshot.
+    ///
+    /// The context is left unchanged if the snapshot is invalid.
+    pub fn restore(&mut self, snapshot: &[u8]) -> Result<()> {
+        let mut decoder = Decoder {
+            data: snapshot,
+            dictionary: &self.dictionary,
+            boxes: Vec::new(),
+            nodes: Vec::new(),
+            depth: 0,
+        };
+        let snapshot = decoder.read_snapshot().context("Invalid snapshot")?;
+
+        // Words are defined in a copy, so that a failed definition
+        // doesn't leave the dictionary partially restored
+        let mut dictionary = self.dictionary.clone();
+        for word in snapshot.words {
+            if !dictionary.has_vocabulary(&word.vocabulary) {
+                dictionary.define_vocabulary(word.vocabulary.clone())?;
+            }
+            dictionary.define_word_in(&word.vocabulary, word.name, word.entry, true)?;
+        }
+        self.dictionary = dictionary;
+        self.stack.replace_items(snapshot.stack);
+        Ok(())
+    }
+}
+
+const MAGIC: &[u8] = b"FIFTSNAP\x02";
+
+/// Max nesting of tuples, so that values are decoded without a stack overflow.
+const MAX_TUPLE_DEPTH: usize = 1024;
+
+const VALUE_NULL: u8 = 0;
+const VALUE_INT: u8 = 1;
+const VALUE_CELL: u8 = 2;
+const VALUE_BUILDER: u8 = 3;
+const VALUE_SLICE: u8 = 4;
+const VALUE_STRING: u8 = 5;
+const VALUE_BYTES: u8 = 6;
+const VALUE_TUPLE: u8 = 7;
+const VALUE_CONT: u8 = 8;
+const VALUE_WORD_LIST: u8 = 9;
+const VALUE_BOX: u8 = 10;
+const VALUE_NAMED_ATOM: u8 = 11;
+const VALUE_UNNAMED_ATOM: u8 = 12;
+
+const NODE_NOP: u8 = 0;
+const NODE_NAMED: u8 = 1;
+const NODE_LIST: u8 = 2;
+const NODE_LIT: u8 = 3;
+
+struct Encoder<'a> {
+    dictionary: &'a Dictionary,
+    /// Serialized continuations, children before parents.
+    nodes: Vec<u8>,
+    node_count: usize,
+    node_ids: HashMap<*const (), usize>,
+    boxes: Vec<SharedBox>,
+    box_ids: HashMap<*const (), usize>,
+    /// Nesting of the tuple which is being written.
+    depth: usize,
+}
+
+impl<'a> Encoder<'a> {
+    fn new(dictionary: &'a Dictionary) -> Self {
+        Self {
+            dictionary,
+            nodes: Vec::new(),
+            node_count: 0,
+            node_ids: Default::default(),
+            boxes: Vec::new(),
+            box_ids: Default::default(),
+            depth: 0,
+        }
+    }
+
+    fn write_value(&mut self, out: &mut Vec<u8>, value: &dyn StackValue) -> Result<()> {
+        match value.ty() {
+            StackValueType::Null => out.push(VALUE_NULL),
+            StackValueType::Int => {
+                out.push(VALUE_INT);
+                write_bytes(out, &value.as_int()?.to_signed_bytes_le());
+            }
+            StackValueType::Cell => {
+                out.push(VALUE_CELL);
+                write_bytes(out, &Boc::encode(value.as_cell()?.as_ref()));
+            }
+            StackValueType::Builder => {
+                out.push(VALUE_BUILDER);
+                let cell = value.as_builder()?.clone().build()?;
+                write_bytes(out, &Boc::encode(cell));
+            }
+            StackValueType::Slice => {
+                out.push(VALUE_SLICE);
+                let cs = value.as_slice()?;
+                write_bytes(out, &Boc::encode(cs.cell()));
+                let range = cs.range();
+                write_len(out, range.bits_offset() as usize);
+                write_len(out, range.refs_offset() as usize);
+                write_len(out, range.remaining_bits() as usize);
+                write_len(out, range.remaining_refs() as usize);
+            }
+            StackValueType::String => {
+                out.push(VALUE_STRING);
+                write_str(out, value.as_string()?);
+            }
+            StackValueType::Bytes => {
+                out.push(VALUE_BYTES);
+                write_bytes(out, value.as_bytes()?);
+            }
+            StackValueType::Tuple => {
+                let items = value.as_tuple()?;
+                anyhow::ensure!(
+                    self.depth < MAX_TUPLE_DEPTH,
+                    "Tuples are nested deeper than {MAX_TUPLE_DEPTH} levels"
+                );
+                out.push(VALUE_TUPLE);
+                write_len(out, items.len());
+                self.depth += 1;
+                for item in items {
+                    self.write_value(out, item.as_ref())?;
+                }
+                self.depth -= 1;
+            }
+            StackValueType::Cont => {
+                let id = self.write_cont(value.as_cont()?)?;
+                out.push(VALUE_CONT);
+                write_len(out, id);
+            }
+            StackValueType::WordList => {
+                let ids = self.write_word_list(value.as_word_list()?)?;
+                out.push(VALUE_WORD_LIST);
+                write_len(out, ids.len());
+                for id in ids {
+                    write_len(out, id);
+                }
+            }
+            StackValueType::SharedBox => {
+                let shared_box = value.as_box()?;
+                let id = match self.box_ids.entry(shared_box.as_ptr()) {
+                    hash_map::Entry::Occupied(entry) => *entry.get(),
+                    hash_map::Entry::Vacant(entry) => {
+                        self.boxes.push(shared_box.clone());
+                        *entry.insert(self.boxes.len() - 1)
+                    }
+                };
+                out.push(VALUE_BOX);
+                write_len(out, id);
+            }
+            StackValueType::Atom => match value.as_atom()? {
+                Atom::Named(name) => {
+                    out.push(VALUE_NAMED_ATOM);
+                    write_str(out, name);
+                }
+                Atom::Unnamed(id) => {
+                    out.push(VALUE_UNNAMED_ATOM);
+                    out.extend_from_slice(&id.to_le_bytes());
+                }
+            },
+            ty => anyhow::bail!("Values of type `{ty:?}` cannot be saved"),
+        }
+        Ok(())
+    }
+
+    fn write_cont(&mut self, cont: &Cont) -> Result<usize> {
+        let addr = Rc::as_ptr(cont) as *const ();
+        if let Some(id) = self.node_ids.get(&addr) {
+            return Ok(*id);
+        }
+
+        let mut node = Vec::new();
+        if self.dictionary.is_nop(cont.as_ref()) {
+            node.push(NODE_NOP);
+        } else if let Some(list) = cont.word_list() {
+            let ids = self.write_word_list(list)?;
+            node.push(NODE_LIST);
+            write_len(&mut node, ids.len());
+            for id in ids {
+                write_len(&mut node, id);
+            }
+        } else if let Some(values) = cont.literals() {
+            node.push(NODE_LIT);
+            write_len(&mut node, values.len());
+            for value in values {
+                self.write_value(&mut node, value.as_ref())?;
+            }
+        } else if let Some(name) = self.dictionary.resolve_name(cont.as_ref()) {
+            node.push(NODE_NAMED);
+            write_str(&mut node, name);
+        } else {
+            anyhow::bail!(
+                "Continuation `{}` cannot be saved",
+                cont.display_name(self.dictionary)
+            );
+        }
+
+        let id = self.node_count;
+        self.node_count += 1;
+        self.nodes.extend_from_slice(&node);
+        self.node_ids.insert(addr, id);
+        Ok(id)
+    }
+
+    fn write_word_list(&mut self, list: &WordList) -> Result<Vec<usize>> {
+        list.items
+            .iter()
+            .map(|item| self.write_cont(item))
+            .collect()
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    dictionary: &'a Dictionary,
+    boxes: Vec<SharedBox>,
+    nodes: Vec<Cont>,
+    /// Nesting of the tuple which is being read.
+    depth: usize,
+}
+
+struct Snapshot {
+    stack: Vec<Box<dyn StackValue>>,
+    words: Vec<SavedWord>,
+}
+
+struct SavedWord {
+    vocabulary: String,
+    name: String,
+    entry: DictionaryEntry,
+}
+
+impl<'a> Decoder<'a> {
+    fn read_snapshot(&mut self) -> Result<Snapshot> {
+        anyhow::ensure!(
+            self.data.starts_with(MAGIC),
+            "Unknown snapshot format or version"
+        );
+        self.data = &self.data[MAGIC.len()..];
+
+        let box_count = self.read_count()?;
+        self.boxes = (0..box_count).map(|_| SharedBox::default()).collect();
+
+        let node_count = self.read_count()?;
+        for _ in 0..node_count {
+            let node = self.read_node()?;
+            self.nodes.push(node);
+        }
+
+        for i in 0..box_count {
+            let frozen = self.read_u8()? != 0;
+            let value = self.read_value()?;
+            self.boxes[i].store(value);
+            if frozen {
+                self.boxes[i].freeze();
+            }
+        }
+
+        let depth = self.read_count()?;
+        let stack = (0..depth)
+            .map(|_| self.read_value())
+            .collect::<Result<Vec<_>>>()?;
+
+        let word_count = self.read_count()?;
+        let mut words = Vec::with_capacity(word_count);
+        for _ in 0..word_count {
+            let vocabulary = self.read_str()?;
+            let name = self.read_str()?;
+            let active = self.read_u8()? != 0;
+            let location = WordLocation {
+                source_block: self.read_str()?,
+                line_number: self.read_len()?,
+                column: self.read_len()?,
+            };
+            let definition = self.read_node_ref()?;
+            words.push(SavedWord {
+                vocabulary,
+                name,
+                entry: DictionaryEntry {
+                    definition,
+                    active,
+                    location: Some(location),
+                },
+            });
+        }
+
+        anyhow::ensure!(self.data.is_empty(), "Unexpected data after the snapshot");
+        Ok(Snapshot { stack, words })
+    }
+
+    fn read_node(&mut self) -> Result<Cont> {
+        Ok(match self.read_u8()? {
+            NODE_NOP => self.dictionary.make_nop(),
+            NODE_NAMED => {
+                let name = self.read_str()?;
+                match self.dictionary.lookup(&name) {
+                    Some(entry) => entry.definition.clone(),
+                    None => anyhow::bail!("Unknown word `{}`", name.trim_end()),
+                }
+            }
+            NODE_LIST => Rc::new(ListCont {
+                list: Rc::new(self.read_word_list()?),
+                after: None,
+                pos: 0,
+            }),
+            NODE_LIT => {
+                let len = self.read_count()?;
+                let mut values = (0..len)
+                    .map(|_| self.read_value())
+                    .collect::<Result<Vec<_>>>()?;
+                match values.len() {
+                    1 => Rc::new(LitCont(values.remove(0))),
+                    _ => Rc::new(MultiLitCont(values)),
+                }
+            }
+            tag => anyhow::bail!("Unknown continuation tag {tag}"),
+        })
+    }
+
+    fn read_node_ref(&mut self) -> Result<Cont> {
+        let id = self.read_len()?;
+        match self.nodes.get(id) {
+            Some(node) => Ok(node.clone()),
+            None => anyhow::bail!("Unknown continuation id {id}"),
+        }
+    }
+
+    fn read_word_list(&mut self) -> Result<WordList> {
+        let len = self.read_count()?;
+        Ok(WordList {
+            items: (0..len)
+                .map(|_| self.read_node_ref())
+                .collect::<Result<_>>()?,
+            positions: Vec::new(),
+        })
+    }
+
+    fn read_value(&mut self) -> Result<Box<dyn StackValue>> {
+        Ok(match self.read_u8()? {
+            VALUE_NULL => Box::new(()),
+            VALUE_INT => Box::new(BigInt::from_signed_bytes_le(self.read_bytes()?)),
+            VALUE_CELL => Box::new(Boc::decode(self.read_bytes()?)?),
+            VALUE_BUILDER => {
+                let cell = Boc::decode(self.read_bytes()?)?;
+                let mut builder = CellBuilder::new();
+                builder.store_slice(DynCell::as_slice(cell.as_ref())?)?;
+                Box::new(builder)
+            }
+            VALUE_SLICE => {
+                let cell = Boc::decode(self.read_bytes()?)?;
+                let bits_offset = u16::try_from(self.read_len()?).context("Invalid slice range")?;
+                let refs_offset = u8::try_from(self.read_len()?).context("Invalid slice range")?;
+                let bits = u16::try_from(self.read_len()?).context("Invalid slice range")?;
+                let refs = u8::try_from(self.read_len()?).context("Invalid slice range")?;
+
+                let mut cs = DynCell::as_slice(cell.as_ref())?;
+                anyhow::ensure!(
+                    cs.try_advance(bits_offset, refs_offset),
+                    "Invalid slice range"
+                );
+                anyhow::ensure!(cs.has_remaining(bits, refs), "Invalid slice range");
+                let range = cs.get_prefix(bits, refs).range();
+                Box::new(OwnedCellSlice::from((cell, range)))
+            }
+            VALUE_STRING => Box::new(self.read_str()?),
+            VALUE_BYTES => Box::new(self.read_bytes()?.to_vec()),
+            VALUE_TUPLE => {
+                anyhow::ensure!(
+                    self.depth < MAX_TUPLE_DEPTH,
+                    "Tuples are nested deeper than {MAX_TUPLE_DEPTH} levels"
+                );
+                let len = self.read_count()?;
+                self.depth += 1;
+                let items = (0..len)
+                    .map(|_| self.read_value())
+                    .collect::<Result<Vec<_>>>()?;
+                self.depth -= 1;
+                Box::new(items)
+            }
+            VALUE_CONT => Box::new(self.read_node_ref()?),
+            VALUE_WORD_LIST => Box::new(self.read_word_list()?),
+            VALUE_BOX => {
+                let id = self.read_len()?;
+                match self.boxes.get(id) {
+                    Some(shared_box) => Box::new(shared_box.clone()),
+                    None => anyhow::bail!("Unknown box id {id}"),
+                }
+            }
+            VALUE_NAMED_ATOM => Box::new(Atom::Named(Rc::from(self.read_str()?))),
+            VALUE_UNNAMED_ATOM => {
+                let id = self.read_array::<4>()?;
+                Box::new(Atom::Unnamed(i32::from_le_bytes(id)))
+            }
+            tag => anyhow::bail!("Unknown value tag {tag}"),
+        })
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let [byte] = self.read_array::<1>()?;
+        Ok(byte)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes = self.read_raw(N)?;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let mut result = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.read_u8()?;
+            result |= ((byte & 0x7f) as usize)
+                .checked_shl(shift)
+                .context("Length overflow")?;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        anyhow::bail!("Length overflow")
+    }
+
+    /// Reads the number of items which follow, each of them takes
+    /// at least one byte, so that the count can't exceed the input.
+    fn read_count(&mut self) -> Result<usize> {
+        let count = self.read_len()?;
+        anyhow::ensure!(count <= self.data.len(), "Unexpected end of snapshot");
+        Ok(count)
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.read_len()?;
+        self.read_raw(len)
+    }
+
+    fn read_str(&mut self) -> Result<String> {
+        let bytes = self.read_bytes()?;
+        Ok(std::str::from_utf8(bytes)?.to_owned())
+    }
+
+    fn read_raw(&mut self, len: usize) -> Result<&'a [u8]> {
+        anyhow::ensure!(len <= self.data.len(), "Unexpected end of snapshot");
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+}
+
+fn write_len(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 0x80 {
+        out.push((len as u8) | 0x80);
+        len >>= 7;
+    }
+    out.push(len as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_len(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_bytes(out, s.as_bytes());
+}
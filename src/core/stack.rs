@@ -6,10 +6,13 @@ use anyhow::Result;
 use dyn_clone::DynClone;
 use everscale_types::prelude::*;
 use num_bigint::BigInt;
-use num_traits::{One, ToPrimitive, Zero};
+use num_traits::{Bounded, One, ToPrimitive, Zero};
 
 use super::cont::*;
-use crate::util::DisplaySliceExt;
+use super::coverage::WordPosition;
+use super::env::SourceBlockValue;
+use crate::format::DisplayInt;
+use crate::util::{int_to_hash, DisplaySliceExt};
 
 pub struct Stack {
     items: Vec<Box<dyn StackValue>>,
@@ -30,6 +33,16 @@ impl Stack {
         self.items.len()
     }
 
+    /// Returns the max number of items, `None` means unlimited.
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Sets the max number of items, `None` means unlimited.
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+    }
+
     pub fn atoms(&self) -> &Atoms {
         &self.atoms
     }
@@ -60,17 +73,64 @@ impl Stack {
         Ok(())
     }
 
+    /// Moves the item at the specified depth to the top.
+    pub fn roll(&mut self, idx: usize) -> Result<()> {
+        self.check_underflow(idx + 1)?;
+        let len = self.items.len();
+        self.items[len - idx - 1..].rotate_left(1);
+        Ok(())
+    }
+
+    /// Moves the top item to the specified depth.
+    pub fn roll_rev(&mut self, idx: usize) -> Result<()> {
+        self.check_underflow(idx + 1)?;
+        let len = self.items.len();
+        self.items[len - idx - 1..].rotate_right(1);
+        Ok(())
+    }
+
+    /// Reverses the order of `n` items below the top `m` items.
+    pub fn reverse(&mut self, n: usize, m: usize) -> Result<()> {
+        if n == 0 {
+            return Ok(());
+        }
+        self.check_underflow(n + m)?;
+        let len = self.items.len();
+        self.items[len - n - m..len - m].reverse();
+        Ok(())
+    }
+
+    /// Removes the top `n` items.
+    pub fn drop_n(&mut self, n: usize) -> Result<()> {
+        self.check_underflow(n)?;
+        self.items.truncate(self.items.len() - n);
+        Ok(())
+    }
+
+    /// Duplicates the top `n` items.
+    pub fn dup_n(&mut self, n: usize) -> Result<()> {
+        self.check_underflow(n)?;
+        if let Some(capacity) = self.capacity {
+            anyhow::ensure!(
+                self.items.len() + n <= capacity,
+                StackError::StackOverflow(capacity)
+            );
+        }
+        let len = self.items.len();
+        self.items.extend_from_within(len - n..);
+        Ok(())
+    }
+
     pub fn push<T: StackValue + 'static>(&mut self, item: T) -> Result<()> {
         self.push_raw(Box::new(item))
     }
 
     pub fn push_raw(&mut self, item: Box<dyn StackValue>) -> Result<()> {
-        if let Some(capacity) = &mut self.capacity {
+        if let Some(capacity) = self.capacity {
             anyhow::ensure!(
-                self.items.len() < *capacity,
-                StackError::StackOverflow(*capacity)
+                self.items.len() < capacity,
+                StackError::StackOverflow(capacity)
             );
-            *capacity += 1;
         }
         self.items.push(item);
         //eprintln!("AFTER PUSH: {}", self.display_dump());
@@ -114,8 +174,8 @@ impl Stack {
             }
         }
         anyhow::bail!(StackError::IntegerOutOfRange {
-            min,
-            max: max as usize,
+            min: min.into(),
+            max: max.into(),
             actual: item.to_string(),
         })
     }
@@ -127,7 +187,60 @@ impl Stack {
         }
         anyhow::bail!(StackError::IntegerOutOfRange {
             min: 0,
-            max: usize::MAX,
+            max: usize::MAX as i128,
+            actual: item.to_string(),
+        })
+    }
+
+    /// Pops a cell hash as a cell or an unsigned 256-bit integer.
+    pub fn pop_hash(&mut self) -> Result<HashBytes> {
+        self.pop()?.as_hash()
+    }
+
+    pub fn pop_u8_checked(&mut self) -> Result<u8> {
+        self.pop_int_checked()
+    }
+
+    pub fn pop_u16_checked(&mut self) -> Result<u16> {
+        self.pop_int_checked()
+    }
+
+    pub fn pop_u32_checked(&mut self) -> Result<u32> {
+        self.pop_int_checked()
+    }
+
+    pub fn pop_u64_checked(&mut self) -> Result<u64> {
+        self.pop_int_checked()
+    }
+
+    pub fn pop_i8_checked(&mut self) -> Result<i8> {
+        self.pop_int_checked()
+    }
+
+    pub fn pop_i16_checked(&mut self) -> Result<i16> {
+        self.pop_int_checked()
+    }
+
+    pub fn pop_i32_checked(&mut self) -> Result<i32> {
+        self.pop_int_checked()
+    }
+
+    pub fn pop_i64_checked(&mut self) -> Result<i64> {
+        self.pop_int_checked()
+    }
+
+    /// Pops an integer which must fit into `T` without truncation.
+    fn pop_int_checked<T>(&mut self) -> Result<T>
+    where
+        T: for<'a> TryFrom<&'a BigInt> + Bounded + Into<i128>,
+    {
+        let item = self.pop_int()?;
+        if let Ok(item) = T::try_from(item.as_ref()) {
+            return Ok(item);
+        }
+        anyhow::bail!(StackError::IntegerOutOfRange {
+            min: T::min_value().into(),
+            max: T::max_value().into(),
             actual: item.to_string(),
         })
     }
@@ -188,6 +301,10 @@ impl Stack {
         self.pop()?.into_atom()
     }
 
+    pub fn pop_source_block(&mut self) -> Result<Box<SourceBlockValue>> {
+        self.pop()?.into_source_block()
+    }
+
     pub fn items(&self) -> &[Box<dyn StackValue>] {
         &self.items
     }
@@ -196,6 +313,20 @@ impl Stack {
         self.items.clear();
     }
 
+    /// Removes items above the specified depth.
+    pub fn truncate(&mut self, depth: usize) {
+        self.items.truncate(depth);
+    }
+
+    /// Removes and returns items above the specified depth.
+    pub fn split_off(&mut self, depth: usize) -> Vec<Box<dyn StackValue>> {
+        self.items.split_off(std::cmp::min(depth, self.items.len()))
+    }
+
+    pub fn replace_items(&mut self, items: Vec<Box<dyn StackValue>>) {
+        self.items = items;
+    }
+
     pub fn display_dump(&self) -> impl std::fmt::Display + '_ {
         struct DisplayDump<'a>(&'a Stack);
 
@@ -239,6 +370,21 @@ impl Stack {
     }
 }
 
+/// Writes bytes as hex without allocating the whole string.
+fn write_hex(f: &mut std::fmt::Formatter<'_>, bytes: &[u8], upper: bool) -> std::fmt::Result {
+    const CHUNK: usize = 64;
+    let mut buffer = [0u8; CHUNK * 2];
+    for chunk in bytes.chunks(CHUNK) {
+        let buffer = &mut buffer[..chunk.len() * 2];
+        hex::encode_to_slice(chunk, buffer).unwrap();
+        if upper {
+            buffer.make_ascii_uppercase();
+        }
+        f.write_str(std::str::from_utf8(buffer).unwrap())?;
+    }
+    Ok(())
+}
+
 macro_rules! define_stack_value {
     ($trait:ident($value_type:ident), {$(
         $name:ident($ty:ty) = {
@@ -323,13 +469,16 @@ define_stack_value! {
         },
         Int(BigInt) = {
             eq(a, b) = a == b,
-            fmt_dump(v, f) = std::fmt::Display::fmt(v, f),
+            fmt_dump(v, f) = std::fmt::Display::fmt(&DisplayInt::new(v, 10), f),
             as_int(v): &BigInt = Ok(v),
             into_int,
         },
         Cell(Cell) = {
             eq(a, b) = a.as_ref() == b.as_ref(),
-            fmt_dump(v, f) = write!(f, "C{{{}}}", v.repr_hash()),
+            fmt_dump(v, f) = match v.cell_type() {
+                CellType::Ordinary => write!(f, "C{{{}}}", v.repr_hash()),
+                ty => write!(f, "C{{{ty:?}:{}}}", v.repr_hash()),
+            },
             as_cell(v): &Cell = Ok(v),
             into_cell,
         },
@@ -337,7 +486,9 @@ define_stack_value! {
             eq(a, b) = a == b,
             fmt_dump(v, f) = {
                 let bytes = (v.bit_len() + 7) / 8;
-                write!(f, "BC{{{}, bits={}}}", hex::encode(&v.raw_data()[..bytes as usize]), v.bit_len())
+                f.write_str("BC{")?;
+                write_hex(f, &v.raw_data()[..bytes as usize], false)?;
+                write!(f, ", bits={}}}", v.bit_len())
             },
             as_builder(v): &CellBuilder = Ok(v),
             into_builder,
@@ -345,7 +496,7 @@ define_stack_value! {
         Slice(OwnedCellSlice) = {
             eq(a, b) = *a == b,
             fmt_dump(v, f) = std::fmt::Display::fmt(v, f),
-            as_slice(v): CellSlice = v.apply(),
+            as_slice(v): CellSlice<'_> = v.apply(),
             into_slice,
         },
         String(String) = {
@@ -356,7 +507,10 @@ define_stack_value! {
         },
         Bytes(Vec<u8>) = {
             eq(a, b) = a == b,
-            fmt_dump(v, f) = write!(f, "BYTES:{}", hex::encode_upper(v)),
+            fmt_dump(v, f) = {
+                f.write_str("BYTES:")?;
+                write_hex(f, v, true)
+            },
             as_bytes(v): &[u8] = Ok(v),
             into_bytes,
         },
@@ -400,12 +554,8 @@ define_stack_value! {
             }
         },
         SharedBox(SharedBox) = {
-            eq(a, b) = {
-                let a = Rc::as_ptr(&a.value) as *const ();
-                let b = Rc::as_ptr(&b.value) as *const ();
-                std::ptr::eq(a, b)
-            },
-            fmt_dump(v, f) = write!(f, "Box{{{:?}}}", Rc::as_ptr(&v.value)),
+            eq(a, b) = std::ptr::eq(a.as_ptr(), b.as_ptr()),
+            fmt_dump(v, f) = write!(f, "Box{{{:?}}}", v.as_ptr()),
             as_box(v): &SharedBox = Ok(v),
             into_shared_box,
         },
@@ -414,6 +564,12 @@ define_stack_value! {
             fmt_dump(v, f) = std::fmt::Display::fmt(v, f),
             as_atom(v): &Atom = Ok(v),
             into_atom,
+        },
+        SourceBlock(SourceBlockValue) = {
+            eq(a, b) = a == b,
+            fmt_dump(v, f) = write!(f, "SourceBlock{{{}}}", v.name()),
+            as_source_block(v): &SourceBlockValue = Ok(v),
+            into_source_block,
         }
     }
 }
@@ -447,6 +603,89 @@ impl dyn StackValue + '_ {
         self.ty() == StackValueType::Null
     }
 
+    /// Returns the representation hash of a cell or an unsigned 256-bit integer.
+    pub fn as_hash(&self) -> Result<HashBytes> {
+        match self.ty() {
+            StackValueType::Cell => Ok(*self.as_cell()?.repr_hash()),
+            _ => int_to_hash(self.as_int()?),
+        }
+    }
+
+    /// Compares values structurally.
+    ///
+    /// Unlike [`StackValue::is_equal`], shared boxes are compared by their
+    /// contents instead of identity. Values of different types are never equal.
+    pub fn is_value_equal(&self, other: &dyn StackValue) -> bool {
+        const MAX_DEPTH: usize = 128;
+
+        fn compare(a: &dyn StackValue, b: &dyn StackValue, depth: usize) -> bool {
+            if depth == 0 {
+                return a.is_equal(b);
+            }
+
+            if let (Ok(a), Ok(b)) = (a.as_tuple(), b.as_tuple()) {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|(a, b)| compare(a.as_ref(), b.as_ref(), depth - 1))
+            } else if let (Ok(a), Ok(b)) = (a.as_box(), b.as_box()) {
+                std::ptr::eq(a.as_ptr(), b.as_ptr())
+                    || compare(
+                        a.inner.value.borrow().as_ref(),
+                        b.inner.value.borrow().as_ref(),
+                        depth - 1,
+                    )
+            } else {
+                a.is_equal(b)
+            }
+        }
+
+        compare(self, other, MAX_DEPTH)
+    }
+
+    /// Displays differences between two values, one line per differing path.
+    ///
+    /// Paths consist of tuple indices (`[i]`) and cell references (`^i`).
+    pub fn display_diff<'a>(&'a self, other: &'a dyn StackValue) -> impl std::fmt::Display + 'a {
+        pub struct DisplayDiff<'a>(&'a dyn StackValue, &'a dyn StackValue);
+
+        impl std::fmt::Display for DisplayDiff<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let mut diff = ValueDiff::default();
+                diff.values(self.0, self.1, &mut String::new(), 128);
+
+                for (path, left, right) in &diff.entries {
+                    let path = if path.is_empty() { "top" } else { path };
+                    writeln!(f, "at {path}: {left} != {right}")?;
+                }
+                if diff.skipped > 0 {
+                    writeln!(f, "... and {} more differences", diff.skipped)?;
+                }
+                Ok(())
+            }
+        }
+
+        DisplayDiff(self, other)
+    }
+
+    /// Compares integers, strings or bytes of the same type.
+    pub fn compare(&self, other: &dyn StackValue) -> Result<std::cmp::Ordering> {
+        let ty = self.ty();
+        anyhow::ensure!(
+            ty == other.ty(),
+            StackError::UnexpectedType {
+                expected: ty,
+                actual: other.ty(),
+            }
+        );
+        Ok(match ty {
+            StackValueType::Int => self.as_int()?.cmp(other.as_int()?),
+            StackValueType::String => self.as_string()?.cmp(other.as_string()?),
+            StackValueType::Bytes => self.as_bytes()?.cmp(other.as_bytes()?),
+            _ => anyhow::bail!("Values of type `{ty:?}` can't be compared"),
+        })
+    }
+
     fn as_pair(&self) -> Option<(&dyn StackValue, &dyn StackValue)> {
         let tuple = self.as_tuple().ok()?;
         match tuple.as_slice() {
@@ -565,11 +804,14 @@ impl std::fmt::Display for OwnedCellSlice {
 #[derive(Default, Clone)]
 pub struct WordList {
     pub items: Vec<Cont>,
+    /// Source positions of items (only filled when coverage is enabled).
+    pub positions: Vec<Option<WordPosition>>,
 }
 
 impl WordList {
     pub fn finish(self) -> Cont {
-        if self.items.len() == 1 {
+        // Keep the list for items with positions to track their execution
+        if self.items.len() == 1 && self.positions.is_empty() {
             return self.items.into_iter().next().unwrap();
         }
 
@@ -596,7 +838,12 @@ impl PartialEq for WordList {
 
 #[derive(Clone)]
 pub struct SharedBox {
-    value: Rc<RefCell<Box<dyn StackValue>>>,
+    inner: Rc<SharedBoxInner>,
+}
+
+struct SharedBoxInner {
+    value: RefCell<Box<dyn StackValue>>,
+    frozen: std::cell::Cell<bool>,
 }
 
 impl Default for SharedBox {
@@ -608,16 +855,43 @@ impl Default for SharedBox {
 impl SharedBox {
     pub fn new(value: Box<dyn StackValue>) -> Self {
         Self {
-            value: Rc::new(RefCell::new(value)),
+            inner: Rc::new(SharedBoxInner {
+                value: RefCell::new(value),
+                frozen: std::cell::Cell::new(false),
+            }),
         }
     }
 
+    /// Replaces the value, even if the box is frozen.
     pub fn store(&self, value: Box<dyn StackValue>) {
-        *self.value.borrow_mut() = value;
+        *self.inner.value.borrow_mut() = value;
+    }
+
+    /// Replaces the value if the box is not frozen.
+    pub fn try_store(&self, value: Box<dyn StackValue>) -> Result<()> {
+        anyhow::ensure!(!self.is_frozen(), "Cannot store into a frozen box");
+        self.store(value);
+        Ok(())
     }
 
     pub fn fetch(&self) -> Box<dyn StackValue> {
-        self.value.borrow().clone()
+        self.inner.value.borrow().clone()
+    }
+
+    /// Makes all subsequent [`try_store`] calls fail for this box and its clones.
+    ///
+    /// [`try_store`]: Self::try_store
+    pub fn freeze(&self) {
+        self.inner.frozen.set(true);
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.inner.frozen.get()
+    }
+
+    /// Returns the address of the shared value to compare boxes by identity.
+    pub fn as_ptr(&self) -> *const () {
+        Rc::as_ptr(&self.inner) as *const ()
     }
 }
 
@@ -645,7 +919,7 @@ impl<T: AsRef<str>> PartialEq<T> for Atom {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Atoms {
     named: HashMap<Rc<str>, Atom>,
     total_anon: u32,
@@ -691,10 +965,113 @@ pub enum StackError {
     },
     #[error("Expected integer in range {min}..={max}, found {actual}")]
     IntegerOutOfRange {
-        min: u32,
-        max: usize,
+        min: i128,
+        max: i128,
         actual: String,
     },
     #[error("Expected a valid utf8 char code, found {0}")]
     InvalidChar(String),
 }
+
+#[derive(Default)]
+struct ValueDiff {
+    entries: Vec<(String, String, String)>,
+    skipped: usize,
+}
+
+impl ValueDiff {
+    const MAX_ENTRIES: usize = 16;
+
+    fn values(&mut self, a: &dyn StackValue, b: &dyn StackValue, path: &mut String, depth: usize) {
+        if depth == 0 || a.ty() != b.ty() {
+            if !a.is_value_equal(b) {
+                self.push(path, a.display_dump(), b.display_dump());
+            }
+            return;
+        }
+
+        match (a.as_tuple(), b.as_tuple()) {
+            (Ok(a), Ok(b)) => {
+                for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("[{i}]"));
+                    self.values(a.as_ref(), b.as_ref(), path, depth - 1);
+                    path.truncate(len);
+                }
+                if a.len() != b.len() {
+                    self.push(
+                        path,
+                        format_args!("tuple of {} items", a.len()),
+                        format_args!("tuple of {} items", b.len()),
+                    );
+                }
+                return;
+            }
+            _ if a.ty() == StackValueType::Cell => {
+                if let (Ok(a), Ok(b)) = (a.as_cell(), b.as_cell()) {
+                    self.cells(a.as_ref(), b.as_ref(), path, depth);
+                }
+                return;
+            }
+            _ if a.ty() == StackValueType::Slice => {
+                if let (Ok(a), Ok(b)) = (a.as_slice(), b.as_slice()) {
+                    self.slices(a, b, path, depth);
+                    return;
+                }
+            }
+            _ => {}
+        }
+
+        if !a.is_value_equal(b) {
+            self.push(path, a.display_dump(), b.display_dump());
+        }
+    }
+
+    fn cells(&mut self, a: &DynCell, b: &DynCell, path: &mut String, depth: usize) {
+        if a.repr_hash() == b.repr_hash() {
+            return;
+        }
+        match (a.as_slice(), b.as_slice()) {
+            (Ok(a), Ok(b)) if depth > 0 => self.slices(a, b, path, depth),
+            _ => self.push(
+                path,
+                format_args!("C{{{}}}", a.repr_hash()),
+                format_args!("C{{{}}}", b.repr_hash()),
+            ),
+        }
+    }
+
+    fn slices(&mut self, a: CellSlice<'_>, b: CellSlice<'_>, path: &mut String, depth: usize) {
+        let (a_data, b_data) = (a.display_slice_data(), b.display_slice_data());
+        let (a_data, b_data) = (a_data.to_string(), b_data.to_string());
+        if a_data != b_data {
+            self.push(path, &a_data, &b_data);
+        }
+
+        let (a_refs, b_refs) = (a.remaining_refs(), b.remaining_refs());
+        for i in 0..std::cmp::min(a_refs, b_refs) {
+            if let (Ok(a), Ok(b)) = (a.get_reference(i), b.get_reference(i)) {
+                let len = path.len();
+                path.push_str(&format!("^{i}"));
+                self.cells(a, b, path, depth - 1);
+                path.truncate(len);
+            }
+        }
+        if a_refs != b_refs {
+            self.push(
+                path,
+                format_args!("{a_refs} refs"),
+                format_args!("{b_refs} refs"),
+            );
+        }
+    }
+
+    fn push<L: std::fmt::Display, R: std::fmt::Display>(&mut self, path: &str, left: L, right: R) {
+        if self.entries.len() < Self::MAX_ENTRIES {
+            self.entries
+                .push((path.to_owned(), left.to_string(), right.to_string()));
+        } else {
+            self.skipped += 1;
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use ahash::HashMap;
+
+/// Registry of words which are rejected in strict mode.
+///
+/// Contains words which are deprecated or not available in the upstream
+/// Fift implementation, so that scripts can be kept portable.
+#[derive(Clone)]
+pub struct StrictMode {
+    words: HashMap<String, StrictWord>,
+}
+
+impl Default for StrictMode {
+    fn default() -> Self {
+        let mut res = Self {
+            words: Default::default(),
+        };
+        for &(name, replacement) in NON_STANDARD_WORDS {
+            res.register(name, StrictReason::NonStandard, replacement);
+        }
+        res
+    }
+}
+
+impl StrictMode {
+    /// Creates an empty registry.
+    pub fn empty() -> Self {
+        Self {
+            words: Default::default(),
+        }
+    }
+
+    /// Adds a word to the registry, replacing the previous entry.
+    pub fn register<T: Into<String>>(
+        &mut self,
+        name: T,
+        reason: StrictReason,
+        replacement: Option<&str>,
+    ) {
+        self.words.insert(
+            name.into(),
+            StrictWord {
+                reason,
+                replacement: replacement.map(ToOwned::to_owned),
+            },
+        );
+    }
+
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.words.remove(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StrictWord> {
+        self.words.get(name.strip_suffix(' ').unwrap_or(name))
+    }
+
+    /// Fails if the word (with or without a trailing space) is registered.
+    pub fn check(&self, name: &str) -> anyhow::Result<()> {
+        let name = name.strip_suffix(' ').unwrap_or(name);
+        let Some(word) = self.words.get(name) else {
+            return Ok(());
+        };
+
+        match &word.replacement {
+            Some(replacement) => anyhow::bail!(
+                "{} word `{name}` is not allowed in strict mode, use `{replacement}` instead",
+                word.reason
+            ),
+            None => anyhow::bail!(
+                "{} word `{name}` is not allowed in strict mode",
+                word.reason
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StrictWord {
+    pub reason: StrictReason,
+    /// Suggested portable replacement.
+    pub replacement: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrictReason {
+    /// The word is going to be removed.
+    Deprecated,
+    /// The word is not available in (or behaves differently than) upstream Fift.
+    NonStandard,
+}
+
+impl std::fmt::Display for StrictReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Deprecated => "Deprecated",
+            Self::NonStandard => "Non-standard",
+        })
+    }
+}
+
+const NON_STANDARD_WORDS: &[(&str, Option<&str>)] = &[
+    // Type predicates
+    ("cell?", None),
+    ("slice?", None),
+    ("builder?", None),
+    ("bytes?", None),
+    ("box?", None),
+    ("cont?", None),
+    ("type-of", None),
+    // Maybe helpers
+    ("maybe-map", None),
+    ("flag>maybe", None),
+    ("maybe>flag", None),
+    ("maybe-or", None),
+    // Range checks
+    ("fits-u8?", Some("8 ufits")),
+    ("fits-u16?", Some("16 ufits")),
+    ("fits-u32?", Some("32 ufits")),
+    ("fits-u64?", Some("64 ufits")),
+    ("fits-i8?", Some("8 fits")),
+    ("fits-i16?", Some("16 fits")),
+    ("fits-i32?", Some("32 fits")),
+    ("fits-i64?", Some("64 fits")),
+    ("clamp", Some("max min")),
+    // Wrapping arithmetic
+    ("u8+", None),
+    ("u16+", None),
+    ("u32+", None),
+    ("u64+", None),
+    ("u8-", None),
+    ("u16-", None),
+    ("u32-", None),
+    ("u64-", None),
+    ("u8*", None),
+    ("u16*", None),
+    ("u32*", None),
+    ("u64*", None),
+    // Stack
+    ("ndrop", None),
+    ("ndup", None),
+    ("mark", None),
+    ("count-to-mark", None),
+    ("collect-to-mark", None),
+    ("clear-to-mark", None),
+    // Boxes
+    ("freeze", None),
+    ("frozen?", None),
+    // Number theory
+    ("powmod", None),
+    ("invmod", None),
+    ("prime?", None),
+    ("nextprime", None),
+    ("randprime", None),
+    // Hashing
+    ("sha256", None),
+    ("sha512", None),
+    ("keccak256", None),
+    // secp256k1
+    ("secp256k1_priv>pub", None),
+    ("secp256k1_sign", None),
+    ("secp256k1_chksign", None),
+    ("secp256k1_recover", None),
+    // Addresses
+    ("$>smca", None),
+    ("smca>$", None),
+    ("smca>raw$", None),
+    // Mnemonics
+    ("generate_mnemonic", None),
+    ("generate_mnemonic_pwd", None),
+    ("mnemonic>priv", None),
+    ("mnemonic_pwd>priv", None),
+    ("mnemonic?", None),
+    ("mnemonic_pwd?", None),
+    // Key files
+    ("B>keyfile", None),
+    ("keyfile>B", None),
+    // Keychain
+    ("keychain-store", None),
+    ("keychain-load", None),
+    // Cells
+    ("B>boc+", None),
+    ("cell>dot", None),
+    // Dictionaries
+    ("sdict-foreach", None),
+    ("udict-foreach", None),
+    ("idict-foreach", None),
+    ("sdict>", None),
+    ("udict>", None),
+    ("idict>", None),
+    (">sdict", None),
+    (">udict", None),
+    (">idict", None),
+    ("dict-merge", None),
+    // TL-B
+    ("tlb-load", None),
+    ("tlb-include", None),
+    ("tlb-deserialize", None),
+    ("tlb-serialize", None),
+    // JSON
+    ("json>", None),
+    (">json", None),
+    // Merkle proofs
+    ("cell-type", None),
+    ("cell-level", None),
+    ("prune", None),
+    ("virtualize", None),
+    ("merkle-proof", None),
+    ("merkle-proof-for", None),
+    ("merkle-proof>", None),
+    // Snapshots
+    ("save-state", None),
+    ("load-state", None),
+    // Input and evaluation
+    ("include-limited", None),
+    ("parse", None),
+    ("char-read", None),
+    ("skip-to-eol", None),
+    ("evaluate", None),
+    ("ask", None),
+    ("ask-secret", None),
+    ("param", None),
+    ("param?", None),
+    // Output
+    (".s-page", None),
+    ("log-output-to", None),
+    ("log-output-off", None),
+    // Debugging
+    ("assert-eq", None),
+    ("(diff)", None),
+    ("history", None),
+    ("bp", None),
+    ("unbp", None),
+    ("bps", None),
+    ("step", None),
+    ("continue", None),
+    ("see", None),
+    ("where", None),
+    ("alloc-stats", None),
+    ("trace-on", None),
+    ("trace-off", None),
+    ("profile-report", None),
+    ("profile-reset", None),
+];
@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+/// Monotonic time source for [`Timings`].
+pub type Clock = fn() -> Duration;
+
+/// Statistics of the interpreted source blocks, in the order they were opened.
+#[derive(Debug, Clone)]
+pub struct Timings {
+    pub blocks: Vec<SourceBlockTiming>,
+    open: Vec<OpenTiming>,
+    clock: Clock,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::with_clock(default_clock)
+    }
+}
+
+impl Timings {
+    /// Creates empty statistics measured with the specified clock,
+    /// e.g. `performance.now()` in a browser.
+    pub fn with_clock(clock: Clock) -> Self {
+        Self {
+            blocks: Vec::new(),
+            open: Vec::new(),
+            clock,
+        }
+    }
+
+    /// Starts measuring a new source block nested into the current one.
+    pub fn enter(&mut self, name: &str) {
+        self.open.push(OpenTiming {
+            index: self.blocks.len(),
+            started_at: (self.clock)(),
+            nested: Duration::ZERO,
+        });
+        self.blocks.push(SourceBlockTiming {
+            name: name.to_owned(),
+            depth: self.open.len() - 1,
+            tokens: 0,
+            words_defined: 0,
+            elapsed: Duration::ZERO,
+            self_time: Duration::ZERO,
+            finished: false,
+        });
+    }
+
+    /// Finishes measuring the current source block.
+    pub fn exit(&mut self) {
+        let Some(open) = self.open.pop() else {
+            return;
+        };
+        let elapsed = (self.clock)().saturating_sub(open.started_at);
+        if let Some(parent) = self.open.last_mut() {
+            parent.nested += elapsed;
+        }
+
+        let block = &mut self.blocks[open.index];
+        block.elapsed = elapsed;
+        block.self_time = elapsed.saturating_sub(open.nested);
+        block.finished = true;
+    }
+
+    /// Counts a word scanned from the current source block.
+    pub fn add_token(&mut self) {
+        if let Some(block) = self.current_mut() {
+            block.tokens += 1;
+        }
+    }
+
+    /// Counts a word defined while reading the current source block.
+    pub fn add_word_defined(&mut self) {
+        if let Some(block) = self.current_mut() {
+            block.words_defined += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.open.clear();
+    }
+
+    fn current_mut(&mut self) -> Option<&mut SourceBlockTiming> {
+        let open = self.open.last()?;
+        self.blocks.get_mut(open.index)
+    }
+
+    /// Displays the collected statistics as a table.
+    pub fn display_table(&self) -> impl std::fmt::Display + '_ {
+        struct DisplayTable<'a>(&'a Timings);
+
+        impl std::fmt::Display for DisplayTable<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                writeln!(
+                    f,
+                    "{:>10} {:>10} {:>8} {:>6}  source",
+                    "total ms", "self ms", "tokens", "words"
+                )?;
+                for block in &self.0.blocks {
+                    let (total, self_time) = if block.finished {
+                        (as_ms(block.elapsed), as_ms(block.self_time))
+                    } else {
+                        ("-".to_owned(), "-".to_owned())
+                    };
+                    writeln!(
+                        f,
+                        "{total:>10} {self_time:>10} {:>8} {:>6}  {:indent$}{}",
+                        block.tokens,
+                        block.words_defined,
+                        "",
+                        block.name,
+                        indent = block.depth * 2,
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        fn as_ms(duration: Duration) -> String {
+            format!("{:.3}", duration.as_secs_f64() * 1000.0)
+        }
+
+        DisplayTable(self)
+    }
+}
+
+/// Statistics of a single source block.
+#[derive(Debug, Clone)]
+pub struct SourceBlockTiming {
+    pub name: String,
+    /// Number of source blocks which were open when this one was opened.
+    pub depth: usize,
+    /// Number of words scanned directly from this block.
+    pub tokens: u64,
+    /// Number of words defined directly from this block.
+    pub words_defined: u64,
+    /// Time between opening and closing the block, including nested blocks.
+    pub elapsed: Duration,
+    /// Time spent in the block itself, without nested blocks.
+    pub self_time: Duration,
+    /// Whether the block was read until the end.
+    pub finished: bool,
+}
+
+#[derive(Debug, Clone)]
+struct OpenTiming {
+    index: usize,
+    started_at: Duration,
+    nested: Duration,
+}
+
+/// Time since the first call.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn default_clock() -> Duration {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed()
+}
+
+/// There is no system clock on `wasm32-unknown-unknown`,
+/// so blocks are measured as instant unless a clock is provided.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn default_clock() -> Duration {
+    Duration::ZERO
+}
@@ -0,0 +1,143 @@
+use sha2::Digest;
+
+use crate::config::FiftConfig;
+use crate::core::Context;
+use crate::error::FiftError;
+
+/// Details of an uncaught error which can be attached to a bug report.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CrashReport {
+    /// Version of the interpreter.
+    pub version: String,
+    /// Error message.
+    pub error: String,
+    /// Error causes, from the outermost to the innermost one.
+    pub causes: Vec<String>,
+    pub position: Option<CrashPosition>,
+    /// Continuations which were left to execute.
+    pub backtrace: Option<String>,
+    /// Stack items, from the bottom to the top.
+    pub stack: Vec<CrashStackItem>,
+    /// Files included by the script.
+    pub includes: Vec<CrashInclude>,
+    pub script: Option<String>,
+    pub config: Option<FiftConfig>,
+}
+
+impl CrashReport {
+    /// Creates a report without the interpreter state.
+    pub fn new(error: &anyhow::Error) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            error: error.to_string(),
+            causes: error.chain().skip(1).map(ToString::to_string).collect(),
+            position: None,
+            backtrace: None,
+            stack: Vec::new(),
+            includes: Vec::new(),
+            script: None,
+            config: None,
+        }
+    }
+
+    /// Collects the state of the context after the error.
+    pub fn capture(ctx: &Context<'_>, error: &anyhow::Error) -> Self {
+        let position = match error.downcast_ref::<FiftError>() {
+            Some(e) => Some(CrashPosition {
+                source_block: e.source_block.clone(),
+                line_number: e.line_number,
+                line: e.line.clone(),
+            }),
+            None => ctx.input.get_position().map(|pos| CrashPosition {
+                source_block: pos.source_block_name.to_owned(),
+                line_number: pos.line_number,
+                line: pos.line.to_owned(),
+            }),
+        };
+
+        let backtrace = ctx
+            .next
+            .as_ref()
+            .map(|next| next.display_backtrace(&ctx.dictionary).to_string());
+
+        let stack = ctx
+            .stack
+            .items()
+            .iter()
+            .map(|item| CrashStackItem {
+                ty: format!("{:?}", item.ty()),
+                value: item.display_dump().to_string(),
+            })
+            .collect();
+
+        let includes = ctx
+            .includes
+            .iter()
+            .map(|name| CrashInclude {
+                name: name.clone(),
+                sha256: hash_include(ctx, name),
+            })
+            .collect();
+
+        Self {
+            position,
+            backtrace,
+            stack,
+            includes,
+            ..Self::new(error)
+        }
+    }
+
+    pub fn with_script<T: Into<String>>(mut self, script: T) -> Self {
+        self.script = Some(script.into());
+        self
+    }
+
+    pub fn with_config(mut self, config: FiftConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+}
+
+impl std::fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.error)?;
+        if let Some(pos) = &self.position {
+            write!(f, " at {}:{}", pos.source_block, pos.line_number)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CrashReport {}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CrashPosition {
+    pub source_block: String,
+    pub line_number: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CrashStackItem {
+    pub ty: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CrashInclude {
+    pub name: String,
+    /// Hex encoded hash of the current file contents, if it is still readable.
+    pub sha256: Option<String>,
+}
+
+fn hash_include(ctx: &Context<'_>, name: &str) -> Option<String> {
+    let mut block = ctx.env.include(name).ok()?;
+    let mut data = Vec::new();
+    block.buffer_mut().read_to_end(&mut data).ok()?;
+    Some(hex::encode(sha2::Sha256::digest(data)))
+}
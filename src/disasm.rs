@@ -0,0 +1,1218 @@
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use everscale_types::dict::RawIter;
+use everscale_types::prelude::*;
+use num_bigint::BigInt;
+
+use crate::util::DisplaySliceExt;
+
+/// Width of the offset column (including the separator).
+const OFFSET_WIDTH: usize = 6;
+
+/// Max nesting level of printed continuations.
+///
+/// Each level is indented by two more spaces.
+const MAX_DEPTH: usize = 64;
+
+/// Max number of printed lines.
+///
+/// Each cell is printed once, but dictionaries with shared branches
+/// can still contain exponentially many entries.
+const MAX_LINES: usize = 1 << 17;
+
+/// Disassembles TVM bytecode into a list of mnemonics.
+///
+/// Each instruction is prefixed with its bit offset inside the cell it
+/// was read from. Nested continuations (inline and referenced) and
+/// constant method dictionaries are printed recursively.
+///
+/// Cells which are referenced multiple times are printed once, with
+/// their hash, and later references only mention the hash.
+///
+/// Fails if the output exceeds [`MAX_LINES`] lines.
+pub fn disassemble(code: CellSlice<'_>) -> Result<String> {
+    let mut printer = Printer {
+        out: String::new(),
+        shared: find_shared_cells(code),
+        printed: Default::default(),
+        lines: 0,
+    };
+    printer.write_code(code, 0)?;
+    Ok(printer.out)
+}
+
+/// Returns hashes of cells which are referenced more than once.
+fn find_shared_cells(code: CellSlice<'_>) -> ahash::HashSet<HashBytes> {
+    let mut visited = ahash::HashSet::default();
+    let mut shared = ahash::HashSet::default();
+
+    let mut stack = code.references().collect::<Vec<_>>();
+    while let Some(cell) = stack.pop() {
+        if !visited.insert(*cell.repr_hash()) {
+            shared.insert(*cell.repr_hash());
+            continue;
+        }
+        stack.extend(cell.references());
+    }
+    shared
+}
+
+struct Printer {
+    out: String,
+    shared: ahash::HashSet<HashBytes>,
+    printed: ahash::HashSet<HashBytes>,
+    lines: usize,
+}
+
+impl Printer {
+    fn new_line(&mut self) -> Result<()> {
+        self.lines += 1;
+        anyhow::ensure!(
+            self.lines <= MAX_LINES,
+            "Disassembly exceeds {MAX_LINES} lines"
+        );
+        Ok(())
+    }
+
+    fn write_code(&mut self, mut cs: CellSlice<'_>, indent: usize) -> Result<()> {
+        if indent / 2 > MAX_DEPTH {
+            self.new_line()?;
+            writeln!(
+                self.out,
+                "{:>4}: {:indent$}<max depth exceeded>",
+                cs.bits_offset(),
+                ""
+            )?;
+            return Ok(());
+        }
+
+        loop {
+            let offset = cs.bits_offset();
+
+            if cs.remaining_bits() == 0 {
+                // TVM implicitly jumps to the first reference
+                // when the data of the continuation is exhausted
+                if let Ok(cell) = cs.get_reference(0) {
+                    self.new_line()?;
+                    writeln!(self.out, "{offset:>4}: {:indent$}IMPLICIT JMPREF <{{", "")?;
+                    self.write_cell(cell, indent + 2)?;
+                    writeln!(self.out, "{:width$}}}>", "", width = OFFSET_WIDTH + indent)?;
+                }
+                return Ok(());
+            }
+
+            self.new_line()?;
+            let instr = match decode(&cs) {
+                Ok(instr) if cs.has_remaining(instr.bits, instr.refs) => instr,
+                _ => {
+                    writeln!(
+                        self.out,
+                        "{offset:>4}: {:indent$}??? {}",
+                        "",
+                        cs.display_slice_data()
+                    )?;
+                    return Ok(());
+                }
+            };
+            cs.try_advance(instr.bits, instr.refs);
+
+            write!(self.out, "{offset:>4}: {:indent$}{}", "", instr.name)?;
+            let mut nested = instr.nested.iter().peekable();
+            while let Some(item) = nested.next() {
+                self.write_nested(item, indent)?;
+                let width = OFFSET_WIDTH + indent;
+                if nested.peek().is_some() {
+                    write!(self.out, "{:width$}{}", "", item.closing())?;
+                } else {
+                    writeln!(self.out, "{:width$}{}", "", item.closing())?;
+                }
+            }
+            if instr.nested.is_empty() {
+                writeln!(self.out)?;
+            }
+        }
+    }
+
+    fn write_cell(&mut self, cell: &DynCell, indent: usize) -> Result<()> {
+        let width = OFFSET_WIDTH + indent;
+        let hash = cell.repr_hash();
+        if self.shared.contains(hash) {
+            self.new_line()?;
+            if !self.printed.insert(*hash) {
+                writeln!(self.out, "{:width$}<cell {hash} printed above>", "")?;
+                return Ok(());
+            }
+            writeln!(self.out, "{:width$}<cell {hash}>", "")?;
+        }
+
+        match cell.as_slice() {
+            Ok(cs) => self.write_code(cs, indent),
+            Err(_) => {
+                self.new_line()?;
+                writeln!(self.out, "{:width$}{}", "", DisplayExotic(cell))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_nested(&mut self, nested: &Nested<'_>, indent: usize) -> Result<()> {
+        match nested {
+            Nested::Code(cs) => {
+                writeln!(self.out, " <{{")?;
+                self.write_code(*cs, indent + 2)
+            }
+            Nested::Cell(cell) => {
+                writeln!(self.out, " <{{")?;
+                self.write_cell(*cell, indent + 2)
+            }
+            Nested::Dict { key_bits, root } => {
+                writeln!(self.out, " {{")?;
+
+                let width = OFFSET_WIDTH + indent + 2;
+                let root = Some(root.clone());
+                for entry in RawIter::new(&root, *key_bits) {
+                    self.new_line()?;
+                    let Ok((key, value)) = entry else {
+                        writeln!(self.out, "{:width$}<invalid dictionary>", "")?;
+                        return Ok(());
+                    };
+                    let bytes = (*key_bits as usize + 7) / 8;
+                    let key = int_from_bytes(&key.raw_data()[..bytes], *key_bits);
+                    writeln!(self.out, "{:width$}{key} => <{{", "")?;
+                    self.write_code(value, indent + 4)?;
+                    writeln!(self.out, "{:width$}}}>", "")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Exotic cells (e.g. pruned branches or library references) can't be
+/// executed directly, so only their type and hash are printed.
+struct DisplayExotic<'a>(&'a DynCell);
+
+impl std::fmt::Display for DisplayExotic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{:?} cell {}>", self.0.cell_type(), self.0.repr_hash())
+    }
+}
+
+struct Instr<'a> {
+    name: String,
+    bits: u16,
+    refs: u8,
+    nested: Vec<Nested<'a>>,
+}
+
+impl<'a> Instr<'a> {
+    fn new(bits: u16, name: String) -> Self {
+        Self {
+            name,
+            bits,
+            refs: 0,
+            nested: Vec::new(),
+        }
+    }
+
+    fn with_refs(mut self, refs: u8) -> Self {
+        self.refs = refs;
+        self
+    }
+
+    fn with_nested(mut self, nested: Nested<'a>) -> Self {
+        self.nested.push(nested);
+        self
+    }
+}
+
+enum Nested<'a> {
+    Code(CellSlice<'a>),
+    Cell(&'a DynCell),
+    Dict { key_bits: u16, root: Cell },
+}
+
+impl Nested<'_> {
+    fn closing(&self) -> &'static str {
+        match self {
+            Self::Code(_) | Self::Cell(_) => "}>",
+            Self::Dict { .. } => "}",
+        }
+    }
+}
+
+macro_rules! op {
+    ($bits:expr, $($fmt:tt)+) => {
+        Instr::new($bits, format!($($fmt)+))
+    };
+}
+
+fn decode<'a>(cs: &CellSlice<'a>) -> Result<Instr<'a>> {
+    let window = cs.remaining_bits().min(24);
+    anyhow::ensure!(window >= 8, "Incomplete opcode");
+
+    let w = (cs.get_uint(0, window)? as u32) << (24 - window);
+    let b0 = (w >> 16) as u8;
+    let b1 = (w >> 8) as u8;
+    let b2 = w as u8;
+    let l0 = b0 & 0xf;
+    let (h1, l1) = (b1 >> 4, b1 & 0xf);
+
+    Ok(match b0 {
+        // === Stack manipulation ===
+        0x00 => op!(8, "NOP"),
+        0x01 => op!(8, "SWAP"),
+        0x02..=0x0f => op!(8, "XCHG s0,s{l0}"),
+        0x10 => op!(16, "XCHG s{h1},s{l1}"),
+        0x11 => op!(16, "XCHG s0,s{b1}"),
+        0x12..=0x1f => op!(8, "XCHG s1,s{l0}"),
+        0x20 => op!(8, "DUP"),
+        0x21 => op!(8, "OVER"),
+        0x22..=0x2f => op!(8, "PUSH s{l0}"),
+        0x30 => op!(8, "DROP"),
+        0x31 => op!(8, "NIP"),
+        0x32..=0x3f => op!(8, "POP s{l0}"),
+        0x40..=0x4f => op!(16, "XCHG3 s{l0},s{h1},s{l1}"),
+        0x50 => op!(16, "XCHG2 s{h1},s{l1}"),
+        0x51 => op!(16, "XCPU s{h1},s{l1}"),
+        0x52 => op!(16, "PUXC s{h1},{}", s_reg(l1, 1)),
+        0x53 => op!(16, "PUSH2 s{h1},s{l1}"),
+        0x54 => {
+            let (i, j, k) = (l1, b2 >> 4, b2 & 0xf);
+            match h1 {
+                0 => op!(24, "XCHG3 s{i},s{j},s{k}"),
+                1 => op!(24, "XC2PU s{i},s{j},s{k}"),
+                2 => op!(24, "XCPUXC s{i},s{j},{}", s_reg(k, 1)),
+                3 => op!(24, "XCPU2 s{i},s{j},s{k}"),
+                4 => op!(24, "PUXC2 s{i},{},{}", s_reg(j, 1), s_reg(k, 1)),
+                5 => op!(24, "PUXCPU s{i},{},{}", s_reg(j, 1), s_reg(k, 1)),
+                6 => op!(24, "PU2XC s{i},{},{}", s_reg(j, 1), s_reg(k, 2)),
+                7 => op!(24, "PUSH3 s{i},s{j},s{k}"),
+                _ => anyhow::bail!("Invalid opcode"),
+            }
+        }
+        0x55 => op!(16, "BLKSWAP {},{}", h1 + 1, l1 + 1),
+        0x56 => op!(16, "PUSH s{b1}"),
+        0x57 => op!(16, "POP s{b1}"),
+        0x58 => op!(8, "ROT"),
+        0x59 => op!(8, "ROTREV"),
+        0x5a => op!(8, "SWAP2"),
+        0x5b => op!(8, "DROP2"),
+        0x5c => op!(8, "DUP2"),
+        0x5d => op!(8, "OVER2"),
+        0x5e => op!(16, "REVERSE {},{l1}", h1 + 2),
+        0x5f if h1 == 0 => op!(16, "BLKDROP {l1}"),
+        0x5f => op!(16, "BLKPUSH {h1},{l1}"),
+        0x60..=0x6b => op!(8, "{}", STACK_X_OPS[(b0 - 0x60) as usize]),
+        0x6c if h1 != 0 => op!(16, "BLKDROP2 {h1},{l1}"),
+        0x6d => op!(8, "PUSHNULL"),
+        0x6e => op!(8, "ISNULL"),
+
+        // === Tuples ===
+        0x6f => match h1 {
+            0x0..=0x7 => op!(16, "{} {l1}", TUPLE_OPS[h1 as usize]),
+            0x8 => op!(16, "{}", lookup(TUPLE_VAR_OPS, l1)?),
+            0xa if l1 < 8 => op!(16, "{}", NULLSWAP_OPS[l1 as usize]),
+            0xb => op!(16, "INDEX2 {},{}", l1 >> 2, l1 & 3),
+            0xc..=0xf => op!(16, "INDEX3 {},{},{}", (b1 >> 4) & 3, (b1 >> 2) & 3, b1 & 3),
+            _ => anyhow::bail!("Invalid opcode"),
+        },
+
+        // === Constants ===
+        0x70..=0x7f => op!(8, "PUSHINT {}", ((l0 as i8) << 4) >> 4),
+        0x80 => op!(16, "PUSHINT {}", b1 as i8),
+        0x81 => op!(24, "PUSHINT {}", ((b1 as u16) << 8 | b2 as u16) as i16),
+        0x82 => {
+            let len = 8 * (b1 >> 3) as u16 + 19;
+            op!(13 + len, "PUSHINT {}", load_int(cs, 13, len)?)
+        }
+        0x83 if b1 == 0xff => op!(16, "PUSHNAN"),
+        0x83 => op!(16, "PUSHPOW2 {}", b1 as u16 + 1),
+        0x84 => op!(16, "PUSHPOW2DEC {}", b1 as u16 + 1),
+        0x85 => op!(16, "PUSHNEGPOW2 {}", b1 as u16 + 1),
+        0x88 => op!(8, "PUSHREF {}", display_ref(cs, 0)?).with_refs(1),
+        0x89 => op!(8, "PUSHREFSLICE {}", display_ref(cs, 0)?).with_refs(1),
+        0x8a => op!(8, "PUSHREFCONT")
+            .with_refs(1)
+            .with_nested(Nested::Cell(cs.get_reference(0)?)),
+        0x8b => {
+            let bits = 8 * h1 as u16 + 4;
+            let data = load_tagged_slice(cs, 12, bits, 0)?;
+            op!(12 + bits, "PUSHSLICE {}", display_slice(&data))
+        }
+        0x8c => {
+            let refs = (b1 >> 6) + 1;
+            let bits = 8 * ((b1 >> 1) & 0x1f) as u16 + 1;
+            let data = load_tagged_slice(cs, 15, bits, refs)?;
+            op!(15 + bits, "PUSHSLICE {}", display_slice(&data)).with_refs(refs)
+        }
+        0x8d => {
+            let refs = b1 >> 5;
+            anyhow::ensure!(refs <= 4, "Invalid opcode");
+            let bits = 8 * ((w >> 6) & 0x7f) as u16 + 6;
+            let data = load_tagged_slice(cs, 18, bits, refs)?;
+            op!(18 + bits, "PUSHSLICE {}", display_slice(&data)).with_refs(refs)
+        }
+        0x8e | 0x8f => {
+            let refs = ((w >> 15) & 0b11) as u8;
+            let bits = 8 * (b1 & 0x7f) as u16;
+            let body = load_slice(cs, 16, bits, refs)?;
+            op!(16 + bits, "PUSHCONT")
+                .with_refs(refs)
+                .with_nested(Nested::Code(body))
+        }
+        0x90..=0x9f => {
+            let bits = 8 * l0 as u16;
+            let body = load_slice(cs, 8, bits, 0)?;
+            op!(8 + bits, "PUSHCONT").with_nested(Nested::Code(body))
+        }
+
+        // === Arithmetic ===
+        0xa0 => op!(8, "ADD"),
+        0xa1 => op!(8, "SUB"),
+        0xa2 => op!(8, "SUBR"),
+        0xa3 => op!(8, "NEGATE"),
+        0xa4 => op!(8, "INC"),
+        0xa5 => op!(8, "DEC"),
+        0xa6 => op!(16, "ADDCONST {}", b1 as i8),
+        0xa7 => op!(16, "MULCONST {}", b1 as i8),
+        0xa8 => op!(8, "MUL"),
+        0xa9 => {
+            let (names, with_const) = match h1 {
+                0x0 => (["DIV{}", "MOD{}", "DIVMOD{}"], false),
+                0x2 => (["RSHIFT{}", "MODPOW2{}", "RSHIFT{}MOD"], false),
+                0x3 => (["RSHIFT{}#", "MODPOW2{}#", "RSHIFT{}#MOD"], true),
+                0x8 => (["MULDIV{}", "MULMOD{}", "MULDIVMOD{}"], false),
+                0xa => (["MULRSHIFT{}", "MULMODPOW2{}", "MULRSHIFT{}MOD"], false),
+                0xb => (["MULRSHIFT{}#", "MULMODPOW2{}#", "MULRSHIFT{}#MOD"], true),
+                0xc => (["LSHIFTDIV{}", "LSHIFTMOD{}", "LSHIFTDIVMOD{}"], false),
+                0xd => (["LSHIFT#DIV{}", "LSHIFT#MOD{}", "LSHIFT#DIVMOD{}"], true),
+                _ => anyhow::bail!("Invalid opcode"),
+            };
+            let (d, round) = (l1 >> 2, l1 & 3);
+            anyhow::ensure!(d != 0 && round != 3, "Invalid opcode");
+            let name = names[d as usize - 1].replace("{}", ["", "R", "C"][round as usize]);
+            if with_const {
+                op!(24, "{name} {}", b2 as u16 + 1)
+            } else {
+                op!(16, "{name}")
+            }
+        }
+        0xaa => op!(16, "LSHIFT# {}", b1 as u16 + 1),
+        0xab => op!(16, "RSHIFT# {}", b1 as u16 + 1),
+        0xac => op!(8, "LSHIFT"),
+        0xad => op!(8, "RSHIFT"),
+        0xae => op!(8, "POW2"),
+        0xb0 => op!(8, "AND"),
+        0xb1 => op!(8, "OR"),
+        0xb2 => op!(8, "XOR"),
+        0xb3 => op!(8, "NOT"),
+        0xb4 => op!(16, "FITS {}", b1 as u16 + 1),
+        0xb5 => op!(16, "UFITS {}", b1 as u16 + 1),
+        0xb6 => op!(16, "{}", lookup(INT_MISC_OPS, b1)?),
+        0xb7 => {
+            let mut rest = *cs;
+            anyhow::ensure!(
+                rest.try_advance(8, 0) && matches!(b1, 0xa0..=0xc5),
+                "Invalid opcode"
+            );
+            let instr = decode(&rest)?;
+            Instr {
+                name: format!("Q{}", instr.name),
+                bits: instr.bits + 8,
+                ..instr
+            }
+        }
+
+        // === Comparison ===
+        0xb8 => op!(8, "SGN"),
+        0xb9 => op!(8, "LESS"),
+        0xba => op!(8, "EQUAL"),
+        0xbb => op!(8, "LEQ"),
+        0xbc => op!(8, "GREATER"),
+        0xbd => op!(8, "NEQ"),
+        0xbe => op!(8, "GEQ"),
+        0xbf => op!(8, "CMP"),
+        0xc0 => op!(16, "EQINT {}", b1 as i8),
+        0xc1 => op!(16, "LESSINT {}", b1 as i8),
+        0xc2 => op!(16, "GTINT {}", b1 as i8),
+        0xc3 => op!(16, "NEQINT {}", b1 as i8),
+        0xc4 => op!(8, "ISNAN"),
+        0xc5 => op!(8, "CHKNAN"),
+        0xc7 => op!(16, "{}", lookup(SLICE_CMP_OPS, b1)?),
+
+        // === Cell serialization ===
+        0xc8 => op!(8, "NEWC"),
+        0xc9 => op!(8, "ENDC"),
+        0xca => op!(16, "STI {}", b1 as u16 + 1),
+        0xcb => op!(16, "STU {}", b1 as u16 + 1),
+        0xcc => op!(8, "STREF"),
+        0xcd => op!(8, "STBREFR"),
+        0xce => op!(8, "STSLICE"),
+        0xcf => match b1 {
+            0x00..=0x07 => op!(16, "{}", STORE_X_OPS[b1 as usize]),
+            0x08..=0x0f => op!(24, "{} {}", STORE_OPS[b1 as usize - 8], b2 as u16 + 1),
+            0x20 => op!(16, "STREFCONST {}", display_ref(cs, 0)?).with_refs(1),
+            0x21 => op!(
+                16,
+                "STREF2CONST {} {}",
+                display_ref(cs, 0)?,
+                display_ref(cs, 1)?
+            )
+            .with_refs(2),
+            0x38 => op!(24, "BCHKBITS# {}", b2 as u16 + 1),
+            0x3c => op!(24, "BCHKBITSQ# {}", b2 as u16 + 1),
+            0x80..=0xff => {
+                let refs = (b1 >> 5) & 0b11;
+                let bits = 8 * ((b1 >> 2) & 0b111) as u16 + 2;
+                let data = load_tagged_slice(cs, 14, bits, refs)?;
+                op!(14 + bits, "STSLICECONST {}", display_slice(&data)).with_refs(refs)
+            }
+            _ => op!(16, "{}", lookup(STORE_MISC_OPS, b1)?),
+        },
+
+        // === Cell deserialization ===
+        0xd0 => op!(8, "CTOS"),
+        0xd1 => op!(8, "ENDS"),
+        0xd2 => op!(16, "LDI {}", b1 as u16 + 1),
+        0xd3 => op!(16, "LDU {}", b1 as u16 + 1),
+        0xd4 => op!(8, "LDREF"),
+        0xd5 => op!(8, "LDREFRTOS"),
+        0xd6 => op!(16, "LDSLICE {}", b1 as u16 + 1),
+        0xd7 => match b1 {
+            0x00..=0x07 => op!(16, "{}", LOAD_X_OPS[b1 as usize]),
+            0x08..=0x0f => op!(24, "{} {}", LOAD_OPS[b1 as usize - 8], b2 as u16 + 1),
+            0x1c..=0x1f => op!(
+                24,
+                "{} {}",
+                LOAD_SLICE_OPS[b1 as usize - 0x1c],
+                b2 as u16 + 1
+            ),
+            0x4c..=0x4f => op!(16, "PLDREFIDX {}", b1 & 3),
+            _ => op!(16, "{}", lookup(LOAD_MISC_OPS, b1)?),
+        },
+
+        // === Control flow ===
+        0xd8 => op!(8, "EXECUTE"),
+        0xd9 => op!(8, "JMPX"),
+        0xda => op!(16, "CALLXARGS {h1},{}", nargs(l1)),
+        0xdb => match h1 {
+            0x0 => op!(16, "CALLXARGS {l1},-1"),
+            0x1 => op!(16, "JMPXARGS {l1}"),
+            0x2 => op!(16, "RETARGS {l1}"),
+            0x3 => match l1 {
+                0x6 => op!(24, "CALLCCARGS {},{}", b2 >> 4, nargs(b2 & 0xf)),
+                0xc..=0xe => op!(
+                    16,
+                    "{}",
+                    ["CALLREF", "JMPREF", "JMPREFDATA"][l1 as usize - 0xc]
+                )
+                .with_refs(1)
+                .with_nested(Nested::Cell(cs.get_reference(0)?)),
+                _ => op!(16, "{}", lookup(CALL_MISC_OPS, l1)?),
+            },
+            _ => anyhow::bail!("Invalid opcode"),
+        },
+        0xdc => op!(8, "IFRET"),
+        0xdd => op!(8, "IFNOTRET"),
+        0xde => op!(8, "IF"),
+        0xdf => op!(8, "IFNOT"),
+        0xe0 => op!(8, "IFJMP"),
+        0xe1 => op!(8, "IFNOTJMP"),
+        0xe2 => op!(8, "IFELSE"),
+        0xe3 => match b1 {
+            0x00..=0x03 | 0x0d | 0x0e => {
+                let name = match b1 {
+                    0x00 => "IFREF",
+                    0x01 => "IFNOTREF",
+                    0x02 => "IFJMPREF",
+                    0x03 => "IFNOTJMPREF",
+                    0x0d => "IFREFELSE",
+                    _ => "IFELSEREF",
+                };
+                op!(16, "{name}")
+                    .with_refs(1)
+                    .with_nested(Nested::Cell(cs.get_reference(0)?))
+            }
+            0x0f => op!(16, "IFREFELSEREF")
+                .with_refs(2)
+                .with_nested(Nested::Cell(cs.get_reference(0)?))
+                .with_nested(Nested::Cell(cs.get_reference(1)?)),
+            0x80..=0xbf => {
+                let name = if b1 < 0xa0 { "IFBITJMP" } else { "IFNBITJMP" };
+                op!(16, "{name} {}", b1 & 0x1f)
+            }
+            0xc0..=0xff => {
+                let name = if b1 < 0xe0 {
+                    "IFBITJMPREF"
+                } else {
+                    "IFNBITJMPREF"
+                };
+                op!(16, "{name} {}", b1 & 0x1f)
+                    .with_refs(1)
+                    .with_nested(Nested::Cell(cs.get_reference(0)?))
+            }
+            _ => op!(16, "{}", lookup(COND_MISC_OPS, b1)?),
+        },
+        0xe4..=0xeb => op!(8, "{}", LOOP_OPS[(b0 - 0xe4) as usize]),
+        0xec => op!(16, "SETCONTARGS {h1},{}", nargs(l1)),
+        0xed => match h1 {
+            0x0 => op!(16, "RETURNARGS {l1}"),
+            0x1 => op!(16, "{}", lookup(CONT_ARGS_OPS, l1)?),
+            0x4..=0xc => op!(16, "{} c{l1}", CTR_OPS[h1 as usize - 4]),
+            0xe if l1 < 3 => op!(
+                16,
+                "{}",
+                ["PUSHCTRX", "POPCTRX", "SETCONTCTRX"][l1 as usize]
+            ),
+            0xf if l1 < 12 => op!(16, "{}", CONT_COMPOSE_OPS[l1 as usize]),
+            _ => anyhow::bail!("Invalid opcode"),
+        },
+        0xee => op!(16, "BLESSARGS {h1},{}", nargs(l1)),
+        0xf0 => op!(16, "CALLDICT {b1}"),
+        0xf1 => {
+            let n = ((b1 as u16 & 0x3f) << 8) | b2 as u16;
+            match b1 >> 6 {
+                0 => op!(24, "CALLDICT {n}"),
+                1 => op!(24, "JMPDICT {n}"),
+                2 => op!(24, "PREPAREDICT {n}"),
+                _ => anyhow::bail!("Invalid opcode"),
+            }
+        }
+
+        // === Exceptions ===
+        0xf2 => match b1 {
+            0x00..=0xbf => {
+                let name = ["THROW", "THROWIF", "THROWIFNOT"][(b1 >> 6) as usize];
+                op!(16, "{name} {}", b1 & 0x3f)
+            }
+            0xc0..=0xef => {
+                let n = ((b1 as u16 & 0b111) << 8) | b2 as u16;
+                op!(24, "{} {n}", THROW_OPS[((b1 >> 3) & 0b111) as usize])
+            }
+            0xf0..=0xf5 => op!(16, "{}", THROW_ANY_OPS[(b1 - 0xf0) as usize]),
+            0xff => op!(16, "TRY"),
+            _ => anyhow::bail!("Invalid opcode"),
+        },
+        0xf3 => op!(16, "TRYARGS {h1},{l1}"),
+
+        // === Dictionaries ===
+        0xf4 => match b1 {
+            0xa4..=0xa7 => {
+                let key_bits = ((b1 as u16 & 0b11) << 8) | b2 as u16;
+                op!(24, "DICTPUSHCONST {key_bits}")
+                    .with_refs(1)
+                    .with_nested(Nested::Dict {
+                        key_bits,
+                        root: cs.get_reference_cloned(0)?,
+                    })
+            }
+            0xac..=0xaf => {
+                let key_bits = ((b1 as u16 & 0b11) << 8) | b2 as u16;
+                op!(24, "PFXDICTCONSTGETJMP {key_bits} {}", display_ref(cs, 0)?).with_refs(1)
+            }
+            _ => op!(16, "{}", lookup(DICT_OPS, b1)?),
+        },
+
+        // === Blockchain specific ===
+        0xf8 => match b1 {
+            0x20..=0x2f => match lookup(PARAM_OPS, l1) {
+                Ok(name) => op!(16, "{name}"),
+                Err(_) => op!(16, "GETPARAM {l1}"),
+            },
+            0x41..=0x5f => op!(16, "GETGLOB {}", b1 & 0x1f),
+            0x61..=0x7f => op!(16, "SETGLOB {}", b1 & 0x1f),
+            _ => op!(16, "{}", lookup(APP_MISC_OPS, b1)?),
+        },
+        0xf9 => op!(16, "{}", lookup(APP_CRYPTO_OPS, b1)?),
+        0xfa => op!(16, "{}", lookup(APP_CURRENCY_OPS, b1)?),
+        0xfb => op!(16, "{}", lookup(APP_ACTION_OPS, b1)?),
+
+        // === Debug and codepage ===
+        0xfe => match b1 {
+            0x00 => op!(16, "DUMPSTK"),
+            0x20..=0x2f => op!(16, "DUMP s{l1}"),
+            0xf0..=0xff => {
+                let bits = 8 * (l1 as u16 + 1);
+                let data = load_slice(cs, 16, bits, 0)?;
+                let mut bytes = [0u8; 16];
+                let bytes = data.get_raw(0, &mut bytes, bits)?;
+                op!(16 + bits, "DEBUGSTR {:?}", String::from_utf8_lossy(bytes))
+            }
+            _ => op!(16, "DEBUG {b1}"),
+        },
+        0xff => match b1 {
+            0x00 => op!(16, "SETCP0"),
+            0xf0 => op!(16, "SETCPX"),
+            0xf1..=0xff => op!(16, "SETCP {}", b1 as i16 - 0x100),
+            _ => op!(16, "SETCP {b1}"),
+        },
+
+        _ => anyhow::bail!("Invalid opcode"),
+    })
+}
+
+fn s_reg(i: u8, offset: u8) -> String {
+    format!("s{}", i as i16 - offset as i16)
+}
+
+fn nargs(n: u8) -> i8 {
+    if n == 15 {
+        -1
+    } else {
+        n as i8
+    }
+}
+
+fn lookup<T: PartialEq>(table: &[(T, &'static str)], key: T) -> Result<&'static str> {
+    match table.iter().find(|(k, _)| *k == key) {
+        Some((_, name)) => Ok(name),
+        None => anyhow::bail!("Invalid opcode"),
+    }
+}
+
+fn load_int(cs: &CellSlice<'_>, offset: u16, bits: u16) -> Result<BigInt> {
+    let mut buffer = [0u8; 33];
+    let bytes = cs.get_raw(offset, &mut buffer, bits)?;
+    Ok(int_from_bytes(bytes, bits))
+}
+
+/// Parses a signed big-endian integer from the first `bits` of `bytes`.
+fn int_from_bytes(bytes: &[u8], bits: u16) -> BigInt {
+    let int = BigInt::from_signed_bytes_be(bytes);
+    int >> (bytes.len() * 8 - bits as usize)
+}
+
+fn load_slice<'a>(cs: &CellSlice<'a>, offset: u16, bits: u16, refs: u8) -> Result<CellSlice<'a>> {
+    let mut cs = *cs;
+    anyhow::ensure!(
+        cs.try_advance(offset, 0) && cs.has_remaining(bits, refs),
+        "Incomplete opcode"
+    );
+    Ok(cs.get_prefix(bits, refs))
+}
+
+/// Loads a slice with a completion tag (the trailing `1` followed by zeros).
+fn load_tagged_slice<'a>(
+    cs: &CellSlice<'a>,
+    offset: u16,
+    mut bits: u16,
+    refs: u8,
+) -> Result<CellSlice<'a>> {
+    let data = load_slice(cs, offset, bits, refs)?;
+    while bits > 0 {
+        bits -= 1;
+        if data.get_bit(bits)? {
+            break;
+        }
+    }
+    Ok(data.get_prefix(bits, refs))
+}
+
+fn display_slice(cs: &CellSlice<'_>) -> String {
+    let mut result = cs.display_slice_data().to_string();
+    let refs = cs.remaining_refs();
+    if refs > 0 {
+        write!(&mut result, " ({refs} refs)").ok();
+    }
+    result
+}
+
+fn display_ref(cs: &CellSlice<'_>, index: u8) -> Result<String> {
+    let cell = cs.get_reference(index)?;
+    Ok(match cell.as_slice() {
+        Ok(cs) => display_slice(&cs),
+        Err(_) => DisplayExotic(cell).to_string(),
+    })
+}
+
+pub(crate) const STACK_X_OPS: [&str; 12] = [
+    "PICK", "ROLLX", "-ROLLX", "BLKSWX", "REVX", "DROPX", "TUCK", "XCHGX", "DEPTH", "CHKDEPTH",
+    "ONLYTOPX", "ONLYX",
+];
+
+const TUPLE_OPS: [&str; 8] = [
+    "TUPLE",
+    "INDEX",
+    "UNTUPLE",
+    "UNPACKFIRST",
+    "EXPLODE",
+    "SETINDEX",
+    "INDEXQ",
+    "SETINDEXQ",
+];
+
+pub(crate) const TUPLE_VAR_OPS: &[(u8, &str)] = &[
+    (0x0, "TUPLEVAR"),
+    (0x1, "INDEXVAR"),
+    (0x2, "UNTUPLEVAR"),
+    (0x3, "UNPACKFIRSTVAR"),
+    (0x4, "EXPLODEVAR"),
+    (0x5, "SETINDEXVAR"),
+    (0x6, "INDEXVARQ"),
+    (0x7, "SETINDEXVARQ"),
+    (0x8, "TLEN"),
+    (0x9, "QTLEN"),
+    (0xa, "ISTUPLE"),
+    (0xb, "LAST"),
+    (0xc, "TPUSH"),
+    (0xd, "TPOP"),
+];
+
+pub(crate) const NULLSWAP_OPS: [&str; 8] = [
+    "NULLSWAPIF",
+    "NULLSWAPIFNOT",
+    "NULLROTRIF",
+    "NULLROTRIFNOT",
+    "NULLSWAPIF2",
+    "NULLSWAPIFNOT2",
+    "NULLROTRIF2",
+    "NULLROTRIFNOT2",
+];
+
+pub(crate) const INT_MISC_OPS: &[(u8, &str)] = &[
+    (0x00, "FITSX"),
+    (0x01, "UFITSX"),
+    (0x02, "BITSIZE"),
+    (0x03, "UBITSIZE"),
+    (0x08, "MIN"),
+    (0x09, "MAX"),
+    (0x0a, "MINMAX"),
+    (0x0b, "ABS"),
+];
+
+pub(crate) const SLICE_CMP_OPS: &[(u8, &str)] = &[
+    (0x00, "SEMPTY"),
+    (0x01, "SDEMPTY"),
+    (0x02, "SREMPTY"),
+    (0x03, "SDFIRST"),
+    (0x04, "SDLEXCMP"),
+    (0x05, "SDEQ"),
+    (0x08, "SDPFX"),
+    (0x09, "SDPFXREV"),
+    (0x0a, "SDPPFX"),
+    (0x0b, "SDPPFXREV"),
+    (0x0c, "SDSFX"),
+    (0x0d, "SDSFXREV"),
+    (0x0e, "SDPSFX"),
+    (0x0f, "SDPSFXREV"),
+    (0x10, "SDCNTLEAD0"),
+    (0x11, "SDCNTLEAD1"),
+    (0x12, "SDCNTTRAIL0"),
+    (0x13, "SDCNTTRAIL1"),
+];
+
+pub(crate) const STORE_X_OPS: [&str; 8] = [
+    "STIX", "STUX", "STIXR", "STUXR", "STIXQ", "STUXQ", "STIXRQ", "STUXRQ",
+];
+
+const STORE_OPS: [&str; 8] = [
+    "STI", "STU", "STIR", "STUR", "STIQ", "STUQ", "STIRQ", "STURQ",
+];
+
+pub(crate) const STORE_MISC_OPS: &[(u8, &str)] = &[
+    (0x10, "STREF"),
+    (0x11, "STBREF"),
+    (0x12, "STSLICE"),
+    (0x13, "STB"),
+    (0x14, "STREFR"),
+    (0x15, "STBREFR"),
+    (0x16, "STSLICER"),
+    (0x17, "STBR"),
+    (0x18, "STREFQ"),
+    (0x19, "STBREFQ"),
+    (0x1a, "STSLICEQ"),
+    (0x1b, "STBQ"),
+    (0x1c, "STREFRQ"),
+    (0x1d, "STBREFRQ"),
+    (0x1e, "STSLICERQ"),
+    (0x1f, "STBRQ"),
+    (0x23, "ENDXC"),
+    (0x28, "STILE4"),
+    (0x29, "STULE4"),
+    (0x2a, "STILE8"),
+    (0x2b, "STULE8"),
+    (0x30, "BDEPTH"),
+    (0x31, "BBITS"),
+    (0x32, "BREFS"),
+    (0x33, "BBITREFS"),
+    (0x35, "BREMBITS"),
+    (0x36, "BREMREFS"),
+    (0x37, "BREMBITREFS"),
+    (0x39, "BCHKBITS"),
+    (0x3a, "BCHKREFS"),
+    (0x3b, "BCHKBITREFS"),
+    (0x3d, "BCHKBITSQ"),
+    (0x3e, "BCHKREFSQ"),
+    (0x3f, "BCHKBITREFSQ"),
+    (0x40, "STZEROES"),
+    (0x41, "STONES"),
+    (0x42, "STSAME"),
+];
+
+pub(crate) const LOAD_X_OPS: [&str; 8] = [
+    "LDIX", "LDUX", "PLDIX", "PLDUX", "LDIXQ", "LDUXQ", "PLDIXQ", "PLDUXQ",
+];
+
+const LOAD_OPS: [&str; 8] = [
+    "LDI", "LDU", "PLDI", "PLDU", "LDIQ", "LDUQ", "PLDIQ", "PLDUQ",
+];
+
+const LOAD_SLICE_OPS: [&str; 4] = ["LDSLICE", "PLDSLICE", "LDSLICEQ", "PLDSLICEQ"];
+
+pub(crate) const LOAD_MISC_OPS: &[(u8, &str)] = &[
+    (0x18, "LDSLICEX"),
+    (0x19, "PLDSLICEX"),
+    (0x1a, "LDSLICEXQ"),
+    (0x1b, "PLDSLICEXQ"),
+    (0x20, "SDCUTFIRST"),
+    (0x21, "SDSKIPFIRST"),
+    (0x22, "SDCUTLAST"),
+    (0x23, "SDSKIPLAST"),
+    (0x24, "SDSUBSTR"),
+    (0x26, "SDBEGINSX"),
+    (0x27, "SDBEGINSXQ"),
+    (0x30, "SCUTFIRST"),
+    (0x31, "SSKIPFIRST"),
+    (0x32, "SCUTLAST"),
+    (0x33, "SSKIPLAST"),
+    (0x34, "SUBSLICE"),
+    (0x36, "SPLIT"),
+    (0x37, "SPLITQ"),
+    (0x39, "XCTOS"),
+    (0x3a, "XLOAD"),
+    (0x3b, "XLOADQ"),
+    (0x41, "SCHKBITS"),
+    (0x42, "SCHKREFS"),
+    (0x43, "SCHKBITREFS"),
+    (0x45, "SCHKBITSQ"),
+    (0x46, "SCHKREFSQ"),
+    (0x47, "SCHKBITREFSQ"),
+    (0x48, "PLDREFVAR"),
+    (0x49, "SBITS"),
+    (0x4a, "SREFS"),
+    (0x4b, "SBITREFS"),
+    (0x50, "LDILE4"),
+    (0x51, "LDULE4"),
+    (0x52, "LDILE8"),
+    (0x53, "LDULE8"),
+    (0x54, "PLDILE4"),
+    (0x55, "PLDULE4"),
+    (0x56, "PLDILE8"),
+    (0x57, "PLDULE8"),
+    (0x58, "LDILE4Q"),
+    (0x59, "LDULE4Q"),
+    (0x5a, "LDILE8Q"),
+    (0x5b, "LDULE8Q"),
+    (0x5c, "PLDILE4Q"),
+    (0x5d, "PLDULE4Q"),
+    (0x5e, "PLDILE8Q"),
+    (0x5f, "PLDULE8Q"),
+    (0x60, "LDZEROES"),
+    (0x61, "LDONES"),
+    (0x62, "LDSAME"),
+    (0x64, "SDEPTH"),
+    (0x65, "CDEPTH"),
+];
+
+pub(crate) const CALL_MISC_OPS: &[(u8, &str)] = &[
+    (0x0, "RET"),
+    (0x1, "RETALT"),
+    (0x2, "BRANCH"),
+    (0x4, "CALLCC"),
+    (0x5, "JMPXDATA"),
+    (0x8, "CALLXVARARGS"),
+    (0x9, "RETVARARGS"),
+    (0xa, "JMPXVARARGS"),
+    (0xb, "CALLCCVARARGS"),
+    (0xf, "RETDATA"),
+];
+
+pub(crate) const COND_MISC_OPS: &[(u8, &str)] = &[
+    (0x04, "CONDSEL"),
+    (0x05, "CONDSELCHK"),
+    (0x08, "IFRETALT"),
+    (0x09, "IFNOTRETALT"),
+    (0x14, "REPEATBRK"),
+    (0x15, "REPEATENDBRK"),
+    (0x16, "UNTILBRK"),
+    (0x17, "UNTILENDBRK"),
+    (0x18, "WHILEBRK"),
+    (0x19, "WHILEENDBRK"),
+    (0x1a, "AGAINBRK"),
+    (0x1b, "AGAINENDBRK"),
+];
+
+pub(crate) const LOOP_OPS: [&str; 8] = [
+    "REPEAT",
+    "REPEATEND",
+    "UNTIL",
+    "UNTILEND",
+    "WHILE",
+    "WHILEEND",
+    "AGAIN",
+    "AGAINEND",
+];
+
+pub(crate) const CONT_ARGS_OPS: &[(u8, &str)] = &[
+    (0x0, "RETURNVARARGS"),
+    (0x1, "SETCONTVARARGS"),
+    (0x2, "SETNUMVARARGS"),
+    (0xe, "BLESS"),
+    (0xf, "BLESSVARARGS"),
+];
+
+const CTR_OPS: [&str; 9] = [
+    "PUSH",
+    "POP",
+    "SETCONTCTR",
+    "SETRETCTR",
+    "SETALTCTR",
+    "POPSAVE",
+    "SAVE",
+    "SAVEALT",
+    "SAVEBOTH",
+];
+
+pub(crate) const CONT_COMPOSE_OPS: [&str; 12] = [
+    "COMPOS",
+    "COMPOSALT",
+    "COMPOSBOTH",
+    "ATEXIT",
+    "ATEXITALT",
+    "SETEXITALT",
+    "THENRET",
+    "THENRETALT",
+    "INVERT",
+    "BOOLEVAL",
+    "SAMEALT",
+    "SAMEALTSAVE",
+];
+
+const THROW_OPS: [&str; 6] = [
+    "THROW",
+    "THROWARG",
+    "THROWIF",
+    "THROWARGIF",
+    "THROWIFNOT",
+    "THROWARGIFNOT",
+];
+
+pub(crate) const THROW_ANY_OPS: [&str; 6] = [
+    "THROWANY",
+    "THROWARGANY",
+    "THROWANYIF",
+    "THROWARGANYIF",
+    "THROWANYIFNOT",
+    "THROWARGANYIFNOT",
+];
+
+pub(crate) const DICT_OPS: &[(u8, &str)] = &[
+    (0x00, "STDICT"),
+    (0x01, "SKIPDICT"),
+    (0x02, "LDDICTS"),
+    (0x03, "PLDDICTS"),
+    (0x04, "LDDICT"),
+    (0x05, "PLDDICT"),
+    (0x06, "LDDICTQ"),
+    (0x07, "PLDDICTQ"),
+    (0x0a, "DICTGET"),
+    (0x0b, "DICTGETREF"),
+    (0x0c, "DICTIGET"),
+    (0x0d, "DICTIGETREF"),
+    (0x0e, "DICTUGET"),
+    (0x0f, "DICTUGETREF"),
+    (0x12, "DICTSET"),
+    (0x13, "DICTSETREF"),
+    (0x14, "DICTISET"),
+    (0x15, "DICTISETREF"),
+    (0x16, "DICTUSET"),
+    (0x17, "DICTUSETREF"),
+    (0x1a, "DICTSETGET"),
+    (0x1b, "DICTSETGETREF"),
+    (0x1c, "DICTISETGET"),
+    (0x1d, "DICTISETGETREF"),
+    (0x1e, "DICTUSETGET"),
+    (0x1f, "DICTUSETGETREF"),
+    (0x22, "DICTREPLACE"),
+    (0x23, "DICTREPLACEREF"),
+    (0x24, "DICTIREPLACE"),
+    (0x25, "DICTIREPLACEREF"),
+    (0x26, "DICTUREPLACE"),
+    (0x27, "DICTUREPLACEREF"),
+    (0x2a, "DICTREPLACEGET"),
+    (0x2b, "DICTREPLACEGETREF"),
+    (0x2c, "DICTIREPLACEGET"),
+    (0x2d, "DICTIREPLACEGETREF"),
+    (0x2e, "DICTUREPLACEGET"),
+    (0x2f, "DICTUREPLACEGETREF"),
+    (0x32, "DICTADD"),
+    (0x33, "DICTADDREF"),
+    (0x34, "DICTIADD"),
+    (0x35, "DICTIADDREF"),
+    (0x36, "DICTUADD"),
+    (0x37, "DICTUADDREF"),
+    (0x3a, "DICTADDGET"),
+    (0x3b, "DICTADDGETREF"),
+    (0x3c, "DICTIADDGET"),
+    (0x3d, "DICTIADDGETREF"),
+    (0x3e, "DICTUADDGET"),
+    (0x3f, "DICTUADDGETREF"),
+    (0x41, "DICTSETB"),
+    (0x42, "DICTISETB"),
+    (0x43, "DICTUSETB"),
+    (0x45, "DICTSETGETB"),
+    (0x46, "DICTISETGETB"),
+    (0x47, "DICTUSETGETB"),
+    (0x49, "DICTREPLACEB"),
+    (0x4a, "DICTIREPLACEB"),
+    (0x4b, "DICTUREPLACEB"),
+    (0x4d, "DICTREPLACEGETB"),
+    (0x4e, "DICTIREPLACEGETB"),
+    (0x4f, "DICTUREPLACEGETB"),
+    (0x51, "DICTADDB"),
+    (0x52, "DICTIADDB"),
+    (0x53, "DICTUADDB"),
+    (0x55, "DICTADDGETB"),
+    (0x56, "DICTIADDGETB"),
+    (0x57, "DICTUADDGETB"),
+    (0x59, "DICTDEL"),
+    (0x5a, "DICTIDEL"),
+    (0x5b, "DICTUDEL"),
+    (0x62, "DICTDELGET"),
+    (0x63, "DICTDELGETREF"),
+    (0x64, "DICTIDELGET"),
+    (0x65, "DICTIDELGETREF"),
+    (0x66, "DICTUDELGET"),
+    (0x67, "DICTUDELGETREF"),
+    (0x69, "DICTGETOPTREF"),
+    (0x6a, "DICTIGETOPTREF"),
+    (0x6b, "DICTUGETOPTREF"),
+    (0x6d, "DICTSETGETOPTREF"),
+    (0x6e, "DICTISETGETOPTREF"),
+    (0x6f, "DICTUSETGETOPTREF"),
+    (0x70, "PFXDICTSET"),
+    (0x71, "PFXDICTREPLACE"),
+    (0x72, "PFXDICTADD"),
+    (0x73, "PFXDICTDEL"),
+    (0x74, "DICTGETNEXT"),
+    (0x75, "DICTGETNEXTEQ"),
+    (0x76, "DICTGETPREV"),
+    (0x77, "DICTGETPREVEQ"),
+    (0x78, "DICTIGETNEXT"),
+    (0x79, "DICTIGETNEXTEQ"),
+    (0x7a, "DICTIGETPREV"),
+    (0x7b, "DICTIGETPREVEQ"),
+    (0x7c, "DICTUGETNEXT"),
+    (0x7d, "DICTUGETNEXTEQ"),
+    (0x7e, "DICTUGETPREV"),
+    (0x7f, "DICTUGETPREVEQ"),
+    (0x82, "DICTMIN"),
+    (0x83, "DICTMINREF"),
+    (0x84, "DICTIMIN"),
+    (0x85, "DICTIMINREF"),
+    (0x86, "DICTUMIN"),
+    (0x87, "DICTUMINREF"),
+    (0x8a, "DICTMAX"),
+    (0x8b, "DICTMAXREF"),
+    (0x8c, "DICTIMAX"),
+    (0x8d, "DICTIMAXREF"),
+    (0x8e, "DICTUMAX"),
+    (0x8f, "DICTUMAXREF"),
+    (0x92, "DICTREMMIN"),
+    (0x93, "DICTREMMINREF"),
+    (0x94, "DICTIREMMIN"),
+    (0x95, "DICTIREMMINREF"),
+    (0x96, "DICTUREMMIN"),
+    (0x97, "DICTUREMMINREF"),
+    (0x9a, "DICTREMMAX"),
+    (0x9b, "DICTREMMAXREF"),
+    (0x9c, "DICTIREMMAX"),
+    (0x9d, "DICTIREMMAXREF"),
+    (0x9e, "DICTUREMMAX"),
+    (0x9f, "DICTUREMMAXREF"),
+    (0xa0, "DICTIGETJMP"),
+    (0xa1, "DICTUGETJMP"),
+    (0xa2, "DICTIGETEXEC"),
+    (0xa3, "DICTUGETEXEC"),
+    (0xa8, "PFXDICTGETQ"),
+    (0xa9, "PFXDICTGET"),
+    (0xaa, "PFXDICTGETJMP"),
+    (0xab, "PFXDICTGETEXEC"),
+    (0xb1, "SUBDICTGET"),
+    (0xb2, "SUBDICTIGET"),
+    (0xb3, "SUBDICTUGET"),
+    (0xb5, "SUBDICTRPGET"),
+    (0xb6, "SUBDICTIRPGET"),
+    (0xb7, "SUBDICTURPGET"),
+    (0xbc, "DICTIGETJMPZ"),
+    (0xbd, "DICTUGETJMPZ"),
+    (0xbe, "DICTIGETEXECZ"),
+    (0xbf, "DICTUGETEXECZ"),
+];
+
+pub(crate) const PARAM_OPS: &[(u8, &str)] = &[
+    (0x3, "NOW"),
+    (0x4, "BLOCKLT"),
+    (0x5, "LTIME"),
+    (0x6, "RANDSEED"),
+    (0x7, "BALANCE"),
+    (0x8, "MYADDR"),
+    (0x9, "CONFIGROOT"),
+];
+
+pub(crate) const APP_MISC_OPS: &[(u8, &str)] = &[
+    (0x00, "ACCEPT"),
+    (0x01, "SETGASLIMIT"),
+    (0x0f, "COMMIT"),
+    (0x10, "RANDU256"),
+    (0x11, "RAND"),
+    (0x14, "SETRAND"),
+    (0x15, "ADDRAND"),
+    (0x30, "CONFIGDICT"),
+    (0x32, "CONFIGPARAM"),
+    (0x33, "CONFIGOPTPARAM"),
+    (0x40, "GETGLOBVAR"),
+    (0x60, "SETGLOBVAR"),
+];
+
+pub(crate) const APP_CRYPTO_OPS: &[(u8, &str)] = &[
+    (0x00, "HASHCU"),
+    (0x01, "HASHSU"),
+    (0x02, "SHA256U"),
+    (0x10, "CHKSIGNU"),
+    (0x11, "CHKSIGNS"),
+    (0x40, "CDATASIZEQ"),
+    (0x41, "CDATASIZE"),
+    (0x42, "SDATASIZEQ"),
+    (0x43, "SDATASIZE"),
+];
+
+pub(crate) const APP_CURRENCY_OPS: &[(u8, &str)] = &[
+    (0x00, "LDGRAMS"),
+    (0x01, "LDVARINT16"),
+    (0x02, "STGRAMS"),
+    (0x03, "STVARINT16"),
+    (0x04, "LDVARUINT32"),
+    (0x05, "LDVARINT32"),
+    (0x06, "STVARUINT32"),
+    (0x07, "STVARINT32"),
+    (0x40, "LDMSGADDR"),
+    (0x41, "LDMSGADDRQ"),
+    (0x42, "PARSEMSGADDR"),
+    (0x43, "PARSEMSGADDRQ"),
+    (0x44, "REWRITESTDADDR"),
+    (0x45, "REWRITESTDADDRQ"),
+    (0x46, "REWRITEVARADDR"),
+    (0x47, "REWRITEVARADDRQ"),
+];
+
+pub(crate) const APP_ACTION_OPS: &[(u8, &str)] = &[
+    (0x00, "SENDRAWMSG"),
+    (0x02, "RAWRESERVE"),
+    (0x03, "RAWRESERVEX"),
+    (0x04, "SETCODE"),
+    (0x06, "SETLIBCODE"),
+    (0x07, "CHANGELIB"),
+];
@@ -1,3 +1,6 @@
+use crate::core::lexer::{LexerPosition, LiteralKind};
+use crate::core::OpenBlock;
+
 pub use anyhow::Error;
 
 #[derive(Debug, thiserror::Error)]
@@ -7,5 +10,242 @@ pub struct ExecutionAborted {
 }
 
 #[derive(Debug, thiserror::Error)]
-#[error("Unexpected eof")]
-pub struct UnexpectedEof;
+#[error("Execution cancelled")]
+pub struct Cancelled {
+    pub backtrace: String,
+}
+
+/// The execution budget set with [`Fuel`] is exceeded.
+///
+/// [`Fuel`]: crate::core::Fuel
+#[derive(Debug, thiserror::Error)]
+#[error("Fuel exhausted: {reason}")]
+pub struct FuelExhausted {
+    pub reason: String,
+    pub backtrace: String,
+}
+
+/// A value thrown by `throw` which was not caught by `try`.
+#[derive(Debug, thiserror::Error)]
+#[error("Uncaught exception: {value}")]
+pub struct Thrown {
+    pub value: String,
+}
+
+#[derive(Debug, Default)]
+pub struct UnexpectedEof {
+    /// An unterminated literal which was being scanned.
+    pub literal: Option<OpenLiteral>,
+}
+
+impl std::fmt::Display for UnexpectedEof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.literal {
+            None => f.write_str("Unexpected eof"),
+            Some(literal) => write!(
+                f,
+                "Unexpected eof in {} started at {}:{}:{}, missing closing `{}`",
+                literal.kind,
+                literal.source_block,
+                literal.line_number,
+                literal.column,
+                literal.kind.closing()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UnexpectedEof {}
+
+/// Position of an unterminated literal.
+#[derive(Debug, Clone)]
+pub struct OpenLiteral {
+    pub kind: LiteralKind,
+    pub source_block: String,
+    pub line_number: usize,
+    /// Column right after the opening token, starting from 1.
+    pub column: usize,
+}
+
+/// End of input with unclosed `{ ... }` blocks.
+#[derive(Debug)]
+pub struct UnclosedBlocks {
+    pub blocks: Vec<OpenBlock>,
+}
+
+impl std::fmt::Display for UnclosedBlocks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unexpected eof, {} unclosed block(s)", self.blocks.len())?;
+        for block in &self.blocks {
+            write!(f, "\n  `{{` opened at {block}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnclosedBlocks {}
+
+/// An error with the location of the token which has caused it.
+pub struct FiftError {
+    pub source_block: String,
+    pub line_number: usize,
+    /// Column of the token, starting from 1.
+    pub column: usize,
+    /// Source line without the trailing newline.
+    pub line: String,
+    /// Byte range of the token in the line.
+    pub span: std::ops::Range<usize>,
+    pub error: anyhow::Error,
+}
+
+impl FiftError {
+    pub fn new(pos: &LexerPosition<'_>, error: anyhow::Error) -> Self {
+        let (line, span) = token_span(pos);
+        Self {
+            source_block: pos.source_block_name.to_owned(),
+            line_number: pos.line_number,
+            column: line[..span.start].chars().count() + 1,
+            line: line.to_owned(),
+            span,
+            error,
+        }
+    }
+
+    pub fn snippet(&self) -> SourceSnippet {
+        SourceSnippet::new(&self.line, self.span.clone())
+    }
+
+    /// Displays the source line with the token underlined.
+    pub fn display_snippet(&self) -> impl std::fmt::Display + '_ {
+        struct Snippet<'a>(&'a FiftError);
+
+        impl std::fmt::Display for Snippet<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let e = self.0;
+                let number = e.line_number.to_string();
+                let pad = " ".repeat(number.len());
+                let snippet = e.snippet();
+                let offset = " ".repeat(snippet.span.start);
+                let carets = "^".repeat(snippet.span.len());
+
+                writeln!(
+                    f,
+                    "{pad}--> {}:{}:{}",
+                    e.source_block, e.line_number, e.column
+                )?;
+                writeln!(f, "{pad} |")?;
+                writeln!(f, "{number} | {}", snippet.text)?;
+                write!(f, "{pad} | {offset}{carets}")
+            }
+        }
+
+        Snippet(self)
+    }
+}
+
+/// Source line prepared for rendering with the token underlined.
+///
+/// Tabs are expanded and long lines are truncated around the token.
+pub struct SourceSnippet {
+    pub text: String,
+    /// Non-empty range of the token in chars of the text.
+    pub span: std::ops::Range<usize>,
+}
+
+impl SourceSnippet {
+    pub const MAX_WIDTH: usize = 100;
+    pub const TAB_WIDTH: usize = 4;
+
+    const ELLIPSIS: &'static str = "...";
+
+    /// Prepares the line with the token at the specified byte range.
+    pub fn new(line: &str, span: std::ops::Range<usize>) -> Self {
+        let mut chars = Vec::with_capacity(line.len());
+        let (mut start, mut end) = (None, None);
+        for (i, c) in line.char_indices() {
+            if i == span.start {
+                start = Some(chars.len());
+            }
+            if i == span.end {
+                end = Some(chars.len());
+            }
+            match c {
+                '\t' => chars.resize((chars.len() / Self::TAB_WIDTH + 1) * Self::TAB_WIDTH, ' '),
+                c => chars.push(c),
+            }
+        }
+        let start = start.unwrap_or(chars.len());
+        let end = end.unwrap_or(chars.len());
+
+        let (from, to) = if chars.len() <= Self::MAX_WIDTH {
+            (0, chars.len())
+        } else {
+            // Keep some context before the token
+            let from = std::cmp::min(
+                start.saturating_sub(Self::MAX_WIDTH / 4),
+                chars.len() - Self::MAX_WIDTH,
+            );
+            (from, from + Self::MAX_WIDTH)
+        };
+
+        let mut text = String::new();
+        if from > 0 {
+            text.push_str(Self::ELLIPSIS);
+        }
+        let start = text.len() + start - from;
+        let end = text.len() + std::cmp::min(end, to) - from;
+        text.extend(&chars[from..to]);
+        if to < chars.len() {
+            text.push_str(Self::ELLIPSIS);
+        }
+
+        Self {
+            text,
+            span: start..std::cmp::max(end, start + 1),
+        }
+    }
+
+    pub fn from_position(pos: &LexerPosition<'_>) -> Self {
+        let (line, span) = token_span(pos);
+        Self::new(line, span)
+    }
+}
+
+/// Returns the line without the trailing newline and the byte range of the token.
+fn token_span<'a>(pos: &LexerPosition<'a>) -> (&'a str, std::ops::Range<usize>) {
+    let line = pos.line.trim_end_matches(['\r', '\n']);
+
+    let end = std::cmp::min(pos.line_offset_end, line.len());
+    let mut start = std::cmp::min(pos.line_offset_start, end);
+    // Offsets may point to the preceding whitespace
+    start += line[start..end].len() - line[start..end].trim_start().len();
+    (line, start..end)
+}
+
+impl FiftError {
+    /// Returns the inner error if the error has a position attached.
+    pub fn unwrap_position(error: &anyhow::Error) -> &anyhow::Error {
+        match error.downcast_ref::<Self>() {
+            Some(e) => &e.error,
+            None => error,
+        }
+    }
+}
+
+impl std::fmt::Display for FiftError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::fmt::Debug for FiftError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}\n\n{}", self.error, self.display_snippet())
+    }
+}
+
+impl std::error::Error for FiftError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
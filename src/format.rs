@@ -0,0 +1,54 @@
+//! Number formatting which does not depend on the platform or locale.
+//!
+//! The output only consists of ASCII digits, latin letters and `-`,
+//! so it can be used in generated fixtures and parsed back by the interpreter.
+
+use num_bigint::{BigInt, Sign};
+
+/// Displays an integer in the specified radix (2..=36).
+pub struct DisplayInt<'a> {
+    int: &'a BigInt,
+    radix: u32,
+    uppercase: bool,
+}
+
+impl<'a> DisplayInt<'a> {
+    /// # Panics
+    ///
+    /// Panics if the radix is not in range 2..=36.
+    pub fn new(int: &'a BigInt, radix: u32) -> Self {
+        assert!((2..=36).contains(&radix), "radix must be in range 2..=36");
+        Self {
+            int,
+            radix,
+            uppercase: false,
+        }
+    }
+
+    pub fn uppercase(mut self, uppercase: bool) -> Self {
+        self.uppercase = uppercase;
+        self
+    }
+}
+
+impl std::fmt::Display for DisplayInt<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        if self.int.sign() == Sign::Minus {
+            f.write_char('-')?;
+        }
+        for digit in self.int.magnitude().to_radix_be(self.radix) {
+            f.write_char(digit_to_ascii(digit, self.uppercase))?;
+        }
+        Ok(())
+    }
+}
+
+fn digit_to_ascii(digit: u8, uppercase: bool) -> char {
+    (match digit {
+        0..=9 => b'0' + digit,
+        _ if uppercase => b'A' + digit - 10,
+        _ => b'a' + digit - 10,
+    }) as char
+}
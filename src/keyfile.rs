@@ -0,0 +1,146 @@
+//! Passphrase-protected key container.
+//!
+//! ```text
+//! magic:8 m_cost:u32le t_cost:u32le p_cost:u32le salt:16 nonce:12 ciphertext+tag
+//! ```
+//!
+//! The encryption key is derived from the passphrase with Argon2id,
+//! the secret is encrypted with AES-256-GCM and the header is authenticated
+//! as associated data, so the file can be moved between platforms as is.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::Result;
+use rand::Rng;
+
+pub const MAGIC: &[u8; 8] = b"FIFTKEY\x01";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 12 + SALT_LEN + NONCE_LEN;
+
+/// Argon2id cost parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyfileParams {
+    /// Memory size in KiB.
+    pub m_cost: u32,
+    /// Number of iterations.
+    pub t_cost: u32,
+    /// Degree of parallelism.
+    pub p_cost: u32,
+}
+
+impl Default for KeyfileParams {
+    /// OWASP recommended minimum for Argon2id.
+    fn default() -> Self {
+        Self {
+            m_cost: 19 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+        }
+    }
+}
+
+impl KeyfileParams {
+    /// Upper bounds which protect from files which would take
+    /// too much memory or time to decrypt (at most 1 GiB of memory).
+    const MAX_M_COST: u32 = 1024 * 1024;
+    const MAX_T_COST: u32 = 64;
+    const MAX_P_COST: u32 = 16;
+
+    fn check(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.m_cost <= Self::MAX_M_COST
+                && self.t_cost <= Self::MAX_T_COST
+                && self.p_cost <= Self::MAX_P_COST,
+            "Key file parameters are out of range"
+        );
+        Ok(())
+    }
+}
+
+/// Encrypts the secret with the default parameters.
+pub fn encrypt(secret: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    encrypt_with_params(secret, passphrase, KeyfileParams::default())
+}
+
+pub fn encrypt_with_params(
+    secret: &[u8],
+    passphrase: &[u8],
+    params: KeyfileParams,
+) -> Result<Vec<u8>> {
+    params.check()?;
+
+    let mut rng = rand::thread_rng();
+    let salt: [u8; SALT_LEN] = rng.gen();
+    let nonce: [u8; NONCE_LEN] = rng.gen();
+
+    let mut result = Vec::with_capacity(HEADER_LEN + secret.len() + TAG_LEN);
+    result.extend_from_slice(MAGIC);
+    result.extend_from_slice(&params.m_cost.to_le_bytes());
+    result.extend_from_slice(&params.t_cost.to_le_bytes());
+    result.extend_from_slice(&params.p_cost.to_le_bytes());
+    result.extend_from_slice(&salt);
+    result.extend_from_slice(&nonce);
+
+    let cipher = make_cipher(passphrase, &salt, params)?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: secret,
+                aad: &result,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt key file"))?;
+
+    result.extend_from_slice(&ciphertext);
+    Ok(result)
+}
+
+/// Decrypts the secret from the container.
+pub fn decrypt(container: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    anyhow::ensure!(
+        container.len() >= HEADER_LEN + TAG_LEN && container.starts_with(MAGIC),
+        "Invalid key file"
+    );
+
+    let (header, ciphertext) = container.split_at(HEADER_LEN);
+    let read_u32 = |offset: usize| {
+        let offset = MAGIC.len() + offset * 4;
+        u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap())
+    };
+    let params = KeyfileParams {
+        m_cost: read_u32(0),
+        t_cost: read_u32(1),
+        p_cost: read_u32(2),
+    };
+    params.check()?;
+
+    let salt = &header[HEADER_LEN - NONCE_LEN - SALT_LEN..HEADER_LEN - NONCE_LEN];
+    let nonce = &header[HEADER_LEN - NONCE_LEN..];
+
+    let cipher = make_cipher(passphrase, salt, params)?;
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("Invalid passphrase or corrupted key file"))
+}
+
+fn make_cipher(passphrase: &[u8], salt: &[u8], params: KeyfileParams) -> Result<Aes256Gcm> {
+    let params = argon2::Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| anyhow::anyhow!("Invalid key file parameters: {e}"))?;
+    let argon2 = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+
+    let mut key = [0u8; 32];
+    argon2
+        .hash_password_into(passphrase, salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Failed to derive key file key: {e}"))?;
+    Ok(Aes256Gcm::new(&key.into()))
+}
@@ -2,25 +2,58 @@ extern crate self as fift;
 
 use anyhow::Result;
 
+pub use self::builder::{Fift, FiftBuilder};
+pub use self::config::FiftConfig;
 pub use self::core::Context;
 
+pub mod alloc;
+pub mod builder;
+pub mod config;
 pub mod core;
+pub mod crash;
+pub mod disasm;
 pub mod error;
+pub mod format;
+#[cfg(feature = "keyfile")]
+pub mod keyfile;
+pub mod metrics;
 pub mod modules;
+#[cfg(feature = "stdlib")]
+pub mod stdlib;
 pub mod util;
 
 impl Context<'_> {
     pub fn with_basic_modules(self) -> Result<Self> {
         use modules::*;
-        self.with_module(BaseModule)?
+        let ctx = self
+            .with_module(BaseModule)?
             .with_module(Arithmetic)?
             .with_module(CellUtils)?
             .with_module(DictUtils)?
+            .with_module(DnsUtils)?
             .with_module(Control)?
             .with_module(DebugUtils)?
             .with_module(StackUtils)?
             .with_module(StringUtils)?
             .with_module(Crypto)?
-            .with_module(VmUtils)
+            .with_module(Hashing)?
+            .with_module(VmUtils)?
+            .with_module(TokenUtils)?
+            .with_module(MultisigUtils)?
+            .with_module(TlbUtils)?;
+
+        #[cfg(feature = "serde")]
+        let ctx = ctx.with_module(JsonUtils)?;
+
+        #[cfg(feature = "fec")]
+        let ctx = ctx.with_module(FecUtils)?;
+
+        #[cfg(feature = "keychain")]
+        let ctx = ctx.with_module(KeychainUtils)?;
+
+        #[cfg(feature = "keyfile")]
+        let ctx = ctx.with_module(KeyfileUtils)?;
+
+        Ok(ctx)
     }
 }
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::Context;
+
+/// Counters aggregated over multiple script runs, e.g. by a host
+/// application which serves them in the Prometheus text format.
+#[derive(Debug, Default, Clone)]
+pub struct Metrics {
+    /// Number of finished runs.
+    pub scripts: u64,
+    /// Number of runs which failed with an error.
+    pub errors: u64,
+    /// Number of executed continuations.
+    pub steps: u64,
+    /// Calls and time spent in each dictionary word,
+    /// only collected when the context has a profiler.
+    pub words: BTreeMap<String, WordMetrics>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WordMetrics {
+    pub calls: u64,
+    pub elapsed: Duration,
+}
+
+impl Metrics {
+    /// Adds the counters of the finished run.
+    pub fn record(&mut self, ctx: &Context<'_>, failed: bool) {
+        self.scripts += 1;
+        self.errors += failed as u64;
+        self.steps += ctx.steps;
+
+        if let Some(profiler) = &ctx.profiler {
+            for word in profiler.report(&ctx.dictionary) {
+                let entry = self.words.entry(word.name).or_default();
+                entry.calls += word.calls;
+                entry.elapsed += word.elapsed;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Displays the counters in the Prometheus text exposition format.
+    pub fn display_prometheus(&self) -> impl std::fmt::Display + '_ {
+        struct DisplayPrometheus<'a>(&'a Metrics);
+
+        impl std::fmt::Display for DisplayPrometheus<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let m = self.0;
+                for (name, help, value) in [
+                    (
+                        "fift_scripts_total",
+                        "Number of executed scripts.",
+                        m.scripts,
+                    ),
+                    ("fift_errors_total", "Number of failed scripts.", m.errors),
+                    (
+                        "fift_steps_total",
+                        "Number of executed continuations.",
+                        m.steps,
+                    ),
+                ] {
+                    write_header(f, name, help)?;
+                    writeln!(f, "{name} {value}")?;
+                }
+
+                if m.words.is_empty() {
+                    return Ok(());
+                }
+
+                write_header(f, "fift_word_calls_total", "Number of calls of each word.")?;
+                for (word, stats) in &m.words {
+                    writeln!(
+                        f,
+                        "fift_word_calls_total{{word=\"{}\"}} {}",
+                        EscapeLabel(word),
+                        stats.calls
+                    )?;
+                }
+
+                write_header(f, "fift_word_seconds_total", "Time spent in each word.")?;
+                for (word, stats) in &m.words {
+                    writeln!(
+                        f,
+                        "fift_word_seconds_total{{word=\"{}\"}} {}",
+                        EscapeLabel(word),
+                        stats.elapsed.as_secs_f64()
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        DisplayPrometheus(self)
+    }
+}
+
+fn write_header(f: &mut std::fmt::Formatter<'_>, name: &str, help: &str) -> std::fmt::Result {
+    writeln!(f, "# HELP {name} {help}")?;
+    writeln!(f, "# TYPE {name} counter")
+}
+
+struct EscapeLabel<'a>(&'a str);
+
+impl std::fmt::Display for EscapeLabel<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        for c in self.0.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
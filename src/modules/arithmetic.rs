@@ -3,9 +3,10 @@ use std::rc::Rc;
 use anyhow::Result;
 use num_bigint::{BigInt, Sign};
 use num_integer::Integer;
-use num_traits::{One, Signed, Zero};
+use num_traits::{One, Signed, ToPrimitive, Zero};
 
 use crate::core::*;
+use crate::util::fits_into_bits;
 
 pub struct Arithmetic;
 
@@ -74,12 +75,13 @@ impl Arithmetic {
     }
 
     #[cmd(name = "/", stack, args(r = Rounding::Floor))]
+    #[cmd(name = "/f", stack, args(r = Rounding::Floor))]
     #[cmd(name = "/r", stack, args(r = Rounding::Nearest))]
     #[cmd(name = "/c", stack, args(r = Rounding::Ceil))]
     fn interpret_div(stack: &mut Stack, r: Rounding) -> Result<()> {
         let y = stack.pop_int()?;
         let x = stack.pop_int()?;
-        stack.push(divmod(&x, &y, r).0)
+        stack.push(divmod(&x, &y, r)?.0)
     }
 
     #[cmd(name = "mod", stack, args(r = Rounding::Floor))]
@@ -88,7 +90,7 @@ impl Arithmetic {
     fn interpret_mod(stack: &mut Stack, r: Rounding) -> Result<()> {
         let y = stack.pop_int()?;
         let x = stack.pop_int()?;
-        stack.push(divmod(&x, &y, r).1)
+        stack.push(divmod(&x, &y, r)?.1)
     }
 
     #[cmd(name = "/mod", stack, args(r = Rounding::Floor))]
@@ -97,11 +99,123 @@ impl Arithmetic {
     fn interpret_divmod(stack: &mut Stack, r: Rounding) -> Result<()> {
         let y = stack.pop_int()?;
         let x = stack.pop_int()?;
-        let (q, r) = divmod(&x, &y, r);
+        let (q, r) = divmod(&x, &y, r)?;
         stack.push(q)?;
         stack.push(r)
     }
 
+    #[cmd(name = "*/", stack, args(r = Rounding::Floor))]
+    #[cmd(name = "*/r", stack, args(r = Rounding::Nearest))]
+    #[cmd(name = "*/c", stack, args(r = Rounding::Ceil))]
+    fn interpret_muldiv(stack: &mut Stack, r: Rounding) -> Result<()> {
+        let z = stack.pop_int()?;
+        let y = stack.pop_int()?;
+        let x = stack.pop_int()?;
+        stack.push(divmod(&(*x * *y), &z, r)?.0)
+    }
+
+    #[cmd(name = "*mod", stack, args(r = Rounding::Floor))]
+    #[cmd(name = "*rmod", stack, args(r = Rounding::Nearest))]
+    #[cmd(name = "*cmod", stack, args(r = Rounding::Ceil))]
+    fn interpret_mulmod(stack: &mut Stack, r: Rounding) -> Result<()> {
+        let z = stack.pop_int()?;
+        let y = stack.pop_int()?;
+        let x = stack.pop_int()?;
+        stack.push(divmod(&(*x * *y), &z, r)?.1)
+    }
+
+    #[cmd(name = "*/mod", stack, args(r = Rounding::Floor))]
+    #[cmd(name = "*/rmod", stack, args(r = Rounding::Nearest))]
+    #[cmd(name = "*/cmod", stack, args(r = Rounding::Ceil))]
+    fn interpret_muldivmod(stack: &mut Stack, r: Rounding) -> Result<()> {
+        let z = stack.pop_int()?;
+        let y = stack.pop_int()?;
+        let x = stack.pop_int()?;
+        let (q, r) = divmod(&(*x * *y), &z, r)?;
+        stack.push(q)?;
+        stack.push(r)
+    }
+
+    // Rounding mode as in TVM: 0 - floor, 1 - nearest, 2 - ceil
+    #[cmd(name = "divmod-mode", stack)]
+    fn interpret_divmod_mode(stack: &mut Stack) -> Result<()> {
+        let r = Rounding::from_mode(stack.pop_smallint_range(0, 2)?);
+        let y = stack.pop_int()?;
+        let x = stack.pop_int()?;
+        let (q, r) = divmod(&x, &y, r)?;
+        stack.push(q)?;
+        stack.push(r)
+    }
+
+    #[cmd(name = "muldivmod-mode", stack)]
+    fn interpret_muldivmod_mode(stack: &mut Stack) -> Result<()> {
+        let r = Rounding::from_mode(stack.pop_smallint_range(0, 2)?);
+        let z = stack.pop_int()?;
+        let y = stack.pop_int()?;
+        let x = stack.pop_int()?;
+        let (q, r) = divmod(&(*x * *y), &z, r)?;
+        stack.push(q)?;
+        stack.push(r)
+    }
+
+    #[cmd(name = "powmod", stack)]
+    fn interpret_powmod(stack: &mut Stack) -> Result<()> {
+        let m = stack.pop_int()?;
+        let y = stack.pop_int()?;
+        let x = stack.pop_int()?;
+        anyhow::ensure!(!m.is_zero(), "Division by zero");
+
+        let res = if y.sign() == Sign::Minus {
+            modpow(&modinv(&x, &m)?, &-*y, &m)
+        } else {
+            modpow(&x, &y, &m)
+        };
+        stack.push(res)
+    }
+
+    #[cmd(name = "invmod", stack)]
+    fn interpret_invmod(stack: &mut Stack) -> Result<()> {
+        let m = stack.pop_int()?;
+        let x = stack.pop_int()?;
+        anyhow::ensure!(!m.is_zero(), "Division by zero");
+        stack.push(modinv(&x, &m)?)
+    }
+
+    #[cmd(name = "prime?", stack)]
+    fn interpret_is_prime(stack: &mut Stack) -> Result<()> {
+        let x = stack.pop_int()?;
+        stack.push_bool(is_probable_prime(&x))
+    }
+
+    #[cmd(name = "nextprime", stack)]
+    fn interpret_next_prime(stack: &mut Stack) -> Result<()> {
+        let x = stack.pop_int()?;
+        let mut p = std::cmp::max(*x + 1, BigInt::from(2));
+        while !is_probable_prime(&p) {
+            p += 1;
+        }
+        stack.push(p)
+    }
+
+    #[cmd(name = "randprime", stack)]
+    fn interpret_random_prime(stack: &mut Stack) -> Result<()> {
+        use rand::Rng;
+
+        let bits = stack.pop_smallint_range(2, 4096)? as u64;
+        let mut bytes = vec![0u8; ((bits + 7) / 8) as usize];
+        let mut rng = rand::thread_rng();
+        loop {
+            rng.fill(bytes.as_mut_slice());
+            let mut p = BigInt::from_bytes_be(Sign::Plus, &bytes);
+            // Keep exactly `bits` bits with the highest one set and make it odd
+            p &= (BigInt::one() << bits) - 1;
+            p |= (BigInt::one() << (bits - 1)) | BigInt::one();
+            if is_probable_prime(&p) {
+                break stack.push(p);
+            }
+        }
+    }
+
     #[cmd(name = "1<<", stack, args(negate = false, minus_one = false))]
     #[cmd(name = "-1<<", stack, args(negate = true, minus_one = false))]
     #[cmd(name = "1<<1-", stack, args(negate = false, minus_one = true))]
@@ -145,9 +259,7 @@ impl Arithmetic {
         let mut x = stack.pop_int()?;
         match r {
             Rounding::Floor => *x >>= y,
-            // TODO
-            Rounding::Nearest => unimplemented!(),
-            Rounding::Ceil => unimplemented!(),
+            r => *x = divmod(&x, &(BigInt::one() << y), r)?.0,
         }
         stack.push_raw(x)
     }
@@ -203,7 +315,6 @@ impl Arithmetic {
 
     // === Integer comparison ===
 
-    #[cmd(name = "cmp", stack, args(map = [-1, 0, 1]))]
     #[cmd(name = "=", stack, args(map = [0, -1, 0]))]
     #[cmd(name = "<>", stack, args(map = [-1, 0, -1]))]
     #[cmd(name = "<=", stack, args(map = [-1, -1, 0]))]
@@ -217,6 +328,57 @@ impl Arithmetic {
         stack.push_int(map[map_index as usize])
     }
 
+    // === Generic comparison ===
+
+    #[cmd(name = "cmp", stack)]
+    fn interpret_cmp_generic(stack: &mut Stack) -> Result<()> {
+        let y = stack.pop()?;
+        let x = stack.pop()?;
+        stack.push_int(x.compare(y.as_ref())? as i8)
+    }
+
+    #[cmd(name = "min", stack, args(min = true))]
+    #[cmd(name = "max", stack, args(min = false))]
+    fn interpret_min_max(stack: &mut Stack, min: bool) -> Result<()> {
+        let y = stack.pop()?;
+        let x = stack.pop()?;
+        let x_is_less = x.compare(y.as_ref())?.is_le();
+        stack.push_raw(if x_is_less == min { x } else { y })
+    }
+
+    #[cmd(name = "minmax", stack)]
+    fn interpret_minmax(stack: &mut Stack) -> Result<()> {
+        let y = stack.pop()?;
+        let x = stack.pop()?;
+        let (min, max) = if x.compare(y.as_ref())?.is_le() {
+            (x, y)
+        } else {
+            (y, x)
+        };
+        stack.push_raw(min)?;
+        stack.push_raw(max)
+    }
+
+    #[cmd(name = "clamp", stack)]
+    fn interpret_clamp(stack: &mut Stack) -> Result<()> {
+        let hi = stack.pop()?;
+        let lo = stack.pop()?;
+        let x = stack.pop()?;
+        anyhow::ensure!(
+            lo.compare(hi.as_ref())?.is_le(),
+            "Invalid clamp range: {} > {}",
+            lo.display_dump(),
+            hi.display_dump()
+        );
+        stack.push_raw(if x.compare(lo.as_ref())?.is_lt() {
+            lo
+        } else if x.compare(hi.as_ref())?.is_gt() {
+            hi
+        } else {
+            x
+        })
+    }
+
     #[cmd(name = "sgn", stack, args(map = [-1, 0, 1]))]
     #[cmd(name = "0=", stack, args(map = [0, -1, 0]))]
     #[cmd(name = "0<>", stack, args(map = [-1, 0, -1]))]
@@ -241,6 +403,46 @@ impl Arithmetic {
         let x = stack.pop_int()?;
         stack.push_bool(x.bits() <= y as u64)
     }
+
+    // === Wrapping arithmetic ===
+
+    #[cmd(name = "u8+", stack, args(bits = 8, op = WrappingOp::Add))]
+    #[cmd(name = "u16+", stack, args(bits = 16, op = WrappingOp::Add))]
+    #[cmd(name = "u32+", stack, args(bits = 32, op = WrappingOp::Add))]
+    #[cmd(name = "u64+", stack, args(bits = 64, op = WrappingOp::Add))]
+    #[cmd(name = "u8-", stack, args(bits = 8, op = WrappingOp::Sub))]
+    #[cmd(name = "u16-", stack, args(bits = 16, op = WrappingOp::Sub))]
+    #[cmd(name = "u32-", stack, args(bits = 32, op = WrappingOp::Sub))]
+    #[cmd(name = "u64-", stack, args(bits = 64, op = WrappingOp::Sub))]
+    #[cmd(name = "u8*", stack, args(bits = 8, op = WrappingOp::Mul))]
+    #[cmd(name = "u16*", stack, args(bits = 16, op = WrappingOp::Mul))]
+    #[cmd(name = "u32*", stack, args(bits = 32, op = WrappingOp::Mul))]
+    #[cmd(name = "u64*", stack, args(bits = 64, op = WrappingOp::Mul))]
+    fn interpret_wrapping_op(stack: &mut Stack, bits: u16, op: WrappingOp) -> Result<()> {
+        let y = stack.pop_int()?;
+        let mut x = stack.pop_int()?;
+        match op {
+            WrappingOp::Add => *x += *y,
+            WrappingOp::Sub => *x -= *y,
+            WrappingOp::Mul => *x *= *y,
+        }
+        // Bitwise ops use two's complement for negative numbers
+        *x &= (BigInt::one() << bits) - 1;
+        stack.push_raw(x)
+    }
+
+    #[cmd(name = "fits-u8?", stack, args(bits = 8, signed = false))]
+    #[cmd(name = "fits-u16?", stack, args(bits = 16, signed = false))]
+    #[cmd(name = "fits-u32?", stack, args(bits = 32, signed = false))]
+    #[cmd(name = "fits-u64?", stack, args(bits = 64, signed = false))]
+    #[cmd(name = "fits-i8?", stack, args(bits = 8, signed = true))]
+    #[cmd(name = "fits-i16?", stack, args(bits = 16, signed = true))]
+    #[cmd(name = "fits-i32?", stack, args(bits = 32, signed = true))]
+    #[cmd(name = "fits-i64?", stack, args(bits = 64, signed = true))]
+    fn interpret_fits_native(stack: &mut Stack, bits: u16, signed: bool) -> Result<()> {
+        let x = stack.pop_int()?;
+        stack.push_bool(fits_into_bits(&x, bits, signed))
+    }
 }
 
 enum Rounding {
@@ -249,12 +451,29 @@ enum Rounding {
     Ceil,
 }
 
+impl Rounding {
+    fn from_mode(mode: u32) -> Self {
+        match mode {
+            0 => Self::Floor,
+            1 => Self::Nearest,
+            _ => Self::Ceil,
+        }
+    }
+}
+
+enum WrappingOp {
+    Add,
+    Sub,
+    Mul,
+}
+
 // Math code from:
 // https://github.com/tonlabs/ever-vm/blob/master/src/stack/integer/math.rs
 
 #[inline]
-fn divmod(lhs: &BigInt, rhs: &BigInt, rounding: Rounding) -> (BigInt, BigInt) {
-    match rounding {
+fn divmod(lhs: &BigInt, rhs: &BigInt, rounding: Rounding) -> Result<(BigInt, BigInt)> {
+    anyhow::ensure!(!rhs.is_zero(), "Division by zero");
+    Ok(match rounding {
         Rounding::Floor => lhs.div_mod_floor(rhs),
         Rounding::Nearest => {
             let (mut q, mut r) = lhs.div_rem(rhs);
@@ -266,7 +485,86 @@ fn divmod(lhs: &BigInt, rhs: &BigInt, rounding: Rounding) -> (BigInt, BigInt) {
             round_ceil(&mut q, &mut r, lhs, rhs);
             (q, r)
         }
+    })
+}
+
+/// Miller-Rabin primality test.
+///
+/// Deterministic for numbers below 3.3 * 10^24, uses additional
+/// random bases for larger ones.
+fn is_probable_prime(n: &BigInt) -> bool {
+    use rand::Rng;
+
+    const BASES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+    const RANDOM_ROUNDS: usize = 16;
+    const TRIAL_DIVISION_LIMIT: u32 = 2000;
+
+    if let Some(n) = n.to_u64().filter(|n| *n < 1 << 21) {
+        return n >= 2 && (2..).take_while(|i| i * i <= n).all(|i| n % i != 0);
+    }
+    if n.sign() == Sign::Minus || (2..TRIAL_DIVISION_LIMIT).any(|p| (n % p).is_zero()) {
+        return false;
+    }
+
+    let n_minus_one = n - 1u32;
+    let s = n_minus_one.trailing_zeros().unwrap_or_default();
+    let d = &n_minus_one >> s;
+
+    let is_witness = |a: &BigInt| {
+        let mut x = a.modpow(&d, n);
+        if x.is_one() || x == n_minus_one {
+            return false;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == n_minus_one {
+                return false;
+            }
+        }
+        true
+    };
+
+    if BASES.iter().any(|a| is_witness(&BigInt::from(*a))) {
+        return false;
+    }
+    if n.bits() <= 81 {
+        return true;
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut bytes = vec![0u8; ((n.bits() + 7) / 8) as usize];
+    (0..RANDOM_ROUNDS).all(|_| {
+        rng.fill(bytes.as_mut_slice());
+        let a = BigInt::from_bytes_be(Sign::Plus, &bytes) % (n - 3u32) + 2u32;
+        !is_witness(&a)
+    })
+}
+
+/// `BigInt::modpow` uses Montgomery multiplication for odd moduli,
+/// which only pays off for long exponents because of its setup cost
+/// (see `benches/powmod.rs`), so short ones use square-and-multiply.
+fn modpow(x: &BigInt, y: &BigInt, m: &BigInt) -> BigInt {
+    const MONTGOMERY_MIN_BITS: u64 = 128;
+
+    if y.bits() >= MONTGOMERY_MIN_BITS {
+        return x.modpow(y, m);
+    }
+
+    let base = x.mod_floor(m);
+    let mut res = BigInt::one().mod_floor(m);
+    for i in (0..y.bits()).rev() {
+        res = (&res * &res).mod_floor(m);
+        if y.bit(i) {
+            res = (&res * &base).mod_floor(m);
+        }
     }
+    res
+}
+
+fn modinv(x: &BigInt, m: &BigInt) -> Result<BigInt> {
+    let e = x.extended_gcd(m);
+    anyhow::ensure!(e.gcd.is_one(), "{x} is not invertible modulo {m}");
+    Ok(e.x.mod_floor(m))
 }
 
 #[inline]
@@ -0,0 +1,764 @@
+use std::rc::Rc;
+
+use anyhow::{Context as _, Result};
+use everscale_types::cell::DefaultFinalizer;
+use everscale_types::dict::{dict_insert, SetMode};
+use everscale_types::prelude::*;
+
+use crate::core::*;
+use crate::error::UnexpectedEof;
+use crate::util::store_int_to_builder;
+
+use self::ops::*;
+
+mod ops;
+
+/// Native TVM assembler, compatible with words from `Asm.fif`.
+///
+/// Not a part of the basic modules, scripts load it with `"Asm.fif" include`
+/// (from the embedded library) or `(load-asm)`.
+///
+/// Instructions take the code builder from the stack and append
+/// themselves to it, e.g. `<{ 2 PUSHINT ADD }>c`.
+pub struct Asm;
+
+#[fift_module]
+impl Asm {
+    #[init]
+    fn init(d: &mut Dictionary) -> Result<()> {
+        define_fixed_ops(d)?;
+
+        let mut make_int_lit = |name: String, value: i32| {
+            d.define_word(
+                name,
+                DictionaryEntry::new_ordinary(Rc::new(cont::IntLitCont::from(value))),
+                false,
+            )
+        };
+        for i in 0..16 {
+            make_int_lit(format!("s{i} "), i)?;
+        }
+        make_int_lit("s(-1) ".to_owned(), -1)?;
+        make_int_lit("s(-2) ".to_owned(), -2)?;
+        for i in 0..8 {
+            make_int_lit(format!("c{i} "), CONTROL_REGISTER | i)?;
+        }
+        Ok(())
+    }
+
+    #[cmd(name = "s()", stack)]
+    fn interpret_stack_register(stack: &mut Stack) -> Result<()> {
+        let i = pop_signed_range(stack, -2, 255)?;
+        stack.push_int(i)
+    }
+
+    #[cmd(name = "c()", stack)]
+    fn interpret_control_register(stack: &mut Stack) -> Result<()> {
+        let i = stack.pop_smallint_range(0, 15)?;
+        stack.push_int(CONTROL_REGISTER | i as i32)
+    }
+
+    // === Code blocks ===
+
+    #[cmd(name = "<{", args(end = BlockEnd::Plain))]
+    #[cmd(name = "CONT:<{", args(end = BlockEnd::Then(None)))]
+    #[cmd(name = "IF:<{", args(end = BlockEnd::Then(Some(0xde))))]
+    #[cmd(name = "IFNOT:<{", args(end = BlockEnd::Then(Some(0xdf))))]
+    #[cmd(name = "IFJMP:<{", args(end = BlockEnd::Then(Some(0xe0))))]
+    #[cmd(name = "IFNOTJMP:<{", args(end = BlockEnd::Then(Some(0xe1))))]
+    #[cmd(name = "REPEAT:<{", args(end = BlockEnd::Then(Some(0xe4))))]
+    #[cmd(name = "UNTIL:<{", args(end = BlockEnd::Then(Some(0xe6))))]
+    #[cmd(name = "AGAIN:<{", args(end = BlockEnd::Then(Some(0xea))))]
+    #[cmd(name = "WHILE:<{", args(end = BlockEnd::While))]
+    fn interpret_block_begin(ctx: &mut Context, end: BlockEnd) -> Result<()> {
+        asm_state(ctx).blocks.push(end);
+        ctx.stack.push(CellBuilder::new())
+    }
+
+    #[cmd(name = "}>")]
+    fn interpret_block_end(ctx: &mut Context) -> Result<()> {
+        let end = asm_state(ctx).blocks.pop().context("Unbalanced `}>`")?;
+        let code = ctx.stack.pop_builder()?.build()?;
+        let code = DynCell::as_slice(code.as_ref())?;
+
+        match end {
+            BlockEnd::Plain => {
+                let mut builder = CellBuilder::new();
+                builder.store_slice(code)?;
+                ctx.stack.push(builder)
+            }
+            BlockEnd::Then(opcode) => {
+                let mut builder = ctx.stack.pop_builder()?;
+                store_push_cont(&mut builder, &code)?;
+                if let Some(opcode) = opcode {
+                    store_opcode(&mut builder, opcode, 8)?;
+                }
+                ctx.stack.push_raw(builder)
+            }
+            BlockEnd::Else { inverted } => {
+                let then_code = ctx.stack.pop_builder()?.build()?;
+                let then_code = DynCell::as_slice(then_code.as_ref())?;
+                let mut builder = ctx.stack.pop_builder()?;
+                if inverted {
+                    store_push_cont(&mut builder, &code)?;
+                    store_push_cont(&mut builder, &then_code)?;
+                } else {
+                    store_push_cont(&mut builder, &then_code)?;
+                    store_push_cont(&mut builder, &code)?;
+                }
+                store_opcode(&mut builder, 0xe2, 8)?;
+                ctx.stack.push_raw(builder)
+            }
+            BlockEnd::While => anyhow::bail!("`WHILE:<{{` must be followed by `}}>DO<{{`"),
+            BlockEnd::WhileBody => {
+                let cond = ctx.stack.pop_builder()?.build()?;
+                let cond = DynCell::as_slice(cond.as_ref())?;
+                let mut builder = ctx.stack.pop_builder()?;
+                store_push_cont(&mut builder, &cond)?;
+                store_push_cont(&mut builder, &code)?;
+                store_opcode(&mut builder, 0xe8, 8)?;
+                ctx.stack.push_raw(builder)
+            }
+            BlockEnd::Proc(id) => {
+                let mut builder = CellBuilder::new();
+                builder.store_slice(code)?;
+                asm_program(ctx)?.define(id, builder.build()?)
+            }
+        }
+    }
+
+    #[cmd(name = "}>ELSE<{")]
+    fn interpret_block_else(ctx: &mut Context) -> Result<()> {
+        let state = asm_state(ctx);
+        let inverted = match state.blocks.pop() {
+            Some(BlockEnd::Then(Some(0xde))) => false,
+            Some(BlockEnd::Then(Some(0xdf))) => true,
+            _ => anyhow::bail!("`}}>ELSE<{{` can only be used after `IF:<{{` or `IFNOT:<{{`"),
+        };
+        state.blocks.push(BlockEnd::Else { inverted });
+        ctx.stack.push(CellBuilder::new())
+    }
+
+    #[cmd(name = "}>DO<{")]
+    fn interpret_block_do(ctx: &mut Context) -> Result<()> {
+        let state = asm_state(ctx);
+        anyhow::ensure!(
+            matches!(state.blocks.pop(), Some(BlockEnd::While)),
+            "`}}>DO<{{` can only be used after `WHILE:<{{`"
+        );
+        state.blocks.push(BlockEnd::WhileBody);
+        ctx.stack.push(CellBuilder::new())
+    }
+
+    #[cmd(name = "}>c", args(as_slice = false))]
+    #[cmd(name = "}>s", args(as_slice = true))]
+    fn interpret_block_end_to_cell(ctx: &mut Context, as_slice: bool) -> Result<()> {
+        anyhow::ensure!(
+            matches!(asm_state(ctx).blocks.pop(), Some(BlockEnd::Plain)),
+            "Unbalanced `}}>c`"
+        );
+        let cell = ctx.stack.pop_builder()?.build()?;
+        if as_slice {
+            ctx.stack.push(OwnedCellSlice::new(cell))
+        } else {
+            ctx.stack.push(cell)
+        }
+    }
+
+    // === Stack manipulation ===
+
+    #[cmd(name = "PUSH", stack)]
+    fn interpret_push(stack: &mut Stack) -> Result<()> {
+        let i = pop_register(stack)?;
+        let mut builder = stack.pop_builder()?;
+        match i {
+            Register::Control(i) => store_opcode(&mut builder, 0xed40 | i, 16)?,
+            Register::Stack(i @ 0..=15) => store_opcode(&mut builder, 0x20 | i, 8)?,
+            Register::Stack(i) => store_opcode(&mut builder, 0x5600 | i, 16)?,
+        }
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "POP", stack)]
+    fn interpret_pop(stack: &mut Stack) -> Result<()> {
+        let i = pop_register(stack)?;
+        let mut builder = stack.pop_builder()?;
+        match i {
+            Register::Control(i) => store_opcode(&mut builder, 0xed50 | i, 16)?,
+            Register::Stack(i @ 0..=15) => store_opcode(&mut builder, 0x30 | i, 8)?,
+            Register::Stack(i) => store_opcode(&mut builder, 0x5700 | i, 16)?,
+        }
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "PUSHCTR", stack, args(opcode = 0xed40))]
+    #[cmd(name = "POPCTR", stack, args(opcode = 0xed50))]
+    fn interpret_ctr_op(stack: &mut Stack, opcode: u32) -> Result<()> {
+        let i = match pop_register(stack)? {
+            Register::Control(i) => i,
+            Register::Stack(i) if i < 16 => i,
+            Register::Stack(_) => anyhow::bail!("Invalid control register"),
+        };
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, opcode | i, 16)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "XCHG0", stack, args(two = false))]
+    #[cmd(name = "XCHG", stack, args(two = true))]
+    fn interpret_xchg(stack: &mut Stack, two: bool) -> Result<()> {
+        let j = pop_stack_register(stack)?;
+        let i = if two { pop_stack_register(stack)? } else { 0 };
+        let (i, j) = (i.min(j), i.max(j));
+        let mut builder = stack.pop_builder()?;
+        match (i, j) {
+            _ if i == j => {}
+            (0, 1..=15) => store_opcode(&mut builder, j, 8)?,
+            (0, _) => store_opcode(&mut builder, 0x1100 | j, 16)?,
+            (1, 2..=15) => store_opcode(&mut builder, 0x10 | j, 8)?,
+            (_, 2..=15) => store_opcode(&mut builder, 0x1000 | i << 4 | j, 16)?,
+            _ => anyhow::bail!("Unsupported XCHG s{i},s{j}"),
+        }
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "XCHG2", stack, args(opcode = 0x50))]
+    #[cmd(name = "XCPU", stack, args(opcode = 0x51))]
+    #[cmd(name = "PUSH2", stack, args(opcode = 0x53))]
+    fn interpret_stack_op2(stack: &mut Stack, opcode: u32) -> Result<()> {
+        let j = pop_nibble(stack)?;
+        let i = pop_nibble(stack)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, opcode << 8 | i << 4 | j, 16)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "PUSH3", stack)]
+    fn interpret_push3(stack: &mut Stack) -> Result<()> {
+        let k = pop_nibble(stack)?;
+        let j = pop_nibble(stack)?;
+        let i = pop_nibble(stack)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, 0x5470 << 8 | i << 8 | j << 4 | k, 24)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "BLKSWAP", stack, args(opcode = 0x55, min = 1))]
+    #[cmd(name = "REVERSE", stack, args(opcode = 0x5e, min = 2))]
+    fn interpret_blk_op(stack: &mut Stack, opcode: u32, min: u32) -> Result<()> {
+        let j = stack.pop_smallint_range(0, 15)?;
+        let i = stack.pop_smallint_range(min, 15 + min)? - min;
+        let j = if opcode == 0x55 {
+            anyhow::ensure!(j >= 1, "Block size must be positive");
+            j - 1
+        } else {
+            j
+        };
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, opcode << 8 | i << 4 | j, 16)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "BLKDROP", stack)]
+    fn interpret_blkdrop(stack: &mut Stack) -> Result<()> {
+        let n = stack.pop_smallint_range(0, 15)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, 0x5f00 | n, 16)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "BLKPUSH", stack)]
+    fn interpret_blkpush(stack: &mut Stack) -> Result<()> {
+        let j = stack.pop_smallint_range(0, 15)?;
+        let n = stack.pop_smallint_range(1, 15)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, 0x5f00 | n << 4 | j, 16)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "BLKDROP2", stack)]
+    fn interpret_blkdrop2(stack: &mut Stack) -> Result<()> {
+        let j = stack.pop_smallint_range(0, 15)?;
+        let n = stack.pop_smallint_range(1, 15)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, 0x6c00 | n << 4 | j, 16)?;
+        stack.push_raw(builder)
+    }
+
+    // === Tuples ===
+
+    #[cmd(name = "TUPLE", stack, args(op = 0x0))]
+    #[cmd(name = "INDEX", stack, args(op = 0x1))]
+    #[cmd(name = "UNTUPLE", stack, args(op = 0x2))]
+    #[cmd(name = "UNPACKFIRST", stack, args(op = 0x3))]
+    #[cmd(name = "EXPLODE", stack, args(op = 0x4))]
+    #[cmd(name = "SETINDEX", stack, args(op = 0x5))]
+    #[cmd(name = "INDEXQ", stack, args(op = 0x6))]
+    #[cmd(name = "SETINDEXQ", stack, args(op = 0x7))]
+    fn interpret_tuple_op(stack: &mut Stack, op: u32) -> Result<()> {
+        let n = stack.pop_smallint_range(0, 15)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, 0x6f00 | op << 4 | n, 16)?;
+        stack.push_raw(builder)
+    }
+
+    // === Constants ===
+
+    #[cmd(name = "PUSHINT", stack)]
+    #[cmd(name = "INT", stack)]
+    fn interpret_push_int(stack: &mut Stack) -> Result<()> {
+        let int = stack.pop_int()?;
+        let mut builder = stack.pop_builder()?;
+        store_push_int(&mut builder, &int)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "PUSHPOW2", stack, args(opcode = 0x83))]
+    #[cmd(name = "PUSHPOW2DEC", stack, args(opcode = 0x84))]
+    #[cmd(name = "PUSHNEGPOW2", stack, args(opcode = 0x85))]
+    #[cmd(name = "FITS", stack, args(opcode = 0xb4))]
+    #[cmd(name = "UFITS", stack, args(opcode = 0xb5))]
+    #[cmd(name = "LSHIFT#", stack, args(opcode = 0xaa))]
+    #[cmd(name = "RSHIFT#", stack, args(opcode = 0xab))]
+    #[cmd(name = "STI", stack, args(opcode = 0xca))]
+    #[cmd(name = "STU", stack, args(opcode = 0xcb))]
+    #[cmd(name = "LDI", stack, args(opcode = 0xd2))]
+    #[cmd(name = "LDU", stack, args(opcode = 0xd3))]
+    #[cmd(name = "LDSLICE", stack, args(opcode = 0xd6))]
+    #[cmd(name = "MODPOW2#", stack, args(opcode = 0xa938))]
+    #[cmd(name = "PLDI", stack, args(opcode = 0xd70a))]
+    #[cmd(name = "PLDU", stack, args(opcode = 0xd70b))]
+    #[cmd(name = "PLDSLICE", stack, args(opcode = 0xd71d))]
+    fn interpret_bits_op(stack: &mut Stack, opcode: u32) -> Result<()> {
+        let n = stack.pop_smallint_range(1, 256)?;
+        let mut builder = stack.pop_builder()?;
+        let bits = if opcode > 0xff { 24 } else { 16 };
+        store_opcode(&mut builder, opcode << 8 | (n - 1), bits)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "ADDCONST", stack, args(opcode = 0xa6))]
+    #[cmd(name = "ADDINT", stack, args(opcode = 0xa6))]
+    #[cmd(name = "MULCONST", stack, args(opcode = 0xa7))]
+    #[cmd(name = "MULINT", stack, args(opcode = 0xa7))]
+    #[cmd(name = "EQINT", stack, args(opcode = 0xc0))]
+    #[cmd(name = "LESSINT", stack, args(opcode = 0xc1))]
+    #[cmd(name = "GTINT", stack, args(opcode = 0xc2))]
+    #[cmd(name = "NEQINT", stack, args(opcode = 0xc3))]
+    fn interpret_tinyint_op(stack: &mut Stack, opcode: u32) -> Result<()> {
+        let x = pop_signed_range(stack, -128, 127)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, opcode << 8 | (x as u8 as u32), 16)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "PUSHSLICE", stack)]
+    #[cmd(name = "SLICE", stack)]
+    fn interpret_push_slice(stack: &mut Stack) -> Result<()> {
+        let slice = stack.pop_slice()?;
+        let mut builder = stack.pop_builder()?;
+        store_push_slice(&mut builder, &slice.apply()?)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "PUSHREF", stack, args(opcode = 0x88))]
+    #[cmd(name = "PUSHREFSLICE", stack, args(opcode = 0x89))]
+    #[cmd(name = "PUSHREFCONT", stack, args(opcode = 0x8a))]
+    fn interpret_push_ref(stack: &mut Stack, opcode: u32) -> Result<()> {
+        let cell = pop_cell_or_builder(stack)?;
+        let mut builder = stack.pop_builder()?;
+        store_op_with_ref(&mut builder, opcode, 8, cell)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "PUSHCONT", stack)]
+    fn interpret_push_cont(stack: &mut Stack) -> Result<()> {
+        let code = pop_cell_or_builder(stack)?;
+        let mut builder = stack.pop_builder()?;
+        store_push_cont(&mut builder, &DynCell::as_slice(code.as_ref())?)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "STSLICECONST", stack)]
+    fn interpret_store_slice_const(stack: &mut Stack) -> Result<()> {
+        let slice = stack.pop_slice()?;
+        let slice = slice.apply()?;
+        let (bits, refs) = (slice.remaining_bits(), slice.remaining_refs());
+        anyhow::ensure!(
+            bits < 58 && refs <= 3,
+            "Slice is too big for STSLICECONST, use PUSHSLICE STSLICER instead"
+        );
+        let len = bits.saturating_sub(1).saturating_add(7) / 8;
+        let opcode = 0xcf << 6 | 1 << 5 | (refs as u32) << 3 | len as u32;
+        let mut builder = stack.pop_builder()?;
+        store_op_with_data(&mut builder, opcode, 14, &slice, 8 * len + 2)?;
+        stack.push_raw(builder)
+    }
+
+    // === Control flow ===
+
+    #[cmd(name = "CALLDICT", args(mode = 0))]
+    #[cmd(name = "JMPDICT", args(mode = 1))]
+    #[cmd(name = "PREPAREDICT", args(mode = 2))]
+    fn interpret_call_dict(ctx: &mut Context, mode: u32) -> Result<()> {
+        let n = pop_signed_range(&mut ctx.stack, -(1 << 13), (1 << 14) - 1)?;
+
+        // Procedures defined with `PROCREF:<{` are called by reference
+        if mode == 0 {
+            if let Some(code) = get_asm_program(ctx).and_then(|p| p.find_ref(n)) {
+                let mut builder = ctx.stack.pop_builder()?;
+                store_op_with_ref(&mut builder, 0xdb3c, 16, code)?;
+                return ctx.stack.push_raw(builder);
+            }
+        }
+
+        anyhow::ensure!(n >= 0, "Invalid procedure id {n}");
+        let n = n as u32;
+        let mut builder = ctx.stack.pop_builder()?;
+        if mode == 0 && n < 256 {
+            store_opcode(&mut builder, 0xf000 | n, 16)?;
+        } else {
+            store_opcode(&mut builder, 0xf10000 | mode << 14 | n, 24)?;
+        }
+        ctx.stack.push_raw(builder)
+    }
+
+    #[cmd(name = "INLINECALLDICT")]
+    fn interpret_inline_call_dict(ctx: &mut Context) -> Result<()> {
+        let n = ctx.stack.pop_i32_checked()?;
+        let code = asm_program(ctx)?.find_body(n)?;
+        let code = DynCell::as_slice(code.as_ref())?;
+        let mut builder = ctx.stack.pop_builder()?;
+        anyhow::ensure!(
+            builder.has_capacity(code.remaining_bits(), code.remaining_refs()),
+            "Code block is too long to fit into a cell"
+        );
+        builder.store_slice(code)?;
+        ctx.stack.push_raw(builder)
+    }
+
+    #[cmd(name = "THROW", stack, args(op = 0))]
+    #[cmd(name = "THROWARG", stack, args(op = 1))]
+    #[cmd(name = "THROWIF", stack, args(op = 2))]
+    #[cmd(name = "THROWARGIF", stack, args(op = 3))]
+    #[cmd(name = "THROWIFNOT", stack, args(op = 4))]
+    #[cmd(name = "THROWARGIFNOT", stack, args(op = 5))]
+    fn interpret_throw(stack: &mut Stack, op: u32) -> Result<()> {
+        let n = stack.pop_smallint_range(0, 2047)?;
+        let mut builder = stack.pop_builder()?;
+        if op % 2 == 0 && n < 64 {
+            store_opcode(&mut builder, 0xf200 | (op / 2) << 6 | n, 16)?;
+        } else {
+            store_opcode(&mut builder, 0xf2c000 | op << 11 | n, 24)?;
+        }
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "GETGLOB", stack, args(opcode = 0xf840))]
+    #[cmd(name = "SETGLOB", stack, args(opcode = 0xf860))]
+    fn interpret_glob_op(stack: &mut Stack, opcode: u32) -> Result<()> {
+        let k = stack.pop_smallint_range(1, 31)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, opcode | k, 16)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "GETPARAM", stack)]
+    fn interpret_get_param(stack: &mut Stack) -> Result<()> {
+        let n = stack.pop_smallint_range(0, 15)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, 0xf820 | n, 16)?;
+        stack.push_raw(builder)
+    }
+
+    #[cmd(name = "SETCP", stack)]
+    fn interpret_setcp(stack: &mut Stack) -> Result<()> {
+        let n = pop_signed_range(stack, -15, 239)?;
+        let mut builder = stack.pop_builder()?;
+        store_opcode(&mut builder, 0xff00 | (n as u8 as u32), 16)?;
+        stack.push_raw(builder)
+    }
+
+    // === Dictionaries ===
+
+    #[cmd(name = "DICTPUSHCONST", stack)]
+    fn interpret_dict_push_const(stack: &mut Stack) -> Result<()> {
+        let key_bits = stack.pop_smallint_range(0, 1023)?;
+        let dict = stack.pop_cell()?;
+        let mut builder = stack.pop_builder()?;
+        store_op_with_ref(&mut builder, 0xf4a400 | key_bits, 24, *dict)?;
+        stack.push_raw(builder)
+    }
+
+    // === Programs ===
+
+    #[cmd(name = "PROGRAM{")]
+    fn interpret_program_begin(ctx: &mut Context) -> Result<()> {
+        asm_state(ctx).program = Some(AsmProgram::default());
+        Ok(())
+    }
+
+    #[cmd(name = "DECLPROC", args(method = false))]
+    #[cmd(name = "DECLMETHOD", args(method = true))]
+    fn interpret_decl_proc(ctx: &mut Context, method: bool) -> Result<()> {
+        let name = ctx
+            .input
+            .scan_word()?
+            .ok_or_else(UnexpectedEof::default)?
+            .data
+            .to_owned();
+        let id = if method {
+            pop_signed_range(&mut ctx.stack, -(1 << 18), (1 << 18) - 1)?
+        } else {
+            match name.as_str() {
+                "main" | "recv_internal" => 0,
+                "recv_external" => -1,
+                "run_ticktock" => -2,
+                "split_prepare" => -3,
+                "split_install" => -4,
+                _ => asm_program(ctx)?.next_id(),
+            }
+        };
+        asm_program(ctx)?.declare(&name, id)?;
+        define_constant(ctx, name, id)
+    }
+
+    #[cmd(name = "DECLGLOBVAR")]
+    fn interpret_decl_glob_var(ctx: &mut Context) -> Result<()> {
+        let name = ctx
+            .input
+            .scan_word()?
+            .ok_or_else(UnexpectedEof::default)?
+            .data
+            .to_owned();
+        let program = asm_program(ctx)?;
+        anyhow::ensure!(program.globals < 31, "Too many global variables");
+        program.globals += 1;
+        let id = program.globals as i32;
+        define_constant(ctx, name, id)
+    }
+
+    #[cmd(name = "PROC:<{", args(kind = ProcKind::Dict))]
+    #[cmd(name = "PROCINLINE:<{", args(kind = ProcKind::Inline))]
+    #[cmd(name = "PROCREF:<{", args(kind = ProcKind::Ref))]
+    #[cmd(name = "METHOD:<{", args(kind = ProcKind::Dict))]
+    fn interpret_proc_begin(ctx: &mut Context, kind: ProcKind) -> Result<()> {
+        let id = ctx.stack.pop_i32_checked()?;
+        asm_program(ctx)?.set_kind(id, kind)?;
+        asm_state(ctx).blocks.push(BlockEnd::Proc(id));
+        ctx.stack.push(CellBuilder::new())
+    }
+
+    #[cmd(name = "}END>c", args(as_slice = false))]
+    #[cmd(name = "}END>s", args(as_slice = true))]
+    fn interpret_program_end(ctx: &mut Context, as_slice: bool) -> Result<()> {
+        let program = asm_state(ctx)
+            .program
+            .take()
+            .context("`}END>c` without `PROGRAM{`")?;
+
+        let mut dict = None;
+        for proc in &program.procs {
+            let Some(code) = &proc.code else {
+                anyhow::bail!("Procedure `{}` is declared but not defined", proc.name);
+            };
+            if proc.kind != ProcKind::Dict {
+                continue;
+            }
+
+            let mut key = CellBuilder::new();
+            store_int_to_builder(&mut key, &mut proc.id.into(), PROC_KEY_BITS, true)?;
+            let key = key.build()?;
+            dict = dict_insert(
+                &dict,
+                &mut DynCell::as_slice(key.as_ref())?,
+                PROC_KEY_BITS,
+                &DynCell::as_slice(code.as_ref())?,
+                SetMode::Add,
+                &mut Cell::default_finalizer(),
+            )
+            .with_context(|| format!("Failed to add procedure `{}`", proc.name))?;
+        }
+        let dict = dict.context("Program has no procedures")?;
+
+        let mut builder = CellBuilder::new();
+        store_opcode(&mut builder, 0xff00, 16)?; // SETCP0
+        store_op_with_ref(&mut builder, 0xf4a400 | PROC_KEY_BITS as u32, 24, dict)?; // 19 DICTPUSHCONST
+        store_opcode(&mut builder, 0xf4bc, 16)?; // DICTIGETJMPZ
+        store_opcode(&mut builder, 0xf2c80b, 24)?; // 11 THROWARG
+
+        let cell = builder.build()?;
+        if as_slice {
+            ctx.stack.push(OwnedCellSlice::new(cell))
+        } else {
+            ctx.stack.push(cell)
+        }
+    }
+}
+
+const CONTROL_REGISTER: i32 = 0x100;
+const PROC_KEY_BITS: u16 = 19;
+
+#[derive(Clone, Copy)]
+enum BlockEnd {
+    /// Leaves the code builder on the stack.
+    Plain,
+    /// Pushes the code as a continuation followed by an optional opcode.
+    Then(Option<u32>),
+    Else {
+        inverted: bool,
+    },
+    While,
+    WhileBody,
+    Proc(i32),
+}
+
+#[derive(Default)]
+struct AsmState {
+    blocks: Vec<BlockEnd>,
+    program: Option<AsmProgram>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ProcKind {
+    Dict,
+    Inline,
+    Ref,
+}
+
+struct AsmProc {
+    name: String,
+    id: i32,
+    kind: ProcKind,
+    code: Option<Cell>,
+}
+
+#[derive(Default)]
+struct AsmProgram {
+    procs: Vec<AsmProc>,
+    last_id: i32,
+    globals: u32,
+}
+
+impl AsmProgram {
+    fn next_id(&mut self) -> i32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn declare(&mut self, name: &str, id: i32) -> Result<()> {
+        anyhow::ensure!(
+            self.procs.iter().all(|proc| proc.id != id),
+            "Procedure `{name}` has a duplicate id {id}"
+        );
+        self.procs.push(AsmProc {
+            name: name.to_owned(),
+            id,
+            kind: ProcKind::Dict,
+            code: None,
+        });
+        Ok(())
+    }
+
+    fn get_mut(&mut self, id: i32) -> Result<&mut AsmProc> {
+        self.procs
+            .iter_mut()
+            .find(|proc| proc.id == id)
+            .with_context(|| format!("Procedure with id {id} is not declared"))
+    }
+
+    fn set_kind(&mut self, id: i32, kind: ProcKind) -> Result<()> {
+        let proc = self.get_mut(id)?;
+        anyhow::ensure!(
+            proc.code.is_none(),
+            "Procedure `{}` is already defined",
+            proc.name
+        );
+        proc.kind = kind;
+        Ok(())
+    }
+
+    fn define(&mut self, id: i32, code: Cell) -> Result<()> {
+        self.get_mut(id)?.code = Some(code);
+        Ok(())
+    }
+
+    fn find_ref(&self, id: i32) -> Option<Cell> {
+        let proc = self.procs.iter().find(|proc| proc.id == id)?;
+        match proc.kind {
+            ProcKind::Ref => proc.code.clone(),
+            _ => None,
+        }
+    }
+
+    fn find_body(&mut self, id: i32) -> Result<Cell> {
+        let proc = self.get_mut(id)?;
+        proc.code
+            .clone()
+            .with_context(|| format!("Procedure `{}` is not defined yet", proc.name))
+    }
+}
+
+fn asm_state<'a>(ctx: &'a mut Context) -> &'a mut AsmState {
+    ctx.user_data_or_default()
+}
+
+fn get_asm_program<'a>(ctx: &'a Context) -> Option<&'a AsmProgram> {
+    ctx.get_user_data::<AsmState>()?.program.as_ref()
+}
+
+fn asm_program<'a>(ctx: &'a mut Context) -> Result<&'a mut AsmProgram> {
+    asm_state(ctx)
+        .program
+        .as_mut()
+        .context("`PROGRAM{` expected")
+}
+
+fn define_constant(ctx: &mut Context, name: String, value: i32) -> Result<()> {
+    ctx.dictionary.define_word(
+        format!("{name} "),
+        DictionaryEntry::new_ordinary(Rc::new(cont::IntLitCont::from(value))),
+        true,
+    )
+}
+
+enum Register {
+    Stack(u32),
+    Control(u32),
+}
+
+fn pop_register(stack: &mut Stack) -> Result<Register> {
+    let i = pop_signed_range(stack, 0, CONTROL_REGISTER + 15)? as u32;
+    Ok(if i >= CONTROL_REGISTER as u32 {
+        Register::Control(i - CONTROL_REGISTER as u32)
+    } else {
+        anyhow::ensure!(i < 256, "Invalid stack register s{i}");
+        Register::Stack(i)
+    })
+}
+
+fn pop_stack_register(stack: &mut Stack) -> Result<u32> {
+    stack.pop_smallint_range(0, 255)
+}
+
+fn pop_nibble(stack: &mut Stack) -> Result<u32> {
+    stack.pop_smallint_range(0, 15)
+}
+
+fn pop_cell_or_builder(stack: &mut Stack) -> Result<Cell> {
+    let item = stack.pop()?;
+    Ok(if item.ty() == StackValueType::Builder {
+        item.into_builder()?.build()?
+    } else {
+        *item.into_cell()?
+    })
+}
+
+fn pop_signed_range(stack: &mut Stack, min: i32, max: i32) -> Result<i32> {
+    let value = stack.pop_i32_checked()?;
+    anyhow::ensure!(
+        (min..=max).contains(&value),
+        "Expected integer in range {min}..={max}, got {value}"
+    );
+    Ok(value)
+}
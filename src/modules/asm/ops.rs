@@ -0,0 +1,320 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use everscale_types::prelude::*;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::core::*;
+use crate::disasm::*;
+use crate::util::{fits_into_bits, store_int_to_builder};
+
+/// Instruction without arguments: ( b -- b' ).
+pub struct FixedOpCont {
+    name: &'static str,
+    opcode: u32,
+    bits: u16,
+}
+
+impl ContImpl for FixedOpCont {
+    fn run(self: Rc<Self>, ctx: &mut Context) -> Result<Option<Cont>> {
+        let mut builder = ctx.stack.pop_builder()?;
+        store_opcode(&mut builder, self.opcode, self.bits)?;
+        ctx.stack.push_raw(builder)?;
+        Ok(None)
+    }
+
+    fn fmt_name(&self, _: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+/// Defines all instructions without arguments.
+///
+/// Tables are shared with the disassembler, the shortest encoding
+/// is used when an instruction has several of them.
+pub fn define_fixed_ops(d: &mut Dictionary) -> Result<()> {
+    let mut define = |name: &'static str, opcode: u32, bits: u16| {
+        let word = format!("{name} ");
+        if d.lookup(&word).is_some() {
+            return Ok(());
+        }
+        d.define_word(
+            word,
+            DictionaryEntry::new_ordinary(Rc::new(FixedOpCont { name, opcode, bits })),
+            false,
+        )
+    };
+
+    for (name, opcode, bits) in FIXED_OPS {
+        define(name, *opcode, *bits)?;
+    }
+
+    let arrays: [(&[&'static str], u32, u16, u32); 7] = [
+        (&STACK_X_OPS, 0x6, 4, 0),
+        (&NULLSWAP_OPS, 0x6fa, 4, 0),
+        (&STORE_X_OPS, 0xcf, 8, 0),
+        (&LOAD_X_OPS, 0xd7, 8, 0),
+        (&LOOP_OPS, 0xe, 4, 4),
+        (&CONT_COMPOSE_OPS, 0xedf, 4, 0),
+        (&THROW_ANY_OPS, 0xf2, 8, 0xf0),
+    ];
+    for (names, prefix, key_bits, offset) in arrays {
+        for (i, name) in names.iter().enumerate() {
+            let opcode = prefix << key_bits | (offset + i as u32);
+            define(name, opcode, prefix_bits(prefix) + key_bits)?;
+        }
+    }
+
+    let tables: [(OpsTable, u32, u16); 14] = [
+        (TUPLE_VAR_OPS, 0x6f8, 4),
+        (INT_MISC_OPS, 0xb6, 8),
+        (SLICE_CMP_OPS, 0xc7, 8),
+        (STORE_MISC_OPS, 0xcf, 8),
+        (LOAD_MISC_OPS, 0xd7, 8),
+        (CALL_MISC_OPS, 0xdb3, 4),
+        (COND_MISC_OPS, 0xe3, 8),
+        (CONT_ARGS_OPS, 0xed1, 4),
+        (DICT_OPS, 0xf4, 8),
+        (PARAM_OPS, 0xf82, 4),
+        (APP_MISC_OPS, 0xf8, 8),
+        (APP_CRYPTO_OPS, 0xf9, 8),
+        (APP_CURRENCY_OPS, 0xfa, 8),
+        (APP_ACTION_OPS, 0xfb, 8),
+    ];
+    for (table, prefix, key_bits) in tables {
+        for (key, name) in table {
+            let opcode = prefix << key_bits | *key as u32;
+            define(name, opcode, prefix_bits(prefix) + key_bits)?;
+        }
+    }
+
+    Ok(())
+}
+
+type OpsTable = &'static [(u8, &'static str)];
+
+fn prefix_bits(prefix: u32) -> u16 {
+    // All prefixes start with a non-zero nibble
+    (32 - prefix.leading_zeros() as u16 + 3) / 4 * 4
+}
+
+pub fn store_opcode(builder: &mut CellBuilder, opcode: u32, bits: u16) -> Result<()> {
+    anyhow::ensure!(
+        builder.has_capacity(bits, 0),
+        "Code block is too long to fit into a cell"
+    );
+    builder.store_uint(opcode as u64, bits)?;
+    Ok(())
+}
+
+/// Appends an instruction with inline data and references.
+pub fn store_op_with_data(
+    builder: &mut CellBuilder,
+    opcode: u32,
+    bits: u16,
+    data: &CellSlice<'_>,
+    padded_bits: u16,
+) -> Result<()> {
+    let refs = data.remaining_refs();
+    anyhow::ensure!(
+        builder.has_capacity(bits + padded_bits, refs),
+        "Code block is too long to fit into a cell"
+    );
+    builder.store_uint(opcode as u64, bits)?;
+    builder.store_slice(data)?;
+    // Completion tag
+    let tag_bits = padded_bits - data.remaining_bits();
+    if tag_bits > 0 {
+        builder.store_bit_one()?;
+        builder.store_zeros(tag_bits - 1)?;
+    }
+    Ok(())
+}
+
+pub fn store_push_int(builder: &mut CellBuilder, int: &BigInt) -> Result<()> {
+    if let Some(value) = int.to_i64() {
+        match value {
+            -5..=10 => return store_opcode(builder, 0x70 | (value as u32 & 0xf), 8),
+            -128..=127 => return store_opcode(builder, 0x8000 | (value as u8 as u32), 16),
+            -32768..=32767 => return store_opcode(builder, 0x810000 | (value as u16 as u32), 24),
+            _ => {}
+        }
+    }
+
+    anyhow::ensure!(
+        fits_into_bits(int, 257, true),
+        "Integer does not fit into 257 bits"
+    );
+    let bits = int.bits() as u16 + 1;
+    let len = bits.saturating_sub(19).saturating_add(7) / 8;
+    let value_bits = 19 + 8 * len;
+    anyhow::ensure!(
+        builder.has_capacity(13 + value_bits, 0),
+        "Code block is too long to fit into a cell"
+    );
+    builder.store_uint(0x82 << 5 | len as u64, 13)?;
+    store_int_to_builder(builder, &mut int.clone(), value_bits, true)
+}
+
+pub fn store_push_slice(builder: &mut CellBuilder, data: &CellSlice<'_>) -> Result<()> {
+    let bits = data.remaining_bits();
+    let refs = data.remaining_refs();
+
+    // Lengths include the completion tag
+    if refs == 0 && bits < 124 {
+        let len = (bits + 1).saturating_sub(4).saturating_add(7) / 8;
+        return store_op_with_data(builder, 0x8b0 | len as u32, 12, data, 8 * len + 4);
+    }
+    if (1..=4).contains(&refs) && bits < 249 {
+        let len = bits.saturating_add(7) / 8;
+        let opcode = 0x8c << 7 | (refs as u32 - 1) << 5 | len as u32;
+        return store_op_with_data(builder, opcode, 15, data, 8 * len + 1);
+    }
+    if refs <= 4 && bits < 1022 {
+        let len = (bits + 1).saturating_sub(6).saturating_add(7) / 8;
+        let opcode = 0x8d << 10 | (refs as u32) << 7 | len as u32;
+        return store_op_with_data(builder, opcode, 18, data, 8 * len + 6);
+    }
+
+    let mut cell = CellBuilder::new();
+    cell.store_slice(data)?;
+    store_op_with_ref(builder, 0x89, 8, cell.build()?)
+}
+
+pub fn store_push_cont(builder: &mut CellBuilder, code: &CellSlice<'_>) -> Result<()> {
+    let bits = code.remaining_bits();
+    let refs = code.remaining_refs();
+
+    if bits % 8 == 0 {
+        let len = bits / 8;
+        if refs == 0 && len < 16 && builder.has_capacity(8 + bits, 0) {
+            return store_op_with_data(builder, 0x90 | len as u32, 8, code, bits);
+        }
+        if refs <= 3 && len < 128 && builder.has_capacity(16 + bits, refs) {
+            let opcode = 0b1000111 << 9 | (refs as u32) << 7 | len as u32;
+            return store_op_with_data(builder, opcode, 16, code, bits);
+        }
+    }
+
+    let mut cell = CellBuilder::new();
+    cell.store_slice(code)?;
+    store_op_with_ref(builder, 0x8a, 8, cell.build()?)
+}
+
+pub fn store_op_with_ref(
+    builder: &mut CellBuilder,
+    opcode: u32,
+    bits: u16,
+    cell: Cell,
+) -> Result<()> {
+    anyhow::ensure!(
+        builder.has_capacity(bits, 1),
+        "Code block is too long to fit into a cell"
+    );
+    builder.store_uint(opcode as u64, bits)?;
+    builder.store_reference(cell)?;
+    Ok(())
+}
+
+const FIXED_OPS: &[(&str, u32, u16)] = &[
+    // Stack manipulation
+    ("NOP", 0x00, 8),
+    ("SWAP", 0x01, 8),
+    ("DUP", 0x20, 8),
+    ("OVER", 0x21, 8),
+    ("DROP", 0x30, 8),
+    ("NIP", 0x31, 8),
+    ("ROT", 0x58, 8),
+    ("ROTREV", 0x59, 8),
+    ("-ROT", 0x59, 8),
+    ("SWAP2", 0x5a, 8),
+    ("2SWAP", 0x5a, 8),
+    ("DROP2", 0x5b, 8),
+    ("2DROP", 0x5b, 8),
+    ("DUP2", 0x5c, 8),
+    ("2DUP", 0x5c, 8),
+    ("OVER2", 0x5d, 8),
+    ("2OVER", 0x5d, 8),
+    ("PUSHNULL", 0x6d, 8),
+    ("NULL", 0x6d, 8),
+    ("ISNULL", 0x6e, 8),
+    // Tuples
+    ("NIL", 0x6f00, 16),
+    ("SINGLE", 0x6f01, 16),
+    ("PAIR", 0x6f02, 16),
+    ("TRIPLE", 0x6f03, 16),
+    ("FIRST", 0x6f10, 16),
+    ("SECOND", 0x6f11, 16),
+    ("THIRD", 0x6f12, 16),
+    ("UNSINGLE", 0x6f21, 16),
+    ("UNPAIR", 0x6f22, 16),
+    ("UNTRIPLE", 0x6f23, 16),
+    // Constants
+    ("ZERO", 0x70, 8),
+    ("FALSE", 0x70, 8),
+    ("ONE", 0x71, 8),
+    ("TWO", 0x72, 8),
+    ("TEN", 0x7a, 8),
+    ("TRUE", 0x7f, 8),
+    ("PUSHNAN", 0x83ff, 16),
+    // Arithmetic
+    ("ADD", 0xa0, 8),
+    ("SUB", 0xa1, 8),
+    ("SUBR", 0xa2, 8),
+    ("NEGATE", 0xa3, 8),
+    ("INC", 0xa4, 8),
+    ("DEC", 0xa5, 8),
+    ("MUL", 0xa8, 8),
+    ("DIV", 0xa904, 16),
+    ("DIVR", 0xa905, 16),
+    ("DIVC", 0xa906, 16),
+    ("MOD", 0xa908, 16),
+    ("DIVMOD", 0xa90c, 16),
+    ("MULDIV", 0xa984, 16),
+    ("MULDIVR", 0xa985, 16),
+    ("MULDIVMOD", 0xa98c, 16),
+    ("LSHIFT", 0xac, 8),
+    ("RSHIFT", 0xad, 8),
+    ("POW2", 0xae, 8),
+    ("AND", 0xb0, 8),
+    ("OR", 0xb1, 8),
+    ("XOR", 0xb2, 8),
+    ("NOT", 0xb3, 8),
+    // Comparison
+    ("SGN", 0xb8, 8),
+    ("LESS", 0xb9, 8),
+    ("EQUAL", 0xba, 8),
+    ("LEQ", 0xbb, 8),
+    ("GREATER", 0xbc, 8),
+    ("NEQ", 0xbd, 8),
+    ("GEQ", 0xbe, 8),
+    ("CMP", 0xbf, 8),
+    ("ISNAN", 0xc4, 8),
+    ("CHKNAN", 0xc5, 8),
+    // Cells
+    ("NEWC", 0xc8, 8),
+    ("ENDC", 0xc9, 8),
+    ("STREF", 0xcc, 8),
+    ("STBREFR", 0xcd, 8),
+    ("STSLICE", 0xce, 8),
+    ("CTOS", 0xd0, 8),
+    ("ENDS", 0xd1, 8),
+    ("LDREF", 0xd4, 8),
+    ("LDREFRTOS", 0xd5, 8),
+    // Control flow
+    ("EXECUTE", 0xd8, 8),
+    ("CALLX", 0xd8, 8),
+    ("JMPX", 0xd9, 8),
+    ("IFRET", 0xdc, 8),
+    ("IFNOTRET", 0xdd, 8),
+    ("IF", 0xde, 8),
+    ("IFNOT", 0xdf, 8),
+    ("IFJMP", 0xe0, 8),
+    ("IFNOTJMP", 0xe1, 8),
+    ("IFELSE", 0xe2, 8),
+    ("TRY", 0xf2ff, 16),
+    // Debug and codepage
+    ("DUMPSTK", 0xfe00, 16),
+    ("SETCP0", 0xff00, 16),
+];
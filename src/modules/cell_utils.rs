@@ -1,10 +1,11 @@
 use std::collections::HashSet;
 
 use anyhow::{Context as _, Result};
-use everscale_types::cell::{MAX_BIT_LEN, MAX_REF_COUNT};
+use everscale_types::boc;
+use everscale_types::cell::{CellType, DefaultFinalizer, MAX_BIT_LEN, MAX_REF_COUNT};
+use everscale_types::merkle::{make_pruned_branch, MerkleProof};
 use everscale_types::prelude::*;
 use num_bigint::{BigInt, Sign};
-use num_traits::Zero;
 use sha2::Digest;
 
 use crate::core::*;
@@ -163,6 +164,14 @@ impl CellUtils {
         Ok(())
     }
 
+    #[cmd(name = "ceq?", stack)]
+    #[cmd(name = "hash-eq?", stack)]
+    fn interpret_cell_eq(stack: &mut Stack) -> Result<()> {
+        let y = stack.pop_cell()?;
+        let x = stack.pop_cell()?;
+        stack.push_bool(x.repr_hash() == y.repr_hash())
+    }
+
     #[cmd(name = "hash", stack, args(as_uint = true))]
     #[cmd(name = "hashu", stack, args(as_uint = true))]
     #[cmd(name = "hashB", stack, args(as_uint = false))]
@@ -197,30 +206,7 @@ impl CellUtils {
         let mut raw_cs = stack.pop_slice()?;
         let mut cs = raw_cs.apply()?;
 
-        let int = match bits {
-            0 => Ok(BigInt::zero()),
-            0..=64 if !sgn => cs.load_uint(bits).map(BigInt::from),
-            0..=64 if sgn => cs.load_uint(bits).map(|mut int| {
-                if bits < 64 {
-                    // Clone sign bit into all high bits
-                    int |= ((int >> (bits - 1)) * u64::MAX) << (bits - 1);
-                }
-                BigInt::from(int as i64)
-            }),
-            _ => {
-                let align = 8 - bits % 8;
-                let mut buffer = [0u8; 33];
-                cs.load_raw(&mut buffer, bits).map(|buffer| {
-                    let mut int = if sgn {
-                        BigInt::from_signed_bytes_be(buffer)
-                    } else {
-                        BigInt::from_bytes_be(Sign::Plus, buffer)
-                    };
-                    int >>= align;
-                    int
-                })
-            }
-        };
+        let int = load_int_from_slice(&mut cs, bits, sgn);
         let is_ok = int.is_ok();
 
         match int {
@@ -361,20 +347,73 @@ impl CellUtils {
         stack.push_int(refs)
     }
 
+    // ( c -- S ), Graphviz DOT text of the cell tree
+    #[cmd(name = "cell>dot", stack)]
+    fn interpret_cell_to_dot(stack: &mut Stack) -> Result<()> {
+        let cell = stack.pop_cell()?;
+        let dot = display_cell_dot(cell.as_ref().as_ref()).to_string();
+        stack.push(dot)
+    }
+
+    // === Snake and chunked data ===
+
+    #[cmd(name = "$>snake-cells", stack, args(s = true))]
+    #[cmd(name = "B>snake-cells", stack, args(s = false))]
+    fn interpret_to_snake_cells(stack: &mut Stack, s: bool) -> Result<()> {
+        let data = pop_string_or_bytes(stack, s)?;
+        let mut builder = CellBuilder::new();
+        store_snake_data(&mut builder, &data)?;
+        stack.push(builder.build()?)
+    }
+
+    #[cmd(name = "snake-cells>$", stack, args(s = true))]
+    #[cmd(name = "snake-cells>B", stack, args(s = false))]
+    fn interpret_from_snake_cells(stack: &mut Stack, s: bool) -> Result<()> {
+        let cell = OwnedCellSlice::new(*stack.pop_cell()?);
+        let data = load_snake_data(cell.apply()?)?;
+        push_string_or_bytes(stack, data, s)
+    }
+
+    #[cmd(name = "$>chunked-cells", stack, args(s = true))]
+    #[cmd(name = "B>chunked-cells", stack, args(s = false))]
+    fn interpret_to_chunked_cells(stack: &mut Stack, s: bool) -> Result<()> {
+        let data = pop_string_or_bytes(stack, s)?;
+        let dict = build_chunked_data(&data)?;
+        stack.push(CellBuilder::build_from(dict)?)
+    }
+
+    #[cmd(name = "chunked-cells>$", stack, args(s = true))]
+    #[cmd(name = "chunked-cells>B", stack, args(s = false))]
+    fn interpret_from_chunked_cells(stack: &mut Stack, s: bool) -> Result<()> {
+        let cell = OwnedCellSlice::new(*stack.pop_cell()?);
+        let mut cs = cell.apply()?;
+        let dict = Option::<Cell>::load_from(&mut cs)?;
+        let data = load_chunked_data(dict)?;
+        push_string_or_bytes(stack, data, s)
+    }
+
     // === BOC manipulation ===
 
     #[cmd(name = "B>boc", stack)]
     fn interpret_boc_deserialize(stack: &mut Stack) -> Result<()> {
         let bytes = stack.pop_bytes()?;
-        let cell = Boc::decode(*bytes)?;
-        stack.push(cell)
+        stack.push(decode_boc(&bytes)?)
     }
 
     #[cmd(name = "base64>boc", stack)]
     fn interpret_boc_deserialize_base64(stack: &mut Stack) -> Result<()> {
-        let bytes = stack.pop_string()?;
-        let cell = Boc::decode_base64(*bytes)?;
-        stack.push(cell)
+        let bytes = decode_base64(*stack.pop_string()?)?;
+        stack.push(decode_boc(&bytes)?)
+    }
+
+    #[cmd(name = "B>boc+", stack)]
+    fn interpret_boc_deserialize_ext(stack: &mut Stack) -> Result<()> {
+        let bytes = stack.pop_bytes()?;
+        let roots = decode_boc_roots(&bytes, &boc::de::Options::default())?
+            .into_iter()
+            .map(|root| Box::new(root) as Box<dyn StackValue>)
+            .collect::<StackTuple>();
+        stack.push(roots)
     }
 
     #[cmd(name = "boc>B", stack, args(ext = false, base64 = false))]
@@ -382,28 +421,26 @@ impl CellUtils {
     #[cmd(name = "boc+>B", stack, args(ext = true, base64 = false))]
     #[cmd(name = "boc+>base64", stack, args(ext = true, base64 = true))]
     fn interpret_boc_serialize_ext(stack: &mut Stack, ext: bool, base64: bool) -> Result<()> {
-        use everscale_types::boc::ser::BocHeader;
-
-        const MODE_WITH_CRC: u32 = 0b00010;
-        const SUPPORTED_MODES: u32 = MODE_WITH_CRC;
-
-        let mode = if ext {
-            stack.pop_smallint_range(0, 31)?
+        let options = if ext {
+            BocOptions::from_mode(stack.pop_smallint_range(0, 31)?)?
         } else {
-            0
+            BocOptions::default()
         };
 
-        anyhow::ensure!(
-            mode & !SUPPORTED_MODES == 0,
-            "Unsupported BOC serialization mode 0x{mode:x}"
-        );
-
-        let cell = stack.pop_cell()?;
+        // Extended serialization also accepts a tuple of roots
+        let item = stack.pop()?;
+        let roots = if ext && item.ty() == StackValueType::Tuple {
+            let tuple = item.into_tuple()?;
+            anyhow::ensure!(!tuple.is_empty(), "Expected at least one root cell");
+            tuple
+                .into_iter()
+                .map(|item| item.into_cell().map(|cell| *cell))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            vec![*item.into_cell()?]
+        };
 
-        let mut result = Vec::new();
-        BocHeader::<ahash::RandomState>::new(&**cell)
-            .with_crc(mode & MODE_WITH_CRC != 0)
-            .encode(&mut result);
+        let result = options.encode(&roots);
 
         if base64 {
             stack.push(encode_base64(result))
@@ -412,11 +449,75 @@ impl CellUtils {
         }
     }
 
+    // === Merkle proofs ===
+
+    #[cmd(name = "cell-type", stack)]
+    fn interpret_cell_type(stack: &mut Stack) -> Result<()> {
+        let cell = stack.pop_cell()?;
+        match cell.cell_type() {
+            CellType::Ordinary => stack.push_int(-1),
+            ty => stack.push_int(ty.to_byte()),
+        }
+    }
+
+    #[cmd(name = "cell-level", stack)]
+    fn interpret_cell_level(stack: &mut Stack) -> Result<()> {
+        let cell = stack.pop_cell()?;
+        stack.push_int(cell.level())
+    }
+
+    #[cmd(name = "prune", stack)]
+    fn interpret_prune(stack: &mut Stack) -> Result<()> {
+        let cell = *stack.pop_cell()?;
+        let pruned = make_pruned_branch(cell.as_ref(), 0, &mut Cell::default_finalizer())?;
+        stack.push(pruned)
+    }
+
+    #[cmd(name = "virtualize", stack)]
+    fn interpret_virtualize(stack: &mut Stack) -> Result<()> {
+        let cell = *stack.pop_cell()?;
+        stack.push(Cell::virtualize(cell))
+    }
+
+    #[cmd(name = "merkle-proof", stack)]
+    fn interpret_merkle_proof(stack: &mut Stack) -> Result<()> {
+        let hashes = stack.pop_tuple()?;
+        let cell = *stack.pop_cell()?;
+
+        let mut included = HashSet::<HashBytes, ahash::RandomState>::default();
+        for item in hashes.iter() {
+            included.insert(item.as_hash()?);
+        }
+
+        let proof = MerkleProof::create(cell.as_ref(), included).build()?;
+        stack.push(CellBuilder::build_from(proof)?)
+    }
+
+    #[cmd(name = "merkle-proof-for", stack)]
+    fn interpret_merkle_proof_for(stack: &mut Stack) -> Result<()> {
+        let hash = stack.pop_hash()?;
+        let cell = *stack.pop_cell()?;
+        let proof = MerkleProof::create_for_cell(cell.as_ref(), &hash).build()?;
+        stack.push(CellBuilder::build_from(proof)?)
+    }
+
+    #[cmd(name = "merkle-proof>", stack)]
+    fn interpret_merkle_proof_load(stack: &mut Stack) -> Result<()> {
+        let cell = *stack.pop_cell()?;
+        anyhow::ensure!(
+            cell.cell_type() == CellType::MerkleProof,
+            "Expected a Merkle proof cell"
+        );
+        let proof = cell.parse::<MerkleProof>()?;
+        stack.push(Cell::virtualize(proof.cell))?;
+        stack.push(BigInt::from_bytes_be(Sign::Plus, proof.hash.as_slice()))
+    }
+
     // === Prefix commands ===
 
     #[cmd(name = "x{", active, without_space)]
     fn interpret_bitstring_hex_literal(ctx: &mut Context) -> Result<()> {
-        let s = ctx.input.scan_until('}')?;
+        let s = ctx.input.scan_literal(LiteralKind::Bitstring)?;
         let cell = decode_hex_bitstring(s.data)?.build()?;
         ctx.stack.push(OwnedCellSlice::new(cell))?;
         ctx.stack.push_argcount(1, ctx.dictionary.make_nop())
@@ -424,7 +525,7 @@ impl CellUtils {
 
     #[cmd(name = "b{", active, without_space)]
     fn interpret_bitstring_binary_literal(ctx: &mut Context) -> Result<()> {
-        let s = ctx.input.scan_until('}')?;
+        let s = ctx.input.scan_literal(LiteralKind::Bitstring)?;
         let cell = decode_binary_bitstring(s.data)?.build()?;
         ctx.stack.push(OwnedCellSlice::new(cell))?;
         ctx.stack.push_argcount(1, ctx.dictionary.make_nop())
@@ -506,6 +607,166 @@ impl<'a> StorageStat<'a> {
     }
 }
 
+/// Flags of the BOC serialization (`mode` of `boc+>B`).
+#[derive(Default, Clone, Copy)]
+struct BocOptions {
+    /// Emit an index table with the end offset of each cell.
+    with_index: bool,
+    /// Append CRC32C of the serialized data.
+    with_crc: bool,
+}
+
+impl BocOptions {
+    const MODE_WITH_INDEX: u32 = 0b00001;
+    const MODE_WITH_CRC: u32 = 0b00010;
+    const SUPPORTED_MODES: u32 = Self::MODE_WITH_INDEX | Self::MODE_WITH_CRC;
+
+    fn from_mode(mode: u32) -> Result<Self> {
+        anyhow::ensure!(
+            mode & !Self::SUPPORTED_MODES == 0,
+            "Unsupported BOC serialization mode 0x{mode:x}"
+        );
+        Ok(Self {
+            with_index: mode & Self::MODE_WITH_INDEX != 0,
+            with_crc: mode & Self::MODE_WITH_CRC != 0,
+        })
+    }
+
+    fn encode(&self, roots: &[Cell]) -> Vec<u8> {
+        const CRC_32_C: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISCSI);
+        const BOC_GENERIC_TAG: [u8; 4] = [0xb5, 0xee, 0x9c, 0x72];
+
+        // Cells are collected in post-order, so children come before parents
+        let mut rev_indices = ahash::HashMap::<&HashBytes, u32>::default();
+        let mut rev_cells = Vec::<&DynCell>::new();
+        let mut root_rev_indices = Vec::with_capacity(roots.len());
+        for root in roots {
+            let mut stack = vec![root.as_ref().references()];
+            while let Some(children) = stack.last_mut() {
+                if let Some(child) = children.next() {
+                    if !rev_indices.contains_key(child.repr_hash()) {
+                        stack.push(child.references());
+                    }
+                } else {
+                    let cell = children.cell();
+                    stack.pop();
+                    if let std::collections::hash_map::Entry::Vacant(entry) =
+                        rev_indices.entry(cell.repr_hash())
+                    {
+                        entry.insert(rev_cells.len() as u32);
+                        rev_cells.push(cell);
+                    }
+                }
+            }
+            root_rev_indices.push(rev_indices[root.repr_hash()]);
+        }
+
+        let cell_count = rev_cells.len() as u32;
+        let ref_size = bytes_to_fit(cell_count as u64);
+
+        let mut cell_offsets = Vec::with_capacity(rev_cells.len());
+        let mut total_cells_size = 0u64;
+        for cell in rev_cells.iter().rev() {
+            let descriptor = cell.descriptor();
+            let mut cell_size = 2 + descriptor.byte_len() as u64;
+            if descriptor.store_hashes() {
+                cell_size += descriptor.hash_count() as u64 * (32 + 2);
+            }
+            cell_size += (ref_size * descriptor.reference_count() as usize) as u64;
+
+            total_cells_size += cell_size;
+            cell_offsets.push(total_cells_size);
+        }
+        let offset_size = bytes_to_fit(total_cells_size);
+
+        let flags = ref_size as u8
+            | (u8::from(self.with_index) * 0b1000_0000)
+            | (u8::from(self.with_crc) * 0b0100_0000);
+
+        let mut result = Vec::new();
+        result.extend_from_slice(&BOC_GENERIC_TAG);
+        result.extend_from_slice(&[flags, offset_size as u8]);
+        result.extend_from_slice(&cell_count.to_be_bytes()[4 - ref_size..]);
+        result.extend_from_slice(&(roots.len() as u32).to_be_bytes()[4 - ref_size..]);
+        result.extend_from_slice(&[0; 4][4 - ref_size..]);
+        result.extend_from_slice(&total_cells_size.to_be_bytes()[8 - offset_size..]);
+
+        for rev_index in root_rev_indices {
+            let index = cell_count - rev_index - 1;
+            result.extend_from_slice(&index.to_be_bytes()[4 - ref_size..]);
+        }
+
+        if self.with_index {
+            for offset in cell_offsets {
+                result.extend_from_slice(&offset.to_be_bytes()[8 - offset_size..]);
+            }
+        }
+
+        let write_cell = |result: &mut Vec<u8>, cell: &DynCell| {
+            let descriptor = cell.descriptor();
+            result.extend_from_slice(&[descriptor.d1, descriptor.d2]);
+            if descriptor.store_hashes() {
+                let level_mask = descriptor.level_mask();
+                for level in level_mask {
+                    result.extend_from_slice(cell.hash(level).as_slice());
+                }
+                for level in level_mask {
+                    result.extend_from_slice(&cell.depth(level).to_be_bytes());
+                }
+            }
+            result.extend_from_slice(cell.data());
+            for child in cell.references() {
+                let index = cell_count - rev_indices[child.repr_hash()] - 1;
+                result.extend_from_slice(&index.to_be_bytes()[4 - ref_size..]);
+            }
+        };
+
+        let mut cells = rev_cells;
+        cells.reverse();
+
+        #[cfg(feature = "parallel")]
+        if cells.len() >= parallel_boc::MIN_CELLS {
+            parallel_boc::write_cells(&mut result, &cells, write_cell);
+        } else {
+            for &cell in &cells {
+                write_cell(&mut result, cell);
+            }
+        }
+        #[cfg(not(feature = "parallel"))]
+        for &cell in &cells {
+            write_cell(&mut result, cell);
+        }
+
+        if self.with_crc {
+            let crc = CRC_32_C.checksum(&result);
+            result.extend_from_slice(&crc.to_le_bytes());
+        }
+
+        result
+    }
+}
+
+fn bytes_to_fit(value: u64) -> usize {
+    std::cmp::max(8 - value.leading_zeros() as usize / 8, 1)
+}
+
+fn pop_string_or_bytes(stack: &mut Stack, s: bool) -> Result<Vec<u8>> {
+    Ok(if s {
+        String::into_bytes(*stack.pop_string()?)
+    } else {
+        *stack.pop_bytes()?
+    })
+}
+
+fn push_string_or_bytes(stack: &mut Stack, data: Vec<u8>, s: bool) -> Result<()> {
+    if s {
+        let string = String::from_utf8(data).context("Data is not a valid UTF-8 string")?;
+        stack.push(string)
+    } else {
+        stack.push(data)
+    }
+}
+
 fn len_as_bits<T: AsRef<[u8]>>(name: &str, data: T) -> Result<u16> {
     let bits = data.as_ref().len() * 8;
     anyhow::ensure!(
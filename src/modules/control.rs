@@ -1,3 +1,4 @@
+use std::io::Write;
 use std::rc::Rc;
 
 use anyhow::{Context as _, Result};
@@ -13,10 +14,7 @@ impl Control {
     fn init(d: &mut Dictionary) -> Result<()> {
         d.define_word(
             "'exit-interpret ",
-            DictionaryEntry {
-                definition: Rc::new(ExitInterpretCont),
-                active: false,
-            },
+            DictionaryEntry::new_ordinary(Rc::new(ExitInterpretCont)),
             false,
         )
     }
@@ -101,30 +99,87 @@ impl Control {
         Ok(Some(*body))
     }
 
+    #[cmd(name = "try", tail)]
+    fn interpret_try(ctx: &mut Context) -> Result<Option<Cont>> {
+        let body = ctx.stack.pop_cont()?;
+        Ok(Some(ctx.begin_try(*body)))
+    }
+
+    #[cmd(name = "throw")]
+    fn interpret_throw(ctx: &mut Context) -> Result<()> {
+        let value = ctx.stack.pop()?;
+        Err(ctx.throw(value))
+    }
+
+    #[cmd(name = "maybe-map", tail)]
+    fn interpret_maybe_map(ctx: &mut Context) -> Result<Option<Cont>> {
+        let body = ctx.stack.pop_cont()?;
+        let value = ctx.stack.pop()?;
+        let is_null = value.is_null();
+        ctx.stack.push_raw(value)?;
+        Ok(if is_null { None } else { Some(*body) })
+    }
+
     // === Compiler control ===
 
     #[cmd(name = "[", active)]
     fn interpret_internal_interpret_begin(ctx: &mut Context) -> Result<()> {
+        anyhow::ensure!(
+            ctx.state.is_compile(),
+            "`[` is only allowed inside a `{{ ... }}` block"
+        );
         ctx.state.begin_interpret_internal()?;
         ctx.stack.push_argcount(0, ctx.dictionary.make_nop())
     }
 
     #[cmd(name = "]", active)]
     fn interpret_internal_interpret_end(ctx: &mut Context) -> Result<()> {
+        anyhow::ensure!(
+            ctx.state.is_interpret_internal(),
+            "`]` without a matching `[`"
+        );
         ctx.state.end_interpret_internal()?;
         ctx.stack.push(ctx.dictionary.make_nop())
     }
 
+    #[cmd(name = "state?")]
+    fn interpret_get_state(ctx: &mut Context) -> Result<()> {
+        let state = match ctx.state {
+            State::Interpret => 0,
+            State::Compile(_) => 1,
+            State::InterpretInternal(_) => 2,
+        };
+        ctx.stack.push_int(state)
+    }
+
+    #[cmd(name = "interpret-mode")]
+    fn interpret_is_interpret_mode(ctx: &mut Context) -> Result<()> {
+        ctx.stack.push_bool(!ctx.state.is_compile())
+    }
+
+    #[cmd(name = "compile-depth")]
+    fn interpret_compile_depth(ctx: &mut Context) -> Result<()> {
+        ctx.stack.push_int(ctx.state.depth())
+    }
+
+    #[cmd(name = ".compile-stack")]
+    fn interpret_print_compile_stack(ctx: &mut Context) -> Result<()> {
+        for (i, block) in ctx.open_blocks.iter().enumerate() {
+            writeln!(ctx.stdout, "level {}: {{ at {block}", i + 1)?;
+        }
+        Ok(())
+    }
+
     #[cmd(name = "{", active)]
     fn interpret_wordlist_begin(ctx: &mut Context) -> Result<()> {
-        ctx.state.begin_compile()?;
+        ctx.begin_block()?;
         interpret_wordlist_begin_aux(&mut ctx.stack)?;
         ctx.stack.push_argcount(0, ctx.dictionary.make_nop())
     }
 
     #[cmd(name = "}", active)]
     fn interpret_wordlist_end(ctx: &mut Context) -> Result<()> {
-        ctx.state.end_compile()?;
+        ctx.end_block()?;
         interpret_wordlist_end_aux(ctx)?;
         ctx.stack.push_argcount(1, ctx.dictionary.make_nop())
     }
@@ -153,7 +208,7 @@ impl Control {
 
     #[cmd(name = "'", active)]
     fn interpret_tick(ctx: &mut Context) -> Result<()> {
-        let word = ctx.input.scan_word()?.ok_or(UnexpectedEof)?;
+        let word = ctx.input.scan_word()?.ok_or_else(UnexpectedEof::default)?;
         let entry = match ctx.dictionary.lookup(word.data) {
             Some(entry) => entry,
             None => ctx
@@ -161,6 +216,9 @@ impl Control {
                 .lookup(&format!("{} ", word.data))
                 .with_context(|| format!("Undefined word `{}`", word.data))?,
         };
+        if let Some(strict) = &ctx.strict {
+            strict.check(word.data)?;
+        }
         ctx.stack.push(entry.definition.clone())?;
         ctx.stack.push_argcount(1, ctx.dictionary.make_nop())
     }
@@ -195,21 +253,28 @@ impl Control {
     fn interpret_create(ctx: &mut Context) -> Result<()> {
         // NOTE: same as `:`, but not active
         let cont = ctx.stack.pop_cont()?;
-        let name = ctx.input.scan_word()?.ok_or(UnexpectedEof)?;
+        let name = ctx.input.scan_word()?.ok_or_else(UnexpectedEof::default)?;
+        let name = name.data.to_owned();
+        let location = current_location(ctx);
 
         define_word(
-            &mut ctx.dictionary,
-            name.data.to_owned(),
+            ctx,
+            name,
             *cont,
             DefMode {
                 active: false,
                 prefix: false,
             },
+            location,
         )
     }
 
-    #[cmd(name = "(create)", args(mode = None))]
-    fn interpret_create_aux(ctx: &mut Context, mode: Option<DefMode>) -> Result<()> {
+    #[cmd(name = "(create)", args(mode = None, location = None))]
+    fn interpret_create_aux(
+        ctx: &mut Context,
+        mode: Option<DefMode>,
+        location: Option<WordLocation>,
+    ) -> Result<()> {
         let mode = match mode {
             Some(mode) => mode,
             None => {
@@ -222,7 +287,8 @@ impl Control {
         };
         let word = ctx.stack.pop_string()?;
         let cont = ctx.stack.pop_cont()?;
-        define_word(&mut ctx.dictionary, *word, *cont, mode)
+        let location = location.or_else(|| current_location(ctx));
+        define_word(ctx, *word, *cont, mode, location)
     }
 
     #[cmd(name = ":", active, args(active = false, prefix = false))]
@@ -230,28 +296,97 @@ impl Control {
     #[cmd(name = ":_", active, args(active = false, prefix = true))]
     #[cmd(name = "::_", active, args(active = true, prefix = true))]
     fn interpret_colon(ctx: &mut Context, active: bool, prefix: bool) -> Result<()> {
-        thread_local! {
-            static CREATE_AUX: Cont = Rc::new((|ctx| interpret_create_aux(ctx, None)) as cont::ContextWordFunc);
-        };
-
-        let name = ctx.input.scan_word()?.ok_or(UnexpectedEof)?;
+        let name = ctx.input.scan_word()?.ok_or_else(UnexpectedEof::default)?;
+        let name = name.data.to_owned();
         let mode = (active as u8) | (prefix as u8) << 1;
 
-        let cont = CREATE_AUX.with(|cont| cont.clone());
+        // The word is defined later (e.g. when the enclosing block
+        // is executed), so the position is captured now
+        let cont: Cont = Rc::new(CreateAuxCont {
+            location: current_location(ctx),
+        });
 
-        ctx.stack.push(name.data.to_owned())?;
+        ctx.stack.push(name)?;
         ctx.stack.push_int(mode)?;
         ctx.stack.push_int(2)?;
         ctx.stack.push(cont)
     }
 
+    #[cmd(name = "add-literal-prefix")]
+    fn interpret_add_literal_prefix(ctx: &mut Context) -> Result<()> {
+        let prefix = ctx.stack.pop_string()?;
+        let parser = ctx.stack.pop_cont()?;
+        anyhow::ensure!(
+            !prefix.is_empty() && !prefix.contains(char::is_whitespace),
+            "Invalid literal prefix `{prefix}`"
+        );
+        ctx.dictionary.define_literal_prefix(*prefix, *parser)
+    }
+
+    // === Vocabularies ===
+
+    #[cmd(name = "vocabulary")]
+    fn interpret_vocabulary(ctx: &mut Context) -> Result<()> {
+        let name = ctx.input.scan_word()?.ok_or_else(UnexpectedEof::default)?;
+        let name = name.data.to_owned();
+        ctx.dictionary.define_vocabulary(name.clone())?;
+        ctx.dictionary.define_word(
+            format!("{name} "),
+            DictionaryEntry::new_ordinary(Rc::new(cont::VocabularyCont(name))),
+            false,
+        )
+    }
+
+    #[cmd(name = "forth")]
+    fn interpret_forth_vocabulary(ctx: &mut Context) -> Result<()> {
+        ctx.dictionary
+            .set_top_vocabulary(Dictionary::ROOT_VOCABULARY)
+    }
+
+    #[cmd(name = "also")]
+    fn interpret_also(ctx: &mut Context) -> Result<()> {
+        ctx.dictionary.also();
+        Ok(())
+    }
+
+    #[cmd(name = "previous")]
+    fn interpret_previous(ctx: &mut Context) -> Result<()> {
+        ctx.dictionary.previous()
+    }
+
+    #[cmd(name = "only")]
+    fn interpret_only(ctx: &mut Context) -> Result<()> {
+        ctx.dictionary.only();
+        Ok(())
+    }
+
+    #[cmd(name = "definitions")]
+    fn interpret_definitions(ctx: &mut Context) -> Result<()> {
+        ctx.dictionary.definitions();
+        Ok(())
+    }
+
+    #[cmd(name = "order")]
+    fn interpret_order(ctx: &mut Context) -> Result<()> {
+        for name in ctx.dictionary.search_order() {
+            write!(ctx.stdout, "{name} ")?;
+        }
+        writeln!(
+            ctx.stdout,
+            "{} current: {}",
+            Dictionary::ROOT_VOCABULARY,
+            ctx.dictionary.current_vocabulary()
+        )?;
+        Ok(())
+    }
+
     #[cmd(name = "forget", args(word_from_stack = false))]
     #[cmd(name = "(forget)", args(word_from_stack = true))]
     fn interpret_forget(ctx: &mut Context, word_from_stack: bool) -> Result<()> {
         let mut word = if word_from_stack {
             *ctx.stack.pop_string()?
         } else {
-            let word = ctx.input.scan_word()?.ok_or(UnexpectedEof)?;
+            let word = ctx.input.scan_word()?.ok_or_else(UnexpectedEof::default)?;
             word.data.to_owned()
         };
 
@@ -266,6 +401,20 @@ impl Control {
         Ok(())
     }
 
+    // === Snapshots ===
+
+    #[cmd(name = "save-state")]
+    fn interpret_save_state(ctx: &mut Context) -> Result<()> {
+        let snapshot = ctx.snapshot()?;
+        ctx.stack.push(snapshot)
+    }
+
+    #[cmd(name = "load-state")]
+    fn interpret_load_state(ctx: &mut Context) -> Result<()> {
+        let snapshot = ctx.stack.pop_bytes()?;
+        ctx.restore(&snapshot)
+    }
+
     // === Input parse ===
 
     #[cmd(name = "word")]
@@ -279,6 +428,27 @@ impl Control {
         ctx.stack.push(token.data.to_owned())
     }
 
+    #[cmd(name = "parse")]
+    fn interpret_parse(ctx: &mut Context) -> Result<()> {
+        let delim = ctx.stack.pop_smallint_char()?;
+        let token = ctx.input.scan_literal(LiteralKind::Delimited(delim))?;
+        ctx.stack.push(token.data.to_owned())
+    }
+
+    #[cmd(name = "char-read")]
+    fn interpret_char_read(ctx: &mut Context) -> Result<()> {
+        match ctx.input.scan_char()? {
+            Some(c) => ctx.stack.push_int(c as u32),
+            None => ctx.stack.push_int(-1),
+        }
+    }
+
+    #[cmd(name = "skip-to-eol")]
+    fn interpret_skip_to_eol(ctx: &mut Context) -> Result<()> {
+        ctx.input.skip_line();
+        Ok(())
+    }
+
     #[cmd(name = "skipspc")]
     fn interpret_skipspc(ctx: &mut Context) -> Result<()> {
         ctx.input.scan_skip_whitespace()
@@ -286,13 +456,94 @@ impl Control {
 
     #[cmd(name = "include", tail)]
     fn interpret_include(ctx: &mut Context) -> Result<Option<Cont>> {
-        let name = ctx.stack.pop_string()?;
-        let source_block = ctx.env.include(&name)?;
+        let name = *ctx.stack.pop_string()?;
+        if ctx.request_include(&name) {
+            // The file is included after the async driver fetches it
+            return Ok(Some(Rc::new(IncludeCont(name, None))));
+        }
+        include_file(ctx, name, None)
+    }
+
+    // ( S steps depth -- ), `null` means no limit
+    #[cmd(name = "include-limited", tail)]
+    fn interpret_include_limited(ctx: &mut Context) -> Result<Option<Cont>> {
+        let stack_depth = pop_limit(&mut ctx.stack)?;
+        let steps = pop_limit(&mut ctx.stack)?;
+        let name = *ctx.stack.pop_string()?;
+        let fuel = Fuel {
+            steps: steps.map(|steps| steps as u64),
+            stack_depth,
+        };
+        if ctx.request_include(&name) {
+            return Ok(Some(Rc::new(IncludeCont(name, Some(fuel)))));
+        }
+        include_file(ctx, name, Some(fuel))
+    }
+
+    #[cmd(name = "evaluate", tail)]
+    fn interpret_evaluate(ctx: &mut Context) -> Result<Option<Cont>> {
+        let item = ctx.stack.pop()?;
+        let source_block = if item.ty() == StackValueType::SourceBlock {
+            item.as_source_block()?.to_source_block()
+        } else {
+            let source = *item.into_string()?;
+            SourceBlock::new("<evaluate>", std::io::Cursor::new(source))
+        };
         ctx.input.push_source_block(source_block);
         ctx.next = cont::SeqCont::make(Some(Rc::new(ExitSourceBlockCont)), ctx.next.take());
         Ok(Some(Rc::new(cont::InterpreterCont)))
     }
 
+    // Captures lines until the terminator: `<<EOF ... EOF`.
+    // A standalone `<<` is still the left shift.
+    #[cmd(name = "<<", active, without_space)]
+    fn interpret_source_block_literal(ctx: &mut Context) -> Result<()> {
+        let name = match ctx.input.get_position() {
+            Some(pos) => format!("{}:{}", pos.source_block_name, pos.line_number),
+            None => "<<".to_owned(),
+        };
+
+        let rest = ctx.input.scan_line_rest()?.data;
+        if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+            let len = rest.len();
+            ctx.input.rewind(len);
+            let shift = ctx
+                .dictionary
+                .lookup("<< ")
+                .context("Undefined word `<<`")?;
+            let shift = shift.definition.clone();
+            return ctx.stack.push_argcount(0, shift);
+        }
+
+        let terminator = rest.trim_end().to_owned();
+        anyhow::ensure!(
+            !terminator.contains(char::is_whitespace),
+            "Expected a single terminator word after `<<`"
+        );
+
+        let mut source = String::new();
+        loop {
+            let line = ctx
+                .input
+                .scan_next_line()?
+                .ok_or_else(UnexpectedEof::default)?;
+            if line.data.trim_end() == terminator {
+                break;
+            }
+            source.push_str(line.data);
+            source.push('\n');
+        }
+
+        ctx.stack.push(SourceBlockValue::new(name, source))?;
+        ctx.stack.push_argcount(1, ctx.dictionary.make_nop())
+    }
+
+    #[cmd(name = "source>$", stack)]
+    fn interpret_source_block_to_string(stack: &mut Stack) -> Result<()> {
+        let block = stack.pop_source_block()?;
+        stack.push(block.source().to_owned())
+    }
+
     #[cmd(name = "skip-to-eof", tail)]
     fn interpret_skip_source(ctx: &mut Context) -> Result<Option<Cont>> {
         let cont = ctx.exit_interpret.fetch();
@@ -333,19 +584,34 @@ impl Control {
     }
 }
 
-fn define_word(d: &mut Dictionary, mut word: String, cont: Cont, mode: DefMode) -> Result<()> {
+fn define_word(
+    ctx: &mut Context,
+    mut word: String,
+    cont: Cont,
+    mode: DefMode,
+    location: Option<WordLocation>,
+) -> Result<()> {
     anyhow::ensure!(!word.is_empty(), "Word definition is empty");
     if !mode.prefix {
         word.push(' ');
     }
-    d.define_word(
+    ctx.dictionary.define_word(
         word,
         DictionaryEntry {
             definition: cont,
             active: mode.active,
+            location,
         },
         true,
-    )
+    )?;
+    if let Some(timings) = ctx.input.timings_mut() {
+        timings.add_word_defined();
+    }
+    Ok(())
+}
+
+fn current_location(ctx: &Context) -> Option<WordLocation> {
+    ctx.input.get_position().map(|pos| WordLocation::new(&pos))
 }
 
 #[derive(Default)]
@@ -354,6 +620,22 @@ struct DefMode {
     prefix: bool,
 }
 
+/// `(create)` with the position of the `:` word.
+struct CreateAuxCont {
+    location: Option<WordLocation>,
+}
+
+impl cont::ContImpl for CreateAuxCont {
+    fn run(self: Rc<Self>, ctx: &mut Context) -> Result<Option<Cont>> {
+        interpret_create_aux(ctx, None, self.location.clone())?;
+        Ok(None)
+    }
+
+    fn fmt_name(&self, _: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("(create)")
+    }
+}
+
 struct ExitInterpretCont;
 
 impl cont::ContImpl for ExitInterpretCont {
@@ -367,6 +649,40 @@ impl cont::ContImpl for ExitInterpretCont {
     }
 }
 
+fn include_file(ctx: &mut Context, name: String, limits: Option<Fuel>) -> Result<Option<Cont>> {
+    let source_block = ctx.open_include(&name)?;
+    if !ctx.includes.contains(&name) {
+        ctx.includes.push(name);
+    }
+    let id = ctx.input.push_source_block(source_block);
+    if let Some(fuel) = limits {
+        ctx.limit_source_block(id, fuel);
+    }
+    ctx.next = cont::SeqCont::make(Some(Rc::new(ExitSourceBlockCont)), ctx.next.take());
+    Ok(Some(Rc::new(cont::InterpreterCont)))
+}
+
+fn pop_limit(stack: &mut Stack) -> Result<Option<usize>> {
+    let value = stack.pop()?;
+    if value.is_null() {
+        return Ok(None);
+    }
+    stack.push_raw(value)?;
+    stack.pop_usize().map(Some)
+}
+
+struct IncludeCont(String, Option<Fuel>);
+
+impl cont::ContImpl for IncludeCont {
+    fn run(self: Rc<Self>, ctx: &mut Context) -> Result<Option<Cont>> {
+        include_file(ctx, self.0.clone(), self.1)
+    }
+
+    fn fmt_name(&self, _: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<include {:?}>", self.0)
+    }
+}
+
 struct ExitSourceBlockCont;
 
 impl cont::ContImpl for ExitSourceBlockCont {
@@ -1,8 +1,13 @@
 use anyhow::{Context as _, Result};
-use crc::Crc;
 use everscale_crypto::ed25519;
+use hmac::Mac;
+use num_bigint::{BigInt, Sign};
+use rand::Rng;
+use sha2::Digest;
 
+use super::hashing::CRC_16;
 use crate::core::*;
+use crate::util::{decode_base32, decode_base64, encode_base32, encode_base64, int_to_hash};
 
 pub struct Crypto;
 
@@ -31,6 +36,24 @@ impl Crypto {
         stack.push(signature.to_vec())
     }
 
+    #[cmd(name = "ed25519_sign_uint", stack)]
+    fn interpret_ed25519_sign_uint(stack: &mut Stack) -> Result<()> {
+        let secret = pop_secret_key(stack)?;
+        let public = ed25519::PublicKey::from(&secret);
+        let int = stack.pop_int()?;
+        anyhow::ensure!(
+            int.sign() != num_bigint::Sign::Minus && int.bits() <= 256,
+            "Integer does not fit into 256 bits"
+        );
+
+        let (_, bytes) = int.to_bytes_be();
+        let mut data = [0u8; 32];
+        data[32 - bytes.len()..].copy_from_slice(&bytes);
+
+        let signature = secret.expand().sign_raw(&data, &public);
+        stack.push(signature.to_vec())
+    }
+
     #[cmd(name = "ed25519_chksign", stack)]
     fn interpret_ed25519_chksign(stack: &mut Stack) -> Result<()> {
         let public = pop_public_key(stack)?;
@@ -39,32 +62,444 @@ impl Crypto {
         stack.push_bool(public.verify_raw(&data, &signature))
     }
 
-    #[cmd(name = "crc16", stack)]
-    fn interpret_crc16(stack: &mut Stack) -> Result<()> {
-        let bytes = stack.pop_bytes()?;
-        let mut res = CRC_16.digest();
-        res.update(bytes.as_slice());
-        stack.push_int(res.finalize())
+    #[cmd(name = "secp256k1_priv>pub", stack)]
+    fn interpret_secp256k1_priv_to_pub(stack: &mut Stack) -> Result<()> {
+        let secret = pop_secp256k1_secret_key(stack)?;
+        let public = secret.verifying_key().to_encoded_point(false);
+        stack.push(public.as_bytes().to_vec())
+    }
+
+    #[cmd(name = "secp256k1_sign", stack)]
+    fn interpret_secp256k1_sign(stack: &mut Stack) -> Result<()> {
+        let secret = pop_secp256k1_secret_key(stack)?;
+        let hash = pop_secp256k1_hash(stack)?;
+        let (signature, recovery_id) = secret
+            .sign_prehash_recoverable(&hash)
+            .context("Failed to sign data")?;
+
+        let mut res = signature.to_vec();
+        res.push(recovery_id.to_byte());
+        stack.push(res)
+    }
+
+    #[cmd(name = "secp256k1_chksign", stack)]
+    fn interpret_secp256k1_chksign(stack: &mut Stack) -> Result<()> {
+        use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+        let public = stack.pop_bytes()?;
+        let public = k256::ecdsa::VerifyingKey::from_sec1_bytes(&public)
+            .ok()
+            .context("Invalid secp256k1 public key")?;
+        let (signature, _) = pop_secp256k1_signature(stack)?;
+        let hash = pop_secp256k1_hash(stack)?;
+        stack.push_bool(public.verify_prehash(&hash, &signature).is_ok())
+    }
+
+    #[cmd(name = "secp256k1_recover", stack)]
+    fn interpret_secp256k1_recover(stack: &mut Stack) -> Result<()> {
+        let (signature, recovery_id) = pop_secp256k1_signature(stack)?;
+        let hash = pop_secp256k1_hash(stack)?;
+        let recovery_id = recovery_id.context("Expected a 65-byte recoverable signature")?;
+
+        match k256::ecdsa::VerifyingKey::recover_from_prehash(&hash, &signature, recovery_id) {
+            Ok(public) => {
+                stack.push(public.to_encoded_point(false).as_bytes().to_vec())?;
+                stack.push_bool(true)
+            }
+            Err(_) => stack.push_bool(false),
+        }
+    }
+
+    #[cmd(name = "external-pubkey")]
+    fn interpret_external_pubkey(ctx: &mut Context) -> Result<()> {
+        let public = get_signer(ctx)?.public_key();
+        ctx.stack.push(public.to_vec())
+    }
+
+    #[cmd(name = "external-sign")]
+    fn interpret_external_sign(ctx: &mut Context) -> Result<()> {
+        let data = ctx.stack.pop_bytes()?;
+        let signature = get_signer(ctx)?
+            .sign(&data)
+            .context("External signer failed")?;
+        ctx.stack.push(signature.to_vec())
+    }
+
+    #[cmd(name = "pub>adnl-id", stack)]
+    fn interpret_pub_to_adnl_id(stack: &mut Stack) -> Result<()> {
+        // TL id of `pub.ed25519 key:int256 = PublicKey`
+        const PUB_ED25519_TL_ID: u32 = 0x4813b4c6;
+
+        let public = pop_public_key(stack)?;
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(PUB_ED25519_TL_ID.to_le_bytes());
+        hasher.update(public.as_bytes());
+        stack.push(hasher.finalize().to_vec())
+    }
+
+    #[cmd(name = "adnl-id>$", stack)]
+    fn interpret_adnl_id_to_string(stack: &mut Stack) -> Result<()> {
+        let id = stack.pop_bytes()?;
+        let id: &[u8; 32] = id.as_slice().try_into().ok().context("Invalid ADNL id")?;
+
+        let mut buffer = [0u8; 35];
+        buffer[0] = ADNL_ID_TAG;
+        buffer[1..33].copy_from_slice(id);
+        let crc = CRC_16.checksum(&buffer[..33]);
+        buffer[33..].copy_from_slice(&crc.to_be_bytes());
+
+        // The first char is always the same, so it is omitted
+        let mut string = encode_base32(&buffer);
+        string.remove(0);
+        stack.push(string)
+    }
+
+    #[cmd(name = "$>adnl-id", stack, args(quiet = false))]
+    #[cmd(name = "$>adnl-id?", stack, args(quiet = true))]
+    fn interpret_string_to_adnl_id(stack: &mut Stack, quiet: bool) -> Result<()> {
+        let string = stack.pop_string()?;
+        match parse_adnl_id(&string) {
+            Some(id) => {
+                stack.push(id.to_vec())?;
+                if quiet {
+                    stack.push_bool(true)?;
+                }
+                Ok(())
+            }
+            None if quiet => stack.push_bool(false),
+            None => anyhow::bail!("Invalid ADNL address `{string}`"),
+        }
+    }
+
+    // ( S -- x y z -1 or 0 ), where z is the flags (see `smca>$`), raw addresses have no flags
+    #[cmd(name = "$>smca", stack)]
+    fn interpret_string_to_smc_addr(stack: &mut Stack) -> Result<()> {
+        let string = stack.pop_string()?;
+        match parse_smc_addr(&string) {
+            Some((workchain, addr, flags)) => {
+                stack.push_int(workchain)?;
+                stack.push_int(BigInt::from_bytes_be(Sign::Plus, &addr))?;
+                stack.push_int(flags)?;
+                stack.push_bool(true)
+            }
+            None => stack.push_bool(false),
+        }
+    }
+
+    // ( x y z -- S ), flags z: +1 = non-bounceable, +2 = testnet, +4 = base64url
+    #[cmd(name = "smca>$", stack)]
+    fn interpret_smc_addr_to_string(stack: &mut Stack) -> Result<()> {
+        let flags = stack.pop_smallint_range(0, 7)? as u8;
+        let addr = int_to_hash(&*stack.pop_int()?)?;
+        let workchain = stack.pop_i8_checked()?;
+
+        let mut buffer = [0u8; 36];
+        buffer[0] = match flags & SMCA_NON_BOUNCEABLE {
+            0 => SMCA_BOUNCEABLE_TAG,
+            _ => SMCA_NON_BOUNCEABLE_TAG,
+        };
+        if flags & SMCA_TESTNET != 0 {
+            buffer[0] |= SMCA_TESTNET_TAG;
+        }
+        buffer[1] = workchain as u8;
+        buffer[2..34].copy_from_slice(addr.as_slice());
+        let crc = CRC_16.checksum(&buffer[..34]);
+        buffer[34..].copy_from_slice(&crc.to_be_bytes());
+
+        let mut string = encode_base64(buffer);
+        if flags & SMCA_URL_SAFE != 0 {
+            string = string.replace('+', "-").replace('/', "_");
+        }
+        stack.push(string)
+    }
+
+    // ( x y -- S ), e.g. `0:ab..cd`
+    #[cmd(name = "smca>raw$", stack)]
+    fn interpret_smc_addr_to_raw_string(stack: &mut Stack) -> Result<()> {
+        let addr = int_to_hash(&*stack.pop_int()?)?;
+        let workchain = stack.pop_i8_checked()?;
+        stack.push(format!("{workchain}:{}", hex::encode(addr.as_slice())))
+    }
+
+    // ( B priv pub S -- B' ), where S is a salt (usually the sender address)
+    #[cmd(name = "B-encrypt", stack)]
+    fn interpret_encrypt_bytes(stack: &mut Stack) -> Result<()> {
+        let salt = stack.pop_string()?;
+        let their_public = pop_public_key(stack)?;
+        let secret = pop_secret_key(stack)?;
+        let data = stack.pop_bytes()?;
+
+        let public = ed25519::PublicKey::from(&secret);
+        let shared_secret = secret.expand().compute_shared_secret(&their_public);
+
+        // Data is prefixed with 16..=31 random bytes,
+        // the first of which is the prefix length
+        let prefix_len = 16 + (16 - data.len() % 16) % 16;
+        let mut plain = vec![0u8; prefix_len + data.len()];
+        rand::thread_rng().fill(&mut plain[1..prefix_len]);
+        plain[0] = prefix_len as u8;
+        plain[prefix_len..].copy_from_slice(&data);
+
+        let msg_key = compute_msg_key(salt.as_bytes(), &plain);
+        aes_cbc_apply(&shared_secret, &msg_key, &mut plain, true);
+
+        let mut result = Vec::with_capacity(64 + plain.len());
+        result.extend(
+            public
+                .as_bytes()
+                .iter()
+                .zip(their_public.as_bytes())
+                .map(|(a, b)| a ^ b),
+        );
+        result.extend_from_slice(&msg_key);
+        result.extend_from_slice(&plain);
+        stack.push(result)
+    }
+
+    // ( B' priv S -- B )
+    #[cmd(name = "B-decrypt", stack)]
+    fn interpret_decrypt_bytes(stack: &mut Stack) -> Result<()> {
+        let salt = stack.pop_string()?;
+        let secret = pop_secret_key(stack)?;
+        let data = stack.pop_bytes()?;
+        anyhow::ensure!(
+            data.len() >= 48 + 16 && data.len() % 16 == 0,
+            "Invalid encrypted data length"
+        );
+
+        let public = ed25519::PublicKey::from(&secret);
+        let mut their_public = [0u8; 32];
+        for (i, byte) in their_public.iter_mut().enumerate() {
+            *byte = data[i] ^ public.as_bytes()[i];
+        }
+        let their_public =
+            ed25519::PublicKey::from_bytes(their_public).context("Invalid sender public key")?;
+        let shared_secret = secret.expand().compute_shared_secret(&their_public);
+
+        let msg_key: [u8; 16] = data[32..48].try_into().unwrap();
+        let mut plain = data[48..].to_vec();
+        aes_cbc_apply(&shared_secret, &msg_key, &mut plain, false);
+
+        anyhow::ensure!(
+            compute_msg_key(salt.as_bytes(), &plain) == msg_key,
+            "Failed to decrypt data: message key mismatch"
+        );
+        let prefix_len = plain[0] as usize;
+        anyhow::ensure!(
+            (16..32).contains(&prefix_len) && prefix_len <= plain.len(),
+            "Failed to decrypt data: invalid prefix length"
+        );
+
+        plain.drain(..prefix_len);
+        stack.push(plain)
+    }
+
+    #[cmd(name = "generate_mnemonic", stack, args(with_password = false))]
+    #[cmd(name = "generate_mnemonic_pwd", stack, args(with_password = true))]
+    fn interpret_generate_mnemonic(stack: &mut Stack, with_password: bool) -> Result<()> {
+        let password = pop_mnemonic_password(stack, with_password)?;
+        let words = generate_mnemonic(&password, &mut rand::thread_rng());
+        stack.push(words.join(" "))
+    }
+
+    #[cmd(name = "mnemonic>priv", stack, args(with_password = false))]
+    #[cmd(name = "mnemonic_pwd>priv", stack, args(with_password = true))]
+    fn interpret_mnemonic_to_priv(stack: &mut Stack, with_password: bool) -> Result<()> {
+        let password = pop_mnemonic_password(stack, with_password)?;
+        let words = parse_mnemonic(&stack.pop_string()?)?;
+        anyhow::ensure!(
+            is_valid_mnemonic(&words, &password),
+            "Invalid mnemonic or password"
+        );
+        let seed = mnemonic_seed(&words, &password, MNEMONIC_KEY_SALT, MNEMONIC_KEY_ROUNDS);
+        stack.push(seed[..32].to_vec())
+    }
+
+    #[cmd(name = "mnemonic?", stack, args(with_password = false))]
+    #[cmd(name = "mnemonic_pwd?", stack, args(with_password = true))]
+    fn interpret_is_valid_mnemonic(stack: &mut Stack, with_password: bool) -> Result<()> {
+        let password = pop_mnemonic_password(stack, with_password)?;
+        let valid = match parse_mnemonic(&stack.pop_string()?) {
+            Ok(words) => is_valid_mnemonic(&words, &password),
+            Err(_) => false,
+        };
+        stack.push_bool(valid)
+    }
+}
+
+fn parse_adnl_id(s: &str) -> Option<[u8; 32]> {
+    if s.len() != 55 {
+        return None;
+    }
+    let buffer = decode_base32(&format!("f{s}"))?;
+    let buffer: [u8; 35] = buffer.try_into().ok()?;
+    if buffer[0] != ADNL_ID_TAG || CRC_16.checksum(&buffer[..33]).to_be_bytes() != buffer[33..] {
+        return None;
+    }
+    buffer[1..33].try_into().ok()
+}
+
+/// Parses user-friendly (base64 or base64url) and raw (`wc:hex`) std addresses.
+fn parse_smc_addr(s: &str) -> Option<(i8, [u8; 32], u8)> {
+    if let Some((workchain, addr)) = s.split_once(':') {
+        let workchain = workchain.parse::<i8>().ok()?;
+        let mut buffer = [0u8; 32];
+        hex::decode_to_slice(addr, &mut buffer).ok()?;
+        return Some((workchain, buffer, 0));
+    }
+
+    if s.len() != 48 {
+        return None;
+    }
+    let url_safe = s.contains(['-', '_']);
+    let buffer = decode_base64(s.replace('-', "+").replace('_', "/")).ok()?;
+    let buffer: [u8; 36] = buffer.try_into().ok()?;
+    if CRC_16.checksum(&buffer[..34]).to_be_bytes() != buffer[34..] {
+        return None;
+    }
+
+    let mut flags = 0;
+    let tag = match buffer[0] & SMCA_TESTNET_TAG {
+        0 => buffer[0],
+        _ => {
+            flags |= SMCA_TESTNET;
+            buffer[0] & !SMCA_TESTNET_TAG
+        }
+    };
+    match tag {
+        SMCA_BOUNCEABLE_TAG => {}
+        SMCA_NON_BOUNCEABLE_TAG => flags |= SMCA_NON_BOUNCEABLE,
+        _ => return None,
+    }
+    if url_safe {
+        flags |= SMCA_URL_SAFE;
+    }
+
+    Some((buffer[1] as i8, buffer[2..34].try_into().ok()?, flags))
+}
+
+type HmacSha512 = hmac::Hmac<sha2::Sha512>;
+
+fn compute_msg_key(salt: &[u8], data: &[u8]) -> [u8; 16] {
+    let mut mac = HmacSha512::new_from_slice(salt).unwrap();
+    mac.update(data);
+    let hash = mac.finalize().into_bytes();
+    hash[..16].try_into().unwrap()
+}
+
+/// Encrypts or decrypts the data in place with AES-256-CBC,
+/// the key and IV are derived from the shared secret and the message key.
+fn aes_cbc_apply(shared_secret: &[u8; 32], msg_key: &[u8; 16], data: &mut [u8], encrypt: bool) {
+    use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+
+    let mut mac = HmacSha512::new_from_slice(shared_secret).unwrap();
+    mac.update(msg_key);
+    let x = mac.finalize().into_bytes();
+    let (key, iv) = (&x[..32], &x[32..48]);
+
+    let blocks = data.chunks_exact_mut(16).map(aes::Block::from_mut_slice);
+    if encrypt {
+        let mut cipher = cbc::Encryptor::<aes::Aes256>::new(key.into(), iv.into());
+        blocks.for_each(|block| cipher.encrypt_block_mut(block));
+    } else {
+        let mut cipher = cbc::Decryptor::<aes::Aes256>::new(key.into(), iv.into());
+        blocks.for_each(|block| cipher.decrypt_block_mut(block));
     }
+}
+
+// === Mnemonics ===
+//
+// TON flavour of mnemonic phrases: 24 words from the BIP39 english word list,
+// the private key is derived from the whole phrase instead of the entropy bits.
+
+const MNEMONIC_WORDS: usize = 24;
 
-    #[cmd(name = "crc32", stack)]
-    fn interpret_crc32(stack: &mut Stack) -> Result<()> {
-        let bytes = stack.pop_bytes()?;
-        let mut res = CRC_32.digest();
-        res.update(bytes.as_slice());
-        stack.push_int(res.finalize())
+const MNEMONIC_KEY_SALT: &[u8] = b"TON default seed";
+const MNEMONIC_KEY_ROUNDS: u32 = 100000;
+const MNEMONIC_BASIC_SALT: &[u8] = b"TON seed version";
+const MNEMONIC_BASIC_ROUNDS: u32 = 100000 / 256;
+const MNEMONIC_PASSWORD_SALT: &[u8] = b"TON fast seed version";
+
+fn pop_mnemonic_password(stack: &mut Stack, with_password: bool) -> Result<String> {
+    Ok(if with_password {
+        *stack.pop_string()?
+    } else {
+        String::new()
+    })
+}
+
+fn parse_mnemonic(phrase: &str) -> Result<Vec<&'static str>> {
+    let english = bip39::Language::English;
+    let words = phrase
+        .split_whitespace()
+        .map(|word| {
+            let word = word.to_lowercase();
+            match english.find_word(&word) {
+                Some(index) => Ok(english.word_list()[index as usize]),
+                None => Err(anyhow::anyhow!("Unknown mnemonic word `{word}`")),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    anyhow::ensure!(
+        words.len() == MNEMONIC_WORDS,
+        "Expected {MNEMONIC_WORDS} mnemonic words, got {}",
+        words.len()
+    );
+    Ok(words)
+}
+
+fn generate_mnemonic<R: Rng>(password: &str, rng: &mut R) -> Vec<&'static str> {
+    let word_list = bip39::Language::English.word_list();
+    loop {
+        let words = (0..MNEMONIC_WORDS)
+            .map(|_| word_list[rng.gen_range(0..word_list.len())])
+            .collect::<Vec<_>>();
+        if is_valid_mnemonic(&words, password) {
+            break words;
+        }
     }
+}
 
-    #[cmd(name = "crc32c", stack)]
-    fn interpret_crc32c(stack: &mut Stack) -> Result<()> {
-        let bytes = stack.pop_bytes()?;
-        let mut res = CRC_32_C.digest();
-        res.update(bytes.as_slice());
-        stack.push_int(res.finalize())
+fn is_valid_mnemonic(words: &[&str], password: &str) -> bool {
+    if !password.is_empty() {
+        // A phrase for a password must not be usable without it
+        let entropy = mnemonic_entropy(words, "");
+        if !is_password_seed(&entropy) || is_basic_seed(&entropy) {
+            return false;
+        }
     }
+    is_basic_seed(&mnemonic_entropy(words, password))
+}
+
+fn is_basic_seed(entropy: &[u8; 64]) -> bool {
+    pbkdf2_sha512(entropy, MNEMONIC_BASIC_SALT, MNEMONIC_BASIC_ROUNDS)[0] == 0
+}
+
+fn is_password_seed(entropy: &[u8; 64]) -> bool {
+    pbkdf2_sha512(entropy, MNEMONIC_PASSWORD_SALT, 1)[0] == 1
+}
+
+fn mnemonic_entropy(words: &[&str], password: &str) -> [u8; 64] {
+    let mut mac = HmacSha512::new_from_slice(words.join(" ").as_bytes()).unwrap();
+    mac.update(password.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+fn mnemonic_seed(words: &[&str], password: &str, salt: &[u8], rounds: u32) -> [u8; 64] {
+    pbkdf2_sha512(&mnemonic_entropy(words, password), salt, rounds)
 }
 
-fn pop_secret_key(stack: &mut Stack) -> Result<ed25519::SecretKey> {
+fn pbkdf2_sha512(data: &[u8], salt: &[u8], rounds: u32) -> [u8; 64] {
+    let mut res = [0; 64];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha512>(data, salt, rounds, &mut res);
+    res
+}
+
+fn get_signer<'a>(ctx: &'a mut Context) -> Result<&'a mut dyn Signer> {
+    ctx.env.signer().context("No external signer configured")
+}
+
+pub(super) fn pop_secret_key(stack: &mut Stack) -> Result<ed25519::SecretKey> {
     let b = stack.pop_bytes()?;
     Ok(ed25519::SecretKey::from_bytes(
         b.as_slice().try_into().ok().context("Invalid secret key")?,
@@ -86,6 +521,47 @@ fn pop_signature(stack: &mut Stack) -> Result<[u8; 64]> {
     b.as_slice().try_into().ok().context("Invalid signature")
 }
 
-const CRC_16: Crc<u16> = Crc::<u16>::new(&crc::CRC_16_XMODEM);
-const CRC_32: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
-const CRC_32_C: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISCSI);
+fn pop_secp256k1_secret_key(stack: &mut Stack) -> Result<k256::ecdsa::SigningKey> {
+    let b = stack.pop_bytes()?;
+    k256::ecdsa::SigningKey::from_slice(&b)
+        .ok()
+        .context("Invalid secp256k1 secret key")
+}
+
+fn pop_secp256k1_hash(stack: &mut Stack) -> Result<[u8; 32]> {
+    let b = stack.pop_bytes()?;
+    b.as_slice()
+        .try_into()
+        .ok()
+        .context("Expected a 32-byte hash")
+}
+
+/// Pops a 64-byte `r || s` signature, or a 65-byte one with a recovery id.
+fn pop_secp256k1_signature(
+    stack: &mut Stack,
+) -> Result<(k256::ecdsa::Signature, Option<k256::ecdsa::RecoveryId>)> {
+    let b = stack.pop_bytes()?;
+    let (signature, recovery_id) = match b.len() {
+        64 => (b.as_slice(), None),
+        65 => {
+            let recovery_id = k256::ecdsa::RecoveryId::from_byte(b[64])
+                .context("Invalid signature recovery id")?;
+            (&b[..64], Some(recovery_id))
+        }
+        _ => anyhow::bail!("Invalid signature"),
+    };
+    let signature = k256::ecdsa::Signature::from_slice(signature)
+        .ok()
+        .context("Invalid signature")?;
+    Ok((signature, recovery_id))
+}
+
+const ADNL_ID_TAG: u8 = 0x2d;
+
+const SMCA_BOUNCEABLE_TAG: u8 = 0x11;
+const SMCA_NON_BOUNCEABLE_TAG: u8 = 0x51;
+const SMCA_TESTNET_TAG: u8 = 0x80;
+
+const SMCA_NON_BOUNCEABLE: u8 = 1;
+const SMCA_TESTNET: u8 = 2;
+const SMCA_URL_SAFE: u8 = 4;
@@ -1,6 +1,11 @@
+use std::io::Write;
+
 use anyhow::Result;
 
+use crate::alloc::AllocStats;
 use crate::core::*;
+use crate::error::UnexpectedEof;
+use crate::format::DisplayInt;
 use crate::util::*;
 
 pub struct DebugUtils;
@@ -11,6 +16,7 @@ impl DebugUtils {
     #[cmd(name = "._", args(space_after = false))]
     fn interpret_dot(ctx: &mut Context, space_after: bool) -> Result<()> {
         let int = ctx.stack.pop_int()?;
+        let int = DisplayInt::new(&int, ctx.output_radix());
         write!(ctx.stdout, "{int}{}", opt_space(space_after))?;
         Ok(())
     }
@@ -21,12 +27,8 @@ impl DebugUtils {
     #[cmd(name = "X._", args(uppercase = true, space_after = false))]
     fn interpret_dothex(ctx: &mut Context, uppercase: bool, space_after: bool) -> Result<()> {
         let int = ctx.stack.pop_int()?;
-        let space = opt_space(space_after);
-        if uppercase {
-            write!(ctx.stdout, "{:X}{space}", int.as_ref())
-        } else {
-            write!(ctx.stdout, "{:x}{space}", int.as_ref())
-        }?;
+        let int = DisplayInt::new(&int, 16).uppercase(uppercase);
+        write!(ctx.stdout, "{int}{}", opt_space(space_after))?;
         Ok(())
     }
 
@@ -34,7 +36,8 @@ impl DebugUtils {
     #[cmd(name = "b._", args(space_after = false))]
     fn interpret_dotbin(ctx: &mut Context, space_after: bool) -> Result<()> {
         let int = ctx.stack.pop_int()?;
-        write!(ctx.stdout, "{:b}{}", int.as_ref(), opt_space(space_after))?;
+        let int = DisplayInt::new(&int, 2);
+        write!(ctx.stdout, "{int}{}", opt_space(space_after))?;
         Ok(())
     }
 
@@ -67,16 +70,17 @@ impl DebugUtils {
         Ok(())
     }
 
-    #[cmd(name = ".s")]
-    fn interpret_dotstack(ctx: &mut Context) -> Result<()> {
-        writeln!(ctx.stdout, "{}", ctx.stack.display_dump())?;
-        Ok(())
+    #[cmd(name = ".s", args(list = false))]
+    #[cmd(name = ".sl", args(list = true))]
+    fn interpret_dotstack(ctx: &mut Context, list: bool) -> Result<()> {
+        write_stack_page(ctx, 0, list)
     }
 
-    #[cmd(name = ".sl")]
-    fn interpret_dotstack_list(ctx: &mut Context) -> Result<()> {
-        writeln!(ctx.stdout, "{}", ctx.stack.display_list())?;
-        Ok(())
+    // ( n -- ), prints the n-th page of `.s` counting from the top of the stack
+    #[cmd(name = ".s-page")]
+    fn interpret_dotstack_page(ctx: &mut Context) -> Result<()> {
+        let page = ctx.stack.pop_usize()?;
+        write_stack_page(ctx, page, false)
     }
 
     #[cmd(name = ".dump")]
@@ -93,6 +97,18 @@ impl DebugUtils {
         Ok(())
     }
 
+    #[cmd(name = "history")]
+    fn interpret_history(ctx: &mut Context) -> Result<()> {
+        let Some(history) = &ctx.history else {
+            writeln!(ctx.stdout, "history is disabled")?;
+            return Ok(());
+        };
+        for entry in history.entries() {
+            writeln!(ctx.stdout, "{entry}")?;
+        }
+        Ok(())
+    }
+
     #[cmd(name = ".bt")]
     fn interpret_print_backtrace(ctx: &mut Context) -> Result<()> {
         if let Some(next) = &ctx.next {
@@ -108,6 +124,169 @@ impl DebugUtils {
         Ok(())
     }
 
+    #[cmd(name = "see")]
+    fn interpret_see(ctx: &mut Context) -> Result<()> {
+        let name = ctx
+            .input
+            .scan_word()?
+            .ok_or_else(UnexpectedEof::default)?
+            .data
+            .to_owned();
+
+        let (entry, prefix) = match ctx.dictionary.lookup(&format!("{name} ")) {
+            Some(entry) => (entry, ""),
+            // Prefix words are defined without a trailing space
+            None => match ctx.dictionary.lookup(&name) {
+                Some(entry) => (entry, "_"),
+                None => anyhow::bail!("Undefined word `{name}`"),
+            },
+        };
+
+        let definition = entry.definition.as_ref();
+        if definition.word_list().is_none() && definition.literals().is_none() {
+            writeln!(ctx.stdout, "// `{name}` is a native word")?;
+            return Ok(());
+        }
+
+        let define = if entry.active { "::" } else { ":" };
+        let source = definition.display_source(&ctx.dictionary);
+        writeln!(ctx.stdout, "{source} {define}{prefix} {name}")?;
+        Ok(())
+    }
+
+    #[cmd(name = "where")]
+    fn interpret_where(ctx: &mut Context) -> Result<()> {
+        let name = ctx
+            .input
+            .scan_word()?
+            .ok_or_else(UnexpectedEof::default)?
+            .data
+            .to_owned();
+
+        let entry = match ctx.dictionary.lookup(&format!("{name} ")) {
+            Some(entry) => entry,
+            None => match ctx.dictionary.lookup(&name) {
+                Some(entry) => entry,
+                None => anyhow::bail!("Undefined word `{name}`"),
+            },
+        };
+
+        match &entry.location {
+            Some(location) => writeln!(ctx.stdout, "// `{name}` is defined at {location}")?,
+            None => writeln!(ctx.stdout, "// `{name}` is a native word")?,
+        }
+        Ok(())
+    }
+
+    // === Debugger ===
+
+    #[cmd(name = "bp", args(set = true))]
+    #[cmd(name = "unbp", args(set = false))]
+    fn interpret_breakpoint(ctx: &mut Context, set: bool) -> Result<()> {
+        let name = ctx
+            .input
+            .scan_word()?
+            .ok_or_else(UnexpectedEof::default)?
+            .data
+            .to_owned();
+        if !set {
+            anyhow::ensure!(
+                ctx.debugger.remove_breakpoint(&name),
+                "No breakpoint at `{name}`"
+            );
+            return Ok(());
+        }
+
+        let entry = ctx
+            .dictionary
+            .lookup(&format!("{name} "))
+            .or_else(|| ctx.dictionary.lookup(&name))
+            .ok_or_else(|| anyhow::anyhow!("Undefined word `{name}`"))?;
+        let definition = entry.definition.clone();
+        ctx.debugger.set_breakpoint(name, definition);
+        Ok(())
+    }
+
+    #[cmd(name = "bps")]
+    fn interpret_list_breakpoints(ctx: &mut Context) -> Result<()> {
+        for name in ctx.debugger.breakpoints() {
+            writeln!(ctx.stdout, "{name}")?;
+        }
+        Ok(())
+    }
+
+    #[cmd(name = "step")]
+    fn interpret_step(ctx: &mut Context) -> Result<()> {
+        ctx.debugger.step();
+        Ok(())
+    }
+
+    #[cmd(name = "continue")]
+    fn interpret_continue(ctx: &mut Context) -> Result<()> {
+        ctx.debugger.resume();
+        Ok(())
+    }
+
+    // ( -- n m ), currently allocated and peak heap bytes
+    #[cmd(name = "alloc-stats", stack)]
+    fn interpret_alloc_stats(stack: &mut Stack) -> Result<()> {
+        let Some(stats) = AllocStats::get() else {
+            anyhow::bail!("Allocation stats are not available");
+        };
+        stack.push_int(stats.allocated)?;
+        stack.push_int(stats.peak)
+    }
+
+    #[cmd(name = "trace-on")]
+    fn interpret_trace_on(ctx: &mut Context) -> Result<()> {
+        ctx.trace.get_or_insert(Context::DEFAULT_TRACE_ITEMS);
+        Ok(())
+    }
+
+    #[cmd(name = "trace-off")]
+    fn interpret_trace_off(ctx: &mut Context) -> Result<()> {
+        ctx.trace = None;
+        Ok(())
+    }
+
+    #[cmd(name = "profile-report")]
+    fn interpret_profile_report(ctx: &mut Context) -> Result<()> {
+        let Some(profiler) = &ctx.profiler else {
+            anyhow::bail!("Profiling is not enabled");
+        };
+        write!(ctx.stdout, "{}", profiler.display_report(&ctx.dictionary))?;
+        Ok(())
+    }
+
+    #[cmd(name = "profile-reset")]
+    fn interpret_profile_reset(ctx: &mut Context) -> Result<()> {
+        let Some(profiler) = &mut ctx.profiler else {
+            anyhow::bail!("Profiling is not enabled");
+        };
+        profiler.clear();
+        Ok(())
+    }
+
+    #[cmd(name = "assert-eq", stack)]
+    fn interpret_assert_eq(stack: &mut Stack) -> Result<()> {
+        let y = stack.pop()?;
+        let x = stack.pop()?;
+        anyhow::ensure!(
+            x.is_value_equal(y.as_ref()),
+            "Assertion failed: values are not equal\n{}",
+            x.display_diff(y.as_ref())
+        );
+        Ok(())
+    }
+
+    #[cmd(name = "(diff)", stack)]
+    fn interpret_diff_internal(stack: &mut Stack) -> Result<()> {
+        let y = stack.pop()?;
+        let x = stack.pop()?;
+        let string = x.display_diff(y.as_ref()).to_string();
+        stack.push(string)
+    }
+
     #[cmd(name = "(dump)", stack)]
     fn interpret_dump_internal(stack: &mut Stack) -> Result<()> {
         let string = stack.pop()?.display_dump().to_string();
@@ -122,27 +301,21 @@ impl DebugUtils {
 
     #[cmd(name = "(.)", stack)]
     fn interpret_dot_internal(stack: &mut Stack) -> Result<()> {
-        let string = stack.pop_int()?.to_string();
-        stack.push(string)
+        let int = stack.pop_int()?;
+        stack.push(DisplayInt::new(&int, 10).to_string())
     }
 
     #[cmd(name = "(x.)", stack, args(upper = false))]
     #[cmd(name = "(X.)", stack, args(upper = true))]
     fn interpret_dothex_internal(stack: &mut Stack, upper: bool) -> Result<()> {
         let int = stack.pop_int()?;
-        let string = if upper {
-            format!("{:x}", int.as_ref())
-        } else {
-            format!("{:X}", int.as_ref())
-        };
-        stack.push(string)
+        stack.push(DisplayInt::new(&int, 16).uppercase(upper).to_string())
     }
 
     #[cmd(name = "(b.)", stack)]
     fn interpret_dotbin_internal(stack: &mut Stack) -> Result<()> {
         let int = stack.pop_int()?;
-        let string = format!("{:b}", int.as_ref());
-        stack.push(string)
+        stack.push(DisplayInt::new(&int, 2).to_string())
     }
 }
 
@@ -153,3 +326,35 @@ const fn opt_space(space_after: bool) -> &'static str {
         ""
     }
 }
+
+/// Streams a page of the stack items to the output, from the bottom to the top.
+fn write_stack_page(ctx: &mut Context, page: usize, list: bool) -> Result<()> {
+    let items = ctx.stack.items();
+    let page_size = ctx.stack_page_size.unwrap_or(items.len()).max(1);
+    let end = items.len().saturating_sub(page.saturating_mul(page_size));
+    let start = end.saturating_sub(page_size);
+
+    let mut first = true;
+    if start > 0 {
+        write!(ctx.stdout, "({start} more below)")?;
+        first = false;
+    }
+    for item in &items[start..end] {
+        if !std::mem::take(&mut first) {
+            ctx.stdout.write_all(b" ")?;
+        }
+        if list {
+            write!(ctx.stdout, "{}", item.display_list())?;
+        } else {
+            let item = item.display_styled(&ctx.dictionary, ctx.dump_style);
+            write!(ctx.stdout, "{item}")?;
+        }
+    }
+    let above = items.len() - end;
+    if above > 0 {
+        let space = if first { "" } else { " " };
+        write!(ctx.stdout, "{space}({above} more above)")?;
+    }
+    writeln!(ctx.stdout)?;
+    Ok(())
+}
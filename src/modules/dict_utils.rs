@@ -1,8 +1,12 @@
-use anyhow::Result;
+use std::rc::Rc;
+
+use anyhow::{Context as _, Result};
 use everscale_types::cell::DefaultFinalizer;
 use everscale_types::dict::*;
 use everscale_types::prelude::*;
+use num_bigint::BigInt;
 
+use crate::core::cont::SeqCont;
 use crate::core::*;
 use crate::util::*;
 
@@ -150,8 +154,883 @@ impl DictUtils {
         }
         stack.push_bool(found)
     }
+
+    // === Iteration and range queries ===
+
+    #[cmd(name = "sdict-next", stack, args(key = KeyMode::Slice, dir = Direction::Next, eq = false))]
+    #[cmd(name = "sdict-nexteq", stack, args(key = KeyMode::Slice, dir = Direction::Next, eq = true))]
+    #[cmd(name = "sdict-prev", stack, args(key = KeyMode::Slice, dir = Direction::Prev, eq = false))]
+    #[cmd(name = "sdict-preveq", stack, args(key = KeyMode::Slice, dir = Direction::Prev, eq = true))]
+    #[cmd(name = "udict-next", stack, args(key = KeyMode::Unsigned, dir = Direction::Next, eq = false))]
+    #[cmd(name = "udict-nexteq", stack, args(key = KeyMode::Unsigned, dir = Direction::Next, eq = true))]
+    #[cmd(name = "udict-prev", stack, args(key = KeyMode::Unsigned, dir = Direction::Prev, eq = false))]
+    #[cmd(name = "udict-preveq", stack, args(key = KeyMode::Unsigned, dir = Direction::Prev, eq = true))]
+    #[cmd(name = "idict-next", stack, args(key = KeyMode::Signed, dir = Direction::Next, eq = false))]
+    #[cmd(name = "idict-nexteq", stack, args(key = KeyMode::Signed, dir = Direction::Next, eq = true))]
+    #[cmd(name = "idict-prev", stack, args(key = KeyMode::Signed, dir = Direction::Prev, eq = false))]
+    #[cmd(name = "idict-preveq", stack, args(key = KeyMode::Signed, dir = Direction::Prev, eq = true))]
+    fn interpret_dict_get_near(
+        stack: &mut Stack,
+        key: KeyMode,
+        dir: Direction,
+        eq: bool,
+    ) -> Result<()> {
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let cell = pop_maybe_cell(stack)?;
+        let key_bits = pop_dict_key_bits(stack, key, bits)?;
+
+        let search = DictSearch {
+            bits,
+            signed: matches!(key, KeyMode::Signed),
+            dir,
+        };
+        match search.find(&cell, Some(&key_bits), eq)? {
+            Some(entry) => {
+                push_dict_key(stack, &entry.key, key)?;
+                stack.push(entry.value)?;
+                stack.push_bool(true)
+            }
+            None => stack.push_bool(false),
+        }
+    }
+
+    // ( D n e -- f ), where e is ( k v -- f ) and stops the iteration by returning true
+    #[cmd(name = "sdict-foreach-until", tail, args(key = KeyMode::Slice))]
+    #[cmd(name = "udict-foreach-until", tail, args(key = KeyMode::Unsigned))]
+    #[cmd(name = "idict-foreach-until", tail, args(key = KeyMode::Signed))]
+    fn interpret_dict_foreach_until(ctx: &mut Context, key: KeyMode) -> Result<Option<Cont>> {
+        let body = ctx.stack.pop_cont()?;
+        let bits = ctx.stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let root = pop_maybe_cell(&mut ctx.stack)?;
+        Ok(Some(Rc::new(DictForeachCont {
+            root,
+            bits,
+            key_mode: key,
+            extra: None,
+            body: *body,
+            after: ctx.next.take(),
+            last_key: None,
+            until: true,
+        })))
+    }
+
+    // ( D n e -- ), where e is ( k v -- )
+    #[cmd(name = "sdict-foreach", tail, args(key = KeyMode::Slice))]
+    #[cmd(name = "udict-foreach", tail, args(key = KeyMode::Unsigned))]
+    #[cmd(name = "idict-foreach", tail, args(key = KeyMode::Signed))]
+    fn interpret_dict_foreach(ctx: &mut Context, key: KeyMode) -> Result<Option<Cont>> {
+        let body = ctx.stack.pop_cont()?;
+        let bits = ctx.stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let root = pop_maybe_cell(&mut ctx.stack)?;
+        Ok(Some(Rc::new(DictForeachCont {
+            root,
+            bits,
+            key_mode: key,
+            extra: None,
+            body: *body,
+            after: ctx.next.take(),
+            last_key: None,
+            until: false,
+        })))
+    }
+
+    // ( D n k1 k2 -- t ) with [ k v ] pairs for all k1 <= k <= k2
+    #[cmd(name = "sdict-range", stack, args(key = KeyMode::Slice))]
+    #[cmd(name = "udict-range", stack, args(key = KeyMode::Unsigned))]
+    #[cmd(name = "idict-range", stack, args(key = KeyMode::Signed))]
+    fn interpret_dict_range(stack: &mut Stack, key: KeyMode) -> Result<()> {
+        let to = stack.pop()?;
+        let from = stack.pop()?;
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let cell = pop_maybe_cell(stack)?;
+
+        stack.push_raw(to)?;
+        let to = pop_dict_key_bits(stack, key, bits)?;
+        stack.push_raw(from)?;
+        let mut last = pop_dict_key_bits(stack, key, bits)?;
+
+        let search = DictSearch {
+            bits,
+            signed: matches!(key, KeyMode::Signed),
+            dir: Direction::Next,
+        };
+
+        let mut result = StackTuple::new();
+        let mut eq = true;
+        while let Some(entry) = search.find(&cell, Some(&last), eq)? {
+            if search.compare(&entry.key, &to).is_gt() {
+                break;
+            }
+            let mut key_value = Stack::new(None);
+            push_dict_key(&mut key_value, &entry.key, key)?;
+            key_value.push(entry.value)?;
+            result.push(Box::new(key_value.items().to_vec()));
+
+            last = entry.key;
+            eq = false;
+        }
+        stack.push(result)
+    }
+
+    // ( D n -- t ) with [ k v ] pairs for all entries in the key order
+    #[cmd(name = "sdict>", stack, args(key = KeyMode::Slice))]
+    #[cmd(name = "udict>", stack, args(key = KeyMode::Unsigned))]
+    #[cmd(name = "idict>", stack, args(key = KeyMode::Signed))]
+    fn interpret_dict_to_tuple(stack: &mut Stack, key: KeyMode) -> Result<()> {
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let cell = pop_maybe_cell(stack)?;
+
+        let search = DictSearch {
+            bits,
+            signed: matches!(key, KeyMode::Signed),
+            dir: Direction::Next,
+        };
+
+        let mut result = StackTuple::new();
+        let mut last = None;
+        while let Some(entry) = search.find(&cell, last.as_deref(), false)? {
+            let mut key_value = Stack::new(None);
+            push_dict_key(&mut key_value, &entry.key, key)?;
+            key_value.push(entry.value)?;
+            result.push(Box::new(key_value.items().to_vec()));
+            last = Some(entry.key);
+        }
+        stack.push(result)
+    }
+
+    // ( t n -- D ) from [ k v ] pairs with slice or builder values,
+    // later pairs replace earlier ones with the same key
+    #[cmd(name = ">sdict", stack, args(key = KeyMode::Slice))]
+    #[cmd(name = ">udict", stack, args(key = KeyMode::Unsigned))]
+    #[cmd(name = ">idict", stack, args(key = KeyMode::Signed))]
+    fn interpret_tuple_to_dict(stack: &mut Stack, key: KeyMode) -> Result<()> {
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let pairs = stack.pop_tuple()?;
+
+        let mut root = None;
+        for pair in *pairs {
+            let Ok([k, v]) = <[_; 2]>::try_from(*pair.into_tuple()?) else {
+                anyhow::bail!("Expected a [ key value ] pair");
+            };
+
+            let mut item = Stack::new(None);
+            item.push_raw(k)?;
+            let key_bits = pop_dict_key_bits(&mut item, key, bits)?;
+
+            let value = match v.ty() {
+                StackValueType::Builder => OwnedCellSlice::new(v.into_builder()?.build()?),
+                _ => *v.into_slice()?,
+            };
+            root = dict_set_bits(&root, &key_bits, &value)?;
+        }
+        push_maybe_cell(stack, root)
+    }
+
+    // ( D1 D2 n -- D ), entries of D2 replace entries of D1 with the same key
+    #[cmd(name = "dict-merge", stack)]
+    fn interpret_dict_merge(stack: &mut Stack) -> Result<()> {
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let other = pop_maybe_cell(stack)?;
+        let mut root = pop_maybe_cell(stack)?;
+
+        let search = DictSearch {
+            bits,
+            signed: false,
+            dir: Direction::Next,
+        };
+
+        let mut last = None;
+        while let Some(entry) = search.find(&other, last.as_deref(), false)? {
+            root = dict_set_bits(&root, &entry.key, &entry.value)?;
+            last = Some(entry.key);
+        }
+        push_maybe_cell(stack, root)
+    }
+
+    // === Augmented dictionaries ===
+
+    // Extra types are specified either by name or by a fixed bit length
+
+    // ( s t -- D e s' ), where e is the root extra
+    #[cmd(name = "augdict@+", stack)]
+    fn interpret_load_aug_dict(stack: &mut Stack) -> Result<()> {
+        let extra = pop_aug_extra(stack)?;
+        let mut cs_raw = stack.pop_slice()?;
+        let mut cs = cs_raw.apply()?;
+        let cell = Option::<Cell>::load_from(&mut cs)?;
+        cs_raw.set_range(cs.range());
+
+        let (extra, rest) = extra.split(*cs_raw)?;
+        push_maybe_cell(stack, cell)?;
+        stack.push(extra)?;
+        stack.push(rest)
+    }
+
+    // ( k D n t -- e v -1 | 0 )
+    #[cmd(name = "saugdict@", stack, args(key = KeyMode::Slice))]
+    #[cmd(name = "uaugdict@", stack, args(key = KeyMode::Unsigned))]
+    #[cmd(name = "iaugdict@", stack, args(key = KeyMode::Signed))]
+    fn interpret_aug_dict_get(stack: &mut Stack, key: KeyMode) -> Result<()> {
+        let extra = pop_aug_extra(stack)?;
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let cell = pop_maybe_cell(stack)?;
+        let key = pop_dict_key_bits(stack, key, bits)?;
+
+        let search = DictSearch {
+            bits,
+            signed: false,
+            dir: Direction::Next,
+        };
+        match search.find(&cell, Some(&key), true)? {
+            Some(entry) if entry.key == key => {
+                let (extra, value) = extra.split(entry.value)?;
+                stack.push(extra)?;
+                stack.push(value)?;
+                stack.push_bool(true)
+            }
+            _ => stack.push_bool(false),
+        }
+    }
+
+    // ( D n t e -- f ), where e is ( k x v -- f )
+    #[cmd(name = "saugdict-foreach-until", tail, args(key = KeyMode::Slice))]
+    #[cmd(name = "uaugdict-foreach-until", tail, args(key = KeyMode::Unsigned))]
+    #[cmd(name = "iaugdict-foreach-until", tail, args(key = KeyMode::Signed))]
+    fn interpret_aug_dict_foreach_until(ctx: &mut Context, key: KeyMode) -> Result<Option<Cont>> {
+        let body = ctx.stack.pop_cont()?;
+        let extra = pop_aug_extra(&mut ctx.stack)?;
+        let bits = ctx.stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let root = pop_maybe_cell(&mut ctx.stack)?;
+        Ok(Some(Rc::new(DictForeachCont {
+            root,
+            bits,
+            key_mode: key,
+            extra: Some(extra),
+            body: *body,
+            after: ctx.next.take(),
+            last_key: None,
+            until: true,
+        })))
+    }
+
+    // === Prefix dictionaries ===
+
+    #[cmd(name = "pfxdict!", stack, args(mode = SetMode::Set))]
+    #[cmd(name = "pfxdict!+", stack, args(mode = SetMode::Add))]
+    fn interpret_pfx_dict_set(stack: &mut Stack, mode: SetMode) -> Result<()> {
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let cell = pop_maybe_cell(stack)?;
+        let key = pop_pfx_key(stack, bits)?;
+        let value = stack.pop_slice()?;
+
+        let mut entries = pfx_dict_entries(&cell, bits)?;
+        let conflict = entries.iter().any(|(other, _)| {
+            let common = std::cmp::min(other.len(), key.len());
+            other[..common] == key[..common] && (other.len() != key.len() || mode == SetMode::Add)
+        });
+
+        if conflict {
+            push_maybe_cell(stack, cell)?;
+            return stack.push_bool(false);
+        }
+
+        entries.retain(|(other, _)| *other != key);
+        entries.push((key, *value));
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        push_maybe_cell(stack, build_pfx_dict(&entries, bits)?)?;
+        stack.push_bool(true)
+    }
+
+    #[cmd(name = "pfxdict@", stack)]
+    fn interpret_pfx_dict_get(stack: &mut Stack) -> Result<()> {
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let cell = pop_maybe_cell(stack)?;
+        let key = pop_pfx_key(stack, bits)?;
+
+        match pfx_dict_find(&cell, bits, &key)? {
+            Some((len, value)) if len == key.len() => {
+                stack.push(value)?;
+                stack.push_bool(true)
+            }
+            _ => stack.push_bool(false),
+        }
+    }
+
+    #[cmd(name = "pfxdict-", stack)]
+    fn interpret_pfx_dict_remove(stack: &mut Stack) -> Result<()> {
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let cell = pop_maybe_cell(stack)?;
+        let key = pop_pfx_key(stack, bits)?;
+
+        let mut entries = pfx_dict_entries(&cell, bits)?;
+        let len = entries.len();
+        entries.retain(|(other, _)| *other != key);
+        if entries.len() == len {
+            push_maybe_cell(stack, cell)?;
+            return stack.push_bool(false);
+        }
+
+        push_maybe_cell(stack, build_pfx_dict(&entries, bits)?)?;
+        stack.push_bool(true)
+    }
+
+    // ( s D n -- s' v s'' -1 | s 0 ), where s' is the matched prefix of s
+    #[cmd(name = "pfxdict-match", stack)]
+    fn interpret_pfx_dict_match(stack: &mut Stack) -> Result<()> {
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)? as u16;
+        let cell = pop_maybe_cell(stack)?;
+        let mut input = stack.pop_slice()?;
+
+        let cs = input.apply()?;
+        let len = std::cmp::min(cs.remaining_bits(), bits);
+        let key = (0..len)
+            .map(|i| cs.get_bit(i))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match pfx_dict_find(&cell, bits, &key)? {
+            Some((len, value)) => {
+                let prefix = cs.get_prefix(len as u16, 0).range();
+                let mut rest = cs;
+                rest.try_advance(len as u16, 0);
+
+                let mut matched = input.clone();
+                matched.set_range(prefix);
+                input.set_range(rest.range());
+
+                stack.push_raw(matched)?;
+                stack.push(value)?;
+                stack.push_raw(input)?;
+                stack.push_bool(true)
+            }
+            None => {
+                stack.push_raw(input)?;
+                stack.push_bool(false)
+            }
+        }
+    }
+}
+
+fn pop_pfx_key(stack: &mut Stack, bits: u16) -> Result<Vec<bool>> {
+    let key = stack.pop_slice()?;
+    let key = key.apply()?;
+    anyhow::ensure!(
+        key.remaining_bits() <= bits,
+        "Prefix dictionary key is longer than {bits} bits"
+    );
+    (0..key.remaining_bits())
+        .map(|i| key.get_bit(i).map_err(From::from))
+        .collect()
+}
+
+/// Finds an entry whose key is a prefix of the specified bits,
+/// returns its key length and value.
+fn pfx_dict_find(
+    root: &Option<Cell>,
+    bits: u16,
+    key: &[bool],
+) -> Result<Option<(usize, OwnedCellSlice)>> {
+    let Some(mut cell) = root.clone() else {
+        return Ok(None);
+    };
+
+    let mut prefix = Vec::with_capacity(bits as usize);
+    let mut remaining = bits;
+    loop {
+        let start = prefix.len();
+        let mut cs = DynCell::as_slice(cell.as_ref())?;
+        read_label(&mut cs, remaining, &mut prefix)?;
+
+        let label_len = prefix.len() - start;
+        if prefix.len() > key.len() || prefix[start..] != key[start..prefix.len()] {
+            return Ok(None);
+        }
+        remaining -= label_len as u16;
+
+        if !cs.load_bit()? {
+            // phmn_leaf$0
+            return Ok(Some((
+                prefix.len(),
+                OwnedCellSlice::from((cell.clone(), cs.range())),
+            )));
+        }
+
+        // phmn_fork$1
+        anyhow::ensure!(remaining > 0, "Invalid prefix dictionary fork");
+        let Some(bit) = key.get(prefix.len()).copied() else {
+            return Ok(None);
+        };
+        prefix.push(bit);
+        remaining -= 1;
+        cell = load_child(&cs, bit)?;
+    }
 }
 
+type PfxDictEntry = (Vec<bool>, OwnedCellSlice);
+
+fn pfx_dict_entries(root: &Option<Cell>, bits: u16) -> Result<Vec<PfxDictEntry>> {
+    fn visit(
+        cell: &Cell,
+        remaining: u16,
+        prefix: &mut Vec<bool>,
+        entries: &mut Vec<PfxDictEntry>,
+    ) -> Result<()> {
+        let start = prefix.len();
+        let mut cs = DynCell::as_slice(cell.as_ref())?;
+        read_label(&mut cs, remaining, prefix)?;
+        let remaining = remaining - (prefix.len() - start) as u16;
+
+        if !cs.load_bit()? {
+            entries.push((
+                prefix.clone(),
+                OwnedCellSlice::from((cell.clone(), cs.range())),
+            ));
+        } else {
+            anyhow::ensure!(remaining > 0, "Invalid prefix dictionary fork");
+            for bit in [false, true] {
+                prefix.push(bit);
+                visit(&load_child(&cs, bit)?, remaining - 1, prefix, entries)?;
+                prefix.pop();
+            }
+        }
+
+        prefix.truncate(start);
+        Ok(())
+    }
+
+    let mut entries = Vec::new();
+    if let Some(root) = root {
+        visit(root, bits, &mut Vec::new(), &mut entries)?;
+    }
+    Ok(entries)
+}
+
+/// Builds a prefix dictionary from the entries sorted by keys.
+fn build_pfx_dict(entries: &[PfxDictEntry], bits: u16) -> Result<Option<Cell>> {
+    fn build(entries: &[PfxDictEntry], offset: usize, remaining: u16) -> Result<Cell> {
+        let (first, _) = &entries[0];
+        let (last, _) = &entries[entries.len() - 1];
+
+        // Entries are sorted, so the common prefix of the first and
+        // the last keys is the common prefix of all keys
+        let label_len = first[offset..]
+            .iter()
+            .zip(&last[offset..])
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut builder = CellBuilder::new();
+        write_label(&mut builder, &first[offset..offset + label_len], remaining)?;
+
+        let offset = offset + label_len;
+        let remaining = remaining - label_len as u16;
+        if entries.len() == 1 {
+            builder.store_bit_zero()?;
+            builder.store_slice(entries[0].1.apply()?)?;
+        } else {
+            let split = entries.partition_point(|(key, _)| !key[offset]);
+            builder.store_bit_one()?;
+            builder.store_reference(build(&entries[..split], offset + 1, remaining - 1)?)?;
+            builder.store_reference(build(&entries[split..], offset + 1, remaining - 1)?)?;
+        }
+        Ok(builder.build()?)
+    }
+
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    build(entries, 0, bits).map(Some)
+}
+
+/// Writes `HmLabel ~l m` using the shortest form.
+fn write_label(builder: &mut CellBuilder, label: &[bool], m: u16) -> Result<()> {
+    let len = label.len() as u16;
+    let bits_for_len = (16 - m.leading_zeros()) as u16;
+    let is_same = label.windows(2).all(|w| w[0] == w[1]);
+
+    if len > 1 && is_same && bits_for_len < 2 * len - 1 {
+        // hml_same$11
+        builder.store_small_uint(0b11, 2)?;
+        builder.store_bit(label[0])?;
+        builder.store_uint(len as u64, bits_for_len)?;
+        return Ok(());
+    }
+
+    if bits_for_len < len {
+        // hml_long$10
+        builder.store_small_uint(0b10, 2)?;
+        builder.store_uint(len as u64, bits_for_len)?;
+    } else {
+        // hml_short$0
+        builder.store_bit_zero()?;
+        for _ in 0..len {
+            builder.store_bit_one()?;
+        }
+        builder.store_bit_zero()?;
+    }
+    for bit in label {
+        builder.store_bit(*bit)?;
+    }
+    Ok(())
+}
+
+/// Type of the `extra` part of augmented dictionary nodes.
+#[derive(Clone, Copy)]
+enum AugExtra {
+    Bits(u16),
+    Grams,
+    CurrencyCollection,
+    DepthBalanceInfo,
+    ImportFees,
+    ShardFeeCreated,
+}
+
+impl AugExtra {
+    fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "Grams" => Self::Grams,
+            "CurrencyCollection" => Self::CurrencyCollection,
+            "DepthBalanceInfo" => Self::DepthBalanceInfo,
+            "ImportFees" => Self::ImportFees,
+            "ShardFeeCreated" => Self::ShardFeeCreated,
+            _ => anyhow::bail!("Unknown augmentation type `{name}`"),
+        })
+    }
+
+    /// Splits the leaf slice into the extra and the value.
+    fn split(&self, slice: OwnedCellSlice) -> Result<(OwnedCellSlice, OwnedCellSlice)> {
+        let cs = slice.apply()?;
+        let mut rest = cs;
+        self.skip(&mut rest)?;
+        let extra = cs.get_prefix(
+            cs.remaining_bits() - rest.remaining_bits(),
+            cs.remaining_refs() - rest.remaining_refs(),
+        );
+
+        let (extra_range, rest_range) = (extra.range(), rest.range());
+        let mut extra = slice.clone();
+        extra.set_range(extra_range);
+        let mut value = slice;
+        value.set_range(rest_range);
+        Ok((extra, value))
+    }
+
+    fn skip(&self, cs: &mut CellSlice<'_>) -> Result<()> {
+        fn skip_grams(cs: &mut CellSlice<'_>) -> Result<()> {
+            let len = cs.load_small_uint(4)? as u16;
+            anyhow::ensure!(cs.try_advance(len * 8, 0), "Invalid Grams value");
+            Ok(())
+        }
+
+        fn skip_currency_collection(cs: &mut CellSlice<'_>) -> Result<()> {
+            skip_grams(cs)?;
+            let has_other = cs.load_bit()?;
+            anyhow::ensure!(
+                cs.try_advance(0, has_other as u8),
+                "Invalid CurrencyCollection value"
+            );
+            Ok(())
+        }
+
+        match self {
+            Self::Bits(bits) => {
+                anyhow::ensure!(cs.try_advance(*bits, 0), "Augmentation is too short");
+            }
+            Self::Grams => skip_grams(cs)?,
+            Self::CurrencyCollection => skip_currency_collection(cs)?,
+            Self::DepthBalanceInfo => {
+                // split_depth:(#<= 30)
+                cs.load_small_uint(5)?;
+                skip_currency_collection(cs)?;
+            }
+            Self::ImportFees => {
+                skip_grams(cs)?;
+                skip_currency_collection(cs)?;
+            }
+            Self::ShardFeeCreated => {
+                skip_currency_collection(cs)?;
+                skip_currency_collection(cs)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn pop_aug_extra(stack: &mut Stack) -> Result<AugExtra> {
+    let value = stack.pop()?;
+    if value.ty() == StackValueType::String {
+        AugExtra::from_name(value.as_string()?)
+    } else {
+        stack.push_raw(value)?;
+        let bits = stack.pop_smallint_range(0, MAX_KEY_BITS)?;
+        Ok(AugExtra::Bits(bits as u16))
+    }
+}
+
+struct DictForeachCont {
+    root: Option<Cell>,
+    bits: u16,
+    key_mode: KeyMode,
+    extra: Option<AugExtra>,
+    body: Cont,
+    after: Option<Cont>,
+    last_key: Option<Vec<bool>>,
+    /// Whether the body returns a flag which stops the iteration.
+    until: bool,
+}
+
+impl ContImpl for DictForeachCont {
+    fn run(mut self: Rc<Self>, ctx: &mut Context) -> Result<Option<Cont>> {
+        if self.until && self.last_key.is_some() && ctx.stack.pop_bool()? {
+            ctx.stack.push_bool(true)?;
+            return Ok(self.take_after());
+        }
+
+        let search = DictSearch {
+            bits: self.bits,
+            signed: matches!(self.key_mode, KeyMode::Signed),
+            dir: Direction::Next,
+        };
+        let Some(entry) = search.find(&self.root, self.last_key.as_deref(), false)? else {
+            if self.until {
+                ctx.stack.push_bool(false)?;
+            }
+            return Ok(self.take_after());
+        };
+
+        push_dict_key(&mut ctx.stack, &entry.key, self.key_mode)?;
+        match &self.extra {
+            Some(extra) => {
+                let (extra, value) = extra.split(entry.value)?;
+                ctx.stack.push(extra)?;
+                ctx.stack.push(value)?;
+            }
+            None => ctx.stack.push(entry.value)?,
+        }
+
+        let body = self.body.clone();
+        let next = match Rc::get_mut(&mut self) {
+            Some(this) => {
+                ctx.insert_before_next(&mut this.after);
+                this.last_key = Some(entry.key);
+                self
+            }
+            None => Rc::new(Self {
+                root: self.root.clone(),
+                bits: self.bits,
+                key_mode: self.key_mode,
+                extra: self.extra,
+                body: self.body.clone(),
+                after: SeqCont::make(self.after.clone(), ctx.next.take()),
+                last_key: Some(entry.key),
+                until: self.until,
+            }),
+        };
+        ctx.next = Some(next);
+        Ok(Some(body))
+    }
+
+    fn up(&self) -> Option<&Cont> {
+        self.after.as_ref()
+    }
+
+    fn fmt_name(&self, _: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<dict foreach loop>")
+    }
+
+    fn fmt_dump(&self, d: &Dictionary, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<dict foreach loop:> ")?;
+        ContImpl::fmt_dump(self.body.as_ref(), d, f)
+    }
+}
+
+impl DictForeachCont {
+    fn take_after(mut self: Rc<Self>) -> Option<Cont> {
+        match Rc::get_mut(&mut self) {
+            Some(this) => this.after.take(),
+            None => self.after.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Next,
+    Prev,
+}
+
+struct DictEntry {
+    key: Vec<bool>,
+    value: OwnedCellSlice,
+}
+
+/// Key-ordered search over a raw `HashmapE n X` without loading all entries.
+struct DictSearch {
+    bits: u16,
+    signed: bool,
+    dir: Direction,
+}
+
+impl DictSearch {
+    /// Finds the nearest entry after the key in the search direction,
+    /// or the first entry in this direction if there is no key.
+    fn find(
+        &self,
+        root: &Option<Cell>,
+        key: Option<&[bool]>,
+        eq: bool,
+    ) -> Result<Option<DictEntry>> {
+        let Some(root) = root else {
+            return Ok(None);
+        };
+        let mut prefix = Vec::with_capacity(self.bits as usize);
+        match key {
+            Some(key) => self.find_near(root, &mut prefix, key, eq),
+            None => self.find_first(root, &mut prefix).map(Some),
+        }
+    }
+
+    fn compare(&self, left: &[bool], right: &[bool]) -> std::cmp::Ordering {
+        for (pos, (l, r)) in left.iter().zip(right).enumerate() {
+            if l != r {
+                return self.rank(*l, pos).cmp(&self.rank(*r, pos));
+            }
+        }
+        std::cmp::Ordering::Equal
+    }
+
+    fn find_near(
+        &self,
+        cell: &Cell,
+        prefix: &mut Vec<bool>,
+        key: &[bool],
+        eq: bool,
+    ) -> Result<Option<DictEntry>> {
+        let start = prefix.len();
+        let mut cs = DynCell::as_slice(cell.as_ref())?;
+        read_label(&mut cs, self.bits - start as u16, prefix)?;
+
+        for pos in start..prefix.len() {
+            if prefix[pos] != key[pos] {
+                return if self.is_beyond(prefix[pos], key[pos], pos) {
+                    self.find_first_after_label(cell, cs, prefix).map(Some)
+                } else {
+                    Ok(None)
+                };
+            }
+        }
+
+        if prefix.len() == self.bits as usize {
+            return Ok(eq.then(|| make_entry(prefix, cell, &cs)));
+        }
+
+        let pos = prefix.len();
+        let bit = key[pos];
+
+        prefix.push(bit);
+        if let Some(entry) = self.find_near(&load_child(&cs, bit)?, prefix, key, eq)? {
+            return Ok(Some(entry));
+        }
+        prefix.truncate(pos);
+
+        if self.is_beyond(!bit, bit, pos) {
+            prefix.push(!bit);
+            self.find_first(&load_child(&cs, !bit)?, prefix).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn find_first(&self, cell: &Cell, prefix: &mut Vec<bool>) -> Result<DictEntry> {
+        let mut cs = DynCell::as_slice(cell.as_ref())?;
+        read_label(&mut cs, self.bits - prefix.len() as u16, prefix)?;
+        self.find_first_after_label(cell, cs, prefix)
+    }
+
+    fn find_first_after_label(
+        &self,
+        cell: &Cell,
+        cs: CellSlice<'_>,
+        prefix: &mut Vec<bool>,
+    ) -> Result<DictEntry> {
+        if prefix.len() == self.bits as usize {
+            return Ok(make_entry(prefix, cell, &cs));
+        }
+
+        let pos = prefix.len();
+        let bit = match self.dir {
+            Direction::Next => self.rank(true, pos) == 0,
+            Direction::Prev => self.rank(true, pos) == 1,
+        };
+        prefix.push(bit);
+        self.find_first(&load_child(&cs, bit)?, prefix)
+    }
+
+    /// Returns whether the subtree with the `bit` at `pos` lies
+    /// after the key with `key_bit` at `pos` in the search direction.
+    fn is_beyond(&self, bit: bool, key_bit: bool, pos: usize) -> bool {
+        let (bit, key_bit) = (self.rank(bit, pos), self.rank(key_bit, pos));
+        match self.dir {
+            Direction::Next => bit > key_bit,
+            Direction::Prev => bit < key_bit,
+        }
+    }
+
+    /// Signed keys have an inverted order of the sign bit.
+    fn rank(&self, bit: bool, pos: usize) -> u8 {
+        (bit != (self.signed && pos == 0)) as u8
+    }
+}
+
+fn make_entry(prefix: &[bool], cell: &Cell, value: &CellSlice<'_>) -> DictEntry {
+    DictEntry {
+        key: prefix.to_vec(),
+        value: OwnedCellSlice::from((cell.clone(), value.range())),
+    }
+}
+
+fn load_child(cs: &CellSlice<'_>, bit: bool) -> Result<Cell> {
+    cs.cell()
+        .reference_cloned(bit as u8)
+        .context("Invalid dictionary fork")
+}
+
+/// Reads `HmLabel ~l m` and appends its bits to the prefix.
+fn read_label(cs: &mut CellSlice<'_>, m: u16, prefix: &mut Vec<bool>) -> Result<()> {
+    let bits_for_len = (16 - m.leading_zeros()) as u16;
+    if cs.is_data_empty() && bits_for_len == 0 {
+        return Ok(());
+    }
+
+    let len = if !cs.load_bit()? {
+        // hml_short$0
+        let mut len = 0;
+        while cs.load_bit()? {
+            len += 1;
+        }
+        for _ in 0..len {
+            prefix.push(cs.load_bit()?);
+        }
+        len
+    } else if !cs.load_bit()? {
+        // hml_long$10
+        let len = cs.load_uint(bits_for_len)? as u16;
+        for _ in 0..len {
+            prefix.push(cs.load_bit()?);
+        }
+        len
+    } else {
+        // hml_same$11
+        let bit = cs.load_bit()?;
+        let len = cs.load_uint(bits_for_len)? as u16;
+        prefix.extend(std::iter::repeat(bit).take(len as usize));
+        len
+    };
+    anyhow::ensure!(len <= m, "Invalid dictionary label");
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
 enum KeyMode {
     Slice,
     Unsigned,
@@ -174,6 +1053,66 @@ fn pop_maybe_cell(stack: &mut Stack) -> Result<Option<Cell>> {
     })
 }
 
+fn pop_dict_key_bits(stack: &mut Stack, key_mode: KeyMode, bits: u16) -> Result<Vec<bool>> {
+    let key = pop_dict_key(stack, key_mode, bits)?;
+    let key = key.apply()?;
+    anyhow::ensure!(
+        key.remaining_bits() >= bits,
+        "Not enough bits for a dictionary key"
+    );
+    (0..bits)
+        .map(|i| key.get_bit(i).map_err(From::from))
+        .collect()
+}
+
+fn dict_set_bits(
+    root: &Option<Cell>,
+    key: &[bool],
+    value: &OwnedCellSlice,
+) -> Result<Option<Cell>> {
+    let mut builder = CellBuilder::new();
+    for bit in key {
+        builder.store_bit(*bit)?;
+    }
+    let key_cell = builder.build()?;
+    let mut key_cs = key_cell.as_ref().as_slice()?;
+
+    dict_insert(
+        root,
+        &mut key_cs,
+        key.len() as u16,
+        &value.apply()?,
+        SetMode::Set,
+        &mut Cell::default_finalizer(),
+    )
+    .map_err(From::from)
+}
+
+fn push_dict_key(stack: &mut Stack, key: &[bool], key_mode: KeyMode) -> Result<()> {
+    match key_mode {
+        KeyMode::Slice => {
+            let mut builder = CellBuilder::new();
+            for bit in key {
+                builder.store_bit(*bit)?;
+            }
+            stack.push(OwnedCellSlice::new(builder.build()?))
+        }
+        KeyMode::Unsigned | KeyMode::Signed => {
+            let mut int = BigInt::default();
+            for bit in key {
+                int <<= 1;
+                if *bit {
+                    int += 1;
+                }
+            }
+            if matches!(key_mode, KeyMode::Signed) && key.first() == Some(&true) {
+                int -= BigInt::from(1) << key.len();
+            }
+            stack.push_int(int)
+        }
+    }
+}
+
 fn pop_dict_key(stack: &mut Stack, key_mode: KeyMode, bits: u16) -> Result<OwnedCellSlice> {
     let signed = match key_mode {
         KeyMode::Slice => return Ok(*stack.pop_slice()?),
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use everscale_types::prelude::*;
+use num_bigint::BigInt;
+use sha2::Digest;
+
+use crate::core::*;
+use crate::util::*;
+
+pub struct DnsUtils;
+
+#[fift_module]
+impl DnsUtils {
+    #[cmd(name = "dns-category", stack)]
+    fn interpret_dns_category(stack: &mut Stack) -> Result<()> {
+        let name = stack.pop_string()?;
+        // Empty category means "all categories"
+        let category = if name.is_empty() {
+            BigInt::default()
+        } else {
+            BigInt::from_bytes_be(
+                num_bigint::Sign::Plus,
+                &sha2::Sha256::digest(name.as_bytes()),
+            )
+        };
+        stack.push_int(category)
+    }
+
+    #[cmd(name = "dns-wallet-record", stack, args(tag = DNS_SMC_ADDRESS_TAG))]
+    #[cmd(name = "dns-next-resolver-record", stack, args(tag = DNS_NEXT_RESOLVER_TAG))]
+    fn interpret_dns_address_record(stack: &mut Stack, tag: u16) -> Result<()> {
+        let addr = stack.pop_int()?;
+        let workchain = stack.pop_i8_checked()?;
+
+        let mut builder = CellBuilder::new();
+        builder.store_u16(tag)?;
+        store_std_addr(&mut builder, workchain, &addr)?;
+        if tag == DNS_SMC_ADDRESS_TAG {
+            // flags (no capabilities list)
+            builder.store_u8(0)?;
+        }
+        stack.push(builder.build()?)
+    }
+
+    #[cmd(name = "dns-adnl-record", stack, args(tag = DNS_ADNL_ADDRESS_TAG))]
+    #[cmd(name = "dns-storage-record", stack, args(tag = DNS_STORAGE_ADDRESS_TAG))]
+    fn interpret_dns_hash_record(stack: &mut Stack, tag: u16) -> Result<()> {
+        let hash = stack.pop_bytes()?;
+        anyhow::ensure!(
+            hash.len() == 32,
+            "Expected a 32-byte hash, got {} bytes",
+            hash.len()
+        );
+
+        let mut builder = CellBuilder::new();
+        builder.store_u16(tag)?;
+        builder.store_raw(&hash, 256)?;
+        if tag == DNS_ADNL_ADDRESS_TAG {
+            // flags (no protocols list)
+            builder.store_u8(0)?;
+        }
+        stack.push(builder.build()?)
+    }
+}
+
+const DNS_SMC_ADDRESS_TAG: u16 = 0x9fd3;
+const DNS_NEXT_RESOLVER_TAG: u16 = 0xba93;
+const DNS_ADNL_ADDRESS_TAG: u16 = 0xad01;
+const DNS_STORAGE_ADDRESS_TAG: u16 = 0x7473;
@@ -0,0 +1,378 @@
+use anyhow::Result;
+use num_traits::ToPrimitive;
+
+use crate::core::*;
+
+pub struct FecUtils;
+
+#[fift_module]
+impl FecUtils {
+    // === GF(2^8) ===
+
+    #[cmd(name = "gf256*", stack)]
+    fn interpret_gf_mul(stack: &mut Stack) -> Result<()> {
+        let y = stack.pop_smallint_range(0, 255)? as u8;
+        let x = stack.pop_smallint_range(0, 255)? as u8;
+        stack.push_int(gf_mul(x, y))
+    }
+
+    #[cmd(name = "gf256/", stack)]
+    fn interpret_gf_div(stack: &mut Stack) -> Result<()> {
+        let y = stack.pop_smallint_range(1, 255)? as u8;
+        let x = stack.pop_smallint_range(0, 255)? as u8;
+        stack.push_int(gf_div(x, y))
+    }
+
+    // === Reed-Solomon ===
+
+    #[cmd(name = "rs-encode", stack)]
+    fn interpret_rs_encode(stack: &mut Stack) -> Result<()> {
+        let nsym = stack.pop_smallint_range(1, 254)? as usize;
+        let mut data = stack.pop_bytes()?;
+        check_block_len(data.len(), nsym)?;
+
+        let parity = rs_encode(&data, nsym);
+        data.extend_from_slice(&parity);
+        stack.push_raw(data)
+    }
+
+    #[cmd(name = "rs-check", stack)]
+    fn interpret_rs_check(stack: &mut Stack) -> Result<()> {
+        let nsym = stack.pop_smallint_range(1, 254)? as usize;
+        let block = stack.pop_bytes()?;
+        anyhow::ensure!(block.len() >= nsym, "Block is shorter than its parity");
+        check_block_len(block.len() - nsym, nsym)?;
+
+        let is_valid = syndromes(&block, nsym).iter().all(|s| *s == 0);
+        stack.push_bool(is_valid)
+    }
+
+    #[cmd(name = "rs-decode", stack)]
+    fn interpret_rs_decode(stack: &mut Stack) -> Result<()> {
+        let nsym = stack.pop_smallint_range(1, 254)? as usize;
+        let block = stack.pop_bytes()?;
+        anyhow::ensure!(block.len() >= nsym, "Block is shorter than its parity");
+        check_block_len(block.len() - nsym, nsym)?;
+
+        match rs_decode(&block, nsym) {
+            Some(mut data) => {
+                data.truncate(block.len() - nsym);
+                stack.push(data)?;
+                stack.push_bool(true)
+            }
+            None => stack.push_bool(false),
+        }
+    }
+
+    // === RaptorQ ===
+
+    // ( B symbol-size n -- t ), returns symbols with seqno `0..n`
+    #[cmd(name = "raptorq-encode", stack)]
+    fn interpret_raptorq_encode(stack: &mut Stack) -> Result<()> {
+        let count = stack.pop_smallint_range(0, MAX_RAPTORQ_SYMBOLS)?;
+        let symbol_size = stack.pop_smallint_range(1, u16::MAX as u32)? as u16;
+        let mut data = stack.pop_bytes()?;
+
+        let source_symbols = raptorq_source_symbols(data.len(), symbol_size)?;
+        data.resize(source_symbols as usize * symbol_size as usize, 0);
+
+        let config = raptorq::ObjectTransmissionInformation::new(0, symbol_size, 1, 1, 1);
+        let encoder = raptorq::SourceBlockEncoder::new2(0, &config, &data);
+
+        let mut symbols = encoder.source_packets();
+        symbols.truncate(count as usize);
+        if count > source_symbols {
+            symbols.extend(encoder.repair_packets(0, count - source_symbols));
+        }
+
+        let symbols = symbols
+            .into_iter()
+            .map(|packet| Box::new(packet.split().1) as Box<dyn StackValue>)
+            .collect::<StackTuple>();
+        stack.push(symbols)
+    }
+
+    // ( t data-size symbol-size -- B -1 or 0 ), where `t` consists of `[seqno B]` pairs
+    #[cmd(name = "raptorq-decode", stack)]
+    fn interpret_raptorq_decode(stack: &mut Stack) -> Result<()> {
+        let symbol_size = stack.pop_smallint_range(1, u16::MAX as u32)? as u16;
+        let data_size = stack.pop_smallint_range(0, u32::MAX)? as usize;
+        let symbols = stack.pop_tuple()?;
+
+        let source_symbols = raptorq_source_symbols(data_size, symbol_size)?;
+        let extended_symbols = raptorq::extended_source_block_symbols(source_symbols);
+
+        let mut packets = Vec::with_capacity(symbols.len());
+        for item in symbols.iter() {
+            let pair = item.as_tuple()?;
+            anyhow::ensure!(pair.len() == 2, "Expected a pair of seqno and symbol");
+            let seqno = pair[0].as_int()?;
+            let Some(seqno) = seqno.to_u32().filter(|s| *s < MAX_RAPTORQ_SYMBOLS) else {
+                anyhow::bail!("Invalid symbol seqno {seqno}");
+            };
+            let symbol = pair[1].as_bytes()?;
+            anyhow::ensure!(
+                symbol.len() == symbol_size as usize,
+                "Expected a symbol of {symbol_size} bytes, got {}",
+                symbol.len()
+            );
+
+            // Padding symbols of the extended source block are never sent,
+            // so repair symbols follow the source ones without a gap
+            let esi = if seqno < source_symbols {
+                seqno
+            } else {
+                seqno - source_symbols + extended_symbols
+            };
+            packets.push(raptorq::EncodingPacket::new(
+                raptorq::PayloadId::new(0, esi),
+                symbol.to_vec(),
+            ));
+        }
+
+        let config = raptorq::ObjectTransmissionInformation::new(0, symbol_size, 1, 1, 1);
+        let mut decoder = raptorq::SourceBlockDecoder::new2(
+            0,
+            &config,
+            source_symbols as u64 * symbol_size as u64,
+        );
+        match decoder.decode(packets) {
+            Some(mut data) => {
+                data.truncate(data_size);
+                stack.push(data)?;
+                stack.push_bool(true)
+            }
+            None => stack.push_bool(false),
+        }
+    }
+}
+
+/// Max number of symbols of a single RaptorQ block.
+const MAX_RAPTORQ_SYMBOLS: u32 = 1 << 24;
+
+fn raptorq_source_symbols(data_size: usize, symbol_size: u16) -> Result<u32> {
+    const MAX_SOURCE_SYMBOLS: usize = 56403;
+
+    let symbols = (data_size + symbol_size as usize - 1) / symbol_size as usize;
+    anyhow::ensure!(
+        (1..=MAX_SOURCE_SYMBOLS).contains(&symbols),
+        "RaptorQ block must consist of 1 to {MAX_SOURCE_SYMBOLS} source symbols, got {symbols}"
+    );
+    Ok(symbols as u32)
+}
+
+fn check_block_len(data_len: usize, nsym: usize) -> Result<()> {
+    anyhow::ensure!(
+        data_len + nsym <= 255,
+        "Reed-Solomon block is too long: {} > 255",
+        data_len + nsym
+    );
+    Ok(())
+}
+
+// The code is based on the "Reed-Solomon codes for coders" article,
+// it uses the 0x11d primitive polynomial and 0 as the first consecutive root.
+
+const GF_TABLES: ([u8; 512], [u8; 256]) = {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11d;
+        }
+        i += 1;
+    }
+    while i < 512 {
+        exp[i] = exp[i - 255];
+        i += 1;
+    }
+    (exp, log)
+};
+
+const GF_EXP: [u8; 512] = GF_TABLES.0;
+const GF_LOG: [u8; 256] = GF_TABLES.1;
+
+fn gf_mul(x: u8, y: u8) -> u8 {
+    if x == 0 || y == 0 {
+        return 0;
+    }
+    GF_EXP[GF_LOG[x as usize] as usize + GF_LOG[y as usize] as usize]
+}
+
+fn gf_div(x: u8, y: u8) -> u8 {
+    debug_assert_ne!(y, 0);
+    if x == 0 {
+        return 0;
+    }
+    GF_EXP[(GF_LOG[x as usize] as usize + 255 - GF_LOG[y as usize] as usize) % 255]
+}
+
+fn gf_pow(x: u8, power: i32) -> u8 {
+    GF_EXP[(GF_LOG[x as usize] as i32 * power).rem_euclid(255) as usize]
+}
+
+fn gf_inverse(x: u8) -> u8 {
+    GF_EXP[255 - GF_LOG[x as usize] as usize]
+}
+
+fn poly_scale(p: &[u8], x: u8) -> Vec<u8> {
+    p.iter().map(|c| gf_mul(*c, x)).collect()
+}
+
+fn poly_add(p: &[u8], q: &[u8]) -> Vec<u8> {
+    let len = std::cmp::max(p.len(), q.len());
+    let mut r = vec![0; len];
+    for (i, c) in p.iter().enumerate() {
+        r[i + len - p.len()] = *c;
+    }
+    for (i, c) in q.iter().enumerate() {
+        r[i + len - q.len()] ^= *c;
+    }
+    r
+}
+
+fn poly_mul(p: &[u8], q: &[u8]) -> Vec<u8> {
+    let mut r = vec![0; p.len() + q.len() - 1];
+    for (j, q) in q.iter().enumerate() {
+        for (i, p) in p.iter().enumerate() {
+            r[i + j] ^= gf_mul(*p, *q);
+        }
+    }
+    r
+}
+
+fn poly_eval(p: &[u8], x: u8) -> u8 {
+    let mut y = p[0];
+    for c in &p[1..] {
+        y = gf_mul(y, x) ^ c;
+    }
+    y
+}
+
+/// Returns the remainder of the division by a monic polynomial.
+fn poly_rem(dividend: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut out = dividend.to_vec();
+    let separator = divisor.len() - 1;
+    for i in 0..dividend.len().saturating_sub(separator) {
+        let coef = out[i];
+        if coef != 0 {
+            for (j, d) in divisor.iter().enumerate().skip(1) {
+                out[i + j] ^= gf_mul(*d, coef);
+            }
+        }
+    }
+    out.split_off(out.len() - separator)
+}
+
+fn rs_generator_poly(nsym: usize) -> Vec<u8> {
+    let mut g = vec![1];
+    for i in 0..nsym {
+        g = poly_mul(&g, &[1, gf_pow(2, i as i32)]);
+    }
+    g
+}
+
+fn rs_encode(data: &[u8], nsym: usize) -> Vec<u8> {
+    let mut msg = data.to_vec();
+    msg.resize(data.len() + nsym, 0);
+    poly_rem(&msg, &rs_generator_poly(nsym))
+}
+
+fn syndromes(block: &[u8], nsym: usize) -> Vec<u8> {
+    (0..nsym)
+        .map(|i| poly_eval(block, gf_pow(2, i as i32)))
+        .collect()
+}
+
+/// Corrects up to `nsym / 2` errors, returns `None` if the block is too damaged.
+fn rs_decode(block: &[u8], nsym: usize) -> Option<Vec<u8>> {
+    let synd = syndromes(block, nsym);
+    if synd.iter().all(|s| *s == 0) {
+        return Some(block.to_vec());
+    }
+
+    // Berlekamp-Massey
+    let mut err_loc = vec![1u8];
+    let mut old_loc = vec![1u8];
+    for i in 0..nsym {
+        let mut delta = synd[i];
+        for j in 1..std::cmp::min(err_loc.len(), i + 1) {
+            delta ^= gf_mul(err_loc[err_loc.len() - 1 - j], synd[i - j]);
+        }
+        old_loc.push(0);
+        if delta != 0 {
+            if old_loc.len() > err_loc.len() {
+                let new_loc = poly_scale(&old_loc, delta);
+                old_loc = poly_scale(&err_loc, gf_inverse(delta));
+                err_loc = new_loc;
+            }
+            err_loc = poly_add(&err_loc, &poly_scale(&old_loc, delta));
+        }
+    }
+    let leading_zeros = err_loc.iter().take_while(|c| **c == 0).count();
+    err_loc.drain(..leading_zeros);
+
+    let errs = err_loc.len() - 1;
+    if errs * 2 > nsym {
+        return None;
+    }
+
+    // Chien search
+    let mut err_loc_rev = err_loc.clone();
+    err_loc_rev.reverse();
+    let len = block.len();
+    let err_pos = (0..len)
+        .filter(|i| poly_eval(&err_loc_rev, gf_pow(2, *i as i32)) == 0)
+        .map(|i| len - 1 - i)
+        .collect::<Vec<_>>();
+    if err_pos.len() != errs {
+        return None;
+    }
+
+    // Forney
+    let coef_pos = err_pos.iter().map(|p| len - 1 - p).collect::<Vec<_>>();
+    let mut errata_loc = vec![1u8];
+    for i in &coef_pos {
+        errata_loc = poly_mul(&errata_loc, &poly_add(&[1], &[gf_pow(2, *i as i32), 0]));
+    }
+
+    // Reversed syndromes are padded with zero for the first root
+    let mut synd_rev = synd.clone();
+    synd_rev.reverse();
+    synd_rev.push(0);
+    let mut divisor = vec![0u8; errata_loc.len() + 1];
+    divisor[0] = 1;
+    let err_eval = poly_rem(&poly_mul(&synd_rev, &errata_loc), &divisor);
+
+    let x = coef_pos
+        .iter()
+        .map(|p| gf_pow(2, -(255 - *p as i32)))
+        .collect::<Vec<_>>();
+
+    let mut corrected = block.to_vec();
+    for (i, xi) in x.iter().enumerate() {
+        let xi_inv = gf_inverse(*xi);
+        let mut err_loc_prime = 1u8;
+        for (j, xj) in x.iter().enumerate() {
+            if j != i {
+                err_loc_prime = gf_mul(err_loc_prime, 1 ^ gf_mul(xi_inv, *xj));
+            }
+        }
+        if err_loc_prime == 0 {
+            return None;
+        }
+
+        let y = gf_mul(*xi, poly_eval(&err_eval, xi_inv));
+        corrected[err_pos[i]] ^= gf_div(y, err_loc_prime);
+    }
+
+    syndromes(&corrected, nsym)
+        .iter()
+        .all(|s| *s == 0)
+        .then_some(corrected)
+}
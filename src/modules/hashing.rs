@@ -0,0 +1,162 @@
+use anyhow::Result;
+use crc::Crc;
+use sha2::Digest;
+
+use crate::core::*;
+
+/// Checksums and hashes of `Bytes` or `String` values.
+///
+/// Checksums are returned as integers, hashes as `Bytes`.
+pub struct Hashing;
+
+#[fift_module]
+impl Hashing {
+    #[cmd(name = "crc16", stack)]
+    fn interpret_crc16(stack: &mut Stack) -> Result<()> {
+        let data = pop_data(stack)?;
+        stack.push_int(CRC_16.checksum(&data))
+    }
+
+    #[cmd(name = "crc32", stack)]
+    fn interpret_crc32(stack: &mut Stack) -> Result<()> {
+        let data = pop_data(stack)?;
+        stack.push_int(CRC_32.checksum(&data))
+    }
+
+    #[cmd(name = "crc32c", stack)]
+    fn interpret_crc32c(stack: &mut Stack) -> Result<()> {
+        let data = pop_data(stack)?;
+        stack.push_int(CRC_32_C.checksum(&data))
+    }
+
+    #[cmd(name = "sha256", stack)]
+    fn interpret_sha256(stack: &mut Stack) -> Result<()> {
+        let data = pop_data(stack)?;
+        stack.push(sha2::Sha256::digest(data).to_vec())
+    }
+
+    #[cmd(name = "sha512", stack)]
+    fn interpret_sha512(stack: &mut Stack) -> Result<()> {
+        let data = pop_data(stack)?;
+        stack.push(sha2::Sha512::digest(data).to_vec())
+    }
+
+    #[cmd(name = "keccak256", stack)]
+    fn interpret_keccak256(stack: &mut Stack) -> Result<()> {
+        let data = pop_data(stack)?;
+        stack.push(keccak256(&data).to_vec())
+    }
+}
+
+fn pop_data(stack: &mut Stack) -> Result<Vec<u8>> {
+    let value = stack.pop()?;
+    Ok(match value.ty() {
+        StackValueType::String => String::into_bytes(*value.into_string()?),
+        _ => *value.into_bytes()?,
+    })
+}
+
+/// Keccak-256 with the original padding (as in Ethereum), not SHA3-256.
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    const RATE: usize = 136;
+
+    let mut state = [0u64; 25];
+    let mut chunks = data.chunks_exact(RATE);
+    for chunk in &mut chunks {
+        keccak_absorb(&mut state, chunk);
+    }
+
+    let rest = chunks.remainder();
+    let mut last = [0u8; RATE];
+    last[..rest.len()].copy_from_slice(rest);
+    last[rest.len()] ^= 0x01;
+    last[RATE - 1] ^= 0x80;
+    keccak_absorb(&mut state, &last);
+
+    let mut output = [0u8; 32];
+    for (chunk, lane) in output.chunks_exact_mut(8).zip(state) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    output
+}
+
+fn keccak_absorb(state: &mut [u64; 25], block: &[u8]) {
+    for (lane, bytes) in state.iter_mut().zip(block.chunks_exact(8)) {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(bytes);
+        *lane ^= u64::from_le_bytes(word);
+    }
+    keccak_f1600(state);
+}
+
+fn keccak_f1600(a: &mut [u64; 25]) {
+    const ROUND_CONSTANTS: [u64; 24] = [
+        0x0000000000000001,
+        0x0000000000008082,
+        0x800000000000808a,
+        0x8000000080008000,
+        0x000000000000808b,
+        0x0000000080000001,
+        0x8000000080008081,
+        0x8000000000008009,
+        0x000000000000008a,
+        0x0000000000000088,
+        0x0000000080008009,
+        0x000000008000000a,
+        0x000000008000808b,
+        0x800000000000008b,
+        0x8000000000008089,
+        0x8000000000008003,
+        0x8000000000008002,
+        0x8000000000000080,
+        0x000000000000800a,
+        0x800000008000000a,
+        0x8000000080008081,
+        0x8000000000008080,
+        0x0000000080000001,
+        0x8000000080008008,
+    ];
+    const ROTATIONS: [u32; 24] = [
+        1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+    ];
+    const LANES: [usize; 24] = [
+        10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+    ];
+
+    for round_constant in ROUND_CONSTANTS {
+        // Theta
+        let mut c = [0u64; 5];
+        for (x, c) in c.iter_mut().enumerate() {
+            *c = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+
+        // Rho and pi
+        let mut current = a[1];
+        for (&lane, &rotation) in LANES.iter().zip(&ROTATIONS) {
+            let next = a[lane];
+            a[lane] = current.rotate_left(rotation);
+            current = next;
+        }
+
+        // Chi
+        for row in a.chunks_exact_mut(5) {
+            let copy = [row[0], row[1], row[2], row[3], row[4]];
+            for (x, lane) in row.iter_mut().enumerate() {
+                *lane = copy[x] ^ (!copy[(x + 1) % 5] & copy[(x + 2) % 5]);
+            }
+        }
+
+        // Iota
+        a[0] ^= round_constant;
+    }
+}
+
+pub(super) const CRC_16: Crc<u16> = Crc::<u16>::new(&crc::CRC_16_XMODEM);
+const CRC_32: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+const CRC_32_C: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISCSI);
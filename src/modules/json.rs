@@ -0,0 +1,122 @@
+use anyhow::Result;
+use num_bigint::BigInt;
+use serde_json::{Map, Number, Value};
+
+use crate::core::*;
+
+const MAX_DEPTH: usize = 128;
+
+/// Conversion between JSON and Fift values.
+///
+/// - `null` is null, booleans are `-1` and `0`, numbers are integers;
+/// - strings are strings, arrays are tuples;
+/// - objects are tuples of `[ key value ]` pairs where keys are atoms,
+///   an empty object is indistinguishable from an empty array.
+///
+/// Atoms `true`, `false` and `null` are serialized as JSON literals.
+pub struct JsonUtils;
+
+#[fift_module]
+impl JsonUtils {
+    #[cmd(name = "json>", stack)]
+    fn interpret_json_parse(stack: &mut Stack) -> Result<()> {
+        let s = stack.pop_string()?;
+        let json = serde_json::from_str::<Value>(&s)?;
+        let value = json_to_value(json, stack.atoms_mut())?;
+        stack.push_raw(value)
+    }
+
+    #[cmd(name = ">json", stack)]
+    fn interpret_json_serialize(stack: &mut Stack) -> Result<()> {
+        let value = stack.pop()?;
+        let json = value_to_json(value.as_ref(), 0)?;
+        stack.push(json.to_string())
+    }
+}
+
+fn json_to_value(json: Value, atoms: &mut Atoms) -> Result<Box<dyn StackValue>> {
+    Ok(match json {
+        Value::Null => Box::new(()),
+        Value::Bool(value) => Box::new(-BigInt::from(value as u8)),
+        Value::Number(number) => {
+            let Ok(int) = number.as_str().parse::<BigInt>() else {
+                anyhow::bail!("Only integer numbers are supported, got {number}");
+            };
+            Box::new(int)
+        }
+        Value::String(value) => Box::new(value),
+        Value::Array(items) => Box::new(
+            items
+                .into_iter()
+                .map(|item| json_to_value(item, atoms))
+                .collect::<Result<StackTuple>>()?,
+        ),
+        Value::Object(entries) => Box::new(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let pair: StackTuple = vec![
+                        Box::new(atoms.create_named(key)),
+                        json_to_value(value, atoms)?,
+                    ];
+                    Ok(Box::new(pair) as Box<dyn StackValue>)
+                })
+                .collect::<Result<StackTuple>>()?,
+        ),
+    })
+}
+
+fn value_to_json(value: &dyn StackValue, depth: usize) -> Result<Value> {
+    anyhow::ensure!(depth <= MAX_DEPTH, "Value is too deep for JSON");
+
+    Ok(match value.ty() {
+        StackValueType::Null => Value::Null,
+        StackValueType::Int => {
+            let int = value.as_int()?.to_string();
+            Value::Number(serde_json::from_str::<Number>(&int)?)
+        }
+        StackValueType::String => Value::String(value.as_string()?.to_owned()),
+        StackValueType::Atom => match value.as_atom()? {
+            Atom::Named(name) => match name.as_ref() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                name => Value::String(name.to_owned()),
+            },
+            atom => Value::String(atom.to_string()),
+        },
+        StackValueType::Tuple => {
+            let items = value.as_tuple()?;
+            match as_object_entries(items) {
+                Some(entries) => {
+                    let mut object = Map::with_capacity(entries.len());
+                    for (key, value) in entries {
+                        object.insert(key.to_string(), value_to_json(value, depth + 1)?);
+                    }
+                    Value::Object(object)
+                }
+                None => Value::Array(
+                    items
+                        .iter()
+                        .map(|item| value_to_json(item.as_ref(), depth + 1))
+                        .collect::<Result<_>>()?,
+                ),
+            }
+        }
+        ty => anyhow::bail!("Values of type `{ty:?}` cannot be converted to JSON"),
+    })
+}
+
+/// Returns `[ key value ]` pairs if the tuple is a non-empty object.
+fn as_object_entries(items: &StackTuple) -> Option<Vec<(&Atom, &dyn StackValue)>> {
+    if items.is_empty() {
+        return None;
+    }
+    items
+        .iter()
+        .map(|item| match item.as_tuple().ok()?.as_slice() {
+            [key, value] => Some((key.as_atom().ok()?, value.as_ref())),
+            _ => None,
+        })
+        .collect()
+}
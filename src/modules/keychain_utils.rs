@@ -0,0 +1,34 @@
+use anyhow::{Context as _, Result};
+
+use crate::core::*;
+
+/// Private keys in the storage provided by the environment.
+pub struct KeychainUtils;
+
+#[fift_module]
+impl KeychainUtils {
+    // ( B S -- ), stores the secret under the name
+    #[cmd(name = "keychain-store")]
+    fn interpret_keychain_store(ctx: &mut Context) -> Result<()> {
+        let name = ctx.stack.pop_string()?;
+        let secret = ctx.stack.pop_bytes()?;
+        get_keychain(ctx)?
+            .store(&name, &secret)
+            .with_context(|| format!("Failed to store `{name}` in the keychain"))
+    }
+
+    // ( S -- B )
+    #[cmd(name = "keychain-load")]
+    fn interpret_keychain_load(ctx: &mut Context) -> Result<()> {
+        let name = ctx.stack.pop_string()?;
+        let secret = get_keychain(ctx)?
+            .load(&name)
+            .with_context(|| format!("Failed to load `{name}` from the keychain"))?
+            .with_context(|| format!("No `{name}` in the keychain"))?;
+        ctx.stack.push(secret)
+    }
+}
+
+fn get_keychain<'a>(ctx: &'a mut Context) -> Result<&'a mut dyn Keychain> {
+    ctx.env.keychain().context("No keychain configured")
+}
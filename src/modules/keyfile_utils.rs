@@ -0,0 +1,26 @@
+use anyhow::Result;
+
+use crate::core::*;
+use crate::keyfile;
+
+/// Passphrase-protected key files.
+pub struct KeyfileUtils;
+
+#[fift_module]
+impl KeyfileUtils {
+    // ( B S -- B' ), where S is a passphrase
+    #[cmd(name = "B>keyfile", stack)]
+    fn interpret_encrypt_keyfile(stack: &mut Stack) -> Result<()> {
+        let passphrase = stack.pop_string()?;
+        let secret = stack.pop_bytes()?;
+        stack.push(keyfile::encrypt(&secret, passphrase.as_bytes())?)
+    }
+
+    // ( B' S -- B )
+    #[cmd(name = "keyfile>B", stack)]
+    fn interpret_decrypt_keyfile(stack: &mut Stack) -> Result<()> {
+        let passphrase = stack.pop_string()?;
+        let container = stack.pop_bytes()?;
+        stack.push(keyfile::decrypt(&container, passphrase.as_bytes())?)
+    }
+}
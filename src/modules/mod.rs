@@ -1,25 +1,55 @@
 use anyhow::{Context as _, Result};
 
 use crate::core::*;
+use crate::error::UnexpectedEof;
+use crate::util::ImmediateInt;
 
 pub use self::arithmetic::Arithmetic;
+pub use self::asm::Asm;
 pub use self::cell_utils::CellUtils;
 pub use self::control::Control;
 pub use self::crypto::Crypto;
 pub use self::debug_utils::DebugUtils;
 pub use self::dict_utils::DictUtils;
+pub use self::dns_utils::DnsUtils;
+#[cfg(feature = "fec")]
+pub use self::fec_utils::FecUtils;
+pub use self::hashing::Hashing;
+#[cfg(feature = "serde")]
+pub use self::json::JsonUtils;
+#[cfg(feature = "keychain")]
+pub use self::keychain_utils::KeychainUtils;
+#[cfg(feature = "keyfile")]
+pub use self::keyfile_utils::KeyfileUtils;
+pub use self::multisig_utils::MultisigUtils;
 pub use self::stack_utils::StackUtils;
 pub use self::string_utils::StringUtils;
+pub use self::tlb::TlbUtils;
+pub use self::token_utils::TokenUtils;
 pub use self::vm_utils::VmUtils;
 
 mod arithmetic;
+mod asm;
 mod cell_utils;
 mod control;
 mod crypto;
 mod debug_utils;
 mod dict_utils;
+mod dns_utils;
+#[cfg(feature = "fec")]
+mod fec_utils;
+mod hashing;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "keychain")]
+mod keychain_utils;
+#[cfg(feature = "keyfile")]
+mod keyfile_utils;
+mod multisig_utils;
 mod stack_utils;
 mod string_utils;
+mod tlb;
+mod token_utils;
 mod vm_utils;
 
 pub struct BaseModule;
@@ -36,16 +66,70 @@ impl FiftModule for BaseModule {
         stack.push(())
     }
 
+    #[cmd(name = "flag>maybe", stack)]
+    fn interpret_flag_to_maybe(stack: &mut Stack) -> Result<()> {
+        if !stack.pop_bool()? {
+            stack.push(())?;
+        }
+        Ok(())
+    }
+
+    #[cmd(name = "maybe>flag", stack)]
+    fn interpret_maybe_to_flag(stack: &mut Stack) -> Result<()> {
+        let value = stack.pop()?;
+        if value.is_null() {
+            stack.push_bool(false)
+        } else {
+            stack.push_raw(value)?;
+            stack.push_bool(true)
+        }
+    }
+
+    #[cmd(name = "maybe-or", stack)]
+    fn interpret_maybe_or(stack: &mut Stack) -> Result<()> {
+        let default = stack.pop()?;
+        let value = stack.pop()?;
+        stack.push_raw(if value.is_null() { default } else { value })
+    }
+
     #[cmd(name = "null?", stack, args(ty = StackValueType::Null))]
     #[cmd(name = "integer?", stack, args(ty = StackValueType::Int))]
     #[cmd(name = "string?", stack, args(ty = StackValueType::String))]
     #[cmd(name = "tuple?", stack, args(ty = StackValueType::Tuple))]
     #[cmd(name = "atom?", stack, args(ty = StackValueType::Atom))]
+    #[cmd(name = "cell?", stack, args(ty = StackValueType::Cell))]
+    #[cmd(name = "slice?", stack, args(ty = StackValueType::Slice))]
+    #[cmd(name = "builder?", stack, args(ty = StackValueType::Builder))]
+    #[cmd(name = "bytes?", stack, args(ty = StackValueType::Bytes))]
+    #[cmd(name = "box?", stack, args(ty = StackValueType::SharedBox))]
+    #[cmd(name = "cont?", stack, args(ty = StackValueType::Cont))]
+    #[cmd(name = "source-block?", stack, args(ty = StackValueType::SourceBlock))]
     fn interpret_is_type(stack: &mut Stack, ty: StackValueType) -> Result<()> {
         let is_ty = stack.pop()?.ty() == ty;
         stack.push_bool(is_ty)
     }
 
+    #[cmd(name = "type-of", stack)]
+    fn interpret_type_of(stack: &mut Stack) -> Result<()> {
+        let name = match stack.pop()?.ty() {
+            StackValueType::Null => "null",
+            StackValueType::Int => "integer",
+            StackValueType::Cell => "cell",
+            StackValueType::Builder => "builder",
+            StackValueType::Slice => "slice",
+            StackValueType::String => "string",
+            StackValueType::Bytes => "bytes",
+            StackValueType::Tuple => "tuple",
+            StackValueType::Cont => "cont",
+            StackValueType::WordList => "word-list",
+            StackValueType::SharedBox => "box",
+            StackValueType::Atom => "atom",
+            StackValueType::SourceBlock => "source-block",
+        };
+        let atom = stack.atoms_mut().create_named(name);
+        stack.push(atom)
+    }
+
     #[cmd(name = "hole", stack)]
     fn interpret_hole(stack: &mut Stack) -> Result<()> {
         stack.push(SharedBox::default())
@@ -66,8 +150,21 @@ impl FiftModule for BaseModule {
     #[cmd(name = "!", stack)]
     fn interpret_box_store(stack: &mut Stack) -> Result<()> {
         let value = stack.pop_shared_box()?;
-        value.store(stack.pop()?);
-        Ok(())
+        value.try_store(stack.pop()?)
+    }
+
+    // ( x -- x ), freezes the box or all boxes in the tuple
+    #[cmd(name = "freeze", stack)]
+    fn interpret_freeze(stack: &mut Stack) -> Result<()> {
+        let value = stack.pop()?;
+        freeze_boxes(value.as_ref());
+        stack.push_raw(value)
+    }
+
+    #[cmd(name = "frozen?", stack)]
+    fn interpret_is_frozen(stack: &mut Stack) -> Result<()> {
+        let value = stack.pop_shared_box()?;
+        stack.push_bool(value.is_frozen())
     }
 
     #[cmd(name = "anon", stack)]
@@ -123,6 +220,13 @@ impl FiftModule for BaseModule {
         })
     }
 
+    #[cmd(name = "value-eq?", stack)]
+    fn interpret_is_value_eq(stack: &mut Stack) -> Result<()> {
+        let y = stack.pop()?;
+        let x = stack.pop()?;
+        stack.push_bool(x.is_value_equal(&*y))
+    }
+
     #[cmd(name = "|", stack)]
     fn interpret_empty_tuple(stack: &mut Stack) -> Result<()> {
         stack.push(StackTuple::new())
@@ -242,4 +346,137 @@ impl FiftModule for BaseModule {
         };
         ctx.stack.push_bool(exists)
     }
+
+    #[cmd(name = "ask", args(secret = false))]
+    #[cmd(name = "ask-secret", args(secret = true))]
+    fn interpret_ask(ctx: &mut Context, secret: bool) -> Result<()> {
+        let prompt = ctx.stack.pop_string()?;
+        std::io::Write::flush(&mut ctx.stdout)?;
+        let answer = ctx
+            .env
+            .ask(&prompt, secret)
+            .context("Failed to read user input")?
+            .context("User input is closed")?;
+        ctx.stack.push(answer)
+    }
+
+    #[cmd(name = "param", args(optional = false))]
+    #[cmd(name = "param?", args(optional = true))]
+    fn interpret_param(ctx: &mut Context, optional: bool) -> Result<()> {
+        let mut scan_word = || -> Result<String> {
+            let token = ctx.input.scan_word()?.ok_or_else(UnexpectedEof::default)?;
+            Ok(token.data.to_owned())
+        };
+        let name = scan_word()?;
+        let ty = scan_word()?;
+
+        let name = name
+            .strip_prefix('"')
+            .and_then(|name| name.strip_suffix('"'))
+            .unwrap_or(&name);
+        let ty = ParamType::from_name(&ty)?;
+
+        let Some(value) = ctx.params.get(name) else {
+            anyhow::ensure!(optional, "Missing parameter `{name}`");
+            return ctx.stack.push_bool(false);
+        };
+        ty.push_value(&mut ctx.stack, value)
+            .with_context(|| format!("Invalid value of parameter `{name}`, expected {ty}"))?;
+
+        if optional {
+            ctx.stack.push_bool(true)?;
+        }
+        Ok(())
+    }
+
+    #[cmd(name = "log-output-to")]
+    fn interpret_log_output_to(ctx: &mut Context) -> Result<()> {
+        let name = ctx.stack.pop_string()?;
+        let file = ctx
+            .env
+            .create_file(&name)
+            .with_context(|| format!("Failed to create output log `{name}`"))?;
+        ctx.stdout.set_log(file)?;
+        Ok(())
+    }
+
+    #[cmd(name = "log-output-off")]
+    fn interpret_log_output_off(ctx: &mut Context) -> Result<()> {
+        ctx.stdout.clear_log()?;
+        Ok(())
+    }
+}
+
+/// Freezes boxes reachable from the value, including nested tuples and box contents.
+fn freeze_boxes(value: &dyn StackValue) {
+    match value.ty() {
+        StackValueType::SharedBox => {
+            let Ok(shared_box) = value.as_box() else {
+                return;
+            };
+            // Frozen boxes are skipped to handle cycles
+            if !shared_box.is_frozen() {
+                shared_box.freeze();
+                freeze_boxes(shared_box.fetch().as_ref());
+            }
+        }
+        StackValueType::Tuple => {
+            if let Ok(tuple) = value.as_tuple() {
+                tuple.iter().for_each(|item| freeze_boxes(item.as_ref()));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Type of a named script input, declared with `param`.
+#[derive(Debug, Clone, Copy)]
+enum ParamType {
+    Int,
+    String,
+    Bytes,
+    Bool,
+}
+
+impl ParamType {
+    fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "int" => Self::Int,
+            "string" => Self::String,
+            "bytes" => Self::Bytes,
+            "bool" => Self::Bool,
+            _ => anyhow::bail!(
+                "Unknown parameter type `{name}`, expected `int`, `string`, `bytes` or `bool`"
+            ),
+        })
+    }
+
+    fn push_value(self, stack: &mut Stack, value: &str) -> Result<()> {
+        match self {
+            Self::Int => match ImmediateInt::try_from_str(value)? {
+                Some(ImmediateInt {
+                    num, denom: None, ..
+                }) => stack.push_int(num),
+                _ => anyhow::bail!("Invalid integer"),
+            },
+            Self::String => stack.push(value.to_owned()),
+            Self::Bytes => stack.push(hex::decode(value)?),
+            Self::Bool => match value {
+                "true" | "1" => stack.push_bool(true),
+                "false" | "0" => stack.push_bool(false),
+                _ => anyhow::bail!("Invalid boolean"),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ParamType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Int => "int",
+            Self::String => "string",
+            Self::Bytes => "bytes",
+            Self::Bool => "bool",
+        })
+    }
 }
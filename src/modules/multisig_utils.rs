@@ -0,0 +1,192 @@
+use anyhow::{Context as _, Result};
+use everscale_crypto::ed25519;
+use everscale_types::cell::DefaultFinalizer;
+use everscale_types::dict::Dict;
+use everscale_types::prelude::*;
+
+use super::crypto::pop_secret_key;
+use crate::core::*;
+
+pub struct MultisigUtils;
+
+// Orders follow the layout of the multisig wallet:
+// order: wallet_id:uint32 query_id:uint64 mode:uint8 msg:^Cell
+// body: root_signature:bits512 root_idx:uint8 signatures:(Maybe ^Signatures) order
+// sig$_ signature:bits512 idx:uint8 next:(Maybe ^Signatures) = Signatures
+//
+// Collected signatures are kept as a `HashmapE 8 bits512` dictionary
+// keyed by the signer index.
+
+#[fift_module]
+impl MultisigUtils {
+    // ( wallet-id query-id mode msg -- c )
+    #[cmd(name = "msig-order", stack)]
+    fn interpret_msig_order(stack: &mut Stack) -> Result<()> {
+        let msg = stack.pop_cell()?;
+        let mode = stack.pop_smallint_range(0, 255)? as u8;
+        let query_id = stack.pop_u64_checked()?;
+        let wallet_id = stack.pop_u32_checked()?;
+
+        let mut builder = CellBuilder::new();
+        builder.store_u32(wallet_id)?;
+        builder.store_u64(query_id)?;
+        builder.store_u8(mode)?;
+        builder.store_reference(*msg)?;
+        stack.push(builder.build()?)
+    }
+
+    #[cmd(name = "msig-order-hash", stack)]
+    fn interpret_msig_order_hash(stack: &mut Stack) -> Result<()> {
+        let order = stack.pop_cell()?;
+        stack.push(order.repr_hash().as_slice().to_vec())
+    }
+
+    // ( c priv i D -- D' )
+    #[cmd(name = "msig-sign", stack)]
+    fn interpret_msig_sign(stack: &mut Stack) -> Result<()> {
+        let mut signatures = pop_signatures(stack)?;
+        let idx = stack.pop_smallint_range(0, 255)? as u8;
+        let secret = pop_secret_key(stack)?;
+        let order = stack.pop_cell()?;
+
+        let public = ed25519::PublicKey::from(&secret);
+        let signature = secret
+            .expand()
+            .sign_raw(order.repr_hash().as_slice(), &public);
+        signatures.set(idx, split_signature(&signature))?;
+        push_signatures(stack, signatures)
+    }
+
+    // ( B i D -- D' )
+    #[cmd(name = "msig-add-signature", stack)]
+    fn interpret_msig_add_signature(stack: &mut Stack) -> Result<()> {
+        let mut signatures = pop_signatures(stack)?;
+        let idx = stack.pop_smallint_range(0, 255)? as u8;
+        let signature = stack.pop_bytes()?;
+        let signature: [u8; 64] = signature
+            .as_slice()
+            .try_into()
+            .ok()
+            .context("Invalid signature")?;
+        signatures.set(idx, split_signature(&signature))?;
+        push_signatures(stack, signatures)
+    }
+
+    // ( D1 D2 -- D )
+    #[cmd(name = "msig-merge", stack)]
+    fn interpret_msig_merge(stack: &mut Stack) -> Result<()> {
+        let other = pop_signatures(stack)?;
+        let mut signatures = pop_signatures(stack)?;
+        for entry in other.iter() {
+            let (idx, signature) = entry?;
+            if let Some(existing) = signatures.get(idx)? {
+                anyhow::ensure!(
+                    existing == signature,
+                    "Conflicting signatures for signer {idx}"
+                );
+            }
+            signatures.set(idx, signature)?;
+        }
+        push_signatures(stack, signatures)
+    }
+
+    // ( c D t -- n ), where t is a tuple of signer public keys
+    #[cmd(name = "msig-check", stack)]
+    fn interpret_msig_check(stack: &mut Stack) -> Result<()> {
+        let public_keys = stack.pop_tuple()?;
+        let signatures = pop_signatures(stack)?;
+        let order = stack.pop_cell()?;
+
+        let hash = order.repr_hash();
+        let mut valid = 0;
+        for entry in signatures.iter() {
+            let (idx, signature) = entry?;
+            let public = public_keys
+                .get(idx as usize)
+                .with_context(|| format!("No public key for signer {idx}"))?;
+            let public = <[u8; 32]>::try_from(public.as_bytes()?)
+                .ok()
+                .and_then(ed25519::PublicKey::from_bytes)
+                .with_context(|| format!("Invalid public key of signer {idx}"))?;
+
+            if public.verify_raw(hash.as_slice(), &join_signature(&signature)) {
+                valid += 1;
+            }
+        }
+        stack.push_int(valid)
+    }
+
+    // ( c D i priv -- c' )
+    #[cmd(name = "msig-body", stack)]
+    fn interpret_msig_body(stack: &mut Stack) -> Result<()> {
+        let secret = pop_secret_key(stack)?;
+        let root_idx = stack.pop_smallint_range(0, 255)? as u8;
+        let signatures = pop_signatures(stack)?;
+        let order = *stack.pop_cell()?;
+
+        // The root signer signs the whole body,
+        // so its order signature is not included into the list
+        let mut list = None::<Cell>;
+        for entry in signatures.iter() {
+            let (idx, (head, tail)) = entry?;
+            if idx == root_idx {
+                continue;
+            }
+            let mut builder = CellBuilder::new();
+            builder.store_u256(&head)?;
+            builder.store_u256(&tail)?;
+            builder.store_u8(idx)?;
+            list.store_into(&mut builder, &mut Cell::default_finalizer())?;
+            list = Some(builder.build()?);
+        }
+
+        let mut rest = CellBuilder::new();
+        rest.store_u8(root_idx)?;
+        list.store_into(&mut rest, &mut Cell::default_finalizer())?;
+        rest.store_slice(DynCell::as_slice(order.as_ref())?)?;
+        let rest = rest.build()?;
+
+        let public = ed25519::PublicKey::from(&secret);
+        let signature = secret
+            .expand()
+            .sign_raw(rest.repr_hash().as_slice(), &public);
+
+        let mut builder = CellBuilder::new();
+        builder.store_raw(&signature, 512)?;
+        builder.store_slice(DynCell::as_slice(rest.as_ref())?)?;
+        stack.push(builder.build()?)
+    }
+}
+
+type Signature = (HashBytes, HashBytes);
+
+fn split_signature(signature: &[u8; 64]) -> Signature {
+    let mut head = HashBytes::ZERO;
+    let mut tail = HashBytes::ZERO;
+    head.0.copy_from_slice(&signature[..32]);
+    tail.0.copy_from_slice(&signature[32..]);
+    (head, tail)
+}
+
+fn join_signature((head, tail): &Signature) -> [u8; 64] {
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(head.as_slice());
+    signature[32..].copy_from_slice(tail.as_slice());
+    signature
+}
+
+fn pop_signatures(stack: &mut Stack) -> Result<Dict<u8, Signature>> {
+    let value = stack.pop()?;
+    Ok(Dict::from(if value.is_null() {
+        None
+    } else {
+        Some(*value.into_cell()?)
+    }))
+}
+
+fn push_signatures(stack: &mut Stack, signatures: Dict<u8, Signature>) -> Result<()> {
+    match signatures.root().clone() {
+        Some(cell) => stack.push(cell),
+        None => stack.push(()),
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use num_traits::Zero;
 
 use crate::core::*;
@@ -81,54 +81,58 @@ impl StackUtils {
     #[cmd(name = "pick", stack)]
     fn interpret_pick(stack: &mut Stack) -> Result<()> {
         let n = stack.pop_smallint_range(0, 255)? as usize;
-        stack.push_raw(stack.fetch(n)?)
+        let item = stack.fetch(n).with_context(|| out_of_bounds("pick", n))?;
+        stack.push_raw(item)
     }
 
     #[cmd(name = "roll", stack)]
     fn interpret_roll(stack: &mut Stack) -> Result<()> {
         let n = stack.pop_smallint_range(0, 255)? as usize;
-        for i in (1..=n).rev() {
-            stack.swap(i, i - 1)?;
-        }
-        Ok(())
+        stack.roll(n).with_context(|| out_of_bounds("roll", n))
     }
 
     #[cmd(name = "-roll", stack)]
     fn interpret_roll_rev(stack: &mut Stack) -> Result<()> {
         let n = stack.pop_smallint_range(0, 255)? as usize;
-        for i in 0..n {
-            stack.swap(i, i + 1)?;
-        }
-        Ok(())
+        stack.roll_rev(n).with_context(|| out_of_bounds("-roll", n))
     }
 
     #[cmd(name = "reverse", stack)]
     fn interpret_reverse(stack: &mut Stack) -> Result<()> {
         let m = stack.pop_smallint_range(0, 255)? as usize;
         let n = stack.pop_smallint_range(0, 255)? as usize;
-        if n == 0 {
-            return Ok(());
-        }
+        stack
+            .reverse(n, m)
+            .with_context(|| out_of_bounds("reverse", n + m))
+    }
 
-        stack.check_underflow(n + m)?;
-        let s = 2 * m + n - 1;
-        for i in (m..=(s - 1) >> 1).rev() {
-            stack.swap(i, s - i)?;
-        }
-        Ok(())
+    // ( x1 ... xn n -- )
+    #[cmd(name = "ndrop", stack)]
+    fn interpret_ndrop(stack: &mut Stack) -> Result<()> {
+        let n = stack.pop_smallint_range(0, u32::MAX)? as usize;
+        stack.drop_n(n).with_context(|| out_of_bounds("ndrop", n))
+    }
+
+    // ( x1 ... xn n -- x1 ... xn x1 ... xn )
+    #[cmd(name = "ndup", stack)]
+    fn interpret_ndup(stack: &mut Stack) -> Result<()> {
+        let n = stack.pop_smallint_range(0, u32::MAX)? as usize;
+        stack.dup_n(n).with_context(|| out_of_bounds("ndup", n))
     }
 
     #[cmd(name = "exch", stack)]
     fn interpret_exch(stack: &mut Stack) -> Result<()> {
         let n = stack.pop_smallint_range(0, 255)? as usize;
-        stack.swap(0, n)
+        stack.swap(0, n).with_context(|| out_of_bounds("exch", n))
     }
 
     #[cmd(name = "exch2", stack)]
     fn interpret_exch2(stack: &mut Stack) -> Result<()> {
         let n = stack.pop_smallint_range(0, 255)? as usize;
         let m = stack.pop_smallint_range(0, 255)? as usize;
-        stack.swap(n, m)
+        stack
+            .swap(n, m)
+            .with_context(|| out_of_bounds("exch2", std::cmp::max(n, m)))
     }
 
     #[cmd(name = "depth", stack)]
@@ -144,4 +148,61 @@ impl StackUtils {
         }
         stack.push_raw(item)
     }
+
+    // === Marks ===
+
+    #[cmd(name = "mark")]
+    fn interpret_mark(ctx: &mut Context) -> Result<()> {
+        let depth = ctx.stack.depth();
+        marks(ctx).push(depth);
+        Ok(())
+    }
+
+    // ( mark x1 ... xn -- n ), keeps the mark
+    #[cmd(name = "count-to-mark")]
+    fn interpret_count_to_mark(ctx: &mut Context) -> Result<()> {
+        let mark = last_mark(ctx, "count-to-mark")?;
+        ctx.stack.push_int(ctx.stack.depth() - mark)
+    }
+
+    // ( mark x1 ... xn -- t )
+    #[cmd(name = "collect-to-mark")]
+    fn interpret_collect_to_mark(ctx: &mut Context) -> Result<()> {
+        let mark = last_mark(ctx, "collect-to-mark")?;
+        marks(ctx).pop();
+        let items = ctx.stack.split_off(mark);
+        ctx.stack.push(items)
+    }
+
+    // ( mark x1 ... xn -- )
+    #[cmd(name = "clear-to-mark")]
+    fn interpret_clear_to_mark(ctx: &mut Context) -> Result<()> {
+        let mark = last_mark(ctx, "clear-to-mark")?;
+        marks(ctx).pop();
+        ctx.stack.truncate(mark);
+        Ok(())
+    }
+}
+
+fn out_of_bounds(word: &str, n: usize) -> String {
+    format!("`{n} {word}` is out of the stack bounds")
+}
+
+/// Stack depths saved by `mark`, from the outermost one.
+#[derive(Default)]
+struct Marks(Vec<usize>);
+
+fn marks<'a>(ctx: &'a mut Context) -> &'a mut Vec<usize> {
+    &mut ctx.user_data_or_default::<Marks>().0
+}
+
+fn last_mark(ctx: &mut Context, word: &str) -> Result<usize> {
+    let Some(&mark) = marks(ctx).last() else {
+        anyhow::bail!("`{word}` without a mark");
+    };
+    anyhow::ensure!(
+        mark <= ctx.stack.depth(),
+        "Stack was cleared below the mark for `{word}`"
+    );
+    Ok(mark)
 }
@@ -1,3 +1,5 @@
+use std::io::Write;
+
 use anyhow::Result;
 use num_bigint::{BigInt, Sign};
 use num_traits::Num;
@@ -13,16 +15,16 @@ pub struct StringUtils;
 impl StringUtils {
     #[cmd(name = "\"", active, without_space)]
     fn interpret_quote_str(ctx: &mut Context) -> Result<()> {
-        let word = ctx.input.scan_until('"')?;
+        let word = ctx.input.scan_literal(LiteralKind::String)?;
         ctx.stack.push(word.data.to_owned())?;
         ctx.stack.push_argcount(1, ctx.dictionary.make_nop())
     }
 
     #[cmd(name = "char", active)]
     fn interpret_char(ctx: &mut Context) -> Result<()> {
-        let token = ctx.input.scan_word()?.ok_or(UnexpectedEof)?;
+        let token = ctx.input.scan_word()?.ok_or_else(UnexpectedEof::default)?;
         let mut chars = token.data.chars();
-        let char = chars.next().ok_or(UnexpectedEof)?;
+        let char = chars.next().ok_or_else(UnexpectedEof::default)?;
         anyhow::ensure!(chars.next().is_none(), "Expected exactly one character");
         ctx.stack.push_int(char as u32)?;
         ctx.stack.push_argcount(1, ctx.dictionary.make_nop())
@@ -32,7 +34,7 @@ impl StringUtils {
     fn interpret_char_internal(stack: &mut Stack) -> Result<()> {
         let string = stack.pop_string()?;
         let mut chars = string.chars();
-        let char = chars.next().ok_or(UnexpectedEof)?;
+        let char = chars.next().ok_or_else(UnexpectedEof::default)?;
         anyhow::ensure!(chars.next().is_none(), "Expected exactly one character");
         stack.push_int(char as u32)
     }
@@ -130,18 +132,18 @@ impl StringUtils {
         stack.push_raw(head)
     }
 
-    #[cmd(name = "$=", stack)]
-    fn interpret_str_equal(stack: &mut Stack) -> Result<()> {
-        let lhs = stack.pop_string()?;
+    #[cmd(name = "$=", stack, args(map = [0, -1, 0]))]
+    #[cmd(name = "$cmp", stack, args(map = [-1, 0, 1]))]
+    #[cmd(name = "$<>", stack, args(map = [-1, 0, -1]))]
+    #[cmd(name = "$<=", stack, args(map = [-1, -1, 0]))]
+    #[cmd(name = "$>=", stack, args(map = [0, -1, -1]))]
+    #[cmd(name = "$<", stack, args(map = [-1, 0, 0]))]
+    #[cmd(name = "$>", stack, args(map = [0, 0, -1]))]
+    fn interpret_str_cmp(stack: &mut Stack, map: [i8; 3]) -> Result<()> {
         let rhs = stack.pop_string()?;
-        stack.push_bool(lhs == rhs)
-    }
-
-    #[cmd(name = "$cmp", stack)]
-    fn interpret_str_cmp(stack: &mut Stack) -> Result<()> {
         let lhs = stack.pop_string()?;
-        let rhs = stack.pop_string()?;
-        stack.push_int(lhs.cmp(&rhs) as i8)
+        let map_index = lhs.cmp(&rhs) as i8 + 1;
+        stack.push_int(map[map_index as usize])
     }
 
     #[cmd(name = "$reverse", stack)]
@@ -333,18 +335,18 @@ impl StringUtils {
         stack.push_raw(head)
     }
 
-    #[cmd(name = "B=", stack)]
-    fn interpret_bytes_equal(stack: &mut Stack) -> Result<()> {
-        let lhs = stack.pop_bytes()?;
+    #[cmd(name = "B=", stack, args(map = [0, -1, 0]))]
+    #[cmd(name = "Bcmp", stack, args(map = [-1, 0, 1]))]
+    #[cmd(name = "B<>", stack, args(map = [-1, 0, -1]))]
+    #[cmd(name = "B<=", stack, args(map = [-1, -1, 0]))]
+    #[cmd(name = "B>=", stack, args(map = [0, -1, -1]))]
+    #[cmd(name = "B<", stack, args(map = [-1, 0, 0]))]
+    #[cmd(name = "B>", stack, args(map = [0, 0, -1]))]
+    fn interpret_bytes_cmp(stack: &mut Stack, map: [i8; 3]) -> Result<()> {
         let rhs = stack.pop_bytes()?;
-        stack.push_bool(lhs == rhs)
-    }
-
-    #[cmd(name = "Bcmp", stack)]
-    fn interpret_bytes_cmp(stack: &mut Stack) -> Result<()> {
         let lhs = stack.pop_bytes()?;
-        let rhs = stack.pop_bytes()?;
-        stack.push_int(lhs.cmp(&rhs) as i8)
+        let map_index = lhs.cmp(&rhs) as i8 + 1;
+        stack.push_int(map[map_index as usize])
     }
 
     // TODO: bytes <=> int
@@ -0,0 +1,588 @@
+use std::rc::Rc;
+
+use ahash::HashMap;
+use anyhow::{Context as _, Result};
+use everscale_types::prelude::*;
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
+use super::scheme::*;
+use crate::core::*;
+use crate::util::{fits_into_bits, load_int_from_slice, store_int_to_builder};
+
+const MAX_DEPTH: usize = 512;
+
+/// Converts cells into tagged tuples and back.
+pub struct Codec<'a> {
+    scheme: &'a Scheme,
+}
+
+impl<'a> Codec<'a> {
+    pub fn new(scheme: &'a Scheme) -> Self {
+        Self { scheme }
+    }
+
+    pub fn deserialize(
+        &self,
+        ty: &TypeExpr,
+        cs: &mut CellSlice<'_>,
+    ) -> Result<Box<dyn StackValue>> {
+        self.load(ty, &Env::default(), cs, 0)
+    }
+
+    pub fn serialize(&self, ty: &TypeExpr, value: &dyn StackValue) -> Result<Cell> {
+        let mut builder = CellBuilder::new();
+        self.store(ty, &Env::default(), value, &mut builder, 0)?;
+        Ok(builder.build()?)
+    }
+
+    fn load(
+        &self,
+        ty: &TypeExpr,
+        env: &Env,
+        cs: &mut CellSlice<'_>,
+        depth: usize,
+    ) -> Result<Box<dyn StackValue>> {
+        anyhow::ensure!(depth <= MAX_DEPTH, "TL-B value is too deep");
+
+        if let Some(builtin) = self.builtin(ty, env)? {
+            return builtin.load(cs);
+        }
+
+        match ty {
+            TypeExpr::Named(name) => match env.types.get(name) {
+                Some(closure) => self.load(&closure.ty, &closure.env, cs, depth + 1),
+                None => self.load_scheme_type(name, &[], cs, depth),
+            },
+            TypeExpr::Apply(name, args) => {
+                let args = self.resolve_args(args, env)?;
+                self.load_scheme_type(name, &args, cs, depth)
+            }
+            TypeExpr::Ref(inner) => {
+                if let Some(Builtin::Cell) = self.builtin(inner, env)? {
+                    return Ok(Box::new(cs.load_reference_cloned()?));
+                }
+                let mut child = cs.load_reference_as_slice()?;
+                let value = self.load(inner, env, &mut child, depth + 1)?;
+                anyhow::ensure!(
+                    child.is_data_empty() && child.is_refs_empty(),
+                    "Referenced cell is not fully consumed"
+                );
+                Ok(value)
+            }
+            TypeExpr::Record(fields) => {
+                let mut env = env.clone();
+                let values = self.load_fields(fields, &mut env, cs, depth + 1)?;
+                Ok(Box::new(values))
+            }
+            TypeExpr::Nat(_) => anyhow::bail!("Expected a type, got a natural number"),
+        }
+    }
+
+    fn load_scheme_type(
+        &self,
+        name: &str,
+        args: &[Arg],
+        cs: &mut CellSlice<'_>,
+        depth: usize,
+    ) -> Result<Box<dyn StackValue>> {
+        let mut error = None;
+        for constructor in self.constructors(name)? {
+            let Some(mut env) = bind_params(constructor, args)? else {
+                continue;
+            };
+
+            let tag = constructor.tag;
+            if !cs.has_remaining(tag.len, 0) || tag.len > 0 && cs.get_uint(0, tag.len)? != tag.bits
+            {
+                continue;
+            }
+
+            // Try to parse the rest on a copy to allow backtracking
+            let mut attempt = *cs;
+            attempt.try_advance(tag.len, 0);
+            match self.load_fields(&constructor.fields, &mut env, &mut attempt, depth + 1) {
+                Ok(mut values) => {
+                    values.insert(0, Box::new(constructor.name.clone()));
+                    *cs = attempt;
+                    return Ok(Box::new(values));
+                }
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        Err(error.unwrap_or_else(|| anyhow::anyhow!("No constructor of `{name}` matches the data")))
+    }
+
+    fn load_fields(
+        &self,
+        fields: &[Field],
+        env: &mut Env,
+        cs: &mut CellSlice<'_>,
+        depth: usize,
+    ) -> Result<StackTuple> {
+        let mut values = StackTuple::with_capacity(fields.len());
+        for field in fields {
+            if let Some(cond) = &field.cond {
+                if !env.check(cond)? {
+                    values.push(Box::new(()));
+                    continue;
+                }
+            }
+
+            let value = self
+                .load(&field.ty, env, cs, depth)
+                .with_context(|| field_context(field))?;
+            self.bind_field(field, value.as_ref(), env)?;
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    fn store(
+        &self,
+        ty: &TypeExpr,
+        env: &Env,
+        value: &dyn StackValue,
+        builder: &mut CellBuilder,
+        depth: usize,
+    ) -> Result<()> {
+        anyhow::ensure!(depth <= MAX_DEPTH, "TL-B value is too deep");
+
+        if let Some(builtin) = self.builtin(ty, env)? {
+            return builtin.store(value, builder);
+        }
+
+        match ty {
+            TypeExpr::Named(name) => match env.types.get(name) {
+                Some(closure) => self.store(&closure.ty, &closure.env, value, builder, depth + 1),
+                None => self.store_scheme_type(name, &[], value, builder, depth),
+            },
+            TypeExpr::Apply(name, args) => {
+                let args = self.resolve_args(args, env)?;
+                self.store_scheme_type(name, &args, value, builder, depth)
+            }
+            TypeExpr::Ref(inner) => {
+                let cell = if let Some(Builtin::Cell) = self.builtin(inner, env)? {
+                    value.as_cell()?.clone()
+                } else {
+                    let mut child = CellBuilder::new();
+                    self.store(inner, env, value, &mut child, depth + 1)?;
+                    child.build()?
+                };
+                builder.store_reference(cell)?;
+                Ok(())
+            }
+            TypeExpr::Record(fields) => {
+                let mut env = env.clone();
+                self.store_fields(fields, &mut env, value.as_tuple()?, builder, depth + 1)
+            }
+            TypeExpr::Nat(_) => anyhow::bail!("Expected a type, got a natural number"),
+        }
+    }
+
+    fn store_scheme_type(
+        &self,
+        name: &str,
+        args: &[Arg],
+        value: &dyn StackValue,
+        builder: &mut CellBuilder,
+        depth: usize,
+    ) -> Result<()> {
+        let tuple = value.as_tuple()?;
+        let Some((constructor_name, values)) = tuple.split_first() else {
+            anyhow::bail!("Expected a tuple with the constructor name for `{name}`");
+        };
+        let constructor_name = constructor_name.as_string()?;
+
+        let mut found = false;
+        for constructor in self.constructors(name)? {
+            if constructor.name != constructor_name {
+                continue;
+            }
+            found = true;
+
+            let Some(mut env) = bind_params(constructor, args)? else {
+                continue;
+            };
+            builder.store_uint(constructor.tag.bits, constructor.tag.len)?;
+            return self.store_fields(&constructor.fields, &mut env, values, builder, depth + 1);
+        }
+
+        if found {
+            anyhow::bail!(
+                "Constructor `{constructor_name}` does not match the arguments of `{name}`"
+            )
+        } else {
+            anyhow::bail!("Unknown constructor `{constructor_name}` of `{name}`")
+        }
+    }
+
+    fn store_fields(
+        &self,
+        fields: &[Field],
+        env: &mut Env,
+        values: &[Box<dyn StackValue>],
+        builder: &mut CellBuilder,
+        depth: usize,
+    ) -> Result<()> {
+        anyhow::ensure!(
+            fields.len() == values.len(),
+            "Expected {} field value(s), got {}",
+            fields.len(),
+            values.len()
+        );
+
+        for (field, value) in fields.iter().zip(values) {
+            if let Some(cond) = &field.cond {
+                if !env.check(cond)? {
+                    anyhow::ensure!(
+                        value.is_null(),
+                        "Absent {} must be null",
+                        field_context(field)
+                    );
+                    continue;
+                }
+            }
+
+            self.store(&field.ty, env, value.as_ref(), builder, depth)
+                .with_context(|| field_context(field))?;
+            self.bind_field(field, value.as_ref(), env)?;
+        }
+        Ok(())
+    }
+
+    fn constructors(&self, name: &str) -> Result<&[Rc<Constructor>]> {
+        self.scheme.constructors(name)
+    }
+
+    /// Makes the value of a named integer field available to the next fields.
+    fn bind_field(&self, field: &Field, value: &dyn StackValue, env: &mut Env) -> Result<()> {
+        let Some(name) = &field.name else {
+            return Ok(());
+        };
+        if let Ok(int) = value.as_int() {
+            let nat = match self.builtin(&field.ty, env)? {
+                Some(Builtin::Bool) => Some(!int.is_zero() as u64),
+                _ => int.to_u64(),
+            };
+            if let Some(nat) = nat {
+                env.nats.insert(name.clone(), nat);
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_args(&self, args: &[TypeExpr], env: &Env) -> Result<Vec<Arg>> {
+        args.iter()
+            .map(|arg| {
+                Ok(match arg {
+                    TypeExpr::Nat(_) => Arg::Nat(env.eval(arg)?),
+                    TypeExpr::Named(name) if env.nats.contains_key(name) => {
+                        Arg::Nat(env.eval(arg)?)
+                    }
+                    TypeExpr::Named(name) if env.types.contains_key(name) => {
+                        Arg::Type(env.types[name].clone())
+                    }
+                    _ => Arg::Type(Closure {
+                        ty: arg.clone(),
+                        env: Rc::new(env.clone()),
+                    }),
+                })
+            })
+            .collect()
+    }
+
+    fn builtin(&self, ty: &TypeExpr, env: &Env) -> Result<Option<Builtin>> {
+        Ok(match ty {
+            TypeExpr::Named(name) if !env.types.contains_key(name) => match name.as_str() {
+                "#" => Some(Builtin::Uint(32)),
+                "Bool" => Some(Builtin::Bool),
+                "Any" => Some(Builtin::Any),
+                "Cell" => Some(Builtin::Cell),
+                _ => match parse_sized_type(name) {
+                    Some((SizedType::Uint, bits)) => Some(Builtin::Uint(check_bits(bits, 256)?)),
+                    Some((SizedType::Int, bits)) => Some(Builtin::Int(check_bits(bits, 257)?)),
+                    Some((SizedType::Bits, bits)) => Some(Builtin::Bits(check_bits(bits, 1023)?)),
+                    None => None,
+                },
+            },
+            TypeExpr::Apply(name, args) => {
+                let nat = |index: usize| -> Result<u64> {
+                    match args.get(index) {
+                        Some(arg) => env.eval(arg),
+                        None => anyhow::bail!("Too few arguments for `{name}`"),
+                    }
+                };
+                match name.as_str() {
+                    "##" | "uint" => Some(Builtin::Uint(check_bits(nat(0)?, 256)?)),
+                    "int" => Some(Builtin::Int(check_bits(nat(0)?, 257)?)),
+                    "bits" => Some(Builtin::Bits(check_bits(nat(0)?, 1023)?)),
+                    "#<" => Some(Builtin::Uint(bit_len(nat(0)?.saturating_sub(1)))),
+                    "#<=" => Some(Builtin::Uint(bit_len(nat(0)?))),
+                    "VarUInteger" => Some(Builtin::VarUint(check_var_len(nat(0)?)?)),
+                    "VarInteger" => Some(Builtin::VarInt(check_var_len(nat(0)?)?)),
+                    "HashmapE" => {
+                        nat(0)?;
+                        Some(Builtin::Dict)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Builtin {
+    Uint(u16),
+    Int(u16),
+    Bits(u16),
+    Bool,
+    Any,
+    Cell,
+    VarUint(u64),
+    VarInt(u64),
+    /// Dictionary root as in the dictionary words, cell or null.
+    Dict,
+}
+
+impl Builtin {
+    fn load(self, cs: &mut CellSlice<'_>) -> Result<Box<dyn StackValue>> {
+        Ok(match self {
+            Self::Uint(bits) => Box::new(load_int_from_slice(cs, bits, false)?),
+            Self::Int(bits) => Box::new(load_int_from_slice(cs, bits, true)?),
+            Self::Bits(bits) => {
+                anyhow::ensure!(cs.has_remaining(bits, 0), "Not enough bits");
+                let prefix = cs.get_prefix(bits, 0);
+                cs.try_advance(bits, 0);
+                Box::new(make_slice(prefix)?)
+            }
+            Self::Bool => Box::new(-BigInt::from(cs.load_bit()? as u8)),
+            Self::Any => Box::new(make_slice(cs.load_remaining())?),
+            Self::Cell => anyhow::bail!("`Cell` can only be used as a reference, e.g. `^Cell`"),
+            Self::VarUint(n) | Self::VarInt(n) => {
+                let len = cs.load_uint(bit_len(n - 1))? as u16;
+                let signed = matches!(self, Self::VarInt(_));
+                Box::new(load_int_from_slice(cs, len * 8, signed)?)
+            }
+            Self::Dict => match cs.load_bit()? {
+                true => Box::new(cs.load_reference_cloned()?),
+                false => Box::new(()),
+            },
+        })
+    }
+
+    fn store(self, value: &dyn StackValue, builder: &mut CellBuilder) -> Result<()> {
+        match self {
+            Self::Uint(bits) | Self::Int(bits) => {
+                let signed = matches!(self, Self::Int(_));
+                store_checked_int(builder, value.as_int()?, bits, signed)?;
+            }
+            Self::Bits(bits) => {
+                let slice = value.as_slice()?;
+                anyhow::ensure!(
+                    slice.remaining_bits() == bits && slice.is_refs_empty(),
+                    "Expected a slice with exactly {bits} bits and no references"
+                );
+                builder.store_slice(slice)?;
+            }
+            Self::Bool => builder.store_bit(!value.as_int()?.is_zero())?,
+            Self::Any => builder.store_slice(value.as_slice()?)?,
+            Self::Cell => anyhow::bail!("`Cell` can only be used as a reference, e.g. `^Cell`"),
+            Self::VarUint(n) | Self::VarInt(n) => {
+                let int = value.as_int()?;
+                let signed = matches!(self, Self::VarInt(_));
+                let len = (0..n)
+                    .find(|len| fits_into_bits(int, *len as u16 * 8, signed))
+                    .with_context(|| format!("Integer does not fit into {} bytes", n - 1))?;
+                builder.store_uint(len, bit_len(n - 1))?;
+                store_checked_int(builder, int, len as u16 * 8, signed)?;
+            }
+            Self::Dict => match value.is_null() {
+                true => builder.store_bit_zero()?,
+                false => {
+                    builder.store_bit_one()?;
+                    builder.store_reference(value.as_cell()?.clone())?;
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default, Clone)]
+struct Env {
+    nats: HashMap<String, u64>,
+    types: HashMap<String, Closure>,
+}
+
+impl Env {
+    fn eval(&self, expr: &TypeExpr) -> Result<u64> {
+        match expr {
+            TypeExpr::Nat(expr) => self.eval_nat(expr),
+            TypeExpr::Named(name) => self.eval_nat(&NatExpr::Var(name.clone())),
+            _ => anyhow::bail!("Expected a natural number"),
+        }
+    }
+
+    fn eval_nat(&self, expr: &NatExpr) -> Result<u64> {
+        match expr {
+            NatExpr::Const(value) => Ok(*value),
+            NatExpr::Var(name) => self
+                .nats
+                .get(name)
+                .copied()
+                .with_context(|| format!("Unknown natural number `{name}`")),
+            NatExpr::Add(a, b) => self
+                .eval_nat(a)?
+                .checked_add(self.eval_nat(b)?)
+                .context("Natural number overflow"),
+            NatExpr::Mul(a, b) => self
+                .eval_nat(a)?
+                .checked_mul(self.eval_nat(b)?)
+                .context("Natural number overflow"),
+        }
+    }
+
+    fn check(&self, cond: &Cond) -> Result<bool> {
+        let value = self
+            .nats
+            .get(&cond.var)
+            .copied()
+            .with_context(|| format!("Unknown condition variable `{}`", cond.var))?;
+        Ok(match cond.bit {
+            Some(bit) => bit < 64 && value >> bit & 1 != 0,
+            None => value != 0,
+        })
+    }
+}
+
+/// Type argument with the environment where it was written.
+#[derive(Clone)]
+struct Closure {
+    ty: TypeExpr,
+    env: Rc<Env>,
+}
+
+enum Arg {
+    Nat(u64),
+    Type(Closure),
+}
+
+/// Binds constructor parameters to the type arguments.
+///
+/// Returns `None` if the constructor does not match the arguments.
+fn bind_params(constructor: &Constructor, args: &[Arg]) -> Result<Option<Env>> {
+    anyhow::ensure!(
+        constructor.result.len() == args.len(),
+        "Type `{}` expects {} argument(s), got {}",
+        constructor.type_name,
+        constructor.result.len(),
+        args.len()
+    );
+
+    let mut env = Env::default();
+    for (pattern, arg) in constructor.result.iter().zip(args) {
+        match (pattern, arg) {
+            (TypeExpr::Named(name), arg) if constructor.param_kind(name).is_some() => {
+                match (constructor.param_kind(name), arg) {
+                    (Some(ParamKind::Nat), Arg::Nat(value)) => {
+                        env.nats.insert(name.clone(), *value);
+                    }
+                    (Some(ParamKind::Type), Arg::Type(closure)) => {
+                        env.types.insert(name.clone(), closure.clone());
+                    }
+                    _ => anyhow::bail!(
+                        "Invalid argument kind for `{name}` of `{}`",
+                        constructor.type_name
+                    ),
+                }
+            }
+            (TypeExpr::Nat(pattern), Arg::Nat(value)) if !match_nat(pattern, *value, &mut env)? => {
+                return Ok(None);
+            }
+            (TypeExpr::Nat(_), Arg::Type(_)) => anyhow::bail!(
+                "Expected a natural number argument for `{}`",
+                constructor.type_name
+            ),
+            // Type patterns are not checked
+            _ => {}
+        }
+    }
+    Ok(Some(env))
+}
+
+/// Matches `n`, `n + 1` or `1` patterns of the resulting type.
+fn match_nat(pattern: &NatExpr, value: u64, env: &mut Env) -> Result<bool> {
+    match pattern {
+        NatExpr::Const(c) => Ok(*c == value),
+        NatExpr::Var(name) => {
+            env.nats.insert(name.clone(), value);
+            Ok(true)
+        }
+        NatExpr::Add(a, b) => match (a.as_ref(), b.as_ref()) {
+            (NatExpr::Var(_), NatExpr::Const(c)) | (NatExpr::Const(c), NatExpr::Var(_)) => {
+                let var = if let NatExpr::Var(_) = a.as_ref() {
+                    a
+                } else {
+                    b
+                };
+                match value.checked_sub(*c) {
+                    Some(value) => match_nat(var, value, env),
+                    None => Ok(false),
+                }
+            }
+            _ => anyhow::bail!("Unsupported natural number pattern"),
+        },
+        NatExpr::Mul(..) => anyhow::bail!("Unsupported natural number pattern"),
+    }
+}
+
+fn store_checked_int(
+    builder: &mut CellBuilder,
+    int: &BigInt,
+    bits: u16,
+    signed: bool,
+) -> Result<()> {
+    anyhow::ensure!(
+        fits_into_bits(int, bits, signed),
+        "Integer does not fit into {bits} bits"
+    );
+    store_int_to_builder(builder, &mut int.clone(), bits, signed)
+}
+
+fn make_slice(cs: CellSlice<'_>) -> Result<OwnedCellSlice> {
+    let mut builder = CellBuilder::new();
+    builder.store_slice(cs)?;
+    Ok(OwnedCellSlice::new(builder.build()?))
+}
+
+fn field_context(field: &Field) -> String {
+    match &field.name {
+        Some(name) => format!("field `{name}`"),
+        None => "anonymous field".to_owned(),
+    }
+}
+
+fn check_bits<T: TryInto<u16> + Copy + std::fmt::Display>(bits: T, max: u16) -> Result<u16> {
+    match bits.try_into() {
+        Ok(bits) if bits <= max => Ok(bits),
+        _ => anyhow::bail!("Bit length {bits} is out of range 0..={max}"),
+    }
+}
+
+fn check_var_len(n: u64) -> Result<u64> {
+    anyhow::ensure!(
+        (1..=33).contains(&n),
+        "Variable integer length {n} is out of range 1..=33"
+    );
+    Ok(n)
+}
+
+fn bit_len(value: u64) -> u16 {
+    (64 - value.leading_zeros()) as u16
+}
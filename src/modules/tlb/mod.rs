@@ -0,0 +1,66 @@
+use anyhow::{Context as _, Result};
+
+use crate::core::*;
+
+use self::codec::Codec;
+use self::scheme::{parse_type, Scheme};
+
+mod codec;
+mod scheme;
+
+/// TL-B schemes loaded at runtime.
+///
+/// Values of scheme types are represented as tuples with the constructor
+/// name followed by the field values, e.g. `[ "just" 123 ]` for `Maybe uint8`.
+pub struct TlbUtils;
+
+#[fift_module]
+impl TlbUtils {
+    #[cmd(name = "tlb-load")]
+    fn interpret_tlb_load(ctx: &mut Context) -> Result<()> {
+        let source = ctx.stack.pop_string()?;
+        tlb_scheme(ctx).load(&source)
+    }
+
+    #[cmd(name = "tlb-include")]
+    fn interpret_tlb_include(ctx: &mut Context) -> Result<()> {
+        let name = ctx.stack.pop_string()?;
+        let source = ctx
+            .env
+            .read_file(&name)
+            .with_context(|| format!("Failed to read TL-B scheme `{name}`"))?;
+        let source = String::from_utf8(source)?;
+        tlb_scheme(ctx)
+            .load(&source)
+            .with_context(|| format!("Failed to load TL-B scheme `{name}`"))
+    }
+
+    #[cmd(name = "tlb-deserialize")]
+    fn interpret_tlb_deserialize(ctx: &mut Context) -> Result<()> {
+        let ty = parse_type(&ctx.stack.pop_string()?)?;
+        let value = ctx.stack.pop()?;
+        let mut cs = match value.as_cell() {
+            Ok(cell) => cell.as_ref().as_slice()?,
+            Err(_) => value.as_slice()?,
+        };
+
+        let value = Codec::new(tlb_scheme(ctx)).deserialize(&ty, &mut cs)?;
+        anyhow::ensure!(
+            cs.is_data_empty() && cs.is_refs_empty(),
+            "Cell is not fully consumed by the TL-B value"
+        );
+        ctx.stack.push_raw(value)
+    }
+
+    #[cmd(name = "tlb-serialize")]
+    fn interpret_tlb_serialize(ctx: &mut Context) -> Result<()> {
+        let ty = parse_type(&ctx.stack.pop_string()?)?;
+        let value = ctx.stack.pop()?;
+        let cell = Codec::new(tlb_scheme(ctx)).serialize(&ty, value.as_ref())?;
+        ctx.stack.push(cell)
+    }
+}
+
+fn tlb_scheme<'a>(ctx: &'a mut Context) -> &'a mut Scheme {
+    ctx.user_data_or_default()
+}
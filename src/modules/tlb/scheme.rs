@@ -0,0 +1,659 @@
+use std::rc::Rc;
+
+use ahash::HashMap;
+use anyhow::Result;
+
+/// Types which are always available, their definitions are also
+/// recognized by name so that the standard `block.tlb` can be loaded.
+const PRELUDE: &str = r#"
+unit$_ = Unit;
+true$_ = True;
+nothing$0 {X:Type} = Maybe X;
+just$1 {X:Type} value:X = Maybe X;
+left$0 {X:Type} {Y:Type} value:X = Either X Y;
+right$1 {X:Type} {Y:Type} value:Y = Either X Y;
+pair$_ {X:Type} {Y:Type} first:X second:Y = Both X Y;
+nanograms$_ amount:(VarUInteger 16) = Grams;
+_ grams:Grams = Coins;
+"#;
+
+/// Types which are handled natively, their definitions are ignored.
+const BUILTIN_TYPES: &[&str] = &[
+    "#",
+    "##",
+    "#<",
+    "#<=",
+    "uint",
+    "int",
+    "bits",
+    "Bool",
+    "Any",
+    "Cell",
+    "Type",
+    "VarUInteger",
+    "VarInteger",
+    "HashmapE",
+];
+
+/// Constructors of the loaded TL-B types.
+pub struct Scheme {
+    types: HashMap<String, Vec<Rc<Constructor>>>,
+    /// Types which can be loaded but not used, with the reason.
+    unsupported: HashMap<String, String>,
+}
+
+impl Default for Scheme {
+    fn default() -> Self {
+        let mut scheme = Self {
+            types: Default::default(),
+            unsupported: Default::default(),
+        };
+        // Prelude is a valid scheme
+        scheme.load(PRELUDE).unwrap();
+        scheme
+    }
+}
+
+impl Scheme {
+    /// Parses the scheme and adds its types, replacing the
+    /// previously loaded types with the same names.
+    pub fn load(&mut self, source: &str) -> Result<()> {
+        let mut types = HashMap::<String, Vec<Rc<Constructor>>>::default();
+        let mut unsupported = HashMap::<String, String>::default();
+        let mut parser = Parser::new(source)?;
+        loop {
+            let start = parser.pos;
+            let constructor = match parser.parse_constructor() {
+                Ok(Some(constructor)) => constructor,
+                Ok(None) => break,
+                Err(_) if parser.unsupported.is_some() => {
+                    // Keep the rest of the scheme usable
+                    let reason = parser.unsupported.take().unwrap_or_default();
+                    let type_name = parser.skip_constructor(start)?;
+                    unsupported.entry(type_name).or_insert(reason);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if is_builtin_type(&constructor.type_name) {
+                continue;
+            }
+            types
+                .entry(constructor.type_name.clone())
+                .or_default()
+                .push(Rc::new(constructor));
+        }
+
+        for type_name in types.keys() {
+            self.unsupported.remove(type_name);
+        }
+        self.types.extend(types);
+        for type_name in unsupported.keys() {
+            self.types.remove(type_name);
+        }
+        self.unsupported.extend(unsupported);
+        Ok(())
+    }
+
+    pub fn constructors(&self, type_name: &str) -> Result<&[Rc<Constructor>]> {
+        if let Some(reason) = self.unsupported.get(type_name) {
+            anyhow::bail!("TL-B type `{type_name}` is not supported: {reason}");
+        }
+        match self.types.get(type_name) {
+            Some(constructors) => Ok(constructors),
+            None => anyhow::bail!("Unknown TL-B type `{type_name}`"),
+        }
+    }
+}
+
+/// Parses a type expression, e.g. `Maybe ^Cell`.
+pub fn parse_type(source: &str) -> Result<TypeExpr> {
+    let mut parser = Parser::new(source)?;
+    let ty = parser.parse_expr()?;
+    if let Some(token) = parser.peek() {
+        return Err(parser.error_at(token, "Unexpected token"));
+    }
+    Ok(ty)
+}
+
+pub fn is_builtin_type(name: &str) -> bool {
+    BUILTIN_TYPES.contains(&name) || parse_sized_type(name).is_some()
+}
+
+/// Parses `uintN`, `intN` and `bitsN` type names.
+pub fn parse_sized_type(name: &str) -> Option<(SizedType, u16)> {
+    let (ty, bits) = if let Some(bits) = name.strip_prefix("uint") {
+        (SizedType::Uint, bits)
+    } else if let Some(bits) = name.strip_prefix("int") {
+        (SizedType::Int, bits)
+    } else if let Some(bits) = name.strip_prefix("bits") {
+        (SizedType::Bits, bits)
+    } else {
+        return None;
+    };
+    if !bits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((ty, bits.parse().ok()?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizedType {
+    Uint,
+    Int,
+    Bits,
+}
+
+pub struct Constructor {
+    pub name: String,
+    pub tag: Tag,
+    pub params: Vec<Param>,
+    pub fields: Vec<Field>,
+    pub type_name: String,
+    /// Arguments of the resulting type, e.g. `X` in `= Maybe X`.
+    pub result: Vec<TypeExpr>,
+}
+
+impl Constructor {
+    pub fn param_kind(&self, name: &str) -> Option<ParamKind> {
+        self.params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| param.kind)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tag {
+    pub bits: u64,
+    pub len: u16,
+}
+
+pub struct Param {
+    pub name: String,
+    pub kind: ParamKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Type,
+    Nat,
+}
+
+#[derive(Clone)]
+pub struct Field {
+    pub name: Option<String>,
+    /// The field is present only if the condition is true.
+    pub cond: Option<Cond>,
+    pub ty: TypeExpr,
+}
+
+/// Field condition, `flag?` or `flags.N?`.
+#[derive(Clone)]
+pub struct Cond {
+    pub var: String,
+    pub bit: Option<u32>,
+}
+
+#[derive(Clone)]
+pub enum TypeExpr {
+    /// Type name or a variable.
+    Named(String),
+    /// Type with arguments, e.g. `Maybe X` or `## 8`.
+    Apply(String, Vec<TypeExpr>),
+    Ref(Box<TypeExpr>),
+    /// Anonymous constructor, e.g. `[ a:uint32 b:uint32 ]`.
+    Record(Rc<[Field]>),
+    Nat(NatExpr),
+}
+
+#[derive(Clone)]
+pub enum NatExpr {
+    Const(u64),
+    Var(String),
+    Add(Box<NatExpr>, Box<NatExpr>),
+    Mul(Box<NatExpr>, Box<NatExpr>),
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    /// Reason of the last error if it was caused by an unsupported feature.
+    unsupported: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+}
+
+impl Token<'_> {
+    fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    fn is_word(&self) -> bool {
+        self.text
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || c == b'_')
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Result<Self> {
+        const PUNCT: &[&str] = &["#<=", "##", "#<", "<=", ">=", "!="];
+
+        let mut tokens = Vec::new();
+        let bytes = source.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let rest = &source[i..];
+            let c = bytes[i];
+            if c.is_ascii_whitespace() {
+                i += 1;
+            } else if rest.starts_with("//") {
+                i += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                match rest.find("*/") {
+                    Some(end) => i += end + 2,
+                    None => anyhow::bail!("Unterminated comment at line {}", line_at(source, i)),
+                }
+            } else if c.is_ascii_alphanumeric() || c == b'_' {
+                let len = rest
+                    .bytes()
+                    .take_while(|c| c.is_ascii_alphanumeric() || *c == b'_')
+                    .count();
+                tokens.push(Token {
+                    text: &rest[..len],
+                    start: i,
+                });
+                i += len;
+            } else {
+                let len = match PUNCT.iter().find(|punct| rest.starts_with(*punct)) {
+                    Some(punct) => punct.len(),
+                    None => rest.chars().next().map(char::len_utf8).unwrap_or(1),
+                };
+                tokens.push(Token {
+                    text: &rest[..len],
+                    start: i,
+                });
+                i += len;
+            }
+        }
+
+        Ok(Self {
+            source,
+            tokens,
+            pos: 0,
+            unsupported: None,
+        })
+    }
+
+    /// Skips the constructor which starts at the specified token,
+    /// returns its type name.
+    fn skip_constructor(&mut self, start: usize) -> Result<String> {
+        self.pos = start;
+        let mut depth = 0usize;
+        let mut type_name = None;
+        loop {
+            let Some(token) = self.next() else {
+                return Err(self.error("Expected `;`"));
+            };
+            match token.text {
+                "{" | "(" | "[" => depth += 1,
+                "}" | ")" | "]" => depth = depth.saturating_sub(1),
+                "=" if depth == 0 && type_name.is_none() => {
+                    type_name = self.peek().filter(Token::is_word).map(|token| token.text);
+                }
+                ";" if depth == 0 => break,
+                _ => {}
+            }
+        }
+        match type_name {
+            Some(type_name) => Ok(type_name.to_owned()),
+            None => Err(self.error("Expected type name")),
+        }
+    }
+
+    fn parse_constructor(&mut self) -> Result<Option<Constructor>> {
+        let Some(name) = self.next() else {
+            return Ok(None);
+        };
+        if !name.is_word() {
+            return Err(self.error_at(name, "Expected constructor name"));
+        }
+
+        let tag = match self.peek() {
+            Some(kind) if kind.start == name.end() && matches!(kind.text, "#" | "$") => {
+                self.pos += 1;
+                match self.peek() {
+                    Some(tag) if tag.start == kind.end() && tag.is_word() => {
+                        self.pos += 1;
+                        parse_tag(kind.text, tag.text).ok_or_else(|| {
+                            self.error_at(tag, &format!("Invalid constructor tag `{}`", tag.text))
+                        })?
+                    }
+                    _ => return Err(self.error_at(kind, "Expected constructor tag")),
+                }
+            }
+            _ if name.text == "_" => Tag::default(),
+            _ => {
+                let line = line_at(self.source, name.start);
+                self.unsupported = Some(format!("implicit constructor tag at line {line}"));
+                return Err(self.error_at(
+                    name,
+                    &format!("Constructor `{}` has no explicit tag", name.text),
+                ));
+            }
+        };
+
+        let mut params = Vec::new();
+        let fields = self.parse_fields("=", Some(&mut params))?;
+
+        let type_name = match self.next() {
+            Some(token) if token.is_word() => token.text.to_owned(),
+            _ => return Err(self.error("Expected type name")),
+        };
+        let mut result = Vec::new();
+        while !self.eat(";") {
+            if self.peek().is_none() {
+                return Err(self.error("Expected `;`"));
+            }
+            result.push(self.parse_term()?);
+        }
+
+        Ok(Some(Constructor {
+            name: name.text.to_owned(),
+            tag,
+            params,
+            fields,
+            type_name,
+            result,
+        }))
+    }
+
+    /// Parses fields until the closing token, which is consumed.
+    fn parse_fields(
+        &mut self,
+        close: &str,
+        mut params: Option<&mut Vec<Param>>,
+    ) -> Result<Vec<Field>> {
+        let mut fields = Vec::new();
+        loop {
+            let Some(token) = self.peek() else {
+                return Err(self.error(&format!("Expected `{close}`")));
+            };
+            if token.text == close {
+                self.pos += 1;
+                return Ok(fields);
+            }
+
+            if token.text == "{" {
+                self.pos += 1;
+                match (self.parse_param()?, params.as_deref_mut()) {
+                    (Some(param), Some(params)) => params.push(param),
+                    (Some(_), None) => {
+                        return Err(self.error_at(token, "Implicit parameters are not allowed here"))
+                    }
+                    // Constraints are not checked
+                    (None, _) => {}
+                }
+                continue;
+            }
+
+            let name = match self.tokens.get(self.pos + 1) {
+                Some(colon) if colon.text == ":" && token.is_word() => {
+                    self.pos += 2;
+                    (token.text != "_").then(|| token.text.to_owned())
+                }
+                _ => None,
+            };
+
+            let cond = self.parse_cond();
+            let ty = self.parse_term()?;
+            fields.push(Field { name, cond, ty });
+        }
+    }
+
+    /// Parses `flag?`, `flags.N?` or `(flags . N)?` field condition.
+    fn parse_cond(&mut self) -> Option<Cond> {
+        let texts = self.tokens[self.pos..]
+            .iter()
+            .take(6)
+            .map(|token| token.text)
+            .collect::<Vec<_>>();
+
+        let (var, bit, len) = match texts.as_slice() {
+            [var, ".", bit, "?", ..] => (*var, Some(*bit), 4),
+            ["(", var, ".", bit, ")", "?"] => (*var, Some(*bit), 6),
+            [var, "?", ..] => (*var, None, 2),
+            _ => return None,
+        };
+        let bit = match bit {
+            Some(bit) => Some(bit.parse().ok()?),
+            None => None,
+        };
+        if var.parse::<u64>().is_ok()
+            || !var.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_')
+        {
+            return None;
+        }
+
+        self.pos += len;
+        Some(Cond {
+            var: var.to_owned(),
+            bit,
+        })
+    }
+
+    /// Parses `{X:Type}` and `{n:#}` after `{`, skips constraints.
+    fn parse_param(&mut self) -> Result<Option<Param>> {
+        if let [name, colon, kind, close, ..] = &self.tokens[self.pos..] {
+            let kind = match kind.text {
+                "Type" => Some(ParamKind::Type),
+                "#" => Some(ParamKind::Nat),
+                _ => None,
+            };
+            if let (Some(kind), ":", "}") = (kind, colon.text, close.text) {
+                if name.is_word() {
+                    self.pos += 4;
+                    return Ok(Some(Param {
+                        name: name.text.to_owned(),
+                        kind,
+                    }));
+                }
+            }
+        }
+
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next().map(|token| token.text) {
+                Some("{") => depth += 1,
+                Some("}") => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error("Expected `}`")),
+            }
+        }
+        Ok(None)
+    }
+
+    fn parse_expr(&mut self) -> Result<TypeExpr> {
+        let mut lhs = self.parse_product()?;
+        while self.eat("+") {
+            let rhs = self.parse_product()?;
+            lhs = TypeExpr::Nat(NatExpr::Add(
+                Box::new(self.to_nat(lhs)?),
+                Box::new(self.to_nat(rhs)?),
+            ));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_product(&mut self) -> Result<TypeExpr> {
+        let mut lhs = self.parse_apply()?;
+        while self.eat("*") {
+            let rhs = self.parse_apply()?;
+            lhs = TypeExpr::Nat(NatExpr::Mul(
+                Box::new(self.to_nat(lhs)?),
+                Box::new(self.to_nat(rhs)?),
+            ));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_apply(&mut self) -> Result<TypeExpr> {
+        if let Some(op) = self.peek().filter(|token| is_nat_operator(token.text)) {
+            self.pos += 1;
+            return Ok(TypeExpr::Apply(
+                op.text.to_owned(),
+                vec![self.parse_term()?],
+            ));
+        }
+
+        let head = self.parse_term()?;
+        let mut args = Vec::new();
+        while self.peek().is_some_and(starts_term) {
+            args.push(self.parse_term()?);
+        }
+
+        match head {
+            _ if args.is_empty() => Ok(head),
+            TypeExpr::Named(name) => Ok(TypeExpr::Apply(name, args)),
+            _ => Err(self.error("Expected type name before arguments")),
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<TypeExpr> {
+        let Some(token) = self.next() else {
+            return Err(self.error("Unexpected end of scheme"));
+        };
+        Ok(match token.text {
+            "^" => TypeExpr::Ref(Box::new(self.parse_term()?)),
+            "(" => {
+                let expr = self.parse_expr()?;
+                self.expect(")")?;
+                expr
+            }
+            "[" => TypeExpr::Record(self.parse_fields("]", None)?.into()),
+            "#" => TypeExpr::Named("#".to_owned()),
+            op if is_nat_operator(op) => TypeExpr::Apply(op.to_owned(), vec![self.parse_term()?]),
+            "~" => {
+                let line = line_at(self.source, token.start);
+                self.unsupported = Some(format!("output parameters at line {line}"));
+                return Err(self.error_at(token, "Output parameters are not supported"));
+            }
+            text if token.is_word() => match text.parse::<u64>() {
+                Ok(value) => TypeExpr::Nat(NatExpr::Const(value)),
+                Err(_) => TypeExpr::Named(text.to_owned()),
+            },
+            text => return Err(self.error_at(token, &format!("Unexpected token `{text}`"))),
+        })
+    }
+
+    fn to_nat(&self, expr: TypeExpr) -> Result<NatExpr> {
+        match expr {
+            TypeExpr::Nat(expr) => Ok(expr),
+            TypeExpr::Named(name) => Ok(NatExpr::Var(name)),
+            _ => Err(self.error("Expected natural number expression")),
+        }
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.peek()?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        let matches = matches!(self.peek(), Some(token) if token.text == text);
+        self.pos += matches as usize;
+        matches
+    }
+
+    fn expect(&mut self, text: &str) -> Result<()> {
+        if self.eat(text) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected `{text}`")))
+        }
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        match self.peek() {
+            Some(token) => self.error_at(token, message),
+            None => anyhow::anyhow!("{message} at the end of scheme"),
+        }
+    }
+
+    fn error_at(&self, token: Token<'_>, message: &str) -> anyhow::Error {
+        anyhow::anyhow!("{message} at line {}", line_at(self.source, token.start))
+    }
+}
+
+fn starts_term(token: Token<'_>) -> bool {
+    matches!(token.text, "^" | "(" | "[" | "#" | "~") || token.is_word()
+}
+
+fn is_nat_operator(text: &str) -> bool {
+    matches!(text, "##" | "#<" | "#<=")
+}
+
+/// Parses `$0101`, `#8a`, `#8_` (with the completion tag) and `_` tags.
+fn parse_tag(kind: &str, text: &str) -> Option<Tag> {
+    if text == "_" {
+        return Some(Tag::default());
+    }
+
+    let mut tag = Tag::default();
+    let mut push = |bit: bool| {
+        tag.bits = (tag.bits << 1) | bit as u64;
+        tag.len += 1;
+    };
+    match kind {
+        "$" => {
+            for c in text.chars() {
+                push(match c {
+                    '0' => false,
+                    '1' => true,
+                    _ => return None,
+                });
+            }
+        }
+        _ => {
+            let (digits, completion) = match text.strip_suffix('_') {
+                Some(digits) => (digits, true),
+                None => (text, false),
+            };
+            for c in digits.chars() {
+                let digit = c.to_digit(16)?;
+                for i in (0..4).rev() {
+                    push(digit >> i & 1 != 0);
+                }
+            }
+            if completion {
+                // Strip trailing zeros and the terminating one
+                while tag.len > 0 && tag.bits & 1 == 0 {
+                    tag.bits >>= 1;
+                    tag.len -= 1;
+                }
+                if tag.len == 0 {
+                    return None;
+                }
+                tag.bits >>= 1;
+                tag.len -= 1;
+            }
+        }
+    }
+
+    (tag.len <= 64).then_some(tag)
+}
+
+fn line_at(source: &str, offset: usize) -> usize {
+    source[..offset].bytes().filter(|&c| c == b'\n').count() + 1
+}
@@ -0,0 +1,448 @@
+use std::io::Write;
+
+use anyhow::{Context as _, Result};
+use everscale_types::cell::DefaultFinalizer;
+use everscale_types::dict::Dict;
+use everscale_types::prelude::*;
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+use sha2::Digest;
+
+use crate::core::*;
+use crate::util::*;
+
+pub struct TokenUtils;
+
+#[fift_module]
+impl TokenUtils {
+    // === Message bodies (TEP-74, TEP-62) ===
+
+    // ( query-id amount dest-wc dest-addr resp-wc resp-addr custom-payload fwd-amount fwd-payload -- c )
+    #[cmd(name = "jetton-transfer-body", stack)]
+    fn interpret_jetton_transfer_body(stack: &mut Stack) -> Result<()> {
+        let forward_payload = pop_maybe_cell(stack)?;
+        let forward_amount = stack.pop_int()?;
+        let custom_payload = pop_maybe_cell(stack)?;
+        let response = pop_msg_address(stack)?;
+        let destination = pop_msg_address(stack)?;
+        let amount = stack.pop_int()?;
+        let query_id = stack.pop_u64_checked()?;
+
+        let mut builder = CellBuilder::new();
+        builder.store_u32(JETTON_TRANSFER_OP)?;
+        builder.store_u64(query_id)?;
+        store_var_uint16(&mut builder, &amount)?;
+        store_msg_address(&mut builder, &destination)?;
+        store_msg_address(&mut builder, &response)?;
+        store_maybe_ref(&mut builder, custom_payload)?;
+        store_var_uint16(&mut builder, &forward_amount)?;
+        store_maybe_ref(&mut builder, forward_payload)?;
+        stack.push(builder.build()?)
+    }
+
+    // ( query-id amount from-wc from-addr resp-wc resp-addr fwd-amount fwd-payload -- c )
+    #[cmd(name = "jetton-internal-transfer-body", stack)]
+    fn interpret_jetton_internal_transfer_body(stack: &mut Stack) -> Result<()> {
+        let forward_payload = pop_maybe_cell(stack)?;
+        let forward_amount = stack.pop_int()?;
+        let response = pop_msg_address(stack)?;
+        let from = pop_msg_address(stack)?;
+        let amount = stack.pop_int()?;
+        let query_id = stack.pop_u64_checked()?;
+
+        let mut builder = CellBuilder::new();
+        builder.store_u32(JETTON_INTERNAL_TRANSFER_OP)?;
+        builder.store_u64(query_id)?;
+        store_var_uint16(&mut builder, &amount)?;
+        store_msg_address(&mut builder, &from)?;
+        store_msg_address(&mut builder, &response)?;
+        store_var_uint16(&mut builder, &forward_amount)?;
+        store_maybe_ref(&mut builder, forward_payload)?;
+        stack.push(builder.build()?)
+    }
+
+    // ( query-id owner-wc owner-addr resp-wc resp-addr custom-payload fwd-amount fwd-payload -- c )
+    #[cmd(name = "nft-transfer-body", stack)]
+    fn interpret_nft_transfer_body(stack: &mut Stack) -> Result<()> {
+        let forward_payload = pop_maybe_cell(stack)?;
+        let forward_amount = stack.pop_int()?;
+        let custom_payload = pop_maybe_cell(stack)?;
+        let response = pop_msg_address(stack)?;
+        let new_owner = pop_msg_address(stack)?;
+        let query_id = stack.pop_u64_checked()?;
+
+        let mut builder = CellBuilder::new();
+        builder.store_u32(NFT_TRANSFER_OP)?;
+        builder.store_u64(query_id)?;
+        store_msg_address(&mut builder, &new_owner)?;
+        store_msg_address(&mut builder, &response)?;
+        store_maybe_ref(&mut builder, custom_payload)?;
+        store_var_uint16(&mut builder, &forward_amount)?;
+        store_maybe_ref(&mut builder, forward_payload)?;
+        stack.push(builder.build()?)
+    }
+
+    #[cmd(name = "parse-jetton-transfer", stack)]
+    fn interpret_parse_jetton_transfer(stack: &mut Stack) -> Result<()> {
+        let body = pop_body(stack)?;
+        let mut cs = body.apply()?;
+        load_op(&mut cs, JETTON_TRANSFER_OP, "jetton transfer")?;
+
+        stack.push_int(cs.load_u64()?)?;
+        stack.push_int(load_var_uint16(&mut cs)?)?;
+        push_msg_address(stack, load_msg_address(&mut cs)?)?;
+        push_msg_address(stack, load_msg_address(&mut cs)?)?;
+        push_maybe_cell(stack, load_maybe_ref(&mut cs)?)?;
+        stack.push_int(load_var_uint16(&mut cs)?)?;
+        push_maybe_cell(stack, load_either_cell(&mut cs)?)
+    }
+
+    #[cmd(name = "parse-jetton-internal-transfer", stack)]
+    fn interpret_parse_jetton_internal_transfer(stack: &mut Stack) -> Result<()> {
+        let body = pop_body(stack)?;
+        let mut cs = body.apply()?;
+        load_op(
+            &mut cs,
+            JETTON_INTERNAL_TRANSFER_OP,
+            "jetton internal transfer",
+        )?;
+
+        stack.push_int(cs.load_u64()?)?;
+        stack.push_int(load_var_uint16(&mut cs)?)?;
+        push_msg_address(stack, load_msg_address(&mut cs)?)?;
+        push_msg_address(stack, load_msg_address(&mut cs)?)?;
+        stack.push_int(load_var_uint16(&mut cs)?)?;
+        push_maybe_cell(stack, load_either_cell(&mut cs)?)
+    }
+
+    #[cmd(name = "parse-nft-transfer", stack)]
+    fn interpret_parse_nft_transfer(stack: &mut Stack) -> Result<()> {
+        let body = pop_body(stack)?;
+        let mut cs = body.apply()?;
+        load_op(&mut cs, NFT_TRANSFER_OP, "NFT transfer")?;
+
+        stack.push_int(cs.load_u64()?)?;
+        push_msg_address(stack, load_msg_address(&mut cs)?)?;
+        push_msg_address(stack, load_msg_address(&mut cs)?)?;
+        push_maybe_cell(stack, load_maybe_ref(&mut cs)?)?;
+        stack.push_int(load_var_uint16(&mut cs)?)?;
+        push_maybe_cell(stack, load_either_cell(&mut cs)?)
+    }
+
+    // === Text comments ===
+
+    #[cmd(name = "comment>body", stack)]
+    fn interpret_comment_to_body(stack: &mut Stack) -> Result<()> {
+        let comment = stack.pop_string()?;
+        let mut builder = CellBuilder::new();
+        builder.store_u32(OpCodes::TEXT_COMMENT)?;
+        store_snake_data(&mut builder, comment.as_bytes())?;
+        stack.push(builder.build()?)
+    }
+
+    #[cmd(name = "body>comment", stack, args(quiet = false))]
+    #[cmd(name = "body>comment?", stack, args(quiet = true))]
+    fn interpret_body_to_comment(stack: &mut Stack, quiet: bool) -> Result<()> {
+        let body = pop_body(stack)?;
+        match parse_comment(body.apply()?) {
+            Ok(comment) => {
+                stack.push(comment)?;
+                if quiet {
+                    stack.push_bool(true)?;
+                }
+                Ok(())
+            }
+            Err(_) if quiet => stack.push_bool(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // === Op codes ===
+
+    #[cmd(name = "op-name!")]
+    fn interpret_set_op_name(ctx: &mut Context) -> Result<()> {
+        let name = ctx.stack.pop_string()?;
+        let op = ctx.stack.pop_u32_checked()?;
+        ctx.op_codes.insert(op, *name);
+        Ok(())
+    }
+
+    #[cmd(name = "op-name?")]
+    fn interpret_get_op_name(ctx: &mut Context) -> Result<()> {
+        let op = ctx.stack.pop_u32_checked()?;
+        match ctx.op_codes.get(op) {
+            Some(name) => {
+                ctx.stack.push(name.to_owned())?;
+                ctx.stack.push_bool(true)
+            }
+            None => ctx.stack.push_bool(false),
+        }
+    }
+
+    #[cmd(name = "body.")]
+    fn interpret_print_body(ctx: &mut Context) -> Result<()> {
+        const DEFAULT_RECURSIVE_PRINT_LIMIT: usize = 100;
+
+        let body = pop_body(&mut ctx.stack)?;
+        let body = ctx
+            .op_codes
+            .display_body(body.apply()?, DEFAULT_RECURSIVE_PRINT_LIMIT);
+        write!(ctx.stdout, "{body}")?;
+        Ok(())
+    }
+
+    // === Token metadata (TEP-64) ===
+
+    #[cmd(name = "offchain-metadata", stack)]
+    fn interpret_offchain_metadata(stack: &mut Stack) -> Result<()> {
+        let uri = stack.pop_string()?;
+        let mut builder = CellBuilder::new();
+        builder.store_u8(OFFCHAIN_CONTENT_TAG)?;
+        store_snake_data(&mut builder, uri.as_bytes())?;
+        stack.push(builder.build()?)
+    }
+
+    // ( [ [ key value ] ... ] -- c )
+    #[cmd(name = "onchain-metadata", stack)]
+    fn interpret_onchain_metadata(stack: &mut Stack) -> Result<()> {
+        let entries = stack.pop_tuple()?;
+
+        let mut dict = Dict::<HashBytes, Cell>::new();
+        for entry in entries.iter() {
+            let entry = entry.as_tuple()?;
+            anyhow::ensure!(entry.len() == 2, "Expected a [key value] pair");
+
+            let key = HashBytes(sha2::Sha256::digest(entry[0].as_string()?.as_bytes()).into());
+
+            let value = &entry[1];
+            let value = match value.ty() {
+                StackValueType::String => value.as_string()?.as_bytes(),
+                _ => value.as_bytes()?,
+            };
+            let mut builder = CellBuilder::new();
+            builder.store_u8(SNAKE_DATA_TAG)?;
+            store_snake_data(&mut builder, value)?;
+
+            dict.set(key, builder.build()?)?;
+        }
+
+        let mut builder = CellBuilder::new();
+        builder.store_u8(ONCHAIN_CONTENT_TAG)?;
+        dict.store_into(&mut builder, &mut Cell::default_finalizer())?;
+        stack.push(builder.build()?)
+    }
+
+    // ( c -- S ) for off-chain and ( c -- [ [ key value ] ... ] ) for on-chain metadata
+    #[cmd(name = "parse-metadata", stack)]
+    fn interpret_parse_metadata(stack: &mut Stack) -> Result<()> {
+        let content = pop_body(stack)?;
+        let mut cs = content.apply()?;
+        match cs.load_u8()? {
+            OFFCHAIN_CONTENT_TAG => {
+                let uri = String::from_utf8(load_snake_data(cs)?)
+                    .context("Invalid off-chain metadata URI")?;
+                stack.push(uri)
+            }
+            ONCHAIN_CONTENT_TAG => {
+                let dict = Dict::<HashBytes, Cell>::load_from(&mut cs)?;
+
+                let mut entries = StackTuple::new();
+                for entry in dict.iter() {
+                    let (key, value) = entry?;
+
+                    let key: Box<dyn StackValue> = match known_metadata_key(&key) {
+                        Some(name) => Box::new(name.to_owned()),
+                        None => Box::new(BigInt::from_bytes_be(Sign::Plus, key.as_slice())),
+                    };
+
+                    let mut value = DynCell::as_slice(value.as_ref())?;
+                    let data = match value.load_u8()? {
+                        SNAKE_DATA_TAG => load_snake_data(value)?,
+                        CHUNKED_DATA_TAG => {
+                            load_chunked_data(Option::<Cell>::load_from(&mut value)?)?
+                        }
+                        tag => anyhow::bail!("Unsupported content data tag: 0x{tag:02x}"),
+                    };
+                    let value: Box<dyn StackValue> = match String::from_utf8(data) {
+                        Ok(string) => Box::new(string),
+                        Err(e) => Box::new(e.into_bytes()),
+                    };
+
+                    entries.push(Box::new(vec![key, value]));
+                }
+                stack.push(entries)
+            }
+            tag => anyhow::bail!("Unknown metadata content tag: 0x{tag:02x}"),
+        }
+    }
+}
+
+const JETTON_TRANSFER_OP: u32 = 0x0f8a7ea5;
+const JETTON_INTERNAL_TRANSFER_OP: u32 = 0x178d4519;
+const NFT_TRANSFER_OP: u32 = 0x5fcc3d14;
+
+const ONCHAIN_CONTENT_TAG: u8 = 0x00;
+const OFFCHAIN_CONTENT_TAG: u8 = 0x01;
+const SNAKE_DATA_TAG: u8 = 0x00;
+const CHUNKED_DATA_TAG: u8 = 0x01;
+
+const KNOWN_METADATA_KEYS: &[&str] = &[
+    "uri",
+    "name",
+    "description",
+    "image",
+    "image_data",
+    "symbol",
+    "decimals",
+    "amount_style",
+    "render_type",
+    "content_url",
+    "attributes",
+];
+
+fn known_metadata_key(hash: &HashBytes) -> Option<&'static str> {
+    KNOWN_METADATA_KEYS
+        .iter()
+        .find(|name| sha2::Sha256::digest(name.as_bytes()).as_slice() == hash.as_slice())
+        .copied()
+}
+
+type MsgAddress = Option<(i8, BigInt)>;
+
+/// Pops `wc addr`, where `addr` is null for `addr_none`.
+fn pop_msg_address(stack: &mut Stack) -> Result<MsgAddress> {
+    let addr = stack.pop()?;
+    let workchain = stack.pop_i8_checked()?;
+    Ok(if addr.is_null() {
+        None
+    } else {
+        Some((workchain, addr.as_int()?.clone()))
+    })
+}
+
+fn push_msg_address(stack: &mut Stack, addr: MsgAddress) -> Result<()> {
+    match addr {
+        Some((workchain, addr)) => {
+            stack.push_int(workchain)?;
+            stack.push_int(addr)
+        }
+        None => {
+            stack.push_int(0)?;
+            stack.push(())
+        }
+    }
+}
+
+fn store_msg_address(builder: &mut CellBuilder, addr: &MsgAddress) -> Result<()> {
+    match addr {
+        Some((workchain, addr)) => store_std_addr(builder, *workchain, addr),
+        None => Ok(builder.store_zeros(2)?),
+    }
+}
+
+fn load_msg_address(cs: &mut CellSlice<'_>) -> Result<MsgAddress> {
+    match cs.load_small_uint(2)? {
+        0b00 => Ok(None),
+        0b10 => {
+            anyhow::ensure!(!cs.load_bit()?, "Anycast addresses are not supported");
+            let workchain = cs.load_u8()? as i8;
+            let addr = cs.load_u256()?;
+            Ok(Some((
+                workchain,
+                BigInt::from_bytes_be(Sign::Plus, addr.as_slice()),
+            )))
+        }
+        _ => anyhow::bail!("Only addr_none and addr_std addresses are supported"),
+    }
+}
+
+/// Stores `VarUInteger 16`.
+fn store_var_uint16(builder: &mut CellBuilder, value: &BigInt) -> Result<()> {
+    let value = value
+        .to_u128()
+        .context("Amount does not fit into VarUInteger 16")?;
+    let len = (16 - value.leading_zeros() / 8) as u8;
+    anyhow::ensure!(len < 16, "Amount does not fit into VarUInteger 16");
+    builder.store_small_uint(len, 4)?;
+    builder.store_raw(&value.to_be_bytes()[16 - len as usize..], len as u16 * 8)?;
+    Ok(())
+}
+
+fn load_var_uint16(cs: &mut CellSlice<'_>) -> Result<BigInt> {
+    let len = cs.load_small_uint(4)? as u16;
+    let mut buffer = [0u8; 16];
+    let bytes = cs.load_raw(&mut buffer, len * 8)?;
+    Ok(BigInt::from_bytes_be(Sign::Plus, bytes))
+}
+
+fn store_maybe_ref(builder: &mut CellBuilder, cell: Option<Cell>) -> Result<()> {
+    match cell {
+        Some(cell) => {
+            builder.store_bit_one()?;
+            builder.store_reference(cell)?;
+        }
+        None => builder.store_bit_zero()?,
+    }
+    Ok(())
+}
+
+fn load_maybe_ref(cs: &mut CellSlice<'_>) -> Result<Option<Cell>> {
+    Ok(if cs.load_bit()? {
+        Some(cs.load_reference_cloned()?)
+    } else {
+        None
+    })
+}
+
+/// Loads `Either Cell ^Cell`, an empty inline payload is returned as `None`.
+fn load_either_cell(cs: &mut CellSlice<'_>) -> Result<Option<Cell>> {
+    if cs.load_bit()? {
+        return Ok(Some(cs.load_reference_cloned()?));
+    }
+    let rest = cs.load_remaining();
+    if rest.remaining_bits() == 0 && rest.remaining_refs() == 0 {
+        return Ok(None);
+    }
+    let mut builder = CellBuilder::new();
+    builder.store_slice(rest)?;
+    Ok(Some(builder.build()?))
+}
+
+fn parse_comment(mut cs: CellSlice<'_>) -> Result<String> {
+    load_op(&mut cs, OpCodes::TEXT_COMMENT, "text comment")?;
+    String::from_utf8(load_snake_data(cs)?).context("Comment is not a valid UTF-8 string")
+}
+
+fn load_op(cs: &mut CellSlice<'_>, expected: u32, name: &str) -> Result<()> {
+    let op = cs.load_u32()?;
+    anyhow::ensure!(
+        op == expected,
+        "Not a {name} body: expected op 0x{expected:08x}, got 0x{op:08x}"
+    );
+    Ok(())
+}
+
+/// Pops either a cell or a slice.
+fn pop_body(stack: &mut Stack) -> Result<OwnedCellSlice> {
+    let value = stack.pop()?;
+    Ok(match value.ty() {
+        StackValueType::Cell => OwnedCellSlice::new(*value.into_cell()?),
+        _ => *value.into_slice()?,
+    })
+}
+
+fn pop_maybe_cell(stack: &mut Stack) -> Result<Option<Cell>> {
+    let value = stack.pop()?;
+    Ok(if value.is_null() {
+        None
+    } else {
+        Some(*value.into_cell()?)
+    })
+}
+
+fn push_maybe_cell(stack: &mut Stack, cell: Option<Cell>) -> Result<()> {
+    match cell {
+        Some(cell) => stack.push(cell),
+        None => stack.push(()),
+    }
+}
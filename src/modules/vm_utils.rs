@@ -1,8 +1,10 @@
+use std::io::Write;
 use std::rc::Rc;
 
 use anyhow::Result;
 
 use crate::core::*;
+use crate::disasm::disassemble;
 
 pub struct VmUtils;
 
@@ -24,4 +26,32 @@ impl VmUtils {
 
         Ok(())
     }
+
+    #[cmd(name = "disasm", args(print = true))]
+    #[cmd(name = "(disasm)", args(print = false))]
+    fn interpret_disasm(ctx: &mut Context, print: bool) -> Result<()> {
+        let item = ctx.stack.pop()?;
+        let code = if item.ty() == StackValueType::Cell {
+            OwnedCellSlice::new(*item.into_cell()?)
+        } else {
+            *item.into_slice()?
+        };
+
+        let text = disassemble(code.apply()?)?;
+        if print {
+            write!(ctx.stdout, "{text}")?;
+            Ok(())
+        } else {
+            ctx.stack.push(text)
+        }
+    }
+
+    // Defines the native assembler words in the current vocabulary,
+    // the embedded `Asm.fif` consists of this word.
+    // The words are not in the base dictionary so that instruction names
+    // don't shadow definitions of scripts which don't need the assembler.
+    #[cmd(name = "(load-asm)")]
+    fn interpret_load_asm(ctx: &mut Context) -> Result<()> {
+        ctx.add_module(crate::modules::Asm)
+    }
 }
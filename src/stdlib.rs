@@ -0,0 +1,29 @@
+//! Library sources which are compiled into the binary.
+
+/// Standard preamble which defines the basic words.
+pub const FIFT: &str = include_str!("stdlib/Fift.fif");
+
+/// List utilities.
+pub const LISTS: &str = include_str!("stdlib/Lists.fif");
+
+/// Address, Gram and serialization helpers for TON.
+pub const TON_UTIL: &str = include_str!("stdlib/TonUtil.fif");
+
+/// Loads the native TVM assembler words.
+pub const ASM: &str = include_str!("stdlib/Asm.fif");
+
+/// Embedded library files by their include names.
+pub const FILES: &[(&str, &str)] = &[
+    ("Fift.fif", FIFT),
+    ("Lists.fif", LISTS),
+    ("TonUtil.fif", TON_UTIL),
+    ("Asm.fif", ASM),
+];
+
+/// Returns the source of the embedded library file.
+pub fn find(name: &str) -> Option<&'static str> {
+    FILES
+        .iter()
+        .find(|(file_name, _)| *file_name == name)
+        .map(|(_, source)| *source)
+}
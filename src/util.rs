@@ -1,17 +1,23 @@
-use anyhow::Result;
-use everscale_types::cell::MAX_BIT_LEN;
+use anyhow::{Context as _, Result};
+use everscale_types::boc;
+use everscale_types::cell::{CellDescriptor, CellType, DefaultFinalizer, MAX_BIT_LEN};
 use everscale_types::prelude::*;
-use num_bigint::BigInt;
-use num_traits::{Num, ToPrimitive};
+use num_bigint::{BigInt, Sign};
+use num_traits::{Num, One, ToPrimitive, Zero};
 use unicode_segmentation::UnicodeSegmentation;
 
 pub struct ImmediateInt {
     pub num: BigInt,
     pub denom: Option<BigInt>,
+    pub width: Option<u16>,
 }
 
 impl ImmediateInt {
     pub fn try_from_str(s: &str) -> Result<Option<Self>> {
+        if let Some((left, right)) = s.split_once(':') {
+            return Self::parse_annotated(left, right);
+        }
+
         let (num, denom) = if let Some((left, right)) = s.split_once('/') {
             let Some(num) = Self::parse_single_number(left)? else {
                 return Ok(None);
@@ -26,25 +32,88 @@ impl ImmediateInt {
             };
             (num, None)
         };
-        Ok(Some(ImmediateInt { num, denom }))
+        Ok(Some(ImmediateInt {
+            num,
+            denom,
+            width: None,
+        }))
+    }
+
+    /// Parses a number with a width annotation, e.g. `0x1234:u32`.
+    ///
+    /// The width is also pushed with a double colon (`0x1234::u32`).
+    /// Tokens with an invalid annotation are not numbers.
+    fn parse_annotated(num: &str, annotation: &str) -> Result<Option<Self>> {
+        let (push_width, annotation) = match annotation.strip_prefix(':') {
+            Some(annotation) => (true, annotation),
+            None => (false, annotation),
+        };
+
+        let (signed, bits) = match annotation.split_at(std::cmp::min(1, annotation.len())) {
+            ("u", bits) => (false, bits),
+            ("i", bits) => (true, bits),
+            _ => return Ok(None),
+        };
+        let Some(bits) = bits
+            .parse::<u16>()
+            .ok()
+            .filter(|bits| *bits <= 256 + signed as u16)
+        else {
+            return Ok(None);
+        };
+
+        let Some(num) = Self::parse_single_number(num)? else {
+            return Ok(None);
+        };
+
+        anyhow::ensure!(
+            fits_into_bits(&num, bits, signed),
+            "Integer {num} does not fit into {annotation}"
+        );
+
+        Ok(Some(ImmediateInt {
+            num,
+            denom: None,
+            width: push_width.then_some(bits),
+        }))
     }
 
     fn parse_single_number(s: &str) -> Result<Option<BigInt>> {
+        const MAX_EXPONENT: u32 = 1023;
+
         let (neg, s) = match s.strip_prefix('-') {
             Some(s) => (true, s),
             None => (false, s),
         };
 
         let mut num = if let Some(s) = s.strip_prefix("0x") {
-            BigInt::from_str_radix(s, 16)
+            Self::parse_radix(s, 16)?
         } else if let Some(s) = s.strip_prefix("0b") {
-            BigInt::from_str_radix(s, 2)
-        } else {
-            if !s.chars().all(|c| c.is_ascii_digit()) {
+            Self::parse_radix(s, 2)?
+        } else if let Some(pos) = s.find(['^', 'e']) {
+            // Exact powers: `10^9` or `1e9`
+            let (Some(base), Some(exp)) = (
+                Self::parse_decimal(&s[..pos])?,
+                Self::parse_decimal(&s[pos + 1..])?,
+            ) else {
                 return Ok(None);
+            };
+            // Tokens with larger exponents are looked up as words
+            let Some(exp) = exp.to_u32().filter(|exp| *exp <= MAX_EXPONENT) else {
+                return Ok(None);
+            };
+
+            if s.as_bytes()[pos] == b'^' {
+                base.pow(exp)
+            } else {
+                base * BigInt::from(10u32).pow(exp)
             }
-            BigInt::from_str_radix(s, 10)
-        }?;
+        } else {
+            let Some(num) = Self::parse_decimal(s)? else {
+                return Ok(None);
+            };
+            num
+        };
 
         if neg {
             num = -num;
@@ -52,6 +121,61 @@ impl ImmediateInt {
 
         Ok(Some(num))
     }
+
+    fn parse_radix(s: &str, radix: u32) -> Result<BigInt> {
+        let Some(s) = remove_digit_separators(s) else {
+            anyhow::bail!("Invalid number");
+        };
+        Ok(BigInt::from_str_radix(&s, radix)?)
+    }
+
+    fn parse_decimal(s: &str) -> Result<Option<BigInt>> {
+        let Some(s) = remove_digit_separators(s) else {
+            return Ok(None);
+        };
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+            return Ok(None);
+        }
+        Ok(Some(BigInt::from_str_radix(&s, 10)?))
+    }
+}
+
+/// Returns whether the integer fits into the specified number of bits.
+pub(crate) fn fits_into_bits(x: &BigInt, bits: u16, signed: bool) -> bool {
+    let bits = bits as u64;
+    if signed {
+        if bits == 0 {
+            return x.is_zero();
+        }
+        // `bits()` does not include the sign bit
+        x.bits() < bits || *x == -(BigInt::one() << (bits - 1))
+    } else {
+        x.sign() != Sign::Minus && x.bits() <= bits
+    }
+}
+
+/// Removes `_` between digits, e.g. `1_000_000`.
+fn remove_digit_separators(s: &str) -> Option<std::borrow::Cow<'_, str>> {
+    if !s.contains('_') {
+        return Some(s.into());
+    }
+    if s.starts_with('_') || s.ends_with('_') || s.contains("__") {
+        return None;
+    }
+    Some(s.replace('_', "").into())
+}
+
+/// Converts an unsigned 256-bit integer into a cell hash.
+pub(crate) fn int_to_hash(int: &BigInt) -> Result<HashBytes> {
+    match int.to_biguint() {
+        Some(int) if int.bits() <= 256 => {
+            let bytes = int.to_bytes_be();
+            let mut hash = HashBytes::ZERO;
+            hash.0[32 - bytes.len()..].copy_from_slice(&bytes);
+            Ok(hash)
+        }
+        _ => anyhow::bail!("Expected an unsigned 256-bit hash, got {int}"),
+    }
 }
 
 pub(crate) fn reverse_utf8_string_inplace(s: &mut str) {
@@ -82,6 +206,48 @@ pub(crate) fn reverse_utf8_string_inplace(s: &mut str) {
     }
 }
 
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Encodes data using the lowercase RFC 4648 alphabet without padding.
+pub(crate) fn encode_base32(data: &[u8]) -> String {
+    let mut result = String::with_capacity((data.len() * 8 + 4) / 5);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            result.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        result.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    result
+}
+
+/// Decodes unpadded base32 (case-insensitive), returns `None` on invalid chars.
+pub(crate) fn decode_base32(s: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let value = match c.to_ascii_lowercase() {
+            c @ b'a'..=b'z' => c - b'a',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+        }
+    }
+    Some(result)
+}
+
 #[inline]
 pub(crate) fn encode_base64<T: AsRef<[u8]>>(data: T) -> String {
     use base64::Engine;
@@ -264,6 +430,441 @@ pub fn decode_binary_bitstring(s: &str) -> Result<CellBuilder> {
     Ok(builder)
 }
 
+/// Max number of cells in a snake data chain.
+pub(crate) const MAX_SNAKE_CELLS: usize = 1000;
+
+/// Max number of chunks in a chunked data dictionary.
+pub(crate) const MAX_DATA_CHUNKS: usize = 8192;
+
+const CHUNK_LEN: usize = (MAX_BIT_LEN / 8) as usize;
+
+/// Stores bytes into the remaining space of the builder,
+/// moving the rest into a chain of `SnakeData` cells.
+pub(crate) fn store_snake_data(builder: &mut CellBuilder, data: &[u8]) -> Result<()> {
+    let head_len = std::cmp::min((builder.spare_bits_capacity() / 8) as usize, data.len());
+    let (head, tail) = data.split_at(head_len);
+
+    let cells = 1 + (tail.len() + CHUNK_LEN - 1) / CHUNK_LEN;
+    anyhow::ensure!(
+        cells <= MAX_SNAKE_CELLS,
+        "Snake data is too long: {} bytes would take {cells} cells out of {MAX_SNAKE_CELLS}",
+        data.len()
+    );
+    builder.store_raw(head, head_len as u16 * 8)?;
+
+    let mut next = None::<Cell>;
+    for chunk in tail.chunks(CHUNK_LEN).rev() {
+        let mut child = CellBuilder::new();
+        child.store_raw(chunk, chunk.len() as u16 * 8)?;
+        if let Some(next) = next {
+            child.store_reference(next)?;
+        }
+        next = Some(child.build()?);
+    }
+    if let Some(next) = next {
+        builder.store_reference(next)?;
+    }
+    Ok(())
+}
+
+/// Reads bytes from the slice and all its continuation cells.
+pub(crate) fn load_snake_data(mut slice: CellSlice<'_>) -> Result<Vec<u8>> {
+    let mut result = Vec::new();
+    for _ in 0..MAX_SNAKE_CELLS {
+        let bits = slice.remaining_bits();
+        anyhow::ensure!(bits % 8 == 0, "Snake data is not byte-aligned");
+        anyhow::ensure!(
+            slice.remaining_refs() <= 1,
+            "Snake data has too many references"
+        );
+
+        let mut buffer = [0u8; 128];
+        result.extend_from_slice(slice.load_raw(&mut buffer, bits)?);
+
+        if slice.remaining_refs() == 0 {
+            return Ok(result);
+        }
+        slice = slice.load_reference_as_slice()?;
+    }
+    anyhow::bail!("Snake data is longer than {MAX_SNAKE_CELLS} cells")
+}
+
+/// Builds `ChunkedData` as a `HashmapE 32 ^(SnakeData ~0)` dictionary.
+pub(crate) fn build_chunked_data(data: &[u8]) -> Result<Option<Cell>> {
+    let chunks = (data.len() + CHUNK_LEN - 1) / CHUNK_LEN;
+    anyhow::ensure!(
+        chunks <= MAX_DATA_CHUNKS,
+        "Chunked data is too long: {} bytes would take {chunks} chunks out of {MAX_DATA_CHUNKS}",
+        data.len()
+    );
+
+    let mut dict = everscale_types::dict::Dict::<u32, Cell>::new();
+    for (i, chunk) in data.chunks(CHUNK_LEN).enumerate() {
+        let mut builder = CellBuilder::new();
+        builder.store_raw(chunk, chunk.len() as u16 * 8)?;
+        dict.set(i as u32, builder.build()?)?;
+    }
+    Ok(dict.root().clone())
+}
+
+/// Reads `ChunkedData`, chunk indices must start from zero and have no gaps.
+pub(crate) fn load_chunked_data(root: Option<Cell>) -> Result<Vec<u8>> {
+    let dict = everscale_types::dict::Dict::<u32, Cell>::from(root);
+
+    let mut result = Vec::new();
+    for (i, entry) in dict.iter().enumerate() {
+        anyhow::ensure!(i < MAX_DATA_CHUNKS, "Chunked data has too many chunks");
+        let (index, chunk) = entry?;
+        anyhow::ensure!(index as usize == i, "Chunk {i} is missing");
+
+        let mut cs = DynCell::as_slice(chunk.as_ref())?;
+        let bits = cs.remaining_bits();
+        anyhow::ensure!(
+            bits % 8 == 0 && cs.remaining_refs() == 0,
+            "Invalid data chunk {i}"
+        );
+        let mut buffer = [0u8; 128];
+        result.extend_from_slice(cs.load_raw(&mut buffer, bits)?);
+    }
+    Ok(result)
+}
+
+/// Displays the cell tree as a Graphviz DOT graph.
+///
+/// Each distinct cell is a node labeled with its size and hash prefix,
+/// edges are labeled with reference indices.
+pub fn display_cell_dot(root: &DynCell) -> impl std::fmt::Display + '_ {
+    struct DisplayCellDot<'a>(&'a DynCell);
+
+    impl std::fmt::Display for DisplayCellDot<'_> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            const HASH_PREFIX_LEN: usize = 4;
+
+            writeln!(f, "digraph cells {{")?;
+            writeln!(f, "  node [shape=box, fontname=monospace];")?;
+
+            let mut visited = ahash::HashSet::default();
+            let mut stack = vec![self.0];
+            while let Some(cell) = stack.pop() {
+                let hash = cell.repr_hash();
+                if !visited.insert(*hash) {
+                    continue;
+                }
+
+                write!(f, "  \"{hash}\" [label=\"")?;
+                if cell.cell_type() != CellType::Ordinary {
+                    write!(f, "{:?}\\n", cell.cell_type())?;
+                }
+                write!(
+                    f,
+                    "bits={} refs={}\\n",
+                    cell.bit_len(),
+                    cell.reference_count()
+                )?;
+                for byte in &hash.as_array()[..HASH_PREFIX_LEN] {
+                    write!(f, "{byte:02x}")?;
+                }
+                writeln!(f, "..\"];")?;
+
+                for (i, child) in cell.references().enumerate() {
+                    writeln!(
+                        f,
+                        "  \"{hash}\" -> \"{}\" [label=\"{i}\"];",
+                        child.repr_hash()
+                    )?;
+                }
+                // Children are pushed in reverse to be visited in order
+                let refs = cell.references().collect::<Vec<_>>();
+                stack.extend(refs.into_iter().rev());
+            }
+
+            writeln!(f, "}}")
+        }
+    }
+
+    DisplayCellDot(root)
+}
+
+/// Decodes a BOC with a single root.
+pub(crate) fn decode_boc(data: &[u8]) -> Result<Cell> {
+    let roots = decode_boc_roots(data, &boc::de::Options::exact(1))?;
+    roots
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Root cell not found"))
+}
+
+/// Decodes all roots of the BOC.
+///
+/// Hashes and depths which are stored in the BOC are checked against
+/// the computed ones. With the `parallel` feature, cells of large BOCs
+/// are built by multiple threads, level by level starting from the leaves.
+pub(crate) fn decode_boc_roots(data: &[u8], options: &boc::de::Options) -> Result<Vec<Cell>> {
+    let header = boc::de::BocHeader::decode(data, options)?;
+
+    #[cfg(feature = "parallel")]
+    if header.cells().len() >= parallel_boc::MIN_CELLS {
+        return parallel_boc::decode_roots(&header);
+    }
+
+    let cells = header.finalize(&mut Cell::default_finalizer())?;
+    for (index, raw) in header.cells().iter().enumerate() {
+        if raw_cell_descriptor(raw)?.store_hashes() {
+            let cell = cells.get(index as u32).context("Invalid BOC cell")?;
+            check_stored_hashes(raw, cell.as_ref())?;
+        }
+    }
+
+    header
+        .roots()
+        .iter()
+        .map(|&index| {
+            cells
+                .get(index)
+                .ok_or_else(|| anyhow::anyhow!("Root cell not found"))
+        })
+        .collect()
+}
+
+fn raw_cell_descriptor(raw: &[u8]) -> Result<CellDescriptor> {
+    match raw {
+        [d1, d2, ..] => Ok(CellDescriptor::new([*d1, *d2])),
+        _ => anyhow::bail!("Invalid BOC cell"),
+    }
+}
+
+/// Compares hashes and depths stored in the serialized cell with the computed ones.
+fn check_stored_hashes(raw: &[u8], cell: &DynCell) -> Result<()> {
+    let level_mask = raw_cell_descriptor(raw)?.level_mask();
+    let hash_count = level_mask.level() as usize + 1;
+
+    let hashes = raw
+        .get(2..2 + hash_count * 32)
+        .context("Invalid BOC cell")?;
+    let depths = raw
+        .get(2 + hash_count * 32..2 + hash_count * (32 + 2))
+        .context("Invalid BOC cell")?;
+    for ((level, hash), depth) in level_mask
+        .into_iter()
+        .zip(hashes.chunks_exact(32))
+        .zip(depths.chunks_exact(2))
+    {
+        anyhow::ensure!(
+            cell.hash(level).as_slice() == hash && cell.depth(level).to_be_bytes() == depth,
+            "Stored hash of the BOC cell {} does not match",
+            cell.repr_hash()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "parallel")]
+pub(crate) mod parallel_boc {
+    use anyhow::{Context as _, Result};
+    use everscale_types::boc::de::BocHeader;
+    use everscale_types::prelude::*;
+    use rayon::prelude::*;
+
+    use super::{check_stored_hashes, raw_cell_descriptor, CellDescriptor, DefaultFinalizer};
+
+    /// Smaller BOCs are not worth distributing between threads.
+    pub const MIN_CELLS: usize = 4096;
+    /// Cells of smaller levels are built by the current thread.
+    const MIN_PARALLEL_CELLS: usize = 256;
+
+    /// Cell from the BOC, sliced by [`BocHeader::decode`] which checks
+    /// the descriptor and that the data and references fit into the slice.
+    struct RawCell<'a> {
+        raw: &'a [u8],
+        descriptor: CellDescriptor,
+        data: &'a [u8],
+        bit_len: u16,
+        references: Vec<u32>,
+    }
+
+    impl<'a> RawCell<'a> {
+        fn parse(raw: &'a [u8], ref_size: usize, cell_count: u32) -> Result<Self> {
+            let descriptor = raw_cell_descriptor(raw)?;
+            let mut offset = 2;
+            if descriptor.store_hashes() {
+                offset += (32 + 2) * (descriptor.level_mask().level() as usize + 1);
+            }
+
+            let byte_len = descriptor.byte_len() as usize;
+            let data = raw
+                .get(offset..offset + byte_len)
+                .context("Invalid BOC cell")?;
+            offset += byte_len;
+
+            // The completion tag is checked by `BocHeader::decode`
+            let bit_len = match data.last() {
+                Some(last) if !descriptor.is_aligned() => {
+                    byte_len as u16 * 8 - last.trailing_zeros() as u16 - 1
+                }
+                _ => byte_len as u16 * 8,
+            };
+
+            let references = raw
+                .get(offset..)
+                .context("Invalid BOC cell")?
+                .chunks_exact(ref_size)
+                .take(descriptor.reference_count() as usize)
+                .map(|bytes| {
+                    let index = bytes.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32);
+                    anyhow::ensure!(index < cell_count, "Invalid BOC cell reference");
+                    Ok(index)
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            Ok(Self {
+                raw,
+                descriptor,
+                data,
+                bit_len,
+                references,
+            })
+        }
+
+        fn build(&self, cells: &[Option<Cell>]) -> Result<Cell> {
+            let mut builder = CellBuilder::from_raw_data(self.data, self.bit_len)?;
+            builder.set_exotic(self.descriptor.is_exotic());
+            for &index in &self.references {
+                let child = cells[index as usize].clone();
+                builder.store_reference(child.expect("children are built first"))?;
+            }
+            let cell = builder.build_ext(&mut Cell::default_finalizer())?;
+            if self.descriptor.store_hashes() {
+                check_stored_hashes(self.raw, cell.as_ref())?;
+            }
+            Ok(cell)
+        }
+    }
+
+    pub fn decode_roots(header: &BocHeader<'_>) -> Result<Vec<Cell>> {
+        let cell_count = header.cells().len() as u32;
+        let raw_cells = header
+            .cells()
+            .par_iter()
+            .map(|raw| RawCell::parse(raw, header.ref_size(), cell_count))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Cells only reference the next cells, so each level
+        // depends only on the previous (lower) levels
+        let mut heights = vec![0usize; raw_cells.len()];
+        let mut levels = Vec::<Vec<usize>>::new();
+        for (index, cell) in raw_cells.iter().enumerate().rev() {
+            let mut height = 0;
+            for &child in &cell.references {
+                anyhow::ensure!(child as usize > index, "Invalid BOC cell references order");
+                height = std::cmp::max(height, heights[child as usize] + 1);
+            }
+            heights[index] = height;
+            if levels.len() <= height {
+                levels.resize_with(height + 1, Vec::new);
+            }
+            levels[height].push(index);
+        }
+
+        let mut cells = vec![None::<Cell>; raw_cells.len()];
+        for level in levels {
+            if level.len() < MIN_PARALLEL_CELLS {
+                for index in level {
+                    cells[index] = Some(raw_cells[index].build(&cells)?);
+                }
+                continue;
+            }
+
+            let built = level
+                .par_iter()
+                .map(|&index| Ok((index, raw_cells[index].build(&cells)?)))
+                .collect::<Result<Vec<_>>>()?;
+            for (index, cell) in built {
+                cells[index] = Some(cell);
+            }
+        }
+
+        header
+            .roots()
+            .iter()
+            .map(|&index| {
+                cells
+                    .get(index as usize)
+                    .cloned()
+                    .flatten()
+                    .ok_or_else(|| anyhow::anyhow!("Root cell not found"))
+            })
+            .collect()
+    }
+
+    /// Serializes cells with `write_cell` by multiple threads
+    /// and appends them to `result` in the original order.
+    pub fn write_cells<F>(result: &mut Vec<u8>, cells: &[&DynCell], write_cell: F)
+    where
+        F: Fn(&mut Vec<u8>, &DynCell) + Sync,
+    {
+        let chunks = cells
+            .par_chunks(MIN_PARALLEL_CELLS)
+            .map(|chunk| {
+                let mut data = Vec::new();
+                for &cell in chunk {
+                    write_cell(&mut data, cell);
+                }
+                data
+            })
+            .collect::<Vec<_>>();
+        for chunk in chunks {
+            result.extend_from_slice(&chunk);
+        }
+    }
+}
+
+/// Stores `addr_std$10 anycast:(Maybe Anycast) workchain_id:int8 address:bits256`.
+pub(crate) fn store_std_addr(
+    builder: &mut CellBuilder,
+    workchain: i8,
+    addr: &BigInt,
+) -> Result<()> {
+    anyhow::ensure!(
+        fits_into_bits(addr, 256, false),
+        "Account address does not fit into 256 bits"
+    );
+    builder.store_small_uint(0b100, 3)?;
+    builder.store_u8(workchain as u8)?;
+    store_int_to_builder(builder, &mut addr.clone(), 256, false)
+}
+
+/// Loads an integer of up to 257 bits.
+pub fn load_int_from_slice(
+    cs: &mut CellSlice<'_>,
+    bits: u16,
+    signed: bool,
+) -> Result<BigInt, everscale_types::error::Error> {
+    match bits {
+        0 => Ok(BigInt::zero()),
+        0..=64 if !signed => cs.load_uint(bits).map(BigInt::from),
+        0..=64 if signed => cs.load_uint(bits).map(|mut int| {
+            if bits < 64 {
+                // Clone sign bit into all high bits
+                int |= ((int >> (bits - 1)) * u64::MAX) << (bits - 1);
+            }
+            BigInt::from(int as i64)
+        }),
+        _ => {
+            let align = (8 - bits % 8) % 8;
+            let mut buffer = [0u8; 33];
+            cs.load_raw(&mut buffer, bits).map(|buffer| {
+                let mut int = if signed {
+                    BigInt::from_signed_bytes_be(buffer)
+                } else {
+                    BigInt::from_bytes_be(Sign::Plus, buffer)
+                };
+                int >>= align;
+                int
+            })
+        }
+    }
+}
+
 pub fn store_int_to_builder(
     builder: &mut CellBuilder,
     int: &mut BigInt,
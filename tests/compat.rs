@@ -0,0 +1,73 @@
+//! Compares outputs of the `.fif` programs from `tests/compat` with
+//! the recorded outputs (see `tests/compat/README.md` for their origin).
+//!
+//! Run with `cargo test --features compat-tests --test compat`.
+
+#![cfg(feature = "compat-tests")]
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use fift::core::env::EmptyEnvironment;
+use fift::core::SourceBlock;
+
+const PREAMBLE: &str = include_str!("../src/stdlib/Fift.fif");
+
+#[test]
+fn compat() -> Result<()> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat");
+
+    let mut scripts = std::fs::read_dir(&dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    scripts.retain(|path| path.extension().is_some_and(|ext| ext == "fif"));
+    scripts.sort();
+    anyhow::ensure!(!scripts.is_empty(), "No compat scripts found");
+
+    let mut failed = Vec::new();
+    for path in &scripts {
+        let name = path.file_name().unwrap().to_string_lossy();
+
+        let expected = std::fs::read_to_string(path.with_extension("out"))
+            .with_context(|| format!("Reference output for `{name}` not found"))?;
+
+        match run_script(path) {
+            Ok(output) if output == expected => continue,
+            Ok(output) => failed.push(format!(
+                "{name}: output mismatch\n--- expected\n{expected}\n--- actual\n{output}"
+            )),
+            Err(e) => failed.push(format!("{name}: {e:?}")),
+        }
+    }
+
+    anyhow::ensure!(
+        failed.is_empty(),
+        "{} of {} compat scripts failed:\n\n{}",
+        failed.len(),
+        scripts.len(),
+        failed.join("\n\n")
+    );
+    Ok(())
+}
+
+fn run_script(path: &Path) -> Result<String> {
+    let source = std::fs::read_to_string(path)?;
+
+    let mut env = EmptyEnvironment;
+    let mut stdout = Vec::<u8>::new();
+    let mut ctx = fift::Context::new(&mut env, &mut stdout)
+        .with_basic_modules()?
+        .with_source_block(SourceBlock::new(
+            path.to_string_lossy(),
+            std::io::Cursor::new(source),
+        ))
+        .with_source_block(SourceBlock::new(
+            "<default Fift.fif>",
+            std::io::Cursor::new(PREAMBLE),
+        ));
+
+    ctx.run()?;
+    drop(ctx);
+
+    Ok(String::from_utf8(stdout)?)
+}
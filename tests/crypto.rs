@@ -0,0 +1,139 @@
+//! Known-answer tests of the cryptographic words.
+//!
+//! Expected values were computed independently of this crate, from the
+//! RFC 8032 and RFC 6979 specifications and the TON mnemonic, address
+//! and encrypted payload formats.
+
+use anyhow::Result;
+use fift::core::env::EmptyEnvironment;
+use fift::core::SourceBlock;
+
+fn run(source: &str) -> Result<String> {
+    let mut env = EmptyEnvironment;
+    let mut stdout = Vec::<u8>::new();
+    let mut ctx = fift::Context::new(&mut env, &mut stdout)
+        .with_basic_modules()?
+        .with_source_block(SourceBlock::new(
+            "<test>",
+            std::io::Cursor::new(source.to_owned()),
+        ));
+    ctx.run()?;
+    drop(ctx);
+    Ok(String::from_utf8(stdout)?)
+}
+
+fn run_lines(source: &str) -> Result<Vec<String>> {
+    Ok(run(source)?.lines().map(str::to_owned).collect())
+}
+
+#[test]
+fn ed25519_rfc8032() -> Result<()> {
+    const SECRET: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const PUBLIC: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const SIGNATURE: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+
+    let lines = run_lines(&format!(
+        "\"{SECRET}\" x>B priv>pub B>x type cr \
+         \"\" x>B \"{SECRET}\" x>B ed25519_sign B>x type cr \
+         \"\" x>B \"{SIGNATURE}\" x>B \"{PUBLIC}\" x>B ed25519_chksign ."
+    ))?;
+    assert_eq!(lines, [PUBLIC, SIGNATURE, "-1 "]);
+    Ok(())
+}
+
+#[test]
+fn ton_mnemonic() -> Result<()> {
+    const MNEMONIC: &str = "loud gas twist castle team celery cereal sausage mistake bulk jacket \
+        shrimp angry horror force coyote cute average glow around ethics cover apart try";
+    const SECRET: &str = "a052deeb3e7e3240b5c20505cc5d9f58ef3bcb0a1be5678043310bcc0bd9babf";
+    const PUBLIC: &str = "d42c5621e3b30914ba0590319df2db9cc6add62927872b997041526183bcf37f";
+
+    let invalid = MNEMONIC.replace("loud", "gas");
+    let lines = run_lines(&format!(
+        "\"{MNEMONIC}\" mnemonic? . \"{invalid}\" mnemonic? . cr \
+         \"{MNEMONIC}\" mnemonic>priv dup B>x type cr priv>pub B>x type cr"
+    ))?;
+    assert_eq!(lines, ["-1 0 ", SECRET, PUBLIC]);
+    Ok(())
+}
+
+#[test]
+fn encrypted_payload() -> Result<()> {
+    const SENDER_SECRET: &str = "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20";
+    const RECEIVER_SECRET: &str =
+        "2122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f40";
+    const SALT: &str = "EQCD39VS5jcptHL8vMjEXrzGaRcCVYto7HUn4bpAOg8xqB2N";
+    // "Hello, TON!"
+    const DATA: &str = "48656c6c6f2c20544f4e21";
+    const ENCRYPTED: &str = "9e44348f840a0163bed924f63441c0cec692d81656038063a623f95586136494\
+        cfc44fdca3faa76e95c24db6664921fdbe760c79bd0260d0b28c8bd80a122dc8\
+        80a271b1fecb3cafc534450caa0cf3d0";
+
+    // Encryption uses a random prefix, so only its round trip is checked
+    let lines = run_lines(&format!(
+        "\"{ENCRYPTED}\" x>B \"{RECEIVER_SECRET}\" x>B \"{SALT}\" B-decrypt B>x type cr \
+         \"{DATA}\" x>B \"{SENDER_SECRET}\" x>B \"{RECEIVER_SECRET}\" x>B priv>pub \"{SALT}\" \
+         B-encrypt \"{RECEIVER_SECRET}\" x>B \"{SALT}\" B-decrypt B>x type cr"
+    ))?;
+    assert_eq!(lines, [DATA, DATA]);
+
+    let err = run(&format!(
+        "\"{ENCRYPTED}\" x>B \"{RECEIVER_SECRET}\" x>B \"other salt\" B-decrypt"
+    ))
+    .unwrap_err();
+    assert!(
+        format!("{err:?}").contains("message key mismatch"),
+        "{err:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn secp256k1_rfc6979() -> Result<()> {
+    const SECRET: &str = "48124d4ec35654f6fbd6b4c7484b988fd7a6e7a8cd06aad5f1aca3bdb644bbf7";
+    // sha256("Hello, TON!")
+    const HASH: &str = "ae3c9bff6722168ab28def61b40ee36bb7c7dab7e29009537205192f8cd4c6d5";
+    const PUBLIC: &str = "04f0a223c089a1fb7a6ad8736697abca59b211363079be3885b7ec1fa1d82e52\
+        1537223bc8d73e2295c9146958f7fb1bc8e205a3a1b284501c82fccd8f4902037a";
+    const SIGNATURE: &str = "13e2d4f22c905d028470b29f5df34b11d461e5175f6fe081b1aea1dbcfb6af89\
+        5434a90ca2c04f0ebd99c2930f1c671e38ec745bb8a103958ce1be8c42863e81\
+        01";
+
+    let lines = run_lines(&format!(
+        "\"{SECRET}\" x>B secp256k1_priv>pub B>x type cr \
+         \"{HASH}\" x>B \"{SECRET}\" x>B secp256k1_sign B>x type cr \
+         \"{HASH}\" x>B \"{SIGNATURE}\" x>B \"{PUBLIC}\" x>B secp256k1_chksign . cr \
+         \"{HASH}\" x>B \"{SIGNATURE}\" x>B secp256k1_recover . B>x type cr"
+    ))?;
+    assert_eq!(lines, [PUBLIC, SIGNATURE, "-1 ", &format!("-1 {PUBLIC}")]);
+    Ok(())
+}
+
+#[test]
+fn std_addresses() -> Result<()> {
+    const RAW: &str = "0:83dfd552e63729b472fcbcc8c45ebcc6691702558b68ec7527e1ba403a0f31a8";
+    const BOUNCEABLE: &str = "EQCD39VS5jcptHL8vMjEXrzGaRcCVYto7HUn4bpAOg8xqB2N";
+    const NON_BOUNCEABLE: &str = "UQCD39VS5jcptHL8vMjEXrzGaRcCVYto7HUn4bpAOg8xqEBI";
+    const TESTNET: &str = "kQCD39VS5jcptHL8vMjEXrzGaRcCVYto7HUn4bpAOg8xqKYH";
+    // The account id of `RAW` in decimal
+    const ACCOUNT: &str =
+        "59648462366785489467430652908958923083039847914122061688607845508479932379560";
+
+    let lines = run_lines(&format!(
+        "\"{RAW}\" $>smca drop drop 2dup 4 smca>$ type cr 2dup 5 smca>$ type cr \
+         2dup 6 smca>$ type cr smca>raw$ type cr \
+         \"{NON_BOUNCEABLE}\" $>smca . . . . cr \"{TESTNET}\" $>smca . . drop drop cr"
+    ))?;
+    assert_eq!(
+        lines,
+        [
+            BOUNCEABLE,
+            NON_BOUNCEABLE,
+            TESTNET,
+            RAW,
+            &format!("-1 1 {ACCOUNT} 0 "),
+            "-1 2 ",
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! Disassembly of code with shared cells.
+
+use anyhow::Result;
+use everscale_types::prelude::*;
+use fift::disasm::disassemble;
+
+#[test]
+fn shared_cells_are_printed_once() -> Result<()> {
+    const LEVELS: usize = 20;
+
+    // Each level pushes the previous one twice with `PUSHREFCONT`
+    let leaf = {
+        let mut b = CellBuilder::new();
+        b.store_u8(0x00)?; // NOP
+        b.build()?
+    };
+    let mut code = leaf.clone();
+    for _ in 0..LEVELS {
+        let mut b = CellBuilder::new();
+        b.store_u16(0x8a8a)?;
+        b.store_reference(code.clone())?;
+        b.store_reference(code)?;
+        code = b.build()?;
+    }
+
+    let text = disassemble(code.as_slice()?)?;
+    assert_eq!(text.matches("NOP").count(), 1);
+    assert_eq!(text.matches("printed above>").count(), LEVELS);
+    assert_eq!(text.lines().count(), 1 + 6 * LEVELS);
+
+    let lines = text.lines().collect::<Vec<_>>();
+    let nop = lines.iter().position(|line| line.contains("NOP")).unwrap();
+    let label = format!("<cell {}>", leaf.repr_hash());
+    assert_eq!(lines[nop - 1].trim(), label);
+    Ok(())
+}
@@ -0,0 +1,48 @@
+//! Integers printed with `fift::format` are parsed back by the interpreter.
+
+use fift::format::DisplayInt;
+use fift::util::ImmediateInt;
+use num_bigint::{BigInt, Sign};
+use proptest::prelude::*;
+
+fn any_int() -> impl Strategy<Value = BigInt> {
+    (any::<bool>(), prop::collection::vec(any::<u8>(), 0..64)).prop_map(|(negative, bytes)| {
+        let sign = if negative { Sign::Minus } else { Sign::Plus };
+        BigInt::from_bytes_be(sign, &bytes)
+    })
+}
+
+fn parse_int(literal: &str) -> BigInt {
+    let int = ImmediateInt::try_from_str(literal)
+        .unwrap()
+        .unwrap_or_else(|| panic!("`{literal}` is not a number"));
+    assert!(int.denom.is_none() && int.width.is_none());
+    int.num
+}
+
+proptest! {
+    #[test]
+    fn decimal_round_trip(int in any_int()) {
+        let literal = DisplayInt::new(&int, 10).to_string();
+        prop_assert_eq!(parse_int(&literal), int);
+    }
+
+    #[test]
+    fn prefixed_round_trip(int in any_int(), uppercase in any::<bool>()) {
+        let sign = if int.sign() == Sign::Minus { "-" } else { "" };
+        let magnitude = BigInt::from(int.magnitude().clone());
+
+        let hex = DisplayInt::new(&magnitude, 16).uppercase(uppercase);
+        prop_assert_eq!(parse_int(&format!("{sign}0x{hex}")), int.clone());
+
+        let bin = DisplayInt::new(&magnitude, 2);
+        prop_assert_eq!(parse_int(&format!("{sign}0b{bin}")), int);
+    }
+
+    #[test]
+    fn any_radix_round_trip(int in any_int(), radix in 2u32..=36) {
+        let text = DisplayInt::new(&int, radix).to_string();
+        prop_assert!(text.bytes().all(|c| c == b'-' || c.is_ascii_alphanumeric()));
+        prop_assert_eq!(BigInt::parse_bytes(text.as_bytes(), radix), Some(int));
+    }
+}
@@ -0,0 +1,142 @@
+//! Scanning of lines which are longer than the lexer chunk, and skipped lines.
+
+use anyhow::Result;
+use fift::core::{Lexer, LiteralKind, SourceBlock};
+
+fn make_lexer(source: &str, max_chunk_len: usize) -> Lexer {
+    let mut lexer = Lexer::default();
+    lexer.set_max_chunk_len(max_chunk_len);
+    lexer.push_source_block(SourceBlock::new(
+        "<test>",
+        std::io::Cursor::new(source.to_owned()),
+    ));
+    lexer
+}
+
+fn scan_words(lexer: &mut Lexer) -> Result<Vec<(String, std::ops::Range<usize>)>> {
+    let mut words = Vec::new();
+    while let Some(token) = lexer.scan_word()? {
+        words.push((token.data.to_owned(), token.span));
+    }
+    Ok(words)
+}
+
+#[test]
+fn words_spanning_chunks() -> Result<()> {
+    let source = "first second-word 3rd\nnext line";
+    for max_chunk_len in [1, 2, 3, 5, 7, 64] {
+        let mut lexer = make_lexer(source, max_chunk_len);
+        let words = scan_words(&mut lexer)?;
+
+        let expected = [
+            ("first", 0..5),
+            ("second-word", 6..17),
+            ("3rd", 18..21),
+            ("next", 22..26),
+            ("line", 27..31),
+        ];
+        assert_eq!(words.len(), expected.len(), "chunk len {max_chunk_len}");
+        for ((word, span), (expected_word, expected_span)) in words.iter().zip(expected) {
+            assert_eq!(word, expected_word, "chunk len {max_chunk_len}");
+            assert_eq!(*span, expected_span, "chunk len {max_chunk_len}");
+            assert_eq!(&source[span.clone()], word);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn chunks_do_not_split_chars() -> Result<()> {
+    let mut lexer = make_lexer("привет мир ✓", 1);
+    let words = scan_words(&mut lexer)?;
+    let words = words
+        .iter()
+        .map(|(word, _)| word.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(words, ["привет", "мир", "✓"]);
+    Ok(())
+}
+
+#[test]
+fn literal_spanning_chunks() -> Result<()> {
+    let mut lexer = make_lexer("\" long string literal\" after", 4);
+
+    let token = lexer.scan_word()?.unwrap();
+    assert_eq!(token.data, "\"");
+    // Skip the space after the word
+    lexer.skip_symbol();
+
+    let token = lexer.scan_literal(LiteralKind::String)?;
+    assert_eq!(token.data, "long string literal");
+    assert_eq!(token.span, 2..21);
+
+    let token = lexer.scan_word()?.unwrap();
+    assert_eq!(token.data, "after");
+    Ok(())
+}
+
+#[test]
+fn line_numbers_of_chunked_lines() -> Result<()> {
+    let mut lexer = make_lexer("aaaa bbbb cccc\ndddd", 3);
+    for expected in [1, 1, 1, 2] {
+        lexer.scan_word()?.unwrap();
+        assert_eq!(lexer.get_position().unwrap().line_number, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn skip_rest_of_chunked_line() -> Result<()> {
+    let mut lexer = make_lexer("word // a long comment\nnext", 4);
+
+    assert_eq!(lexer.scan_word()?.unwrap().data, "word");
+    assert_eq!(lexer.scan_word()?.unwrap().data, "//");
+    lexer.skip_line();
+    assert_eq!(lexer.scan_word()?.unwrap().data, "next");
+    assert_eq!(lexer.get_position().unwrap().line_number, 2);
+    Ok(())
+}
+
+#[test]
+fn line_rest_spanning_chunks() -> Result<()> {
+    let mut lexer = make_lexer("<<< some terminator\nnext", 2);
+
+    assert_eq!(lexer.scan_word()?.unwrap().data, "<<<");
+    assert_eq!(lexer.scan_line_rest()?.data, " some terminator");
+    assert_eq!(lexer.scan_word()?.unwrap().data, "next");
+    Ok(())
+}
+
+#[test]
+fn shebang_and_script_comments() -> Result<()> {
+    let source = "#!/usr/bin/env fift\n# comment\n<# #s #>\n  #\nlast";
+    for max_chunk_len in [1, 2, 3, 64] {
+        let mut lexer = make_lexer(source, max_chunk_len);
+        let words = scan_words(&mut lexer)?;
+        let words = words
+            .iter()
+            .map(|(word, _)| word.as_str())
+            .collect::<Vec<_>>();
+        let expected = ["#", "comment", "<#", "#s", "#>", "#", "last"];
+        assert_eq!(words, expected, "chunk len {max_chunk_len}");
+
+        let mut lexer = Lexer::default();
+        lexer.set_max_chunk_len(max_chunk_len);
+        lexer.set_script_mode(true);
+        lexer.push_source_block(SourceBlock::new(
+            "<test>",
+            std::io::Cursor::new(source.to_owned()),
+        ));
+        let words = scan_words(&mut lexer)?;
+        let words = words
+            .iter()
+            .map(|(word, _)| word.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            words,
+            ["<#", "#s", "#>", "last"],
+            "chunk len {max_chunk_len}"
+        );
+    }
+    Ok(())
+}
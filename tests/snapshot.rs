@@ -0,0 +1,34 @@
+//! Saving and restoring the interpreter state with `save-state` and `load-state`.
+
+use anyhow::Result;
+use fift::core::env::EmptyEnvironment;
+use fift::core::SourceBlock;
+
+fn run(source: &str) -> Result<String> {
+    let mut env = EmptyEnvironment;
+    let mut stdout = Vec::<u8>::new();
+    let mut ctx = fift::Context::new(&mut env, &mut stdout)
+        .with_basic_modules()?
+        .with_source_block(SourceBlock::new(
+            "<test>",
+            std::io::Cursor::new(source.to_owned()),
+        ));
+    ctx.run()?;
+    drop(ctx);
+    Ok(String::from_utf8(stdout)?)
+}
+
+#[test]
+fn round_trip() -> Result<()> {
+    let output = run("{ 2 * } : double 3 \"abc\" 2 tuple save-state \
+         { } : double nip load-state 5 double . .s")?;
+    assert_eq!(output, "10 [ 3 \"abc\" ]\n");
+    Ok(())
+}
+
+#[test]
+fn huge_counts_are_rejected() {
+    // A box count of `usize::MAX` followed by a few bytes
+    let err = run("\"46494654534E4150028080808080808080800100\" x>B load-state").unwrap_err();
+    assert!(format!("{err:?}").contains("Invalid snapshot"), "{err:?}");
+}